@@ -8,6 +8,15 @@
 ---@field enable_focus_command boolean
 ---@field global_variable_settings string[]
 ---@field option_settings string[]
+---@field local_settings string[]
+---@field settings_metadata neovide.SettingMetadata[]
+
+---@class neovide.SettingMetadata
+---@field name string
+---@field type "boolean"|"integer"|"number"|"string"|"string|string[]"|"any"
+---@field allowed_values string[]
+---@field default any
+---@field doc string
 
 ---@type Args
 local args = ...
@@ -93,6 +102,21 @@ vim.api.nvim_create_user_command("NeovideFocus", function()
     rpcnotify("neovide.focus_window")
 end, {})
 
+vim.api.nvim_create_user_command("NeovideProfile", function(command)
+    rpcnotify("neovide.set_profile", command.args)
+end, {
+    nargs = "?",
+    complete = function(arg_lead)
+        local ok, names = pcall(vim.rpcrequest, vim.g.neovide_channel_id, "neovide.profile_names")
+        if not ok then
+            return {}
+        end
+        return vim.tbl_filter(function(name)
+            return vim.startswith(name, arg_lead)
+        end, names)
+    end,
+})
+
 
 if vim.fn.has("mac") == 1 then
     local URL_PATTERN = "https?://[%w-_%.]+%.%w[%w-_%.%%%?%.:/+=&%%[%]#]*"
@@ -222,6 +246,96 @@ for _, option_setting in ipairs(args.option_settings) do
     })
 end
 
+local last_local_settings = nil
+
+local function collect_local_settings()
+    local local_settings = {}
+    for _, win in ipairs(vim.api.nvim_list_wins()) do
+        local buf = vim.api.nvim_win_get_buf(win)
+        local settings = {}
+        local has_settings = false
+        for _, name in ipairs(args.local_settings) do
+            -- Window local values take precedence over buffer local ones
+            local value = vim.w[win]["neovide_" .. name]
+            if value == nil then
+                value = vim.b[buf]["neovide_" .. name]
+            end
+            if value ~= nil then
+                settings[name] = value
+                has_settings = true
+            end
+        end
+        if has_settings then
+            table.insert(local_settings, { window = win, settings = settings })
+        end
+    end
+    return local_settings
+end
+
+local function notify_local_settings()
+    local ok, local_settings = pcall(collect_local_settings)
+    if ok and not vim.deep_equal(local_settings, last_local_settings) then
+        last_local_settings = local_settings
+        pcall(rpcnotify, "neovide.local_settings", local_settings)
+    end
+end
+
+local function schedule_local_settings_check()
+    vim.schedule(notify_local_settings)
+end
+
+-- The w: and b: dictionaries can only be watched from Vimscript. The functions are script-local,
+-- so that they don't clash with the user's, and called through the <SNR> prefix of the script.
+local local_watchers = vim.api.nvim_exec2(
+    [[
+function! s:WatchWindowLocal(callback)
+    call dictwatcheradd(w:, 'neovide_*', a:callback)
+endfunction
+function! s:WatchBufferLocal(callback)
+    call dictwatcheradd(b:, 'neovide_*', a:callback)
+endfunction
+echo expand('<SID>')
+]],
+    { output = true }
+)
+local local_watcher_prefix = vim.trim(local_watchers.output)
+
+local watched_windows = {}
+local watched_buffers = {}
+
+local local_settings_group = vim.api.nvim_create_augroup("NeovideLocalSettings", { clear = true })
+
+-- Watch the w: and b: dictionaries of every window and buffer that gets displayed, so that
+-- `:let w:neovide_...` is picked up immediately
+vim.api.nvim_create_autocmd({ "VimEnter", "BufWinEnter", "WinEnter", "BufEnter" }, {
+    group = local_settings_group,
+    callback = function()
+        local win = vim.api.nvim_get_current_win()
+        local buf = vim.api.nvim_get_current_buf()
+        if not watched_windows[win] then
+            vim.fn[local_watcher_prefix .. "WatchWindowLocal"](schedule_local_settings_check)
+            watched_windows[win] = true
+        end
+        if not watched_buffers[buf] then
+            vim.fn[local_watcher_prefix .. "WatchBufferLocal"](schedule_local_settings_check)
+            watched_buffers[buf] = true
+        end
+        schedule_local_settings_check()
+    end,
+})
+
+vim.api.nvim_create_autocmd({ "WinClosed", "BufWipeout", "FileType", "TermOpen" }, {
+    group = local_settings_group,
+    callback = function(event)
+        if event.event == "WinClosed" then
+            watched_windows[tonumber(event.match)] = nil
+        elseif event.event == "BufWipeout" then
+            watched_buffers[event.buf] = nil
+        end
+        schedule_local_settings_check()
+    end,
+})
+
 -- Ignore initial values of lines and columns because they are set by neovim directly.
 -- See https://github.com/neovide/neovide/issues/2300
 vim.api.nvim_create_autocmd({ "VimEnter" }, {
@@ -292,6 +406,10 @@ vim.api.nvim_create_user_command("NeovideConfig", function()
     end
 end, {})
 
+vim.api.nvim_create_user_command("NeovideSettings", function()
+    rpcnotify("neovide.show_settings")
+end, {})
+
 local function progress_bar(data)
     -- Wrap inside pcall to avoid errors if Neovide disconnects.
     pcall(rpcnotify, "neovide.progress_bar", data)
@@ -411,4 +529,144 @@ M.commit_handler = function(commit_raw_text, commit_formatted_text)
     vim.api.nvim_input(commit_formatted_text)
 end
 
+local settings_metadata = {}
+for _, metadata in ipairs(args.settings_metadata) do
+    settings_metadata[metadata.name] = metadata
+end
+
+local function check_string(metadata, value)
+    if type(value) ~= "string" then
+        return false
+    end
+    return #metadata.allowed_values == 0 or vim.tbl_contains(metadata.allowed_values, value)
+end
+
+local function check_setting(name, value)
+    local metadata = settings_metadata[name]
+    if metadata == nil then
+        return string.format("unknown setting `%s`", name)
+    end
+
+    local valid
+    if metadata.type == "boolean" then
+        valid = type(value) == "boolean" or value == 0 or value == 1
+    elseif metadata.type == "integer" then
+        valid = type(value) == "number" and value % 1 == 0
+    elseif metadata.type == "number" then
+        valid = type(value) == "number"
+    elseif metadata.type == "string" then
+        valid = check_string(metadata, value)
+    elseif metadata.type == "string|string[]" then
+        valid = check_string(metadata, value)
+            or (
+                vim.islist(value)
+                and vim.iter(value):all(function(item)
+                    return check_string(metadata, item)
+                end)
+            )
+    else
+        valid = true
+    end
+    if valid then
+        return nil
+    end
+
+    local expected = metadata.type
+    if #metadata.allowed_values > 0 then
+        local allowed_values = vim.tbl_map(vim.inspect, metadata.allowed_values)
+        expected = expected .. " (one of " .. table.concat(allowed_values, ", ") .. ")"
+    end
+    return string.format("invalid value %s for `%s`, expected %s", vim.inspect(value), name, expected)
+end
+
+--- Validates and applies a single setting.
+---@param name string
+---@param value any
+M.set = function(name, value)
+    local error_message = check_setting(name, value)
+    if error_message then
+        error("neovide: " .. error_message, 2)
+    end
+    vim.g["neovide_" .. name] = value
+end
+
+--- Returns the value of a setting, or all settings when no name is given.
+---@param name? string
+---@return any
+M.get = function(name)
+    if name == nil then
+        local values = {}
+        for setting_name, _ in pairs(settings_metadata) do
+            values[setting_name] = vim.g["neovide_" .. setting_name]
+        end
+        return values
+    end
+    if settings_metadata[name] == nil then
+        error(string.format("neovide: unknown setting `%s`", name), 2)
+    end
+    return vim.g["neovide_" .. name]
+end
+
+--- Validates all the given settings, and only applies them when they are all valid.
+---@param settings table<string, any>
+M.setup = function(settings)
+    local errors = {}
+    for name, value in pairs(settings or {}) do
+        table.insert(errors, check_setting(name, value))
+    end
+    if #errors > 0 then
+        table.sort(errors)
+        error("neovide: " .. table.concat(errors, "\nneovide: "), 2)
+    end
+    for name, value in pairs(settings or {}) do
+        vim.g["neovide_" .. name] = value
+    end
+end
+
+--- Binds a key chord to a GUI action, which is handled by Neovide before the keys reach Neovim.
+--- A nil action removes the binding.
+---@param keys string
+---@param action? string
+M.bind = function(keys, action)
+    if type(keys) ~= "string" or (action ~= nil and type(action) ~= "string") then
+        error("neovide: bind expects a key chord and an action name", 2)
+    end
+    rpcnotify("neovide.bind", keys, action)
+end
+
+--- Sets the font of a highlight group, like `Comment`, which is used before the configured fonts and
+--- scaled to fit the cells. A nil font goes back to the font of the config file.
+---@param group string
+---@param font? string|table
+M.set_hl_font = function(group, font)
+    if type(group) ~= "string" or not vim.tbl_contains({ "nil", "string", "table" }, type(font)) then
+        error("neovide: set_hl_font expects a highlight group and a font", 2)
+    end
+    rpcnotify("neovide.set_hl_font", group, font)
+end
+
+local palette_commands = {}
+
+--- Adds an entry to the command palette, which runs the callback or the Ex command when selected.
+--- A nil callback removes the entry.
+---@param title string
+---@param callback? string|function
+M.register_command = function(title, callback)
+    if type(title) ~= "string" or not vim.tbl_contains({ "nil", "string", "function" }, type(callback)) then
+        error("neovide: register_command expects a title and a function or an Ex command", 2)
+    end
+    palette_commands[title] = callback
+    rpcnotify("neovide.register_command", title, callback ~= nil)
+end
+
+M.private.run_palette_command = function(title)
+    local callback = palette_commands[title]
+    if type(callback) == "string" then
+        vim.cmd(callback)
+    elseif callback then
+        callback()
+    end
+end
+
 _G["neovide"] = M
+package.loaded["neovide"] = M
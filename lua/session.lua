@@ -0,0 +1,16 @@
+local function save(session_file)
+    vim.api.nvim_cmd({ cmd = "mksession", args = { vim.fn.fnameescape(session_file) }, bang = true }, {})
+    return vim.fn.getcwd()
+end
+
+local function restore(session_file, directory)
+    vim.api.nvim_cmd({ cmd = "cd", args = { vim.fn.fnameescape(directory) } }, {})
+    vim.api.nvim_cmd({ cmd = "source", args = { vim.fn.fnameescape(session_file) } }, {})
+end
+
+local action, session_file, directory = ...
+if action == "save" then
+    return save(session_file)
+else
+    restore(session_file, directory)
+end
@@ -0,0 +1,233 @@
+//! Derive macro for the JSON schema of the config file.
+//!
+//! The schema follows the serde representation of the type, so the supported `#[serde]`
+//! attributes are interpreted: `rename_all`, `alias`, `default`, `flatten` and `untagged`.
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{
+    Attribute, Data, DataEnum, DeriveInput, Error, Field, Fields, Ident, Lit, LitStr, Meta, Type,
+    parse_macro_input,
+};
+
+#[derive(Default)]
+struct SerdeAttributes {
+    rename_all: Option<String>,
+    aliases: Vec<String>,
+    default: bool,
+    flatten: bool,
+    untagged: bool,
+}
+
+pub fn config_schema(item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as DeriveInput);
+    let attributes = match serde_attributes(&input.attrs) {
+        Ok(attributes) => attributes,
+        Err(error) => return error.to_compile_error().into(),
+    };
+
+    let body = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(_) => struct_schema(&data.fields, &attributes),
+            Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+                let ty = &fields.unnamed[0].ty;
+                Ok(quote! { <#ty as crate::settings::ConfigSchema>::config_schema() })
+            }
+            _ => Err(Error::new_spanned(&input.ident, "Expected named fields or a newtype")),
+        },
+        Data::Enum(data) => enum_schema(&input.ident, data, &attributes),
+        Data::Union(data) => Err(Error::new_spanned(data.union_token, "Unions are not supported")),
+    };
+    let body = match body {
+        Ok(body) => body,
+        Err(error) => return error.to_compile_error().into(),
+    };
+
+    let name = input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let expanded = quote! {
+        impl #impl_generics crate::settings::ConfigSchema for #name #ty_generics #where_clause {
+            fn config_schema() -> serde_json::Value {
+                #body
+            }
+        }
+    };
+    TokenStream::from(expanded)
+}
+
+fn struct_schema(
+    fields: &Fields,
+    attributes: &SerdeAttributes,
+) -> Result<proc_macro2::TokenStream, Error> {
+    let mut fragments = Vec::new();
+    for field in fields {
+        let field_attributes = serde_attributes(&field.attrs)?;
+        let ty = &field.ty;
+        if field_attributes.flatten {
+            fragments.push(quote! {
+                crate::settings::config_schema::flatten(
+                    &mut properties,
+                    <#ty as crate::settings::ConfigSchema>::config_schema(),
+                );
+            });
+            continue;
+        }
+
+        let ident = field.ident.as_ref().unwrap().to_string();
+        let key = rename(&ident, attributes.rename_all.as_deref());
+        let description = match doc_comment(&field.attrs) {
+            Some(doc) => quote! { Some(#doc) },
+            None => quote! { None },
+        };
+        fragments.push(quote! {
+            let schema = crate::settings::config_schema::describe(
+                <#ty as crate::settings::ConfigSchema>::config_schema(),
+                #description,
+            );
+        });
+        for alias in &field_attributes.aliases {
+            fragments.push(quote! {
+                properties.insert(#alias.to_owned(), schema.clone());
+            });
+        }
+        fragments.push(quote! {
+            properties.insert(#key.to_owned(), schema);
+        });
+        if !field_attributes.default && !is_option(field) {
+            fragments.push(quote! {
+                required.push(#key);
+            });
+        }
+    }
+
+    Ok(quote! {
+        let mut properties = serde_json::Map::new();
+        #[allow(unused_mut)]
+        let mut required: Vec<&str> = Vec::new();
+        #(#fragments)*
+        crate::settings::config_schema::object(properties, required)
+    })
+}
+
+fn enum_schema(
+    name: &Ident,
+    data: &DataEnum,
+    attributes: &SerdeAttributes,
+) -> Result<proc_macro2::TokenStream, Error> {
+    if attributes.untagged {
+        let variants = data
+            .variants
+            .iter()
+            .map(|variant| match &variant.fields {
+                Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+                    let ty = &fields.unnamed[0].ty;
+                    Ok(quote! { <#ty as crate::settings::ConfigSchema>::config_schema() })
+                }
+                _ => Err(Error::new_spanned(variant, "Expected a newtype variant")),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        return Ok(quote! {
+            crate::settings::config_schema::any_of(vec![#(#variants),*])
+        });
+    }
+
+    let variants = data
+        .variants
+        .iter()
+        .map(|variant| {
+            if !matches!(variant.fields, Fields::Unit) {
+                return Err(Error::new_spanned(
+                    variant,
+                    format!("{name} must be untagged or only contain unit variants"),
+                ));
+            }
+            // Keep platform specific variants platform specific
+            let cfgs = variant.attrs.iter().filter(|attr| attr.path().is_ident("cfg"));
+            let value = rename(&variant.ident.to_string(), attributes.rename_all.as_deref());
+            Ok(quote! {
+                #(#cfgs)*
+                variants.push(#value);
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(quote! {
+        let mut variants: Vec<&str> = Vec::new();
+        #(#variants)*
+        crate::settings::config_schema::string_enum(variants)
+    })
+}
+
+fn serde_attributes(attrs: &[Attribute]) -> Result<SerdeAttributes, Error> {
+    let mut attributes = SerdeAttributes::default();
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("serde")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename_all") {
+                attributes.rename_all = Some(meta.value()?.parse::<LitStr>()?.value());
+            } else if meta.path.is_ident("alias") {
+                attributes.aliases.push(meta.value()?.parse::<LitStr>()?.value());
+            } else if meta.path.is_ident("default") {
+                attributes.default = true;
+                if meta.input.peek(syn::Token![=]) {
+                    meta.value()?.parse::<LitStr>()?;
+                }
+            } else if meta.path.is_ident("flatten") {
+                attributes.flatten = true;
+            } else if meta.path.is_ident("untagged") {
+                attributes.untagged = true;
+            } else if meta.input.peek(syn::Token![=]) {
+                meta.value()?.parse::<LitStr>()?;
+            }
+            Ok(())
+        })?;
+    }
+    Ok(attributes)
+}
+
+/// Applies `rename_all` the same way as serde, fields are in snake_case and variants in PascalCase.
+fn rename(name: &str, rename_all: Option<&str>) -> String {
+    let snake_case = if name.contains('_') || name.chars().all(|c| !c.is_uppercase()) {
+        name.to_owned()
+    } else {
+        let mut snake_case = String::new();
+        for (i, c) in name.char_indices() {
+            if c.is_uppercase() && i > 0 {
+                snake_case.push('_');
+            }
+            snake_case.push(c.to_ascii_lowercase());
+        }
+        snake_case
+    };
+    match rename_all {
+        Some("kebab-case") => snake_case.replace('_', "-"),
+        Some("snake_case") => snake_case,
+        Some("lowercase") => name.to_lowercase(),
+        _ => name.to_owned(),
+    }
+}
+
+fn is_option(field: &Field) -> bool {
+    match &field.ty {
+        Type::Path(path) => {
+            path.path.segments.last().is_some_and(|segment| segment.ident == "Option")
+        }
+        _ => false,
+    }
+}
+
+pub(crate) fn doc_comment(attrs: &[Attribute]) -> Option<String> {
+    let lines = attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("doc"))
+        .filter_map(|attr| match &attr.meta {
+            Meta::NameValue(name_value) => match &name_value.value {
+                syn::Expr::Lit(expr) => match &expr.lit {
+                    Lit::Str(literal) => Some(literal.value().trim().to_owned()),
+                    _ => None,
+                },
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    (!lines.is_empty()).then(|| lines.join(" "))
+}
@@ -3,6 +3,18 @@
 //! This macro will generate a `SettingGroup` implementation for the struct it is applied to.
 //! It will also generate an enum with the name `{StructName}Changed` that contains a variant for
 //! each field in the struct. The enum will be used to send events when a setting is changed.
+//!
+//! Fields marked with `#[local]` can additionally be overridden per window or buffer through
+//! `w:neovide_*` and `b:neovide_*` variables.
+//!
+//! All fields that are backed by a `g:neovide_*` variable can also be set from a table in
+//! `config.toml`, named after the struct without the `Settings` suffix, e.g. `[progress_bar]`.
+//! Their type, default value and doc comment are registered as metadata, which is used for the Lua
+//! API.
+//!
+//! The `ConfigSchema` derive generates the JSON schema of types that are read from the config file.
+
+mod config_schema;
 
 use convert_case::{Case, Casing};
 use proc_macro::TokenStream;
@@ -11,13 +23,18 @@ use syn::{
     Attribute, Data, DataStruct, DeriveInput, Error, Field, Ident, Lit, Meta, parse_macro_input,
 };
 
-#[proc_macro_derive(SettingGroup, attributes(setting_prefix, option, alias))]
+#[proc_macro_derive(SettingGroup, attributes(setting_prefix, option, alias, local))]
 pub fn setting_group(item: TokenStream) -> TokenStream {
     let input = parse_macro_input!(item as DeriveInput);
     let prefix = setting_prefix(input.attrs.as_ref()).map(|p| format!("{p}_")).unwrap_or_default();
     stream(input, prefix)
 }
 
+#[proc_macro_derive(ConfigSchema, attributes(serde))]
+pub fn config_schema(item: TokenStream) -> TokenStream {
+    config_schema::config_schema(item)
+}
+
 fn stream(input: DeriveInput, prefix: String) -> TokenStream {
     const ERR_MSG: &str = "Derive macro expects a struct";
     match input.data {
@@ -32,6 +49,7 @@ fn stream(input: DeriveInput, prefix: String) -> TokenStream {
 fn struct_stream(name: Ident, prefix: String, data: &DataStruct) -> TokenStream {
     let event_name = format_ident!("{}Changed", name);
     let name_without_settings = Ident::new(&name.to_string().replace("Settings", ""), name.span());
+    let config_section = name_without_settings.to_string().to_case(Case::Snake);
 
     let listener_fragments = data.fields.iter().map(|field| match field.ident {
         Some(ref ident) => {
@@ -53,6 +71,48 @@ fn struct_stream(name: Ident, prefix: String, data: &DataStruct) -> TokenStream
             let case_name = field_ident.to_string().to_case(Case::Pascal);
             let case_ident = Ident::new(&case_name, field_ident.span());
 
+            // Local overrides only make sense for neovide variables, neovim has its own local
+            // options
+            let set_local_setting_handler = match (is_local(field), &option_name) {
+                (true, Some(_)) => {
+                    return Error::new_spanned(field, "Neovim options can't be marked as local")
+                        .to_compile_error();
+                }
+                (true, None) => quote! {
+                    fn local_update(settings: &crate::settings::Settings, value: rmpv::Value) -> Option<crate::settings::SettingsChanged> {
+                        let mut s = settings.get::<#name>();
+                        if !s.#ident.parse_from_value(value) {
+                            log::warn!(concat!("Ignoring the invalid local override of neovide_", #vim_setting_name));
+                            return None;
+                        }
+                        Some(#event_name::#case_ident(s.#ident).into())
+                    }
+
+                    settings.set_local_setting_handler(#vim_setting_name.to_owned(), local_update);
+                },
+                (false, _) => quote! {},
+            };
+
+            // Neovim options are configured from init.vim/init.lua, not from the config file, and
+            // already have their own documentation and type checks
+            let set_config_key_and_metadata = if option_name.is_none() {
+                let config_key = ident.to_string();
+                let ty = &field.ty;
+                let doc = config_schema::doc_comment(&field.attrs).unwrap_or_default();
+                quote! {
+                    settings.set_config_key(#config_section, #config_key, #vim_setting_name);
+                    settings.set_setting_metadata(crate::settings::SettingMetadata {
+                        name: #vim_setting_name.to_owned(),
+                        kind: <#ty as crate::settings::ParseFromValue>::value_kind(),
+                        allowed_values: <#ty as crate::settings::ParseFromValue>::allowed_values(),
+                        default: reader(settings).unwrap_or_default(),
+                        doc: #doc.to_owned(),
+                    });
+                }
+            } else {
+                quote! {}
+            };
+
             // Only create a reader function for global neovide variables
             let reader = if option_name.is_none() {
                 quote! {
@@ -112,6 +172,10 @@ fn struct_stream(name: Ident, prefix: String, data: &DataStruct) -> TokenStream
                 );
 
                 #set_setting_handlers_alias
+
+                #set_local_setting_handler
+
+                #set_config_key_and_metadata
             }}
         }
         None => {
@@ -128,6 +192,33 @@ fn struct_stream(name: Ident, prefix: String, data: &DataStruct) -> TokenStream
             #case_ident(#ty),
         }
     });
+    let local_override_arms = data.fields.iter().filter(|field| is_local(field)).map(|field| {
+        let field_ident = field.ident.as_ref().unwrap();
+        let case_name = field_ident.to_string().to_case(Case::Pascal);
+        let case_ident = Ident::new(&case_name, field_ident.span());
+        quote! {
+            #event_name::#case_ident(value) => self.#field_ident = value.clone(),
+        }
+    });
+    let apply_local_override = if data.fields.iter().any(is_local) {
+        quote! {
+            fn apply_local_override(&mut self, changed: &crate::settings::SettingsChanged) {
+                #[allow(irrefutable_let_patterns)]
+                if let crate::settings::SettingsChanged::#name_without_settings(changed) = changed {
+                    #[allow(unreachable_patterns)]
+                    match changed {
+                        #(#local_override_arms)*
+                        _ => {}
+                    }
+                }
+            }
+        }
+    } else {
+        quote! {
+            fn apply_local_override(&mut self, _changed: &crate::settings::SettingsChanged) {}
+        }
+    };
+
     let expanded = quote! {
         #[derive(Debug, Clone, PartialEq, strum::AsRefStr)]
         pub enum #event_name {
@@ -141,6 +232,8 @@ fn struct_stream(name: Ident, prefix: String, data: &DataStruct) -> TokenStream
                 settings.set(&s);
                 #(#listener_fragments)*
             }
+
+            #apply_local_override
         }
 
         impl From<#event_name> for crate::settings::SettingsChanged {
@@ -167,6 +260,10 @@ fn setting_prefix(attrs: &[Attribute]) -> Option<String> {
     None
 }
 
+fn is_local(field: &Field) -> bool {
+    field.attrs.iter().any(|attr| attr.path().is_ident("local"))
+}
+
 fn get_attribute_value(field: &Field, ident: &str) -> Result<Option<String>, Error> {
     for attr in field.attrs.iter() {
         if !attr.path().is_ident(ident) {
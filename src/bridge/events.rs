@@ -1,7 +1,9 @@
 use std::{
+    collections::HashMap,
     convert::TryInto,
     error,
     fmt::{self, Debug},
+    sync::Arc,
 };
 
 use log::{debug, warn};
@@ -12,6 +14,8 @@ use strum::AsRefStr;
 use super::RestartDetails;
 use crate::{
     editor::{Colors, CursorMode, CursorShape, Style, UnderlineStyle},
+    renderer::fonts::highlight_fonts::HighlightFonts,
+    settings::LocalOverrides,
     window::UserEvent,
 };
 
@@ -55,11 +59,14 @@ impl error::Error for ParseError {
     }
 }
 
+/// The text of a grid cell. It's shared, since most cells contain the same few characters.
+pub type CellText = Arc<str>;
+
 #[derive(Clone, Debug)]
 pub struct GridLineCell {
     /// The UTF-8 text that should be put in the cell. Will be an empty string for the right cell
     /// of a double-width char.
-    pub text: String,
+    pub text: CellText,
     /// A highlight id defined by a previous [`RedrawEvent::HighlightAttributesDefine`]. If `None`,
     /// the most recently seen `highlight_id` in the same [`RedrawEvent::GridLine`] should be used
     /// (it is always sent for the first cell in the event).
@@ -148,6 +155,20 @@ pub enum EditorMode {
     Unknown(String),
 }
 
+impl EditorMode {
+    /// The name of the mode, as sent by Neovim in `mode_change`.
+    pub fn name(&self) -> &str {
+        match self {
+            EditorMode::Normal => "normal",
+            EditorMode::Insert => "insert",
+            EditorMode::Visual => "visual",
+            EditorMode::Replace => "replace",
+            EditorMode::CmdLine => "cmdline_normal",
+            EditorMode::Unknown(name) => name,
+        }
+    }
+}
+
 /// Deserialized representations of [UI events] sent by the neovim process.
 ///
 /// [UI events]: https://neovim.io/doc/user/ui.html
@@ -356,6 +377,7 @@ pub enum RedrawEvent {
     /// win_viewport is received.
     WindowViewport {
         grid: u64,
+        window: u64,
         #[allow(unused)]
         top_line: f64,
         #[allow(unused)]
@@ -438,6 +460,10 @@ pub enum RedrawEvent {
     Suspend,
     NeovideSetRedraw(bool),
     NeovideIntroBannerAllowed(bool),
+    /// The `w:` and `b:` setting overrides, keyed by the window handle.
+    NeovideLocalSettings(HashMap<u64, LocalOverrides>),
+    /// The fonts of the highlight groups changed.
+    NeovideHighlightFonts(HighlightFonts),
 }
 
 fn unpack_color(packed_color: u64) -> Color4f {
@@ -448,19 +474,13 @@ fn unpack_color(packed_color: u64) -> Color4f {
     Color4f { r: r / 255.0, g: g / 255.0, b: b / 255.0, a: 1.0 }
 }
 
-fn extract_values<const REQ: usize>(values: Vec<Value>) -> Result<[Value; REQ]> {
+fn extract_values<const REQ: usize>(mut values: Vec<Value>) -> Result<[Value; REQ]> {
     if REQ > values.len() {
         Err(ParseError::Format(format!("{values:?}")))
     } else {
-        let mut required_values = vec![Value::Nil; REQ];
-
-        for (index, value) in values.into_iter().enumerate() {
-            if index < REQ {
-                required_values[index] = value;
-            }
-        }
-
-        Ok(required_values.try_into().unwrap())
+        // Moves the values into the array without copying them
+        values.truncate(REQ);
+        Ok(values.try_into().unwrap())
     }
 }
 
@@ -504,6 +524,17 @@ fn parse_u64(u64_value: Value) -> Result<u64> {
     u64_value.try_into().map_err(ParseError::U64)
 }
 
+fn parse_window_handle(window_value: Value) -> Result<u64> {
+    // Windows are sent as a msgpack extension type wrapping the handle
+    if let Value::Ext(_, data) = &window_value {
+        return rmpv::decode::read_value(&mut data.as_slice())
+            .ok()
+            .and_then(|value| value.as_u64())
+            .ok_or(ParseError::U64(window_value));
+    }
+    parse_u64(window_value)
+}
+
 fn parse_i64(i64_value: Value) -> Result<i64> {
     i64_value.try_into().map_err(ParseError::I64)
 }
@@ -631,10 +662,11 @@ fn parse_default_colors(default_colors_arguments: Vec<Value>) -> Result<RedrawEv
     })
 }
 
-fn parse_style(style_map: Value, _info_array: Value) -> Result<Style> {
+fn parse_style(style_map: Value, highlight_groups: Vec<String>) -> Result<Style> {
     let attributes = parse_map(style_map)?;
 
     let mut style = Style::new(Colors::new(None, None, None));
+    style.highlight_groups = highlight_groups;
 
     for attribute in attributes {
         if let (Value::String(name), value) = attribute {
@@ -655,6 +687,7 @@ fn parse_style(style_map: Value, _info_array: Value) -> Result<Style> {
                     style.strikethrough = strikethrough
                 }
                 ("blend", Value::Integer(blend)) => style.blend = blend.as_u64().unwrap() as u8,
+                ("altfont", Value::Boolean(altfont)) => style.altfont = altfont,
 
                 ("underline", Value::Boolean(true)) => {
                     style.underline = Some(UnderlineStyle::Underline)
@@ -682,7 +715,8 @@ fn parse_style(style_map: Value, _info_array: Value) -> Result<Style> {
     Ok(style)
 }
 
-fn parse_hl_name(infos: Value) -> Option<String> {
+/// The names of the highlight groups a highlight is made of, from the info of `ext_hlstate`.
+fn parse_hl_names(infos: Value) -> Vec<String> {
     fn take_names(values: Vec<(Value, Value)>, names: &mut Vec<String>) {
         let possible_keys = ["hi_name", "ui_name", "name", "link"];
         for (key, value) in values {
@@ -709,21 +743,26 @@ fn parse_hl_name(infos: Value) -> Option<String> {
         _ => {}
     }
 
+    names
+}
+
+fn parse_hl_name(names: &[String]) -> Option<String> {
     if let Some(name) = names.iter().find(|name| name.starts_with("MatchParen")) {
         return Some(name.clone());
     }
 
-    names.into_iter().next()
+    names.first().cloned()
 }
 
 fn parse_hl_attr_define(hl_attr_define_arguments: Vec<Value>) -> Result<RedrawEvent> {
     let [id, attributes, _terminal_attributes, infos] = extract_values(hl_attr_define_arguments)?;
 
-    let style = parse_style(attributes, infos.clone())?;
+    let names = parse_hl_names(infos);
+    let name = parse_hl_name(&names);
     Ok(RedrawEvent::HighlightAttributesDefine {
         id: parse_u64(id)?,
-        style,
-        name: parse_hl_name(infos),
+        style: parse_style(attributes, names)?,
+        name,
     })
 }
 
@@ -748,7 +787,7 @@ fn parse_grid_line_cell(grid_line_cell: Value) -> Result<GridLineCell> {
     let highlight_id = cell_contents.get_mut(1).map(take_value).map(parse_u64).transpose()?;
     let repeat = cell_contents.get_mut(2).map(take_value).map(parse_u64).transpose()?;
 
-    Ok(GridLineCell { text: parse_string(text_value)?, highlight_id, repeat })
+    Ok(GridLineCell { text: parse_string(text_value)?.into(), highlight_id, repeat })
 }
 
 fn parse_grid_line(grid_line_arguments: Vec<Value>) -> Result<RedrawEvent> {
@@ -906,12 +945,13 @@ fn parse_msg_set_pos(msg_set_pos_arguments: Vec<Value>) -> Result<RedrawEvent> {
 
 fn parse_win_viewport(win_viewport_arguments: Vec<Value>) -> Result<RedrawEvent> {
     let (
-        [grid, _window, top_line, bottom_line, current_line, current_column],
+        [grid, window, top_line, bottom_line, current_line, current_column],
         [line_count, scroll_delta],
     ) = extract_values_with_optional(win_viewport_arguments)?;
 
     Ok(RedrawEvent::WindowViewport {
         grid: parse_u64(grid)?,
+        window: parse_window_handle(window)?,
         top_line: parse_f64(top_line)?,
         bottom_line: parse_f64(bottom_line)?,
         current_line: parse_f64(current_line)?,
@@ -1048,70 +1088,72 @@ pub fn parse_redraw_event(event_value: Value) -> Result<Vec<RedrawEvent>> {
 
     for event in events {
         let event_parameters = parse_array(event)?;
-        let event_parameters_copy = event_parameters.clone();
-        let possible_parsed_event = match event_name.as_str() {
-            "set_title" => Some(parse_set_title(event_parameters)),
-            "restart" => Some(parse_restart(event_parameters)),
-            "set_icon" => None, // Ignore set icon for now
-            "mode_info_set" => Some(parse_mode_info_set(event_parameters)),
-            "option_set" => Some(parse_option_set(event_parameters)),
-            "mode_change" => Some(parse_mode_change(event_parameters)),
-            "mouse_on" => Some(Ok(RedrawEvent::MouseOn)),
-            "mouse_off" => Some(Ok(RedrawEvent::MouseOff)),
-            "busy_start" => Some(Ok(RedrawEvent::BusyStart)),
-            "busy_stop" => Some(Ok(RedrawEvent::BusyStop)),
-            "flush" => Some(Ok(RedrawEvent::Flush)),
-            "grid_resize" => Some(parse_grid_resize(event_parameters)),
-            "default_colors_set" => Some(parse_default_colors(event_parameters)),
-            "hl_attr_define" => Some(parse_hl_attr_define(event_parameters)),
-            "hl_group_set" => Some(parse_hl_group_set(event_parameters)),
-            "grid_line" => Some(parse_grid_line(event_parameters)),
-            "grid_highlight" => Some(parse_grid_highlight(event_parameters)),
-            "grid_clear" => Some(parse_grid_clear(event_parameters)),
-            "grid_destroy" => Some(parse_grid_destroy(event_parameters)),
-            "grid_cursor_goto" => Some(parse_grid_cursor_goto(event_parameters)),
-            "grid_scroll" => Some(parse_grid_scroll(event_parameters)),
-            "win_pos" => Some(parse_win_pos(event_parameters)),
-            "win_float_pos" => Some(parse_win_float_pos(event_parameters)),
-            "win_external_pos" => Some(parse_win_external_pos(event_parameters)),
-            "win_hide" => Some(parse_win_hide(event_parameters)),
-            "win_close" => Some(parse_win_close(event_parameters)),
-            "msg_set_pos" => Some(parse_msg_set_pos(event_parameters)),
-            "win_viewport" => Some(parse_win_viewport(event_parameters)),
-            "win_viewport_margins" => Some(parse_win_viewport_margins(event_parameters)),
-            "cmdline_show" => Some(parse_cmdline_show(event_parameters)),
-            "cmdline_pos" => Some(parse_cmdline_pos(event_parameters)),
-            "cmdline_special_char" => Some(parse_cmdline_special_char(event_parameters)),
-            "cmdline_hide" => Some(Ok(RedrawEvent::CommandLineHide)),
-            "cmdline_block_show" => Some(parse_cmdline_block_show(event_parameters)),
-            "cmdline_block_append" => Some(parse_cmdline_block_append(event_parameters)),
-            "cmdline_block_hide" => Some(Ok(RedrawEvent::CommandLineBlockHide)),
-            "msg_show" => Some(parse_msg_show(event_parameters)),
-            "msg_clear" => Some(Ok(RedrawEvent::MessageClear)),
-            "msg_showmode" => Some(parse_msg_showmode(event_parameters)),
-            "msg_showcmd" => Some(parse_msg_showcmd(event_parameters)),
-            "msg_ruler" => Some(parse_msg_ruler(event_parameters)),
-            "msg_history_show" => Some(parse_msg_history_show(event_parameters)),
-            "suspend" => Some(Ok(RedrawEvent::Suspend)),
-            _ => None,
-        };
-
-        if let Some(parsed_event) = possible_parsed_event {
-            if let Ok(parsed_event) = parsed_event {
-                parsed_events.push(parsed_event);
-            } else {
-                let parser_error = parsed_event.unwrap_err();
-                let error = ParseError::Format(format!(
-                    "for event '{event_name}' - {event_parameters_copy:?} - {parser_error}"
-                ));
-                return Err(error);
-            }
+        // The parameters aren't copied for the error message, since that would copy the text of
+        // every cell. The parse errors include the values they failed on instead.
+        if let Some(parsed_event) = parse_event(&event_name, event_parameters) {
+            parsed_events.push(parsed_event.map_err(|parser_error| {
+                ParseError::Format(format!("for event '{event_name}' - {parser_error}"))
+            })?);
         }
     }
 
     Ok(parsed_events)
 }
 
+/// Parses the parameters of a single `event_name` event. Returns `None` for the events Neovide
+/// doesn't handle.
+pub(super) fn parse_event(
+    event_name: &str,
+    event_parameters: Vec<Value>,
+) -> Option<Result<RedrawEvent>> {
+    match event_name {
+        "set_title" => Some(parse_set_title(event_parameters)),
+        "restart" => Some(parse_restart(event_parameters)),
+        "set_icon" => None, // Ignore set icon for now
+        "mode_info_set" => Some(parse_mode_info_set(event_parameters)),
+        "option_set" => Some(parse_option_set(event_parameters)),
+        "mode_change" => Some(parse_mode_change(event_parameters)),
+        "mouse_on" => Some(Ok(RedrawEvent::MouseOn)),
+        "mouse_off" => Some(Ok(RedrawEvent::MouseOff)),
+        "busy_start" => Some(Ok(RedrawEvent::BusyStart)),
+        "busy_stop" => Some(Ok(RedrawEvent::BusyStop)),
+        "flush" => Some(Ok(RedrawEvent::Flush)),
+        "grid_resize" => Some(parse_grid_resize(event_parameters)),
+        "default_colors_set" => Some(parse_default_colors(event_parameters)),
+        "hl_attr_define" => Some(parse_hl_attr_define(event_parameters)),
+        "hl_group_set" => Some(parse_hl_group_set(event_parameters)),
+        "grid_line" => Some(parse_grid_line(event_parameters)),
+        "grid_highlight" => Some(parse_grid_highlight(event_parameters)),
+        "grid_clear" => Some(parse_grid_clear(event_parameters)),
+        "grid_destroy" => Some(parse_grid_destroy(event_parameters)),
+        "grid_cursor_goto" => Some(parse_grid_cursor_goto(event_parameters)),
+        "grid_scroll" => Some(parse_grid_scroll(event_parameters)),
+        "win_pos" => Some(parse_win_pos(event_parameters)),
+        "win_float_pos" => Some(parse_win_float_pos(event_parameters)),
+        "win_external_pos" => Some(parse_win_external_pos(event_parameters)),
+        "win_hide" => Some(parse_win_hide(event_parameters)),
+        "win_close" => Some(parse_win_close(event_parameters)),
+        "msg_set_pos" => Some(parse_msg_set_pos(event_parameters)),
+        "win_viewport" => Some(parse_win_viewport(event_parameters)),
+        "win_viewport_margins" => Some(parse_win_viewport_margins(event_parameters)),
+        "cmdline_show" => Some(parse_cmdline_show(event_parameters)),
+        "cmdline_pos" => Some(parse_cmdline_pos(event_parameters)),
+        "cmdline_special_char" => Some(parse_cmdline_special_char(event_parameters)),
+        "cmdline_hide" => Some(Ok(RedrawEvent::CommandLineHide)),
+        "cmdline_block_show" => Some(parse_cmdline_block_show(event_parameters)),
+        "cmdline_block_append" => Some(parse_cmdline_block_append(event_parameters)),
+        "cmdline_block_hide" => Some(Ok(RedrawEvent::CommandLineBlockHide)),
+        "msg_show" => Some(parse_msg_show(event_parameters)),
+        "msg_clear" => Some(Ok(RedrawEvent::MessageClear)),
+        "msg_showmode" => Some(parse_msg_showmode(event_parameters)),
+        "msg_showcmd" => Some(parse_msg_showcmd(event_parameters)),
+        "msg_ruler" => Some(parse_msg_ruler(event_parameters)),
+        "msg_history_show" => Some(parse_msg_history_show(event_parameters)),
+        "suspend" => Some(Ok(RedrawEvent::Suspend)),
+        _ => None,
+    }
+}
+
 pub fn parse_progress_bar_event(value: Option<&Value>) -> Option<UserEvent> {
     let map = value.filter(|v| matches!(v, Value::Map(_)))?.as_map()?;
     let percent = map
@@ -1122,3 +1164,75 @@ pub fn parse_progress_bar_event(value: Option<&Value>) -> Option<UserEvent> {
 
     Some(UserEvent::ShowProgressBar { percent })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cell(text: &str, highlight_id: Option<u64>, repeat: Option<u64>) -> Value {
+        let mut cell = vec![Value::from(text)];
+        cell.extend(highlight_id.or(repeat.map(|_| 0)).map(Value::from));
+        cell.extend(repeat.map(Value::from));
+        Value::from(cell)
+    }
+
+    fn grid_line(row: u64, cells: Vec<Value>) -> Value {
+        Value::from(vec![
+            Value::from(1),
+            Value::from(row),
+            Value::from(0),
+            Value::from(cells),
+            Value::from(false),
+        ])
+    }
+
+    #[test]
+    fn test_parse_grid_line() {
+        let event = Value::from(vec![
+            Value::from("grid_line"),
+            grid_line(2, vec![cell("a", Some(5), None), cell(" ", None, Some(4))]),
+        ]);
+        let events = parse_redraw_event(event).unwrap();
+        let [RedrawEvent::GridLine { grid: 1, row: 2, column_start: 0, cells }] = events.as_slice()
+        else {
+            panic!("Unexpected events {events:?}");
+        };
+        assert_eq!(cells.len(), 2);
+        assert_eq!((&*cells[0].text, cells[0].highlight_id), ("a", Some(5)));
+        assert_eq!((&*cells[1].text, cells[1].repeat), (" ", Some(4)));
+
+        let event = Value::from(vec![Value::from("grid_line"), Value::from(vec![Value::from(1)])]);
+        let error = parse_redraw_event(event).unwrap_err().to_string();
+        assert!(error.contains("grid_line"), "{error}");
+    }
+
+    #[test]
+    fn test_parse_hl_attr_define() {
+        let info = |kind: &str, name: &str| {
+            Value::Map(vec![
+                (Value::from("kind"), Value::from(kind)),
+                (Value::from("hi_name"), Value::from(name)),
+            ])
+        };
+        let event = Value::from(vec![
+            Value::from("hl_attr_define"),
+            Value::from(vec![
+                Value::from(3),
+                Value::Map(vec![
+                    (Value::from("italic"), Value::from(true)),
+                    (Value::from("altfont"), Value::from(true)),
+                ]),
+                Value::Map(vec![]),
+                Value::from(vec![info("syntax", "Comment"), info("syntax", "MatchParen")]),
+            ]),
+        ]);
+        let events = parse_redraw_event(event).unwrap();
+        let [RedrawEvent::HighlightAttributesDefine { id: 3, style, name }] = events.as_slice()
+        else {
+            panic!("Unexpected events {events:?}");
+        };
+        assert!(style.italic && style.altfont);
+        assert_eq!(style.highlight_groups, ["Comment", "MatchParen"]);
+        assert_eq!(name.as_deref(), Some("MatchParen"));
+    }
+}
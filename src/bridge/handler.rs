@@ -1,6 +1,9 @@
-use std::sync::{
-    Arc, Mutex, RwLock,
-    atomic::{AtomicBool, Ordering},
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc, Mutex, RwLock,
+        atomic::{AtomicBool, Ordering},
+    },
 };
 
 use async_trait::async_trait;
@@ -18,13 +21,19 @@ use crate::{
         GuiOption, NeovimWriter, ParallelCommand, RedrawEvent,
         clipboard::{get_clipboard_contents, set_clipboard_contents},
         events::parse_redraw_event,
-        parse_progress_bar_event, send_ui,
+        parse_progress_bar_event,
+        redraw_decoder::{DECODED_REDRAW_NOTIFICATION, DecodedRedraws},
+        save_session, send_ui,
     },
     clipboard::ClipboardHandle,
     error_handling::ResultPanicExplanation,
+    renderer::fonts::{font_options::FontDescription, highlight_fonts::HighlightFonts},
     running_tracker::RunningTracker,
-    settings::{FontConfigState, Settings},
-    window::{EventPayload, RouteId, UserEvent, WindowCommand},
+    settings::{
+        FontConfigState, LocalOverrides, Settings,
+        config::{profile_names, switch_profile},
+    },
+    window::{EventPayload, RouteId, UserEvent, WindowCommand, session_enabled, session_file},
 };
 
 use super::ui_commands::UiCommand;
@@ -49,6 +58,8 @@ pub struct NeovimHandler {
     #[allow(dead_code)]
     settings: Arc<Settings>,
     clipboard: ClipboardHandle,
+    /// The redraw notifications decoded while reading from Neovim.
+    decoded_redraws: DecodedRedraws,
 }
 
 impl std::fmt::Debug for NeovimHandler {
@@ -80,9 +91,45 @@ impl NeovimHandler {
             route_id,
             settings,
             clipboard,
+            decoded_redraws: DecodedRedraws::default(),
         }
     }
 
+    pub(crate) fn decoded_redraws(&self) -> DecodedRedraws {
+        self.decoded_redraws.clone()
+    }
+
+    async fn handle_redraw_events(
+        &self,
+        parsed_events: Vec<RedrawEvent>,
+        neovim: &Neovim<NeovimWriter>,
+    ) {
+        for parsed_event in parsed_events {
+            if skip_default_guifont(&parsed_event, &self.settings, neovim).await {
+                continue;
+            }
+
+            match parsed_event {
+                RedrawEvent::Restart { details } => {
+                    let payload =
+                        EventPayload::for_route(UserEvent::NeovimRestart(details), self.route_id);
+                    let _ = self.proxy.lock().unwrap().send_event(payload);
+                }
+                _ => {
+                    let _ = self.redraw_event_sender.send(parsed_event);
+                }
+            }
+        }
+    }
+
+    /// Makes the editor use the current fonts of the highlight groups, and redraws the screen with
+    /// them.
+    pub fn update_highlight_fonts(&self) {
+        let highlight_fonts = self.settings.get::<HighlightFonts>();
+        let _ = self.redraw_event_sender.send(RedrawEvent::NeovideHighlightFonts(highlight_fonts));
+        send_ui(ParallelCommand::RedrawHighlightFonts, self);
+    }
+
     fn send_window_command(&self, command: WindowCommand) {
         let payload = EventPayload::for_route(UserEvent::WindowCommand(command), self.route_id);
         let _ = self.proxy.lock().unwrap().send_event(payload);
@@ -126,7 +173,7 @@ impl Handler for NeovimHandler {
         &self,
         event_name: String,
         arguments: Vec<Value>,
-        _neovim: Neovim<Self::Writer>,
+        neovim: Neovim<Self::Writer>,
     ) -> Result<Value, Value> {
         trace!("Neovim request: {:?}", &event_name);
 
@@ -149,9 +196,23 @@ impl Handler for NeovimHandler {
                     },
                 )
             }
+            "neovide.profile_names" => {
+                Ok(Value::from(profile_names().into_iter().map(Value::from).collect::<Vec<_>>()))
+            }
             "neovide.quit" => {
                 let error_code =
                     arguments[0].as_i64().expect("Could not parse error code from neovim");
+                // Sent from VimLeavePre, so Neovim can still write the session
+                if session_enabled(&self.settings) {
+                    let session_file = session_file(self.route_id);
+                    match save_session(&neovim, &session_file).await {
+                        Ok(directory) => self.send_window_command(WindowCommand::SessionSaved {
+                            session_file,
+                            directory,
+                        }),
+                        Err(error) => warn!("Could not save the session: {error:?}"),
+                    }
+                }
                 self.running_tracker.quit_with_code(error_code as u8, "Quit from neovim");
                 Ok(Value::Nil)
             }
@@ -168,29 +229,26 @@ impl Handler for NeovimHandler {
         trace!("Neovim notification: {:?}", &event_name);
 
         match event_name.as_ref() {
+            DECODED_REDRAW_NOTIFICATION => {
+                let decoded = arguments
+                    .first()
+                    .and_then(Value::as_u64)
+                    .and_then(|id| self.decoded_redraws.take(id));
+                match decoded {
+                    Some(parsed_events) => {
+                        let parsed_events = parsed_events
+                            .unwrap_or_explained_panic("Could not parse event from neovim");
+                        self.handle_redraw_events(parsed_events, &neovim).await;
+                    }
+                    None => warn!("No decoded redraw events for {arguments:?}"),
+                }
+            }
+            // Only used when the stream from Neovim couldn't be decoded by the redraw reader
             "redraw" => {
                 for events in arguments {
                     let parsed_events = parse_redraw_event(events)
                         .unwrap_or_explained_panic("Could not parse event from neovim");
-
-                    for parsed_event in parsed_events {
-                        if skip_default_guifont(&parsed_event, &self.settings, &neovim).await {
-                            continue;
-                        }
-
-                        match parsed_event {
-                            RedrawEvent::Restart { details } => {
-                                let payload = EventPayload::for_route(
-                                    UserEvent::NeovimRestart(details),
-                                    self.route_id,
-                                );
-                                let _ = self.proxy.lock().unwrap().send_event(payload);
-                            }
-                            _ => {
-                                let _ = self.redraw_event_sender.send(parsed_event);
-                            }
-                        }
-                    }
+                    self.handle_redraw_events(parsed_events, &neovim).await;
                 }
             }
             "setting_changed" => {
@@ -218,6 +276,49 @@ impl Handler for NeovimHandler {
             "neovide.focus_window" => {
                 self.send_window_command(WindowCommand::FocusWindow);
             }
+            "neovide.show_settings" => {
+                self.send_window_command(WindowCommand::ShowSettingsPanel);
+            }
+            "neovide.register_command" => {
+                match (arguments.first().and_then(Value::as_str), arguments.get(1)) {
+                    (Some(title), Some(Value::Boolean(registered))) => {
+                        self.send_window_command(WindowCommand::RegisterPaletteCommand {
+                            title: title.to_owned(),
+                            registered: *registered,
+                        });
+                    }
+                    _ => warn!(
+                        "neovide.register_command called with invalid arguments: {arguments:?}"
+                    ),
+                }
+            }
+            "neovide.bind" => {
+                let action = arguments.get(1).unwrap_or(&Value::Nil);
+                match arguments.first().and_then(Value::as_str) {
+                    Some(keys) if action.is_nil() || action.is_str() => {
+                        self.send_window_command(WindowCommand::BindKey {
+                            keys: keys.to_owned(),
+                            action: action.as_str().map(str::to_owned),
+                        });
+                    }
+                    _ => warn!("neovide.bind called with invalid arguments: {arguments:?}"),
+                }
+            }
+            "neovide.set_hl_font" => {
+                let fonts = match arguments.get(1).unwrap_or(&Value::Nil) {
+                    Value::Nil => Some(None),
+                    fonts => parse_font_descriptions(fonts).map(Some),
+                };
+                match (arguments.first().and_then(Value::as_str), fonts) {
+                    (Some(group), Some(fonts)) => {
+                        self.send_window_command(WindowCommand::SetHighlightFont {
+                            group: group.to_owned(),
+                            fonts,
+                        });
+                    }
+                    _ => warn!("neovide.set_hl_font called with invalid arguments: {arguments:?}"),
+                }
+            }
             #[cfg(target_os = "macos")]
             "neovide.force_click" => match parse_force_click_args(&arguments) {
                 Some((col, row, entity, guifont, kind)) => {
@@ -249,6 +350,22 @@ impl Handler for NeovimHandler {
                     }
                 }
             }
+            "neovide.set_profile" => match arguments.first().and_then(Value::as_str) {
+                Some(profile) => switch_profile(profile),
+                None => warn!("neovide.set_profile called with invalid arguments: {arguments:?}"),
+            },
+            "neovide.local_settings" => {
+                match parse_local_settings(arguments.first(), &self.settings) {
+                    Some(local_settings) => {
+                        let _ = self
+                            .redraw_event_sender
+                            .send(RedrawEvent::NeovideLocalSettings(local_settings));
+                    }
+                    None => {
+                        warn!("neovide.local_settings called with invalid arguments: {arguments:?}")
+                    }
+                }
+            }
             "neovide.progress_bar" => {
                 parse_progress_bar_event(arguments.first())
                     .map(|event| {
@@ -270,6 +387,62 @@ impl Handler for NeovimHandler {
     }
 }
 
+// The local settings are sent as a list of `{ window = handle, settings = { name = value } }`
+fn parse_local_settings(
+    value: Option<&Value>,
+    settings: &Settings,
+) -> Option<HashMap<u64, LocalOverrides>> {
+    value?
+        .as_array()?
+        .iter()
+        .map(|entry| {
+            let entry = entry.as_map()?;
+            let field = |name: &str| entry.iter().find(|(key, _)| key.as_str() == Some(name));
+            let (_, window) = field("window")?;
+            let (_, values) = field("settings")?;
+            let values = values
+                .as_map()?
+                .iter()
+                .map(|(name, value)| Some((name.as_str()?.to_owned(), value.clone())))
+                .collect::<Option<Vec<_>>>()?;
+            Some((window.as_u64()?, settings.parse_local_overrides(values)))
+        })
+        .collect()
+}
+
+/// Parses the fonts of `neovide.set_hl_font`, which are a family, a table with a family, a style
+/// and axes, or a list of those.
+fn parse_font_descriptions(value: &Value) -> Option<Vec<FontDescription>> {
+    match value {
+        Value::Array(values) => values.iter().map(parse_font_description).collect(),
+        value => parse_font_description(value).map(|font| vec![font]),
+    }
+}
+
+fn parse_font_description(value: &Value) -> Option<FontDescription> {
+    if let Some(family) = value.as_str() {
+        return Some(FontDescription { family: family.to_owned(), ..Default::default() });
+    }
+
+    let mut font = FontDescription::default();
+    let mut family = None;
+    for (key, value) in value.as_map()? {
+        match key.as_str()? {
+            "family" => family = Some(value.as_str()?.to_owned()),
+            "style" => font.style = Some(value.as_str()?.to_owned()),
+            "axes" => {
+                for (tag, value) in value.as_map()? {
+                    let tag = tag.as_str().filter(|tag| tag.len() == 4)?;
+                    font.axes.set(tag, value.as_f64()? as f32);
+                }
+            }
+            _ => return None,
+        }
+    }
+    font.family = family?;
+    Some(font)
+}
+
 #[cfg(target_os = "macos")]
 fn parse_force_click_args(
     arguments: &[Value],
@@ -3,6 +3,7 @@ mod clipboard;
 mod command;
 mod events;
 mod handler;
+mod redraw_decoder;
 mod restart;
 pub mod session;
 mod setup;
@@ -11,16 +12,19 @@ mod ui_commands;
 use std::{
     io::Error,
     ops::Add,
+    path::{Path, PathBuf},
     sync::{Arc, Mutex},
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use crate::{
     clipboard::ClipboardHandle,
     cmd_line::CmdLineSettings,
     editor::start_editor_handler,
+    renderer::fonts::highlight_fonts::HighlightFonts,
     running_tracker::RunningTracker,
     settings::*,
+    startup_time,
     units::GridSize,
     window::{EventPayload, RouteId, UserEvent, WindowSettings},
 };
@@ -31,12 +35,14 @@ use itertools::Itertools;
 use log::info;
 use mundy::{Interest, Preferences};
 use nvim_rs::{Neovim, UiAttachOptions, Value, error::CallError};
+use redraw_decoder::{RedrawDecoding, RedrawDump};
 use rmpv::Utf8String;
 use session::{NeovimInstance, NeovimSession};
 use setup::{get_api_information, setup_neovide_specific_state};
 use tokio::{
     runtime::{Builder, Runtime},
     select,
+    task::JoinHandle,
     time::timeout,
 };
 use winit::event_loop::EventLoopProxy;
@@ -49,8 +55,8 @@ pub use session::NeovimWriter;
 #[cfg(target_os = "macos")]
 pub use ui_commands::send_or_queue_file_drop;
 pub use ui_commands::{
-    ParallelCommand, SerialCommand, require_active_handler, send_ui, set_active_route_handler,
-    start_ui_command_handler, unregister_route_handler,
+    ParallelCommand, SerialCommand, require_active_handler, save_session, send_ui,
+    set_active_route_handler, start_ui_command_handler, unregister_route_handler,
 };
 
 const NEOVIM_REQUIRED_VERSION: (u64, u64, u64) = (0, 10, 0);
@@ -85,6 +91,12 @@ async fn nvim_exec_output(
         .to_string())
 }
 
+/// A Neovim instance that is being started by [`NeovimRuntime::launch`].
+pub struct Launch {
+    pub handler: NeovimHandler,
+    session: JoinHandle<Result<()>>,
+}
+
 pub struct NeovimRuntime {
     pub runtime: Option<Runtime>,
     clipboard: ClipboardHandle,
@@ -138,13 +150,21 @@ async fn create_neovim_session(
     background: &str,
     restart_details: Option<&RestartDetails>,
 ) -> Result<NeovimSession> {
+    let spawn_start = Instant::now();
     let neovim_instance = neovim_instance(settings.as_ref(), restart_details).await?;
+    let redraw_decoding = RedrawDecoding {
+        decoded: handler.decoded_redraws(),
+        dump: settings.get::<CmdLineSettings>().redraw_dump.as_deref().and_then(RedrawDump::open),
+    };
     #[allow(unused_mut)]
-    let mut session = NeovimSession::new(neovim_instance, handler.clone())
+    let mut session = NeovimSession::new(neovim_instance, handler.clone(), Some(redraw_decoding))
         .await
         .context("Could not locate or start neovim process")?;
+    startup_time::record("neovim spawn", spawn_start);
 
+    let handshake_start = Instant::now();
     let api_information = get_api_information(&session.neovim).await?;
+    startup_time::record("neovim handshake", handshake_start);
     info!("Neovide registered to nvim with channel id {}", api_information.channel);
 
     let (major, minor, patch) = NEOVIM_REQUIRED_VERSION;
@@ -186,8 +206,11 @@ async fn create_neovim_session(
     options.set_linegrid_external(true);
     options.set_multigrid_external(!cmdline_settings.no_multi_grid);
     options.set_rgb(true);
-    #[cfg(target_os = "macos")]
-    options.set_hlstate_external(true);
+    // The highlight groups are needed for their fonts, and for the matching parens on macOS. When
+    // the first group gets a font later, it's enabled by `ParallelCommand::RedrawHighlightFonts`.
+    options.set_hlstate_external(
+        cfg!(target_os = "macos") || !settings.get::<HighlightFonts>().is_empty(),
+    );
     // We can close the handle here, as Neovim already owns it
     #[cfg(not(target_os = "windows"))]
     if let Some(fd) = session.stdin_fd.take() {
@@ -200,11 +223,13 @@ async fn create_neovim_session(
     // Triggers loading the user config
 
     let grid_size = grid_size.map_or(DEFAULT_GRID_SIZE, |v| clamped_grid_size(&v));
+    let attach_start = Instant::now();
     let res = session
         .neovim
         .ui_attach(grid_size.width as i64, grid_size.height as i64, &options)
         .await
         .context("Could not attach ui to neovim process");
+    startup_time::record("ui_attach", attach_start);
 
     info!("Neovim process attached");
     res.map(|()| session)
@@ -311,6 +336,9 @@ impl NeovimRuntime {
         })
     }
 
+    /// Starts Neovim in the background, so that the window can be created in the meantime. The
+    /// handler of the launch can be used right away, the commands sent to it are queued until
+    /// Neovim is attached.
     pub fn launch(
         &mut self,
         route_id: RouteId,
@@ -319,7 +347,7 @@ impl NeovimRuntime {
         running_tracker: RunningTracker,
         settings: Arc<Settings>,
         config: &Config,
-    ) -> Result<NeovimHandler> {
+    ) -> Launch {
         let mut colorscheme_stream = self.colorscheme_stream();
         let editor_handler = start_editor_handler(
             route_id,
@@ -328,40 +356,42 @@ impl NeovimRuntime {
             settings.clone(),
             self.clipboard.clone(),
         );
-        let initial_background =
-            self.runtime().block_on(initial_background_from_stream(&mut colorscheme_stream));
-        self.set_background_preference(&initial_background);
 
         let mut font_config_state = settings.get::<FontConfigState>();
         font_config_state.has_font = config.font.is_some();
         settings.set(&font_config_state);
 
-        let session = match self.runtime().block_on(create_neovim_session(
-            route_id,
-            editor_handler.clone(),
-            grid_size,
-            settings,
-            &initial_background,
-            None,
-        )) {
-            Ok(session) => session,
-            Err(err) => {
-                self.runtime().block_on(async move {
-                    drop(colorscheme_stream);
-                });
-                return Err(err);
+        let handler = editor_handler.clone();
+        let background_preference = self.background_preference.clone();
+        let session = self.runtime().spawn(async move {
+            let initial_background = initial_background_from_stream(&mut colorscheme_stream).await;
+            if let Ok(mut guard) = background_preference.lock() {
+                guard.clear();
+                guard.push_str(&initial_background);
             }
-        };
-
-        self.runtime().spawn(update_colorscheme(
-            colorscheme_stream,
-            self.background_preference.clone(),
-            editor_handler.clone(),
-        ));
 
-        self.runtime().spawn(run(route_id, session, event_loop_proxy));
+            let session = create_neovim_session(
+                route_id,
+                handler.clone(),
+                grid_size,
+                settings,
+                &initial_background,
+                None,
+            )
+            .await?;
+
+            tokio::spawn(update_colorscheme(colorscheme_stream, background_preference, handler));
+            tokio::spawn(run(route_id, session, event_loop_proxy));
+            Ok(())
+        });
+
+        Launch { handler: editor_handler, session }
+    }
 
-        Ok(editor_handler)
+    /// Waits until the Neovim instance of the launch is attached.
+    pub fn wait_for_launch(&self, launch: Launch) -> Result<NeovimHandler> {
+        self.runtime().block_on(launch.session)??;
+        Ok(launch.handler)
     }
 
     pub fn shutdown(mut self, timeout: Duration) {
@@ -392,6 +422,22 @@ impl NeovimRuntime {
         Ok(())
     }
 
+    /// Writes the `:mksession` file of a window from the event loop, waiting at most `duration` for
+    /// Neovim. Returns the working directory of Neovim.
+    pub fn save_session(
+        &self,
+        handler: &NeovimHandler,
+        session_file: &Path,
+        duration: Duration,
+    ) -> Result<PathBuf> {
+        let nvim = handler.clone_current_neovim().context("Neovim is not running")?;
+        self.runtime().block_on(async {
+            timeout(duration, save_session(&nvim, session_file))
+                .await
+                .context("Neovim didn't respond")?
+        })
+    }
+
     fn runtime(&self) -> &Runtime {
         self.runtime.as_ref().expect("runtime must be available while NeovimRuntime is alive")
     }
@@ -407,13 +453,6 @@ impl NeovimRuntime {
         Preferences::stream(Interest::ColorScheme)
     }
 
-    fn set_background_preference(&self, background: &str) {
-        if let Ok(mut guard) = self.background_preference.lock() {
-            guard.clear();
-            guard.push_str(background);
-        }
-    }
-
     fn current_background(&self) -> String {
         self.background_preference
             .lock()
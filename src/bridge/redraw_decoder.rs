@@ -0,0 +1,844 @@
+//! Decodes the redraw notifications straight from the bytes Neovim sends.
+//!
+//! The RPC client decodes every message into an [`rmpv::Value`] tree before the handler sees it,
+//! which allocates a `String` and a `Vec` for every cell of every `grid_line` event. The
+//! [`RedrawReader`] sits between the Neovim connection and the RPC client instead. It frames the
+//! messages, decodes the redraw notifications into [`RedrawEvent`]s without building a `Value`
+//! tree, and forwards a small placeholder notification in their place, so that the handler still
+//! sees them in order with the other messages. All other messages are passed through unchanged.
+
+use std::{
+    collections::{HashSet, VecDeque},
+    fs::{File, OpenOptions},
+    io::{BufWriter, Write},
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use log::warn;
+use rmpv::Value;
+use tokio::io::{AsyncRead, ReadBuf};
+
+use super::{
+    events::{CellText, GridLineCell, ParseError, RedrawEvent, parse_event, parse_redraw_event},
+    session::HANDSHAKE_MESSAGE,
+};
+
+/// The notification forwarded to the handler in place of a decoded redraw notification. Its only
+/// parameter is the id of the batch in [`DecodedRedraws`].
+pub const DECODED_REDRAW_NOTIFICATION: &str = "neovide.decoded_redraw";
+
+/// How much is read from Neovim at once.
+const READ_SIZE: usize = 64 * 1024;
+
+/// Texts other than single ASCII characters that are remembered before the interner starts over.
+const MAX_INTERNED_TEXTS: usize = 4096;
+
+/// How often the redraw dump is flushed to disk.
+const DUMP_FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+type DecodeResult<T> = Result<T, DecodeError>;
+
+#[derive(Debug, PartialEq, Eq)]
+enum DecodeError {
+    /// The data ends before the value does.
+    Incomplete,
+    /// The data isn't valid msgpack, or the value doesn't have the expected type.
+    Invalid,
+}
+
+/// Reads msgpack values from a byte slice, borrowing strings from it.
+struct Reader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, position: 0 }
+    }
+
+    fn remaining(&self) -> &'a [u8] {
+        &self.data[self.position..]
+    }
+
+    fn take(&mut self, length: usize) -> DecodeResult<&'a [u8]> {
+        let bytes = self.remaining().get(..length).ok_or(DecodeError::Incomplete)?;
+        self.position += length;
+        Ok(bytes)
+    }
+
+    fn byte(&mut self) -> DecodeResult<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn uint(&mut self, size: usize) -> DecodeResult<u64> {
+        Ok(self.take(size)?.iter().fold(0, |value, byte| value << 8 | u64::from(*byte)))
+    }
+
+    fn int(&mut self, size: usize) -> DecodeResult<i64> {
+        // Sign extend the big endian value
+        let shift = 64 - 8 * size as u32;
+        Ok(((self.uint(size)? << shift) as i64) >> shift)
+    }
+
+    fn array_len(&mut self) -> DecodeResult<usize> {
+        match self.byte()? {
+            marker @ 0x90..=0x9f => Ok(usize::from(marker & 0x0f)),
+            0xdc => Ok(self.uint(2)? as usize),
+            0xdd => Ok(self.uint(4)? as usize),
+            _ => Err(DecodeError::Invalid),
+        }
+    }
+
+    fn str(&mut self) -> DecodeResult<&'a str> {
+        let length = match self.byte()? {
+            marker @ 0xa0..=0xbf => usize::from(marker & 0x1f),
+            0xd9 => self.uint(1)? as usize,
+            0xda => self.uint(2)? as usize,
+            0xdb => self.uint(4)? as usize,
+            _ => return Err(DecodeError::Invalid),
+        };
+        std::str::from_utf8(self.take(length)?).map_err(|_| DecodeError::Invalid)
+    }
+
+    /// Reads any non-negative integer, like `u64::try_from(Value)` does.
+    fn u64(&mut self) -> DecodeResult<u64> {
+        let value = match self.byte()? {
+            marker @ 0x00..=0x7f => u64::from(marker),
+            marker @ 0xcc..=0xcf => self.uint(1 << (marker - 0xcc))?,
+            marker @ 0xd0..=0xd3 => {
+                u64::try_from(self.int(1 << (marker - 0xd0))?).map_err(|_| DecodeError::Invalid)?
+            }
+            _ => return Err(DecodeError::Invalid),
+        };
+        Ok(value)
+    }
+
+    /// Reads the header of the next value. Returns the number of values it contains, which still
+    /// have to be read.
+    fn skip_header(&mut self) -> DecodeResult<usize> {
+        let (children, length) = match self.byte()? {
+            0x00..=0x7f | 0xe0..=0xff | 0xc0 | 0xc2 | 0xc3 => (0, 0),
+            marker @ 0x80..=0x8f => (2 * usize::from(marker & 0x0f), 0),
+            marker @ 0x90..=0x9f => (usize::from(marker & 0x0f), 0),
+            marker @ 0xa0..=0xbf => (0, usize::from(marker & 0x1f)),
+            0xc4 | 0xd9 => (0, self.uint(1)? as usize),
+            0xc5 | 0xda => (0, self.uint(2)? as usize),
+            0xc6 | 0xdb => (0, self.uint(4)? as usize),
+            0xc7 => (0, self.uint(1)? as usize + 1),
+            0xc8 => (0, self.uint(2)? as usize + 1),
+            0xc9 => (0, self.uint(4)? as usize + 1),
+            0xca => (0, 4),
+            0xcb => (0, 8),
+            marker @ 0xcc..=0xd3 => (0, 1 << (marker & 0x03)),
+            marker @ 0xd4..=0xd8 => (0, (1 << (marker - 0xd4)) + 1),
+            0xdc => (self.uint(2)? as usize, 0),
+            0xdd => (self.uint(4)? as usize, 0),
+            0xde => (2 * self.uint(2)? as usize, 0),
+            0xdf => (2 * self.uint(4)? as usize, 0),
+            0xc1 => return Err(DecodeError::Invalid),
+        };
+        self.take(length)?;
+        Ok(children)
+    }
+
+    fn skip(&mut self) -> DecodeResult<()> {
+        let mut remaining = 1;
+        while remaining > 0 {
+            remaining = remaining - 1 + self.skip_header()?;
+        }
+        Ok(())
+    }
+
+    fn value(&mut self) -> DecodeResult<Value> {
+        let mut remaining = self.remaining();
+        let value = rmpv::decode::read_value(&mut remaining).map_err(|_| DecodeError::Invalid)?;
+        self.position = self.data.len() - remaining.len();
+        Ok(value)
+    }
+}
+
+/// Finds where the messages end, without decoding them. Remembers how far it got into a
+/// message, so that a large message arriving in many reads is only scanned once.
+#[derive(Default)]
+struct Framer {
+    /// How far into the current message the values have been read.
+    position: usize,
+    /// The values left to read before the current message ends.
+    remaining: usize,
+}
+
+impl Framer {
+    /// Returns the length of the message at the start of `data`, or `None` if it's incomplete.
+    fn message_len(&mut self, data: &[u8]) -> DecodeResult<Option<usize>> {
+        if self.position == 0 {
+            self.remaining = 1;
+        }
+        while self.remaining > 0 {
+            let mut reader = Reader::new(data);
+            reader.position = self.position;
+            match reader.skip_header() {
+                Ok(children) => {
+                    self.position = reader.position;
+                    self.remaining = self.remaining - 1 + children;
+                }
+                Err(DecodeError::Incomplete) => return Ok(None),
+                Err(error) => return Err(error),
+            }
+        }
+        Ok(Some(std::mem::take(&mut self.position)))
+    }
+}
+
+/// Returns the parameters of a `[2, "redraw", params]` notification.
+fn redraw_params(message: &[u8]) -> Option<&[u8]> {
+    let mut reader = Reader::new(message);
+    let is_redraw =
+        reader.array_len().ok()? == 3 && reader.u64().ok()? == 2 && reader.str().ok()? == "redraw";
+    is_redraw.then(|| reader.remaining())
+}
+
+/// Shares the texts of the cells, so that a line of text isn't one allocation per cell.
+struct CellTextInterner {
+    ascii: [CellText; 128],
+    texts: HashSet<CellText>,
+}
+
+impl CellTextInterner {
+    fn new() -> Self {
+        Self {
+            ascii: std::array::from_fn(|byte| char::from(byte as u8).to_string().into()),
+            texts: HashSet::new(),
+        }
+    }
+
+    fn intern(&mut self, text: &str) -> CellText {
+        if let [byte @ 0..=0x7f] = text.as_bytes() {
+            return self.ascii[usize::from(*byte)].clone();
+        }
+        if let Some(text) = self.texts.get(text) {
+            return text.clone();
+        }
+        if self.texts.len() >= MAX_INTERNED_TEXTS {
+            self.texts.clear();
+        }
+        let text = CellText::from(text);
+        self.texts.insert(text.clone());
+        text
+    }
+}
+
+/// Decodes the parameters of redraw notifications into [`RedrawEvent`]s.
+struct RedrawDecoder {
+    interner: CellTextInterner,
+}
+
+impl RedrawDecoder {
+    fn new() -> Self {
+        Self { interner: CellTextInterner::new() }
+    }
+
+    /// Decodes the parameters of a redraw notification, an array of `[name, params...]` event
+    /// batches.
+    fn decode(&mut self, params: &[u8]) -> Result<Vec<RedrawEvent>, ParseError> {
+        let mut reader = Reader::new(params);
+        let batch_count = reader
+            .array_len()
+            .map_err(|_| ParseError::Format("invalid redraw notification".to_owned()))?;
+        let mut events = Vec::new();
+        for _ in 0..batch_count {
+            let start = reader.position;
+            let event_count = events.len();
+            if self.decode_batch(&mut reader, &mut events).is_err() {
+                // The value parser handles every format it accepts, and explains what's wrong with
+                // the others, including the event name and the value it failed on.
+                events.truncate(event_count);
+                reader.position = start;
+                let batch = reader.value().map_err(|_| {
+                    ParseError::Format(format!("invalid msgpack in redraw batch at byte {start}"))
+                })?;
+                events.extend(parse_redraw_event(batch)?);
+            }
+        }
+        Ok(events)
+    }
+
+    fn decode_batch(
+        &mut self,
+        reader: &mut Reader,
+        events: &mut Vec<RedrawEvent>,
+    ) -> DecodeResult<()> {
+        let length = reader.array_len()?;
+        if length == 0 {
+            return Err(DecodeError::Invalid);
+        }
+        let name = reader.str()?;
+        events.reserve(length - 1);
+        for _ in 1..length {
+            if name == "grid_line" {
+                events.push(self.decode_grid_line(reader)?);
+                continue;
+            }
+            let Value::Array(params) = reader.value()? else {
+                return Err(DecodeError::Invalid);
+            };
+            match parse_event(name, params) {
+                Some(Ok(event)) => events.push(event),
+                Some(Err(_)) => return Err(DecodeError::Invalid),
+                None => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn decode_grid_line(&mut self, reader: &mut Reader) -> DecodeResult<RedrawEvent> {
+        let length = reader.array_len()?;
+        if length < 4 {
+            return Err(DecodeError::Invalid);
+        }
+        let grid = reader.u64()?;
+        let row = reader.u64()?;
+        let column_start = reader.u64()?;
+        let cell_count = reader.array_len()?;
+        let mut cells = Vec::with_capacity(cell_count);
+        for _ in 0..cell_count {
+            let cell_length = reader.array_len()?;
+            if cell_length == 0 {
+                return Err(DecodeError::Invalid);
+            }
+            let text = self.interner.intern(reader.str()?);
+            let highlight_id = if cell_length > 1 { Some(reader.u64()?) } else { None };
+            let repeat = if cell_length > 2 { Some(reader.u64()?) } else { None };
+            for _ in 3..cell_length {
+                reader.skip()?;
+            }
+            cells.push(GridLineCell { text, highlight_id, repeat });
+        }
+        // The wrap flag isn't used
+        for _ in 4..length {
+            reader.skip()?;
+        }
+        Ok(RedrawEvent::GridLine { grid, row, column_start, cells })
+    }
+}
+
+/// The redraw batches decoded by a [`RedrawReader`], until the handler takes them.
+#[derive(Clone, Default)]
+pub struct DecodedRedraws {
+    batches: Arc<Mutex<DecodedBatches>>,
+}
+
+#[derive(Default)]
+struct DecodedBatches {
+    next_id: u64,
+    batches: VecDeque<(u64, Result<Vec<RedrawEvent>, ParseError>)>,
+}
+
+impl DecodedRedraws {
+    fn push(&self, batch: Result<Vec<RedrawEvent>, ParseError>) -> u64 {
+        let mut decoded = self.batches.lock().unwrap();
+        let id = decoded.next_id;
+        decoded.next_id += 1;
+        decoded.batches.push_back((id, batch));
+        id
+    }
+
+    /// Takes the batch with the given id. The batches are taken in order, so the older ones left
+    /// by a session that has ended are dropped.
+    pub fn take(&self, id: u64) -> Option<Result<Vec<RedrawEvent>, ParseError>> {
+        let mut decoded = self.batches.lock().unwrap();
+        while let Some((batch_id, _)) = decoded.batches.front() {
+            if *batch_id >= id {
+                break;
+            }
+            decoded.batches.pop_front();
+        }
+        match decoded.batches.front() {
+            Some((batch_id, _)) if *batch_id == id => {
+                decoded.batches.pop_front().map(|(_, batch)| batch)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Records the redraw events received from Neovim, for `--redraw-dump`.
+pub struct RedrawDump {
+    writer: BufWriter<File>,
+    last_flush: Instant,
+}
+
+impl RedrawDump {
+    pub fn open(path: &str) -> Option<Self> {
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .inspect_err(|error| warn!("Could not open the redraw dump {path}: {error}"))
+            .ok()
+            .map(|file| Self { writer: BufWriter::new(file), last_flush: Instant::now() })
+    }
+
+    /// Appends the event batches of a redraw notification, each one a msgpack array.
+    fn record(&mut self, params: &[u8]) {
+        let mut reader = Reader::new(params);
+        if reader.array_len().is_err() {
+            return;
+        }
+        // Flushing on every notification would slow down the reader, so it's done once in a
+        // while, and when the dump is dropped.
+        let mut result = self.writer.write_all(reader.remaining());
+        if result.is_ok() && self.last_flush.elapsed() >= DUMP_FLUSH_INTERVAL {
+            self.last_flush = Instant::now();
+            result = self.writer.flush();
+        }
+        if let Err(error) = result {
+            warn!("Could not record the redraw events: {error}");
+        }
+    }
+}
+
+impl Drop for RedrawDump {
+    fn drop(&mut self) {
+        if let Err(error) = self.writer.flush() {
+            warn!("Could not record the redraw events: {error}");
+        }
+    }
+}
+
+/// What a session needs to decode the redraw notifications itself.
+pub struct RedrawDecoding {
+    pub decoded: DecodedRedraws,
+    pub dump: Option<RedrawDump>,
+}
+
+enum ReaderState {
+    /// Waiting for the end of the handshake, after which the stream contains whole msgpack
+    /// messages. Anything Neovim sends before it is passed through.
+    Handshake,
+    /// Splitting the stream into messages.
+    Framing(Framer),
+    /// The stream couldn't be framed, so everything is passed through for the RPC client to
+    /// handle.
+    Passthrough,
+}
+
+/// Wraps the connection to Neovim, see the module documentation.
+pub struct RedrawReader<R> {
+    inner: R,
+    state: ReaderState,
+    decoder: RedrawDecoder,
+    decoded: DecodedRedraws,
+    dump: Option<RedrawDump>,
+    /// The bytes read from Neovim that haven't been handled yet, starting at `input_start`.
+    input: Vec<u8>,
+    input_start: usize,
+    /// The bytes to pass to the RPC client, starting at `output_start`.
+    output: Vec<u8>,
+    output_start: usize,
+    eof: bool,
+}
+
+impl<R> RedrawReader<R> {
+    pub fn new(inner: R, decoding: RedrawDecoding) -> Self {
+        Self {
+            inner,
+            state: ReaderState::Handshake,
+            decoder: RedrawDecoder::new(),
+            decoded: decoding.decoded,
+            dump: decoding.dump,
+            input: Vec::with_capacity(READ_SIZE),
+            input_start: 0,
+            output: Vec::with_capacity(READ_SIZE),
+            output_start: 0,
+            eof: false,
+        }
+    }
+
+    fn pass_through(&mut self, end: usize) {
+        self.output.extend_from_slice(&self.input[self.input_start..end]);
+        self.input_start = end;
+    }
+
+    /// Moves what can be handled from the input to the output.
+    fn process_input(&mut self) {
+        if let ReaderState::Handshake = self.state {
+            let handshake = HANDSHAKE_MESSAGE.as_bytes();
+            let input = &self.input[self.input_start..];
+            if let Some(position) =
+                input.windows(handshake.len()).position(|bytes| bytes == handshake)
+            {
+                self.pass_through(self.input_start + position + handshake.len());
+                self.state = ReaderState::Framing(Framer::default());
+            } else if self.eof {
+                self.pass_through(self.input.len());
+            } else {
+                // Keep what could be the start of the handshake message
+                let keep = input.len().min(handshake.len() - 1);
+                self.pass_through(self.input.len() - keep);
+            }
+        }
+
+        if let ReaderState::Framing(framer) = &mut self.state {
+            loop {
+                let input = &self.input[self.input_start..];
+                match framer.message_len(input) {
+                    Ok(Some(length)) => {
+                        let message = &input[..length];
+                        if let Some(params) = redraw_params(message) {
+                            if let Some(dump) = &mut self.dump {
+                                dump.record(params);
+                            }
+                            let id = self.decoded.push(self.decoder.decode(params));
+                            let placeholder = Value::Array(vec![
+                                Value::from(2),
+                                Value::from(DECODED_REDRAW_NOTIFICATION),
+                                Value::Array(vec![Value::from(id)]),
+                            ]);
+                            rmpv::encode::write_value(&mut self.output, &placeholder)
+                                .expect("Writing to a Vec can't fail");
+                        } else {
+                            self.output.extend_from_slice(message);
+                        }
+                        self.input_start += length;
+                    }
+                    Ok(None) => break,
+                    Err(_) => {
+                        warn!("Could not frame the messages from Neovim, passing them through");
+                        self.state = ReaderState::Passthrough;
+                        break;
+                    }
+                }
+            }
+        }
+
+        if let ReaderState::Passthrough = self.state {
+            self.pass_through(self.input.len());
+        }
+        // An incomplete message at the end of the input stays there, anything else is handled.
+        // At the end of the stream it's passed through, so that the RPC client reports it.
+        if self.eof {
+            self.pass_through(self.input.len());
+        }
+
+        self.input.drain(..self.input_start);
+        self.input_start = 0;
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for RedrawReader<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        loop {
+            if this.output_start < this.output.len() {
+                let output = &this.output[this.output_start..];
+                let length = output.len().min(buf.remaining());
+                buf.put_slice(&output[..length]);
+                this.output_start += length;
+                if this.output_start == this.output.len() {
+                    this.output.clear();
+                    this.output_start = 0;
+                }
+                return Poll::Ready(Ok(()));
+            }
+            if this.eof {
+                return Poll::Ready(Ok(()));
+            }
+
+            let filled = this.input.len();
+            this.input.resize(filled + READ_SIZE, 0);
+            let mut read_buf = ReadBuf::new(&mut this.input[filled..]);
+            let result = Pin::new(&mut this.inner).poll_read(cx, &mut read_buf);
+            let read = read_buf.filled().len();
+            this.input.truncate(filled + read);
+            match result {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(Err(error)) => return Poll::Ready(Err(error)),
+                Poll::Ready(Ok(())) => {
+                    this.eof = read == 0;
+                    this.process_input();
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::AsyncReadExt;
+
+    use super::*;
+
+    fn cell(text: &str, highlight_id: Option<u64>, repeat: Option<u64>) -> Value {
+        let mut cell = vec![Value::from(text)];
+        cell.extend(highlight_id.or(repeat.map(|_| 0)).map(Value::from));
+        cell.extend(repeat.map(Value::from));
+        Value::from(cell)
+    }
+
+    fn grid_line(row: u64, cells: Vec<Value>) -> Value {
+        Value::from(vec![
+            Value::from(1),
+            Value::from(row),
+            Value::from(0),
+            Value::from(cells),
+            Value::from(false),
+        ])
+    }
+
+    /// Scrolling through a log file, redrawing every line of the grid each frame.
+    fn scroll_dump(columns: usize, rows: u64, frames: usize) -> Vec<Value> {
+        (0..frames)
+            .map(|frame| {
+                let mut events = vec![Value::from("grid_line")];
+                events.extend((0..rows).map(|row| {
+                    let line = format!("[{frame:06}:{row:03}] INFO request handled in 12ms → ");
+                    let text = line.repeat(columns / line.chars().count());
+                    let mut cells = text
+                        .chars()
+                        .enumerate()
+                        .map(|(column, ch)| cell(&ch.to_string(), (column == 0).then_some(7), None))
+                        .collect::<Vec<_>>();
+                    let repeat = columns - text.chars().count();
+                    cells.push(cell(" ", None, Some(repeat as u64)));
+                    grid_line(row, cells)
+                }));
+                Value::from(events)
+            })
+            .collect()
+    }
+
+    fn other_events() -> Value {
+        Value::from(vec![
+            Value::from(vec![
+                Value::from("grid_cursor_goto"),
+                Value::from(vec![Value::from(1), Value::from(3), Value::from(4)]),
+            ]),
+            Value::from(vec![
+                Value::from("win_pos"),
+                Value::from(vec![
+                    Value::from(2),
+                    Value::Ext(1, vec![0xcd, 0x03, 0xe8]),
+                    Value::from(0),
+                    Value::from(0),
+                    Value::from(80),
+                    Value::from(20),
+                ]),
+            ]),
+            Value::from(vec![Value::from("win_extmark"), Value::from(vec![Value::from(1)])]),
+            Value::from(vec![Value::from("flush"), Value::from(Vec::<Value>::new())]),
+        ])
+    }
+
+    fn encode(value: &Value) -> Vec<u8> {
+        let mut data = Vec::new();
+        rmpv::encode::write_value(&mut data, value).unwrap();
+        data
+    }
+
+    fn notification(method: &str, params: Value) -> Vec<u8> {
+        encode(&Value::from(vec![Value::from(2), Value::from(method), params]))
+    }
+
+    /// Parses the batches the way the RPC client and the handler do without the decoder.
+    fn parse_values(batches: &[Value]) -> String {
+        let events = batches
+            .iter()
+            .flat_map(|batch| parse_redraw_event(batch.clone()).unwrap())
+            .collect::<Vec<_>>();
+        format!("{events:?}")
+    }
+
+    fn assert_decodes_like_values(batches: Vec<Value>) {
+        let expected = parse_values(&batches);
+        let params = encode(&Value::from(batches));
+        let events = RedrawDecoder::new().decode(&params).unwrap();
+        assert_eq!(format!("{events:?}"), expected);
+    }
+
+    #[test]
+    fn test_decode_matches_value_parser() {
+        assert_decodes_like_values(scroll_dump(120, 10, 3));
+        let Value::Array(batches) = other_events() else { unreachable!() };
+        assert_decodes_like_values(batches);
+    }
+
+    #[test]
+    fn test_decode_interns_cell_text() {
+        let params = encode(&Value::from(vec![Value::from(vec![
+            Value::from("grid_line"),
+            grid_line(0, vec![cell("→", Some(1), None), cell("a", None, None)]),
+            grid_line(1, vec![cell("→", Some(1), None), cell("a", None, None)]),
+        ])]));
+        let events = RedrawDecoder::new().decode(&params).unwrap();
+        let [
+            RedrawEvent::GridLine { cells: first, .. },
+            RedrawEvent::GridLine { cells: second, .. },
+        ] = events.as_slice()
+        else {
+            panic!("Unexpected events {events:?}");
+        };
+        assert!(Arc::ptr_eq(&first[0].text, &second[0].text));
+        assert!(Arc::ptr_eq(&first[1].text, &second[1].text));
+    }
+
+    #[test]
+    fn test_decode_error_names_event_and_value() {
+        let params = encode(&Value::from(vec![Value::from(vec![
+            Value::from("grid_line"),
+            grid_line(0, vec![cell("a", Some(1), None)]),
+            Value::from(vec![Value::from(1), Value::from("row"), Value::from(0), Value::from(1)]),
+        ])]));
+        let error = RedrawDecoder::new().decode(&params).unwrap_err().to_string();
+        assert!(error.contains("grid_line") && error.contains("\"row\""), "{error}");
+    }
+
+    #[test]
+    fn test_framer_resumes_incomplete_messages() {
+        let message = notification("redraw", Value::from(scroll_dump(80, 2, 1)));
+        let mut framer = Framer::default();
+        for end in 1..message.len() {
+            assert_eq!(framer.message_len(&message[..end]), Ok(None));
+        }
+        assert_eq!(framer.message_len(&message), Ok(Some(message.len())));
+        assert_eq!(framer.message_len(&[0xc1]), Err(DecodeError::Invalid));
+    }
+
+    #[tokio::test]
+    async fn test_reader_replaces_redraw_notifications() {
+        let handshake = encode(&Value::from(vec![
+            Value::from(1),
+            Value::from(0),
+            Value::Nil,
+            Value::from(HANDSHAKE_MESSAGE),
+        ]));
+        let option_changed = notification("option_changed", Value::from(vec![Value::from(1)]));
+        let batches = scroll_dump(80, 3, 1);
+        let mut stream = b"junk".to_vec();
+        stream.extend(&handshake);
+        stream.extend(notification("redraw", Value::from(batches.clone())));
+        stream.extend(&option_changed);
+
+        let decoded = DecodedRedraws::default();
+        let decoding = RedrawDecoding { decoded: decoded.clone(), dump: None };
+        // Reading a few bytes at a time splits the messages at every possible place
+        let inner = chunked_reader(stream, 7);
+        let mut reader = RedrawReader::new(inner, decoding);
+        let mut output = Vec::new();
+        reader.read_to_end(&mut output).await.unwrap();
+
+        let mut expected = b"junk".to_vec();
+        expected.extend(&handshake);
+        expected
+            .extend(notification(DECODED_REDRAW_NOTIFICATION, Value::from(vec![Value::from(0)])));
+        expected.extend(&option_changed);
+        assert_eq!(output, expected);
+
+        let events = decoded.take(0).unwrap().unwrap();
+        assert_eq!(format!("{events:?}"), parse_values(&batches));
+        assert!(decoded.take(1).is_none());
+    }
+
+    /// A reader returning at most `chunk` bytes per read.
+    fn chunked_reader(data: Vec<u8>, chunk: usize) -> impl AsyncRead + Unpin {
+        struct Chunked(Vec<u8>, usize, usize);
+        impl AsyncRead for Chunked {
+            fn poll_read(
+                mut self: Pin<&mut Self>,
+                _: &mut Context<'_>,
+                buf: &mut ReadBuf<'_>,
+            ) -> Poll<std::io::Result<()>> {
+                let Chunked(data, position, chunk) = &mut *self;
+                let end = data.len().min(*position + *chunk).min(*position + buf.remaining());
+                buf.put_slice(&data[*position..end]);
+                *position = end;
+                Poll::Ready(Ok(()))
+            }
+        }
+        Chunked(data, 0, chunk)
+    }
+
+    fn read_dump(path: &str) -> Vec<Value> {
+        let data = std::fs::read(path).expect("Could not read the redraw dump");
+        let mut reader = data.as_slice();
+        let mut batches = Vec::new();
+        while !reader.is_empty() {
+            batches.push(rmpv::decode::read_value(&mut reader).expect("Invalid redraw dump"));
+        }
+        batches
+    }
+
+    /// Checks that the decoder agrees with the value parser on real events. Set
+    /// `NEOVIDE_REDRAW_DUMP` to a file recorded with `--redraw-dump` and run it with
+    /// `cargo test decode_redraw_dump -- --ignored`.
+    #[test]
+    #[ignore]
+    fn test_decode_redraw_dump() {
+        let path = std::env::var("NEOVIDE_REDRAW_DUMP").expect("NEOVIDE_REDRAW_DUMP is not set");
+        assert_decodes_like_values(read_dump(&path));
+    }
+
+    /// The fastest of `rounds` runs, after a run to warm up the allocator and the caches.
+    fn fastest(rounds: usize, mut run: impl FnMut()) -> Duration {
+        run();
+        (0..rounds)
+            .map(|_| {
+                let start = Instant::now();
+                run();
+                start.elapsed()
+            })
+            .min()
+            .unwrap()
+    }
+
+    /// Times the previous path, where the RPC client decoded the notifications into `Value`s for
+    /// `parse_redraw_event`, against decoding them from the bytes. Set `NEOVIDE_REDRAW_DUMP` to a
+    /// file recorded with `--redraw-dump` to use real events, otherwise scrolling through a 200
+    /// column log file is simulated. Run it with
+    /// `cargo test --release bench_redraw_decoder -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn bench_redraw_decoder() {
+        const ROUNDS: usize = 10;
+        let batches = match std::env::var("NEOVIDE_REDRAW_DUMP") {
+            Ok(path) => read_dump(&path),
+            Err(_) => scroll_dump(200, 60, 500),
+        };
+        // The dump doesn't keep the notifications apart, so each batch gets its own
+        let notifications = batches
+            .iter()
+            .map(|batch| encode(&Value::from(vec![batch.clone()])))
+            .collect::<Vec<_>>();
+        let bytes = notifications.iter().map(Vec::len).sum::<usize>();
+
+        let value_parser = fastest(ROUNDS, || {
+            for params in &notifications {
+                let params = rmpv::decode::read_value(&mut params.as_slice()).unwrap();
+                let Value::Array(batches) = params else { unreachable!() };
+                for batch in batches {
+                    std::hint::black_box(parse_redraw_event(batch).unwrap());
+                }
+            }
+        });
+        let mut redraw_decoder = RedrawDecoder::new();
+        let decoder = fastest(ROUNDS, || {
+            for params in &notifications {
+                std::hint::black_box(redraw_decoder.decode(params).unwrap());
+            }
+        });
+        println!(
+            "{} redraw batches, {:.1} MiB: value parser {value_parser:?}, decoder {decoder:?} \
+             ({:.2}x)",
+            notifications.len(),
+            bytes as f64 / (1024.0 * 1024.0),
+            value_parser.as_secs_f64() / decoder.as_secs_f64(),
+        );
+    }
+}
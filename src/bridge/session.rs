@@ -19,8 +19,13 @@ use tokio::{
 };
 use tokio_util::compat::{TokioAsyncReadCompatExt, TokioAsyncWriteCompatExt};
 
+use super::redraw_decoder::{RedrawDecoding, RedrawReader};
+
 pub type NeovimWriter = Box<dyn futures::AsyncWrite + Send + Unpin + 'static>;
 
+/// The message Neovim echoes back to finish the handshake.
+pub const HANDSHAKE_MESSAGE: &str = "NeovideToNeovimMagicHandshakeMessage";
+
 type BoxedReader = Box<dyn AsyncRead + Send + Unpin + 'static>;
 type BoxedWriter = Box<dyn AsyncWrite + Send + Unpin + 'static>;
 
@@ -44,6 +49,7 @@ impl NeovimSession {
     pub async fn new(
         instance: NeovimInstance,
         handler: impl Handler<Writer = NeovimWriter>,
+        redraw_decoding: Option<RedrawDecoding>,
     ) -> anyhow::Result<Self> {
         // This needs to be done before the process is spawned, since the file descriptors are
         // inherited on unix-like systems
@@ -62,13 +68,16 @@ impl NeovimSession {
                 lines
             })
         });
-        let handshake_message = "NeovideToNeovimMagicHandshakeMessage";
 
+        let reader: BoxedReader = match redraw_decoding {
+            Some(redraw_decoding) => Box::new(RedrawReader::new(reader, redraw_decoding)),
+            None => reader,
+        };
         let handshake_res = Neovim::<NeovimWriter>::handshake(
             reader.compat(),
             Box::new(writer.compat_write()),
             handler,
-            handshake_message,
+            HANDSHAKE_MESSAGE,
         )
         .await;
         match handshake_res {
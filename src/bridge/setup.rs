@@ -8,7 +8,10 @@ use super::{
 };
 use crate::{
     bridge::NeovimWriter,
-    settings::{SettingLocation, Settings, config::config_path},
+    settings::{
+        SettingLocation, Settings,
+        config::{active_profile, config_path},
+    },
 };
 
 const INIT_LUA: &str = include_str!("../../lua/init.lua");
@@ -34,6 +37,11 @@ pub async fn setup_neovide_specific_state(
         .await
         .context("Could not communicate with neovim process")?;
 
+    // Let the user config branch on the active config profile
+    nvim.set_var("neovide_profile", Value::from(active_profile().unwrap_or_default()))
+        .await
+        .context("Could not set neovide_profile")?;
+
     nvim.exec2("runtime! ginit.vim", nvim_dict!())
         .await
         .context("Error encountered in ginit.vim ")?;
@@ -71,6 +79,9 @@ pub async fn setup_neovide_specific_state(
             _ => None,
         })
         .collect::<Vec<_>>();
+    let local_settings =
+        settings.local_setting_names().into_iter().map(Value::from).collect::<Vec<_>>();
+    let settings_metadata = settings.setting_metadata().iter().map(Value::from).collect::<Vec<_>>();
 
     nvim.exec_lua(
         INIT_LUA,
@@ -83,6 +94,8 @@ pub async fn setup_neovide_specific_state(
             "remote" => remote,
             "global_variable_settings" => global_variable_settings,
             "option_settings" => option_settings,
+            "local_settings" => local_settings,
+            "settings_metadata" => settings_metadata,
         }],
     )
     .await
@@ -1,5 +1,7 @@
 use std::{
     collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
     sync::{Arc, LazyLock, Mutex},
 };
 
@@ -16,6 +18,7 @@ use crate::{
     bridge::{NeovimWriter, nvim_dict},
     cmd_line::CmdLineSettings,
     profiling::{tracy_dynamic_zone, tracy_fiber_enter, tracy_fiber_leave},
+    renderer::{fonts::highlight_fonts::HighlightFonts, input_inspector},
     utils::handle_wslpaths,
     window::RouteId,
 };
@@ -115,6 +118,7 @@ pub enum SerialCommand {
         position: (u32, u32),
         modifier_string: String,
     },
+    Paste(String),
     #[cfg(target_os = "macos")]
     ForceClickCommand,
 }
@@ -217,6 +221,10 @@ impl SerialCommand {
                 )
                 .await
                 .context("Mouse Drag Failed"),
+            SerialCommand::Paste(text) => {
+                trace!("Paste Sent");
+                nvim.paste(&text, true, -1).await.map(|_| ()).context("Paste failed")
+            }
             #[cfg(target_os = "macos")]
             SerialCommand::ForceClickCommand => {
                 nvim.command("NeovideForceClick").await.context("Force click command failed")
@@ -232,13 +240,61 @@ impl SerialCommand {
 #[derive(Debug, Clone, AsRefStr)]
 pub enum ParallelCommand {
     Quit,
-    Resize { width: u64, height: u64 },
+    Resize {
+        width: u64,
+        height: u64,
+    },
     FileDrop(String),
     FocusLost,
     FocusGained,
     DisplayAvailableFonts(Vec<String>),
-    ShowError { lines: Vec<String> },
-    SetBackground { background: String },
+    ShowError {
+        lines: Vec<String>,
+    },
+    SetBackground {
+        background: String,
+    },
+    SetSetting {
+        name: String,
+        value: Value,
+    },
+    SourceSession {
+        session_file: PathBuf,
+        directory: PathBuf,
+    },
+    /// Runs a command palette entry that was registered from Lua.
+    RunPaletteCommand(String),
+    /// Redraws the screen with the current fonts of the highlight groups.
+    RedrawHighlightFonts,
+}
+
+/// Neovim only sends the highlight groups of the highlights with `ext_hlstate`, which is
+/// enabled when the first group gets a font. The highlights defined before that don't have their
+/// groups.
+async fn redraw_highlight_fonts(
+    nvim: &Neovim<NeovimWriter>,
+    settings: &Settings,
+) -> Result<(), Box<CallError>> {
+    if !settings.get::<HighlightFonts>().is_empty() {
+        nvim.ui_set_option("ext_hlstate", Value::from(true)).await?;
+    }
+    nvim.command("redraw!").await
+}
+
+/// Writes the `:mksession` file of a window and returns the working directory of Neovim.
+pub async fn save_session(nvim: &Neovim<NeovimWriter>, session_file: &Path) -> Result<PathBuf> {
+    if let Some(directory) = session_file.parent() {
+        fs::create_dir_all(directory).context("Could not create the sessions directory")?;
+    }
+    let directory = nvim
+        .exec_lua(
+            include_str!("../../lua/session.lua"),
+            call_args!["save", session_file.to_string_lossy().into_owned()],
+        )
+        .await
+        .context("mksession failed")?;
+    let directory = directory.as_str().context("getcwd didn't return a string")?;
+    Ok(PathBuf::from(directory))
 }
 
 async fn display_available_fonts(
@@ -340,6 +396,30 @@ impl ParallelCommand {
                 set_background_if_allowed(&background, nvim).await;
                 Ok(())
             }
+            ParallelCommand::SetSetting { name, value } => nvim
+                .set_var(&format!("neovide_{name}"), value)
+                .await
+                .with_context(|| format!("Could not set neovide_{name}")),
+            ParallelCommand::SourceSession { session_file, directory } => nvim
+                .exec_lua(
+                    include_str!("../../lua/session.lua"),
+                    call_args![
+                        "restore",
+                        session_file.to_string_lossy().into_owned(),
+                        directory.to_string_lossy().into_owned()
+                    ],
+                )
+                .await
+                .map(|_| ())
+                .with_context(|| format!("Could not restore the session {session_file:?}")),
+            ParallelCommand::RunPaletteCommand(title) => nvim
+                .exec_lua("neovide.private.run_palette_command(...)", call_args![title.as_str()])
+                .await
+                .map(|_| ())
+                .with_context(|| format!("Palette command {title} failed")),
+            ParallelCommand::RedrawHighlightFonts => {
+                redraw_highlight_fonts(nvim, settings).await.context("RedrawHighlightFonts failed")
+            }
         };
 
         if let Err(error) = result {
@@ -447,6 +527,9 @@ where
     T: Into<UiCommand>,
 {
     let command: UiCommand = command.into();
+    if let UiCommand::Serial(serial) = &command {
+        input_inspector::record_sent(serial);
+    }
     let sender = handler.get_ui_command_channel().0;
     sender.send(command).expect("The UI command channel has not been initialized");
 }
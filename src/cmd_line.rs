@@ -49,6 +49,14 @@ pub struct CmdLineSettings {
     #[arg(long = "log")]
     pub log_to_file: bool,
 
+    /// Append the redraw events received from Neovim to the file at PATH, for benchmarking
+    #[arg(long, value_name = "PATH")]
+    pub redraw_dump: Option<String>,
+
+    /// Write the timeline of the startup to the file at PATH, once the first frame is shown
+    #[arg(long, value_name = "PATH")]
+    pub startuptime: Option<String>,
+
     /// Connect to the named pipe or socket at ADDRESS
     #[arg(long, alias = "remote-tcp", env = "NEOVIDE_SERVER", value_name = "ADDRESS")]
     pub server: Option<String>,
@@ -176,6 +184,22 @@ pub struct CmdLineSettings {
     /// Change to this directory during startup.
     #[arg(long = "chdir", env = "NEOVIDE_CHDIR")]
     pub chdir: Option<String>,
+
+    /// Overlay the [profile.<NAME>] table of the config file on top of the base config
+    #[arg(long, env = "NEOVIDE_PROFILE", value_name = "NAME")]
+    pub profile: Option<String>,
+
+    /// Check the config file, or the one at PATH, for problems and exit
+    #[arg(long, value_name = "PATH", num_args = 0..=1)]
+    pub check_config: Option<Option<String>>,
+
+    /// Print the JSON schema of the config file and exit
+    #[arg(long)]
+    pub print_config_schema: bool,
+
+    /// Print LuaLS annotations for `require("neovide")` and exit
+    #[arg(long)]
+    pub emit_lua_types: bool,
 }
 
 // geometry, size and maximized are mutually exclusive
@@ -305,6 +329,21 @@ pub fn exit_status_code(status: ExitStatus) -> i32 {
     }
 }
 
+/// Finds the `--profile` argument. The config file is loaded before the command line is parsed, so
+/// this has to be done separately.
+pub fn profile_from_args(args: &[String]) -> Option<String> {
+    let mut args = args.iter().skip(1).take_while(|arg| *arg != "--");
+    while let Some(arg) = args.next() {
+        if arg == "--profile" {
+            return args.next().cloned();
+        }
+        if let Some(profile) = arg.strip_prefix("--profile=") {
+            return Some(profile.to_owned());
+        }
+    }
+    None
+}
+
 fn neovim_passthrough_requested(args: &[String]) -> bool {
     args.iter().any(|arg| NEOVIM_PASSTHROUGH_FLAGS.contains(&arg.as_str()))
 }
@@ -327,6 +366,21 @@ mod tests {
         assert_eq!(settings.get::<CmdLineSettings>().neovim_args, vec!["--clean"]);
     }
 
+    #[test]
+    fn test_profile_from_args() {
+        let args = |args: &[&str]| args.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(
+            profile_from_args(&args(&["neovide", "--profile", "presentation"])),
+            Some("presentation".to_owned())
+        );
+        assert_eq!(
+            profile_from_args(&args(&["neovide", "--profile=pairing", "file.txt"])),
+            Some("pairing".to_owned())
+        );
+        assert_eq!(profile_from_args(&args(&["neovide", "--", "--profile", "x"])), None);
+    }
+
     #[test]
     fn test_files_to_open() {
         let settings = Settings::new();
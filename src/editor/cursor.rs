@@ -68,7 +68,7 @@ impl Cursor {
             blinkoff: None,
             enabled: true,
             double_width: false,
-            grid_cell: (" ".to_string(), None),
+            grid_cell: (" ".into(), None),
         }
     }
 
@@ -218,7 +218,7 @@ mod tests {
     #[test]
     fn test_foreground_falls_back_to_cell_background() {
         let mut cursor = Cursor::new();
-        cursor.grid_cell = ("x".to_string(), Some(Arc::new(Style::new(CELL_COLORS))));
+        cursor.grid_cell = ("x".into(), Some(Arc::new(Style::new(CELL_COLORS))));
 
         assert_eq!(cursor.foreground(&DEFAULT_COLORS, true), CELL_COLORS.background.unwrap());
 
@@ -229,7 +229,7 @@ mod tests {
     #[test]
     fn test_foreground_reverse_swaps_cell_fallback_colors() {
         let mut cursor = Cursor::new();
-        cursor.grid_cell = ("x".to_string(), Some(Arc::new(Style::new(CELL_COLORS))));
+        cursor.grid_cell = ("x".into(), Some(Arc::new(Style::new(CELL_COLORS))));
 
         let mut reverse_cursor_style = Style::new(NONE_COLORS);
         reverse_cursor_style.reverse = true;
@@ -254,7 +254,7 @@ mod tests {
     #[test]
     fn test_background_falls_back_to_cell_foreground() {
         let mut cursor = Cursor::new();
-        cursor.grid_cell = ("x".to_string(), Some(Arc::new(Style::new(CELL_COLORS))));
+        cursor.grid_cell = ("x".into(), Some(Arc::new(Style::new(CELL_COLORS))));
 
         assert_eq!(cursor.background(&DEFAULT_COLORS, true), CELL_COLORS.foreground.unwrap());
 
@@ -265,7 +265,7 @@ mod tests {
     #[test]
     fn test_background_reverse_swaps_cell_fallback_colors() {
         let mut cursor = Cursor::new();
-        cursor.grid_cell = ("x".to_string(), Some(Arc::new(Style::new(CELL_COLORS))));
+        cursor.grid_cell = ("x".into(), Some(Arc::new(Style::new(CELL_COLORS))));
 
         let mut reverse_cursor_style = Style::new(NONE_COLORS);
         reverse_cursor_style.reverse = true;
@@ -1,13 +1,13 @@
 use std::sync::Arc;
 
-use crate::{editor::style::Style, utils::RingBuffer};
+use crate::{bridge::CellText, editor::style::Style, utils::RingBuffer};
 
-pub type GridCell = (String, Option<Arc<Style>>);
+pub type GridCell = (CellText, Option<Arc<Style>>);
 
 #[macro_export]
 macro_rules! default_cell {
     () => {
-        (" ".to_owned(), None)
+        ($crate::bridge::CellText::from(" "), None)
     };
 }
 
@@ -159,7 +159,7 @@ mod tests {
     fn set_grid_line_to_chars(grid: &mut CharacterGrid, row: usize, value: &str) {
         assert_eq!(value.len(), grid.width);
         for (col_nr, chr) in value.chars().enumerate() {
-            *grid.get_cell_mut(col_nr, row).unwrap() = (chr.to_string(), None);
+            *grid.get_cell_mut(col_nr, row).unwrap() = (chr.to_string().into(), None);
         }
     }
 
@@ -172,7 +172,7 @@ mod tests {
     }
 
     fn assert_grid_cell_contents(grid: &CharacterGrid, x: usize, y: usize, char: &str) {
-        let char = char.to_string();
+        let char = CellText::from(char);
         let value = (char, None);
         let cell = Some(&value);
         assert_eq!(grid.get_cell(x, y), cell);
@@ -187,7 +187,7 @@ mod tests {
         let mut grid = CharacterGrid::new((num_columns, num_lines));
         for (row_nr, line) in lines.iter().enumerate() {
             for (col_nr, chr) in line.chars().enumerate() {
-                *grid.get_cell_mut(col_nr, row_nr).unwrap() = (chr.to_string(), None);
+                *grid.get_cell_mut(col_nr, row_nr).unwrap() = (chr.to_string().into(), None);
             }
         }
         grid
@@ -210,8 +210,9 @@ mod tests {
         let mut character_grid = CharacterGrid::new(context.size);
 
         character_grid.lines[context.y].characters[context.x] =
-            ("foo".to_string(), Some(Arc::new(Style::new(context.none_colors.clone()))));
-        let result = ("foo".to_string(), Some(Arc::new(Style::new(context.none_colors.clone()))));
+            (CellText::from("foo"), Some(Arc::new(Style::new(context.none_colors.clone()))));
+        let result =
+            (CellText::from("foo"), Some(Arc::new(Style::new(context.none_colors.clone()))));
 
         // RUN FUNCTION
         assert_eq!(character_grid.get_cell(context.x, context.y).unwrap(), &result);
@@ -223,12 +224,13 @@ mod tests {
         let mut character_grid = CharacterGrid::new(context.size);
 
         character_grid.lines[context.y].characters[context.x] =
-            ("foo".to_string(), Some(Arc::new(Style::new(context.none_colors.clone()))));
-        let result = ("bar".to_string(), Some(Arc::new(Style::new(context.none_colors.clone()))));
+            (CellText::from("foo"), Some(Arc::new(Style::new(context.none_colors.clone()))));
+        let result =
+            (CellText::from("bar"), Some(Arc::new(Style::new(context.none_colors.clone()))));
 
         // RUN FUNCTION
         let cell = character_grid.get_cell_mut(context.x, context.y).unwrap();
-        *cell = ("bar".to_string(), Some(Arc::new(Style::new(context.none_colors.clone()))));
+        *cell = (CellText::from("bar"), Some(Arc::new(Style::new(context.none_colors.clone()))));
 
         assert_eq!(character_grid.get_cell_mut(context.x, context.y).unwrap(), &result);
     }
@@ -237,7 +239,7 @@ mod tests {
     fn set_all_characters_sets_all_cells_to_given_character() {
         let context = Context::new();
         let grid_cell =
-            ("foo".to_string(), Some(Arc::new(Style::new(context.none_colors.clone()))));
+            (CellText::from("foo"), Some(Arc::new(Style::new(context.none_colors.clone()))));
         let mut character_grid = CharacterGrid::new(context.size);
 
         // RUN FUNCTION
@@ -251,7 +253,7 @@ mod tests {
         let mut character_grid = CharacterGrid::new(context.size);
 
         let grid_cell =
-            ("foo".to_string(), Some(Arc::new(Style::new(context.none_colors.clone()))));
+            (CellText::from("foo"), Some(Arc::new(Style::new(context.none_colors.clone()))));
         character_grid.set_all_characters(grid_cell.clone());
 
         // RUN FUNCTION
@@ -268,7 +270,7 @@ mod tests {
         let mut character_grid = CharacterGrid::new(context.size);
         let (width, height) = (rng().random_range(1..=500), rng().random_range(1..=500));
 
-        let grid_cell = ("foo".to_string(), Some(Arc::new(Style::new(context.none_colors))));
+        let grid_cell = (CellText::from("foo"), Some(Arc::new(Style::new(context.none_colors))));
         character_grid.set_all_characters(grid_cell.clone());
 
         // RUN FUNCTION
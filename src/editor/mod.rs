@@ -23,9 +23,9 @@ use crate::{
     bridge::{GridLineCell, GuiOption, NeovimHandler, RedrawEvent, WindowAnchor},
     clipboard::ClipboardHandle,
     profiling::{tracy_named_frame, tracy_zone},
-    renderer::{DrawCommand, WindowDrawCommand},
+    renderer::{DrawCommand, WindowDrawCommand, fonts::highlight_fonts::HighlightFonts},
     running_tracker::RunningTracker,
-    settings::Settings,
+    settings::{LocalOverrides, Settings},
     units::{GridRect, GridSize},
     window::{EventPayload, RouteId, UserEvent, WindowCommand, WindowSettings},
 };
@@ -123,6 +123,10 @@ pub struct Editor {
     settings: Arc<Settings>,
     composition_order: u64,
     intro_message_extender: IntroMessageExtender,
+    // The `w:` and `b:` setting overrides keyed by neovim window handle
+    local_overrides: HashMap<u64, LocalOverrides>,
+    /// The fonts of the highlight groups, to resolve the fonts of the styles.
+    highlight_fonts: HighlightFonts,
     #[cfg(target_os = "macos")]
     match_paren_highlight_ids: HashSet<u64>,
     #[cfg(target_os = "macos")]
@@ -141,6 +145,7 @@ impl Editor {
         event_loop_proxy: EventLoopProxy<EventPayload>,
         settings: Arc<Settings>,
     ) -> Self {
+        let highlight_fonts = settings.get::<HighlightFonts>();
         Editor {
             windows: HashMap::new(),
             cursor: Cursor::new(),
@@ -164,6 +169,8 @@ impl Editor {
             route_id,
             composition_order: 0,
             intro_message_extender: IntroMessageExtender::new(),
+            local_overrides: HashMap::new(),
+            highlight_fonts,
         }
     }
 
@@ -202,6 +209,7 @@ impl Editor {
                 } else {
                     self.current_mode_index = None
                 }
+                self.send_window_command(WindowCommand::ModeChanged(mode.clone()));
                 self.draw_command_batcher.queue(DrawCommand::ModeChanged(mode));
             }
             RedrawEvent::MouseOn => {
@@ -228,6 +236,11 @@ impl Editor {
                 tracy_named_frame!("neovim draw command flush");
                 self.send_cursor_info();
 
+                let window_settings = self.settings.get::<WindowSettings>();
+                for window in self.windows.values_mut() {
+                    window.update_keep_cells(&window_settings, &mut self.draw_command_batcher);
+                }
+
                 {
                     trace!("send_batch");
                     self.draw_command_batcher.send_batch(self.route_id, &self.event_loop_proxy);
@@ -254,6 +267,8 @@ impl Editor {
             }
             RedrawEvent::HighlightAttributesDefine { id, style, name } => {
                 tracy_zone!("EditorHighlightAttributesDefine");
+                let mut style = style;
+                style.font = self.highlight_fonts.resolve(&style.highlight_groups);
                 self.defined_styles.insert(id, Arc::new(style));
 
                 #[cfg(target_os = "macos")]
@@ -396,18 +411,17 @@ impl Editor {
                 tracy_zone!("EditorMessageSetPosition");
                 self.set_message_position(grid, row, scrolled, z_index, comp_index)
             }
-            RedrawEvent::WindowViewport {
-                grid,
-                // Don't send viewport events if they don't have a scroll delta
-                scroll_delta: Some(scroll_delta),
-                ..
-            } => {
+            RedrawEvent::WindowViewport { grid, window, scroll_delta, .. } => {
                 tracy_zone!("EditorWindowViewport");
-                self.set_ui_ready();
-                self.draw_command_batcher.queue(DrawCommand::Window {
-                    grid_id: grid,
-                    command: WindowDrawCommand::Viewport { scroll_delta },
-                });
+                self.set_window_handle(grid, window);
+                // Don't send viewport events if they don't have a scroll delta
+                if let Some(scroll_delta) = scroll_delta {
+                    self.set_ui_ready();
+                    self.draw_command_batcher.queue(DrawCommand::Window {
+                        grid_id: grid,
+                        command: WindowDrawCommand::Viewport { scroll_delta },
+                    });
+                }
             }
             RedrawEvent::WindowViewportMargins { grid, top, bottom, left, right } => {
                 tracy_zone!("EditorWindowViewportMargins");
@@ -423,6 +437,25 @@ impl Editor {
             RedrawEvent::NeovideSetRedraw(enable) => {
                 self.draw_command_batcher.set_enabled(enable, self.route_id, &self.event_loop_proxy)
             }
+            RedrawEvent::NeovideLocalSettings(local_overrides) => {
+                tracy_zone!("EditorLocalSettings");
+                self.local_overrides = local_overrides;
+                let grids = self.windows.keys().copied().collect::<Vec<_>>();
+                for grid in grids {
+                    self.sync_local_overrides(grid);
+                }
+            }
+            RedrawEvent::NeovideHighlightFonts(highlight_fonts) => {
+                tracy_zone!("EditorHighlightFonts");
+                // The screen is redrawn by Neovim afterwards, with the updated styles
+                for style in self.defined_styles.values_mut() {
+                    let font = highlight_fonts.resolve(&style.highlight_groups);
+                    if style.font != font {
+                        Arc::make_mut(style).font = font;
+                    }
+                }
+                self.highlight_fonts = highlight_fonts;
+            }
             RedrawEvent::NeovideIntroBannerAllowed(allowed) => {
                 self.intro_message_extender.set_sponsor_allowed(
                     allowed,
@@ -434,6 +467,28 @@ impl Editor {
         };
     }
 
+    fn set_window_handle(&mut self, grid: u64, window_handle: u64) {
+        let changed = self
+            .windows
+            .get_mut(&grid)
+            .is_some_and(|window| window.set_window_handle(window_handle));
+        if changed {
+            self.sync_local_overrides(grid);
+        }
+    }
+
+    fn sync_local_overrides(&mut self, grid: u64) {
+        let Some(window) = self.windows.get_mut(&grid) else {
+            return;
+        };
+        let local_overrides = window
+            .window_handle()
+            .and_then(|window_handle| self.local_overrides.get(&window_handle))
+            .cloned()
+            .unwrap_or_default();
+        window.set_local_overrides(&mut self.draw_command_batcher, local_overrides);
+    }
+
     fn close_window(&mut self, grid: u64) {
         if let Some(window) = self.windows.remove(&grid) {
             window.close(&mut self.draw_command_batcher);
@@ -455,6 +510,7 @@ impl Editor {
                 None,
                 (0.0, 0.0),
                 (width, height),
+                &self.settings.get::<WindowSettings>(),
                 &mut self.draw_command_batcher,
             );
             self.windows.insert(grid, window);
@@ -484,6 +540,7 @@ impl Editor {
                 None,
                 (start_left as f64, start_top as f64),
                 (width, height),
+                &self.settings.get::<WindowSettings>(),
                 &mut self.draw_command_batcher,
             );
             self.windows.insert(grid, new_window);
@@ -598,6 +655,7 @@ impl Editor {
                 Some(anchor_info),
                 (0.0, grid_top as f64),
                 (parent_width, 1),
+                &self.settings.get::<WindowSettings>(),
                 &mut self.draw_command_batcher,
             );
             self.windows.insert(grid, new_window);
@@ -726,7 +784,7 @@ impl Editor {
     fn grid_cell_text(&self, grid: u64, row: u64, column: u64) -> Option<String> {
         self.windows.get(&grid).and_then(|window| {
             let (text, _, _) = window.get_cursor_grid_cell(column, row);
-            (!text.is_empty()).then_some(text)
+            (!text.is_empty()).then(|| text.to_string())
         })
     }
 
@@ -1019,7 +1077,7 @@ impl Editor {
                 saw_match_paren = true;
             }
 
-            let text = if cell.text.is_empty() { None } else { Some(cell.text.as_str()) };
+            let text = if cell.text.is_empty() { None } else { Some(&*cell.text) };
 
             self.update_match_paren_cache_range_with_text(
                 grid,
@@ -1148,7 +1206,7 @@ impl Editor {
             self.cursor.double_width = double_width;
         } else {
             self.cursor.double_width = false;
-            self.cursor.grid_cell = (" ".to_string(), None);
+            self.cursor.grid_cell = (" ".into(), None);
         }
         self.draw_command_batcher.queue(DrawCommand::UpdateCursor(self.cursor.clone()));
     }
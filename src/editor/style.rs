@@ -1,5 +1,7 @@
 use skia_safe::Color4f;
 
+use crate::renderer::fonts::font_options::FontOverride;
+
 #[derive(new, Debug, Clone, PartialEq)]
 pub struct Colors {
     /// Foreground color.
@@ -19,7 +21,7 @@ pub enum UnderlineStyle {
     UnderCurl,
 }
 
-#[derive(new, Debug, Clone, PartialEq)]
+#[derive(new, Debug, Clone)]
 pub struct Style {
     pub colors: Colors,
     /// Reverse video, i.e. switch foreground and background colors.
@@ -39,6 +41,44 @@ pub struct Style {
     pub blend: u8,
     #[new(default)]
     pub underline: Option<UnderlineStyle>,
+    /// Drawn with the alternative font.
+    #[new(default)]
+    pub altfont: bool,
+    /// The highlight groups this style is made of, used to pick their fonts. They aren't compared,
+    /// only the font resolved from them is.
+    #[new(default)]
+    pub highlight_groups: Vec<String>,
+    /// The font of the highlight groups, resolved when the style is defined.
+    #[new(default)]
+    pub font: Option<FontOverride>,
+}
+
+impl PartialEq for Style {
+    fn eq(&self, other: &Self) -> bool {
+        // The lines are split into fragments where the style changes, so this is done for every
+        // cell, and different groups with the same font must not break up ligatures
+        let Self {
+            colors,
+            reverse,
+            italic,
+            bold,
+            strikethrough,
+            blend,
+            underline,
+            altfont,
+            highlight_groups: _,
+            font,
+        } = self;
+        *colors == other.colors
+            && *reverse == other.reverse
+            && *italic == other.italic
+            && *bold == other.bold
+            && *strikethrough == other.strikethrough
+            && *blend == other.blend
+            && *underline == other.underline
+            && *altfont == other.altfont
+            && *font == other.font
+    }
 }
 
 impl Style {
@@ -125,4 +165,16 @@ mod tests {
         style.colors.special = None;
         assert_eq!(style.special(&DEFAULT_COLORS), style.foreground(&DEFAULT_COLORS),);
     }
+
+    #[test]
+    fn test_eq_compares_font_instead_of_groups() {
+        let mut style = Style::new(COLORS);
+        style.highlight_groups = vec!["Comment".to_owned()];
+        let mut other = Style::new(COLORS);
+        other.highlight_groups = vec!["@comment.lua".to_owned()];
+        assert_eq!(style, other);
+
+        other.font = Some(FontOverride::Highlight(0));
+        assert_ne!(style, other);
+    }
 }
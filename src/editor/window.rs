@@ -3,10 +3,12 @@ use std::{collections::HashMap, ops::Range, sync::Arc};
 use log::warn;
 
 use crate::{
-    bridge::GridLineCell,
+    bridge::{CellText, GridLineCell},
     editor::{AnchorInfo, DrawCommand, DrawCommandBatcher, grid::CharacterGrid, style::Style},
     renderer::{WindowDrawCommand, box_drawing},
+    settings::LocalOverrides,
     units::{GridRect, GridSize},
+    window::{SelectionModifier, WindowSettings},
 };
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -19,7 +21,7 @@ pub enum WindowType {
 pub struct Line {
     pub text: String,
     fragments: Vec<LineFragmentData>,
-    cells: Option<Vec<String>>,
+    cells: Option<Vec<CellText>>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -65,7 +67,7 @@ impl Line {
         })
     }
 
-    pub fn cells(&self) -> Option<&[String]> {
+    pub fn cells(&self) -> Option<&[CellText]> {
         self.cells.as_deref()
     }
 }
@@ -107,6 +109,12 @@ pub struct Window {
 
     pub anchor_info: Option<AnchorInfo>,
     grid_position: (f64, f64),
+
+    // The neovim window shown in the grid, used to look up the `w:` and `b:` overrides
+    window_handle: Option<u64>,
+    local_overrides: LocalOverrides,
+    // Whether the lines are sent with the text of their cells, which only the GUI selection copies
+    keep_cells: bool,
 }
 
 impl Window {
@@ -116,6 +124,7 @@ impl Window {
         anchor_info: Option<AnchorInfo>,
         grid_position: (f64, f64),
         grid_size: (u64, u64),
+        window_settings: &WindowSettings,
         draw_command_batcher: &mut DrawCommandBatcher,
     ) -> Window {
         let window = Window {
@@ -124,6 +133,9 @@ impl Window {
             window_type,
             anchor_info,
             grid_position,
+            window_handle: None,
+            local_overrides: LocalOverrides::default(),
+            keep_cells: keeps_cells(window_settings, window_type),
         };
         window.send_updated_position(draw_command_batcher);
         window
@@ -149,13 +161,11 @@ impl Window {
         &self,
         window_left: u64,
         window_top: u64,
-    ) -> (String, Option<Arc<Style>>, bool) {
+    ) -> (CellText, Option<Arc<Style>>, bool) {
         let grid_cell = self
             .grid
             .get_cell(window_left as usize, window_top as usize)
-            .map_or((" ".to_string(), None), |(character, style)| {
-                (character.clone(), style.clone())
-            });
+            .map_or((" ".into(), None), |(character, style)| (character.clone(), style.clone()));
 
         let double_width = self
             .grid
@@ -340,16 +350,14 @@ impl Window {
             line_fragments.push(line_fragment);
         }
 
-        let cells = matches!(self.window_type, WindowType::Message { .. })
-            .then(|| self.row_cells(row))
-            .flatten();
+        let cells = if self.keep_cells { self.row_cells(row) } else { None };
 
         let line = Line { text, fragments: line_fragments, cells };
 
         self.send_command(batcher, WindowDrawCommand::DrawLine { row, line });
     }
 
-    fn row_cells(&self, row: usize) -> Option<Vec<String>> {
+    fn row_cells(&self, row: usize) -> Option<Vec<CellText>> {
         self.grid.row(row).map(|line| line.iter().map(|(character, _)| character.clone()).collect())
     }
 
@@ -387,7 +395,7 @@ impl Window {
 
         for column in 0..self.grid.width {
             if let Some(cell) = self.grid.get_cell_mut(column, row) {
-                *cell = (" ".to_string(), None);
+                *cell = (" ".into(), None);
             }
         }
 
@@ -407,7 +415,7 @@ impl Window {
             }
 
             if let Some(cell) = self.grid.get_cell_mut(column, row) {
-                *cell = (ch.to_string(), None);
+                *cell = (ch.to_string().into(), None);
             }
         }
 
@@ -466,6 +474,22 @@ impl Window {
         self.send_command(batcher, WindowDrawCommand::Clear);
     }
 
+    /// Starts or stops sending the text of the cells when the GUI selection is enabled or disabled.
+    /// The window is redrawn when it starts, so that its text can be selected right away.
+    pub fn update_keep_cells(
+        &mut self,
+        settings: &WindowSettings,
+        batcher: &mut DrawCommandBatcher,
+    ) {
+        let keep_cells = keeps_cells(settings, self.window_type);
+        if keep_cells != self.keep_cells {
+            self.keep_cells = keep_cells;
+            if keep_cells {
+                self.redraw(batcher);
+            }
+        }
+    }
+
     pub fn redraw(&self, batcher: &mut DrawCommandBatcher) {
         self.send_command(batcher, WindowDrawCommand::Clear);
         // Draw the lines from the bottom up so that underlines don't get overwritten by the line
@@ -486,6 +510,33 @@ impl Window {
     pub fn close(&self, batcher: &mut DrawCommandBatcher) {
         self.send_command(batcher, WindowDrawCommand::Close);
     }
+
+    pub fn window_handle(&self) -> Option<u64> {
+        self.window_handle
+    }
+
+    /// Returns true when the grid now shows a different window.
+    pub fn set_window_handle(&mut self, window_handle: u64) -> bool {
+        self.window_handle.replace(window_handle) != Some(window_handle)
+    }
+
+    pub fn set_local_overrides(
+        &mut self,
+        batcher: &mut DrawCommandBatcher,
+        local_overrides: LocalOverrides,
+    ) {
+        if self.local_overrides != local_overrides {
+            self.local_overrides = local_overrides.clone();
+            self.send_command(batcher, WindowDrawCommand::LocalOverrides(local_overrides));
+        }
+    }
+}
+
+/// The GUI selection works in every window with its modifier, and in the message area by default.
+fn keeps_cells(settings: &WindowSettings, window_type: WindowType) -> bool {
+    settings.gui_selection_modifier != SelectionModifier::None
+        || (settings.message_area_drag_selection
+            && matches!(window_type, WindowType::Message { .. }))
 }
 
 #[cfg(test)]
@@ -509,7 +560,7 @@ mod tests {
         let mut grid = CharacterGrid::new((WIDTH, HEIGHT));
         for (y, row) in rows.iter().enumerate() {
             for (x, (ch, color)) in row.iter().enumerate() {
-                *grid.get_cell_mut(x, y).unwrap() = (ch.to_string(), color.map(make_style));
+                *grid.get_cell_mut(x, y).unwrap() = ((*ch).into(), color.map(make_style));
             }
         }
         Window {
@@ -518,6 +569,9 @@ mod tests {
             window_type: WindowType::Editor,
             anchor_info: None,
             grid_position: (0.0, 0.0),
+            window_handle: None,
+            local_overrides: LocalOverrides::default(),
+            keep_cells: false,
         }
     }
 
@@ -735,4 +789,19 @@ mod tests {
 
         assert_eq!(next_start, window.grid.width);
     }
+
+    #[test]
+    fn test_keeps_cells_only_for_gui_selection() {
+        let message = WindowType::Message { scrolled: false };
+        let mut settings = WindowSettings::default();
+        assert!(!keeps_cells(&settings, WindowType::Editor));
+        assert!(keeps_cells(&settings, message));
+
+        settings.message_area_drag_selection = false;
+        assert!(!keeps_cells(&settings, message));
+
+        settings.gui_selection_modifier = SelectionModifier::Alt;
+        assert!(keeps_cells(&settings, WindowType::Editor));
+        assert!(keeps_cells(&settings, message));
+    }
 }
@@ -5,7 +5,7 @@ use serde::Deserialize;
 use clap::{ValueEnum, builder::PossibleValue};
 
 // Options for the frame decorations
-#[derive(Debug, Clone, Copy, Eq, PartialEq, Default, Deserialize)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Default, Deserialize, ConfigSchema)]
 #[serde(rename_all = "kebab-case")]
 pub enum Frame {
     #[default]
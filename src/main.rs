@@ -27,6 +27,7 @@ mod profiling;
 mod renderer;
 mod running_tracker;
 mod settings;
+mod startup_time;
 mod units;
 mod utils;
 mod window;
@@ -44,10 +45,11 @@ use std::{
     path::PathBuf,
     process::ExitCode,
     sync::Arc,
-    time::SystemTime,
+    time::{Instant, SystemTime},
 };
 
 use anyhow::Result;
+use clap::Parser;
 use log::trace;
 use std::panic::{PanicHookInfo, set_hook};
 use time::{OffsetDateTime, macros::format_description};
@@ -61,6 +63,7 @@ use cmd_line::CmdLineSettings;
 use error_handling::handle_startup_errors;
 use renderer::{
     RendererSettings, cursor_renderer::CursorSettings, progress_bar::ProgressBarSettings,
+    screencast::ScreencastSettings,
 };
 use window::{
     Application, EventPayload, WindowSettings, create_event_loop, determine_grid_size,
@@ -71,7 +74,10 @@ pub use channel_utils::*;
 #[cfg(target_os = "windows")]
 pub use windows_utils::*;
 
-use crate::settings::{Config, Settings, load_last_window_settings};
+use crate::settings::{
+    Config, Settings, config, config_check, load_last_window_settings, lua_types,
+    restore_runtime_settings,
+};
 
 pub use profiling::startup_profiler;
 
@@ -85,6 +91,7 @@ const REQUEST_MESSAGE: &str = "This is a bug and we would love for it to be repo
 const FORKED_FROM_TTY_ENV_VAR: &str = "NEOVIDE_FORKED_FROM_TTY";
 
 fn main() -> ExitCode {
+    startup_time::start();
     set_hook(Box::new(|panic_info| {
         let backtrace = Backtrace::new();
 
@@ -122,7 +129,7 @@ fn main() -> ExitCode {
         settings.set(&window_settings);
     }
 
-    let window_settings = load_last_window_settings().ok();
+    let window_settings = load_last_window_settings(&settings).ok();
     let window_size = determine_window_size(window_settings.as_ref(), &settings);
     let grid_size = determine_grid_size(&window_size, window_settings);
 
@@ -130,6 +137,7 @@ fn main() -> ExitCode {
         window_size,
         grid_size,
         config.font,
+        config.keys,
         event_loop.create_proxy(),
         settings.clone(),
         clipboard,
@@ -218,8 +226,20 @@ fn setup(proxy: EventLoopProxy<EventPayload>, settings: Arc<Settings>) -> Result
     settings.register::<RendererSettings>();
     settings.register::<CursorSettings>();
     settings.register::<ProgressBarSettings>();
+    settings.register::<ScreencastSettings>();
 
+    // The profile decides which parts of the config file are loaded
+    let args: Vec<String> = args().collect();
+    config::set_active_profile(
+        cmd_line::profile_from_args(&args).or_else(|| env::var("NEOVIDE_PROFILE").ok()),
+    );
+    // Before the config file is loaded and watched, --check-config reads it by itself
+    handle_config_commands(&args, &settings)?;
+    let config_start = Instant::now();
     let config = Config::init();
+    startup_time::record("config load", config_start);
+    // Applied before Neovim connects, so that `read_initial_values` sends them on to Neovim
+    settings.apply_config_tables(config.settings.iter());
     Config::watch_config_file(config.clone(), proxy.clone());
 
     set_hook(Box::new({
@@ -235,13 +255,16 @@ fn setup(proxy: EventLoopProxy<EventPayload>, settings: Arc<Settings>) -> Result
     }));
 
     //Will exit if -h or -v
-    cmd_line::handle_command_line_arguments(args().collect(), settings.as_ref())?;
+    cmd_line::handle_command_line_arguments(args, settings.as_ref())?;
     {
         let cmdline_settings = settings.get::<CmdLineSettings>();
         if let Some(status) = cmd_line::maybe_passthrough_to_neovim(&cmdline_settings)? {
             std::process::exit(cmd_line::exit_status_code(status));
         }
     }
+    // Needs the command line for `--chdir`, and has to be done before the window is created
+    restore_runtime_settings(&settings);
+
     #[cfg(not(target_os = "windows"))]
     maybe_disown(&settings);
 
@@ -255,6 +278,29 @@ fn setup(proxy: EventLoopProxy<EventPayload>, settings: Arc<Settings>) -> Result
     Ok(config)
 }
 
+/// Handles the command line flags that only print something about the config and exit.
+fn handle_config_commands(args: &[String], settings: &Settings) -> Result<()> {
+    // Invalid arguments, as well as -h and -v, are reported by the full parse later on
+    let Ok(cmdline_settings) = CmdLineSettings::try_parse_from(args) else {
+        return Ok(());
+    };
+    if cmdline_settings.print_config_schema {
+        let schema = Config::schema(settings);
+        println!("{}", serde_json::to_string_pretty(&schema)?);
+        std::process::exit(0);
+    }
+    if cmdline_settings.emit_lua_types {
+        print!("{}", lua_types(&settings.setting_metadata()));
+        std::process::exit(0);
+    }
+    if let Some(path) = &cmdline_settings.check_config {
+        let path = path.as_ref().map_or_else(config::config_path, PathBuf::from);
+        let valid = config_check::check_config_file(&path, settings);
+        std::process::exit(if valid { 0 } else { 1 });
+    }
+    Ok(())
+}
+
 #[cfg(not(test))]
 pub fn init_logger(settings: &Settings) {
     let cmdline_settings = settings.get::<CmdLineSettings>();
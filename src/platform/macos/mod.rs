@@ -89,7 +89,7 @@ fn store_event_loop_proxy(proxy: EventLoopProxy<EventPayload>) {
     let _ = EVENT_LOOP_PROXY.set(proxy);
 }
 
-fn request_new_window() {
+pub fn request_new_window() {
     let Some(proxy) = EVENT_LOOP_PROXY.get() else {
         log::warn!("New window requested before event loop proxy became available");
         return;
@@ -11,7 +11,7 @@ pub enum OptionAsMeta {
 }
 
 impl ParseFromValue for OptionAsMeta {
-    fn parse_from_value(&mut self, value: Value) {
+    fn parse_from_value(&mut self, value: Value) -> bool {
         if value.is_str() {
             *self = match value.as_str().unwrap() {
                 "only_left" => OptionAsMeta::OnlyLeft,
@@ -22,13 +22,23 @@ impl ParseFromValue for OptionAsMeta {
                     error_msg!(
                         "Setting OptionAsMeta expected one of `only_left`, `only_right`, `both`, or `none`, but received {value:?}"
                     );
-                    return;
+                    return false;
                 }
             };
+            true
         } else {
             error_msg!("Setting OptionAsMeta expected string, but received {value:?}");
+            false
         }
     }
+
+    fn value_kind() -> &'static str {
+        "string"
+    }
+
+    fn allowed_values() -> &'static [&'static str] {
+        &["only_left", "only_right", "both", "none"]
+    }
 }
 
 impl From<OptionAsMeta> for Value {
@@ -2,7 +2,7 @@ use std::collections::HashMap;
 
 use serde::Deserialize;
 
-#[derive(Debug, Deserialize, Default, Clone, PartialEq, Eq)]
+#[derive(Debug, Deserialize, Default, Clone, PartialEq, Eq, ConfigSchema)]
 #[serde(rename_all = "kebab-case")]
 pub enum BoxDrawingMode {
     /// render box chars using glyphs in current font
@@ -16,7 +16,7 @@ pub enum BoxDrawingMode {
     SelectedNative,
 }
 
-#[derive(Debug, Deserialize, Default, Clone, PartialEq)]
+#[derive(Debug, Deserialize, Default, Clone, PartialEq, ConfigSchema)]
 #[serde(rename_all = "kebab-case")]
 pub struct BoxDrawingSettings {
     pub mode: Option<BoxDrawingMode>,
@@ -24,7 +24,7 @@ pub struct BoxDrawingSettings {
     pub sizes: Option<LineSizes>,
 }
 
-#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[derive(Debug, Deserialize, Clone, PartialEq, ConfigSchema)]
 pub struct LineSizes(pub HashMap<String, (u16, u16)>);
 
 impl Default for LineSizes {
@@ -0,0 +1,396 @@
+//! The command palette. It's drawn by Neovide on top of the grid and lists the GUI actions and the
+//! commands registered with `neovide.register_command()`, filtered by a fuzzy search.
+
+use std::cmp::Reverse;
+
+use skia_safe::{Canvas, Color4f, Paint, PaintStyle, Point, Rect, colors};
+use winit::{
+    event::{ElementState, Ime, KeyEvent, MouseButton, MouseScrollDelta, WindowEvent},
+    keyboard::{Key, NamedKey},
+};
+
+use crate::{
+    profiling::tracy_zone,
+    renderer::{
+        GridRenderer,
+        overlay_font::OverlayFont,
+        settings_panel::{contains, draw_text},
+    },
+    window::GuiAction,
+};
+
+const HELP: &str = "↑↓ select  enter run  esc close";
+const MAX_WIDTH_CELLS: f32 = 70.0;
+const MAX_VISIBLE_ROWS: usize = 12;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PaletteCommand {
+    Action(GuiAction),
+    /// A command registered from Lua, identified by its title.
+    User(String),
+}
+
+/// What the window should do with an input event.
+pub enum CommandPaletteInput {
+    /// The palette is closed, so the event goes to Neovim as usual.
+    Ignored,
+    Consumed,
+    Run(PaletteCommand),
+}
+
+struct PaletteEntry {
+    title: String,
+    command: PaletteCommand,
+}
+
+pub struct CommandPalette {
+    font: OverlayFont,
+    user_commands: Vec<String>,
+    entries: Vec<PaletteEntry>,
+    open: bool,
+    query: String,
+    // Indices into `entries`, best match first
+    matches: Vec<usize>,
+    selected: usize,
+    scroll: usize,
+    mouse_position: Point,
+    // The geometry of the last frame, for mouse input
+    panel_rect: Rect,
+    rows: Vec<(usize, Rect)>,
+}
+
+impl CommandPalette {
+    pub fn new() -> Self {
+        Self {
+            font: OverlayFont::new(12.0),
+            user_commands: Vec::new(),
+            entries: Vec::new(),
+            open: false,
+            query: String::new(),
+            matches: Vec::new(),
+            selected: 0,
+            scroll: 0,
+            mouse_position: Point::default(),
+            panel_rect: Rect::default(),
+            rows: Vec::new(),
+        }
+    }
+
+    pub fn open(&mut self) {
+        let actions =
+            GuiAction::ALL.iter().filter(|action| **action != GuiAction::ShowCommandPalette);
+        let actions = actions.map(|action| PaletteEntry {
+            title: action.title().to_owned(),
+            command: PaletteCommand::Action(*action),
+        });
+        let user_commands = self.user_commands.iter().map(|title| PaletteEntry {
+            title: title.clone(),
+            command: PaletteCommand::User(title.clone()),
+        });
+        self.entries = actions.chain(user_commands).collect();
+        self.query.clear();
+        self.update_matches();
+        self.open = true;
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn close(&mut self) {
+        self.open = false;
+        self.rows.clear();
+    }
+
+    /// Adds a command registered from Lua, or removes it when `registered` is false.
+    pub fn register_user_command(&mut self, title: String, registered: bool) {
+        self.user_commands.retain(|existing| *existing != title);
+        if registered {
+            self.user_commands.push(title);
+        }
+    }
+
+    pub fn handle_event(&mut self, event: &WindowEvent) -> CommandPaletteInput {
+        if !self.open {
+            return CommandPaletteInput::Ignored;
+        }
+
+        match event {
+            WindowEvent::KeyboardInput { event, .. } => {
+                if event.state != ElementState::Pressed {
+                    return CommandPaletteInput::Consumed;
+                }
+                self.handle_key(event)
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                self.mouse_position = Point::new(position.x as f32, position.y as f32);
+                CommandPaletteInput::Consumed
+            }
+            WindowEvent::MouseInput {
+                state: ElementState::Pressed,
+                button: MouseButton::Left,
+                ..
+            } => self.click(),
+            WindowEvent::MouseInput { .. } => CommandPaletteInput::Consumed,
+            WindowEvent::MouseWheel { delta, .. } => {
+                let lines = match delta {
+                    MouseScrollDelta::LineDelta(_, lines) => *lines,
+                    MouseScrollDelta::PixelDelta(position) => position.y.signum() as f32,
+                };
+                if lines > 0.0 {
+                    self.select(self.selected.saturating_sub(1));
+                } else if lines < 0.0 {
+                    self.select(self.selected + 1);
+                }
+                CommandPaletteInput::Consumed
+            }
+            WindowEvent::Ime(Ime::Commit(text)) => {
+                self.type_text(text);
+                CommandPaletteInput::Consumed
+            }
+            // The preedit isn't shown, only the committed text is searched for
+            WindowEvent::Ime(_) => CommandPaletteInput::Consumed,
+            _ => CommandPaletteInput::Ignored,
+        }
+    }
+
+    fn handle_key(&mut self, event: &KeyEvent) -> CommandPaletteInput {
+        match event.logical_key.as_ref() {
+            Key::Named(NamedKey::Escape) => self.close(),
+            Key::Named(NamedKey::Enter) => return self.run(self.selected),
+            Key::Named(NamedKey::ArrowUp) => self.select(self.selected.saturating_sub(1)),
+            Key::Named(NamedKey::ArrowDown | NamedKey::Tab) => self.select(self.selected + 1),
+            Key::Named(NamedKey::PageUp) => {
+                self.select(self.selected.saturating_sub(MAX_VISIBLE_ROWS))
+            }
+            Key::Named(NamedKey::PageDown) => self.select(self.selected + MAX_VISIBLE_ROWS),
+            Key::Named(NamedKey::Backspace) => {
+                self.query.pop();
+                self.update_matches();
+            }
+            _ => self.type_text(event.text.as_deref().unwrap_or_default()),
+        }
+        CommandPaletteInput::Consumed
+    }
+
+    fn type_text(&mut self, text: &str) {
+        if !text.is_empty() && !text.chars().any(char::is_control) {
+            self.query.push_str(text);
+            self.update_matches();
+        }
+    }
+
+    fn update_matches(&mut self) {
+        let mut scored: Vec<_> = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter_map(|(index, entry)| Some((fuzzy_score(&self.query, &entry.title)?, index)))
+            .collect();
+        // The sort is stable, so equally good matches keep their order
+        scored.sort_by_key(|(score, _)| Reverse(*score));
+        self.matches = scored.into_iter().map(|(_, index)| index).collect();
+        self.selected = 0;
+        self.scroll = 0;
+    }
+
+    fn select(&mut self, index: usize) {
+        self.selected = index.min(self.matches.len().saturating_sub(1));
+    }
+
+    fn run(&mut self, selected: usize) -> CommandPaletteInput {
+        let Some(entry) = self.matches.get(selected).and_then(|index| self.entries.get(*index))
+        else {
+            return CommandPaletteInput::Consumed;
+        };
+        let command = entry.command.clone();
+        self.close();
+        CommandPaletteInput::Run(command)
+    }
+
+    fn click(&mut self) -> CommandPaletteInput {
+        let position = self.mouse_position;
+        if !contains(&self.panel_rect, position) {
+            self.close();
+            return CommandPaletteInput::Consumed;
+        }
+        match self.rows.iter().find(|(_, row)| contains(row, position)) {
+            Some((selected, _)) => self.run(*selected),
+            None => CommandPaletteInput::Consumed,
+        }
+    }
+
+    pub fn draw(&mut self, canvas: &Canvas, grid_renderer: &GridRenderer) {
+        if !self.open {
+            return;
+        }
+        tracy_zone!("command_palette_draw");
+        let Some(font) = self.font.get() else {
+            return;
+        };
+
+        let mut font = font.clone();
+        font.set_size(grid_renderer.em_size);
+        let cell_width = grid_renderer.grid_scale.width();
+        let row_height = grid_renderer.grid_scale.height() * 1.4;
+        let padding = cell_width * 2.0;
+
+        let default_colors = &grid_renderer.default_style.colors;
+        let foreground = default_colors.foreground.unwrap_or(colors::WHITE);
+        let background = default_colors.background.unwrap_or(colors::BLACK);
+        let faded = |alpha: f32| Color4f { a: alpha, ..foreground };
+
+        // Horizontally centered near the top, sized to fit the matches
+        let size = canvas.base_layer_size();
+        let (canvas_width, canvas_height) = (size.width as f32, size.height as f32);
+        let width = (canvas_width - cell_width * 8.0).min(cell_width * MAX_WIDTH_CELLS).max(0.0);
+        let max_rows = (((canvas_height - row_height * 6.0) / row_height).floor() as usize)
+            .clamp(1, MAX_VISIBLE_ROWS);
+        let visible_rows = self.matches.len().clamp(1, max_rows);
+        let height = row_height * (visible_rows as f32 + 2.5);
+        self.panel_rect =
+            Rect::from_xywh((canvas_width - width) / 2.0, row_height * 2.0, width, height);
+        let panel = self.panel_rect;
+
+        canvas.save();
+        canvas.reset_matrix();
+        canvas.clip_rect(panel, None, Some(true));
+
+        let mut paint = Paint::new(Color4f { a: 0.95, ..background }, None);
+        paint.set_anti_alias(true);
+        canvas.draw_rect(panel, &paint);
+        paint.set_color4f(faded(0.3), None);
+        paint.set_style(PaintStyle::Stroke);
+        canvas.draw_rect(panel.with_inset((0.5, 0.5)), &paint);
+        paint.set_style(PaintStyle::Fill);
+
+        // The query, with a block cursor after it
+        let text_left = panel.left + padding;
+        let query_row = Rect::new(panel.left, panel.top, panel.right, panel.top + row_height * 1.5);
+        paint.set_color4f(foreground, None);
+        let query = format!("> {}█", self.query);
+        draw_text(canvas, &query, text_left, &query_row, &font, &paint);
+
+        if self.selected < self.scroll {
+            self.scroll = self.selected;
+        } else if self.selected >= self.scroll + visible_rows {
+            self.scroll = self.selected + 1 - visible_rows;
+        }
+
+        self.rows.clear();
+        let list_top = query_row.bottom;
+        if self.matches.is_empty() {
+            let row = Rect::new(panel.left, list_top, panel.right, list_top + row_height);
+            paint.set_color4f(faded(0.6), None);
+            draw_text(canvas, "No matching commands", text_left, &row, &font, &paint);
+        }
+        let visible = self.scroll..(self.scroll + visible_rows).min(self.matches.len());
+        for (row_index, selected) in visible.enumerate() {
+            let entry = &self.entries[self.matches[selected]];
+            let top = list_top + row_index as f32 * row_height;
+            let row = Rect::new(panel.left, top, panel.right, top + row_height);
+
+            if selected == self.selected {
+                paint.set_color4f(faded(0.12), None);
+                canvas.draw_rect(row, &paint);
+            }
+            paint.set_color4f(foreground, None);
+            draw_text(canvas, &entry.title, text_left, &row, &font, &paint);
+            if let PaletteCommand::Action(action) = entry.command {
+                // The action name, which can be used in the `[keys]` table
+                paint.set_color4f(faded(0.5), None);
+                let (name_width, _) = font.measure_str(action.name(), None);
+                let left = panel.right - padding - name_width;
+                draw_text(canvas, action.name(), left, &row, &font, &paint);
+            }
+
+            self.rows.push((selected, row));
+        }
+
+        let help_top = panel.bottom - row_height;
+        let help_row = Rect::new(panel.left, help_top, panel.right, panel.bottom);
+        paint.set_color4f(faded(0.6), None);
+        draw_text(canvas, HELP, text_left, &help_row, &font, &paint);
+
+        canvas.restore();
+    }
+}
+
+/// Scores how well the query matches the text as a case insensitive subsequence, or `None` when it
+/// doesn't match at all. Consecutive characters and characters at the start of a word score higher.
+fn fuzzy_score(query: &str, text: &str) -> Option<i32> {
+    let mut query = query.chars().filter(|c| !c.is_whitespace()).flat_map(char::to_lowercase);
+    let mut next = query.next();
+    let mut score = 0;
+    let mut previous: Option<char> = None;
+    let mut previous_matched = false;
+
+    for c in text.chars() {
+        let Some(wanted) = next else {
+            break;
+        };
+        let matched = c.to_lowercase().eq(std::iter::once(wanted));
+        if matched {
+            score += 1;
+            if previous_matched {
+                score += 5;
+            }
+            if previous.is_none_or(|previous| !previous.is_alphanumeric()) {
+                score += 10;
+            }
+            next = query.next();
+        } else {
+            score -= 1;
+        }
+        previous = Some(c);
+        previous_matched = matched;
+    }
+    next.is_none().then_some(score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fuzzy_score() {
+        assert_eq!(fuzzy_score("", "Zoom In"), Some(0));
+        assert!(fuzzy_score("zi", "Zoom In").is_some());
+        assert!(fuzzy_score("ZOOM", "Zoom In").is_some());
+        assert_eq!(fuzzy_score("zx", "Zoom In"), None);
+        assert_eq!(fuzzy_score("Zoom In!", "Zoom In"), None);
+
+        // Word starts and consecutive characters beat scattered characters
+        let full = fuzzy_score("tf", "Toggle Fullscreen").unwrap();
+        let scattered = fuzzy_score("tf", "Save Screenshot for later").unwrap();
+        assert!(full > scattered);
+        let consecutive = fuzzy_score("zoom", "Zoom Out").unwrap();
+        let spread = fuzzy_score("zoom", "Zero Overflow Of Memory").unwrap();
+        assert!(consecutive > spread);
+    }
+
+    #[test]
+    fn test_ime_commit_searches() {
+        let mut palette = CommandPalette::new();
+        assert!(matches!(
+            palette.handle_event(&WindowEvent::Ime(Ime::Commit("zoom".to_owned()))),
+            CommandPaletteInput::Ignored
+        ));
+
+        palette.open();
+        let preedit = Ime::Preedit("zo".to_owned(), Some((2, 2)));
+        assert!(matches!(
+            palette.handle_event(&WindowEvent::Ime(preedit)),
+            CommandPaletteInput::Consumed
+        ));
+        assert!(palette.query.is_empty());
+        let commit = Ime::Commit("zoom".to_owned());
+        assert!(matches!(
+            palette.handle_event(&WindowEvent::Ime(commit)),
+            CommandPaletteInput::Consumed
+        ));
+        assert_eq!(palette.query, "zoom");
+        let first = &palette.entries[palette.matches[0]];
+        assert!(first.title.starts_with("Zoom"));
+    }
+}
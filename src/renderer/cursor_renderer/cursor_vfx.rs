@@ -56,7 +56,7 @@ pub enum VfxMode {
 pub struct VfxModeList(Vec<VfxMode>);
 
 impl ParseFromValue for VfxMode {
-    fn parse_from_value(&mut self, value: Value) {
+    fn parse_from_value(&mut self, value: Value) -> bool {
         if value.is_str() {
             *self = match value.as_str().unwrap() {
                 "sonicboom" => VfxMode::Highlight(HighlightMode::SonicBoom),
@@ -68,35 +68,62 @@ impl ParseFromValue for VfxMode {
                 "" => VfxMode::Disabled,
                 value => {
                     error!("Expected a VfxMode name, but received {value:?}");
-                    return;
+                    return false;
                 }
             };
+            true
         } else {
             error!("Expected a VfxMode string, but received {value:?}");
+            false
         }
     }
+
+    fn value_kind() -> &'static str {
+        "string"
+    }
+
+    fn allowed_values() -> &'static [&'static str] {
+        &["", "railgun", "torpedo", "pixiedust", "sonicboom", "ripple", "wireframe"]
+    }
 }
 
 impl ParseFromValue for VfxModeList {
-    fn parse_from_value(&mut self, value: Value) {
-        self.0.clear();
-        if value.is_str() {
-            let mut vfx_mode = VfxMode::Disabled;
-            vfx_mode.parse_from_value(value);
-            self.0.push(vfx_mode);
+    fn parse_from_value(&mut self, value: Value) -> bool {
+        let items = if value.is_str() {
+            vec![value]
         } else if value.is_array() {
-            for item in value.as_array().unwrap() {
-                if item.is_str() {
-                    let mut vfx_mode = VfxMode::Disabled;
-                    vfx_mode.parse_from_value(item.clone());
-                    self.0.push(vfx_mode);
-                } else {
-                    error!("Expected a VfxMode string in the array, but received {item:?}");
-                }
-            }
+            value.as_array().unwrap().clone()
         } else {
             error!("Expected a string or an array of VfxMode strings, but received {value:?}");
+            return false;
+        };
+
+        // The invalid modes are left out, the value is only ignored when none of them is valid
+        let given = items.len();
+        let modes: Vec<_> = items
+            .into_iter()
+            .filter_map(|item| {
+                if !item.is_str() {
+                    error!("Expected a VfxMode string in the array, but received {item:?}");
+                    return None;
+                }
+                let mut vfx_mode = VfxMode::Disabled;
+                vfx_mode.parse_from_value(item).then_some(vfx_mode)
+            })
+            .collect();
+        if modes.is_empty() && given > 0 {
+            return false;
         }
+        self.0 = modes;
+        true
+    }
+
+    fn value_kind() -> &'static str {
+        "string|string[]"
+    }
+
+    fn allowed_values() -> &'static [&'static str] {
+        VfxMode::allowed_values()
     }
 }
 
@@ -501,3 +528,24 @@ fn rotate_vec(v: PixelVec<f32>, rot: f32) -> PixelVec<f32> {
 
     PixelVec::new(v.x * cos - v.y * sin, v.x * sin + v.y * cos)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_vfx_mode_list() {
+        let mut modes = VfxModeList::default();
+        let value = Value::from(vec![Value::from("railgun"), Value::from("bogus"), Value::from(1)]);
+        assert!(modes.parse_from_value(value));
+        assert_eq!(modes.0, [VfxMode::Trail(TrailMode::Railgun)]);
+
+        // Nothing valid keeps the previous modes
+        assert!(!modes.parse_from_value(Value::from("bogus")));
+        assert!(!modes.parse_from_value(Value::from(vec![Value::from("bogus")])));
+        assert_eq!(modes.0, [VfxMode::Trail(TrailMode::Railgun)]);
+
+        assert!(modes.parse_from_value(Value::from(Vec::<Value>::new())));
+        assert!(modes.0.is_empty());
+    }
+}
@@ -5,7 +5,7 @@ use std::{collections::HashMap, sync::Arc};
 
 use approx::AbsDiffEq;
 use itertools::Itertools;
-use skia_safe::{Canvas, Paint, Path, PathBuilder, op};
+use skia_safe::{Canvas, Paint, Path, PathBuilder, Rect, op};
 use winit::event::WindowEvent;
 
 use crate::{
@@ -13,7 +13,7 @@ use crate::{
     editor::{Cursor, CursorShape, Word},
     profiling::{tracy_plot, tracy_zone},
     renderer::{GridRenderer, RenderedWindow, animation_utils::*},
-    settings::{ParseFromValue, Settings},
+    settings::{LocalOverrides, ParseFromValue, Settings},
     units::{
         GridPos, GridScale, GridSize, PixelPos, PixelRect, PixelSize, PixelVec, to_skia_point,
     },
@@ -48,23 +48,53 @@ static PLOT_NAMES_Y: [&CStr; 4] = [
 #[setting_prefix = "cursor"]
 #[derive(Clone)]
 pub struct CursorSettings {
+    /// Antialias the cursor outline.
     antialiasing: bool,
+    /// How long the cursor takes to reach its destination, in seconds.
+    #[local]
     animation_length: f32,
+    /// The animation length for short moves, like typing.
+    #[local]
     short_animation_length: f32,
+    /// Animate the cursor in insert mode.
+    #[local]
     animate_in_insert_mode: bool,
+    /// Animate the cursor moving to and from the command line.
+    #[local]
     animate_command_line: bool,
+    /// How much the back of the cursor lags behind, from 0.0 to 1.0.
+    #[local]
     trail_size: f32,
+    /// Width of the cursor outline when the window is unfocused, relative to the font size.
     unfocused_outline_width: f32,
+    /// Fade the cursor in and out when blinking.
     smooth_blink: bool,
+    /// Use the color of the cell under the cursor when the cursor has no highlight.
     cell_color_fallback: bool,
 
+    /// The particle effects of the cursor, a single mode or a list of modes.
+    #[local]
     vfx_mode: cursor_vfx::VfxModeList,
+    /// The opacity of the particles, from 0.0 to 256.0.
+    #[local]
     vfx_opacity: f32,
+    /// How long the particles live, in seconds.
+    #[local]
     vfx_particle_lifetime: f32,
+    /// How long the highlight effects live, in seconds.
+    #[local]
     vfx_particle_highlight_lifetime: f32,
+    /// How many particles are emitted, relative to the distance moved.
+    #[local]
     vfx_particle_density: f32,
+    /// The speed of the particles.
+    #[local]
     vfx_particle_speed: f32,
+    /// Only for the railgun mode, how fast the particles spiral.
+    #[local]
     vfx_particle_phase: f32,
+    /// Only for the railgun mode, how much the particles curl.
+    #[local]
     vfx_particle_curl: f32,
 }
 
@@ -226,6 +256,8 @@ pub struct CursorRenderer {
     previous_vfx_mode: cursor_vfx::VfxModeList,
     window_has_focus: bool,
     jumped: bool,
+    // The overrides of the window the cursor is in
+    local_overrides: LocalOverrides,
 
     settings: Arc<Settings>,
 }
@@ -244,6 +276,7 @@ impl CursorRenderer {
             previous_vfx_mode: cursor_vfx::VfxModeList::default(),
             window_has_focus: true,
             jumped: false,
+            local_overrides: LocalOverrides::default(),
 
             settings,
         };
@@ -315,9 +348,13 @@ impl CursorRenderer {
             );
 
             self.destination = grid * grid_scale;
+            if self.local_overrides != window.local_overrides {
+                self.local_overrides = window.local_overrides.clone();
+            }
             Some((window.id, cursor_grid_position))
         } else {
             self.destination = cursor_grid_position_f * grid_scale;
+            self.local_overrides = LocalOverrides::default();
             Some((0, cursor_grid_position))
         };
         if new_cursor_pos != self.previous_cursor_position {
@@ -335,7 +372,7 @@ impl CursorRenderer {
 
     pub fn draw(&mut self, grid_renderer: &mut GridRenderer, canvas: &Canvas) {
         tracy_zone!("cursor_draw");
-        let settings = self.settings.get::<CursorSettings>();
+        let settings = self.local_overrides.resolve(&self.settings.get::<CursorSettings>());
         let render = self.blink_status.should_render() || settings.smooth_blink;
         let opacity = match settings.smooth_blink {
             true => self.blink_status.opacity(),
@@ -379,7 +416,10 @@ impl CursorRenderer {
 
         let baseline_offset = grid_renderer.shaper.baseline_offset();
         let style = &self.cursor.grid_cell.1;
-        let coarse_style = style.as_ref().map(|style| style.into()).unwrap_or_default();
+        let coarse_style = style
+            .as_ref()
+            .map(|style| grid_renderer.shaper.coarse_style(style))
+            .unwrap_or_default();
 
         let box_char_drawn = grid_renderer.box_char_renderer.draw_glyph(
             &character,
@@ -393,11 +433,11 @@ impl CursorRenderer {
         );
         if !box_char_drawn {
             let pos = (self.destination.x, self.destination.y + baseline_offset);
-            let blobs = &grid_renderer
+            let runs = &grid_renderer
                 .shaper
                 .shape_cached(Word::new(&character, &[character.len() as u8]), coarse_style);
-            for blob in blobs.iter() {
-                canvas.draw_text_blob(blob, pos, &paint);
+            for run in runs.iter() {
+                canvas.draw_text_blob(&run.blob, pos, &paint);
             }
         }
 
@@ -418,7 +458,7 @@ impl CursorRenderer {
         if !self.cursor.enabled {
             return false;
         }
-        let settings = self.settings.get::<CursorSettings>();
+        let settings = self.local_overrides.resolve(&self.settings.get::<CursorSettings>());
 
         if settings.vfx_mode != self.previous_vfx_mode {
             self.cursor_vfxs = cursor_vfx::new_cursor_vfxs(&settings.vfx_mode);
@@ -588,4 +628,20 @@ impl CursorRenderer {
     pub fn get_destination(&self) -> PixelPos<f32> {
         self.destination
     }
+
+    /// The area the cursor is drawn in, or `None` when it has effects that can be anywhere.
+    pub fn damage_bounds(&self, grid_scale: GridScale) -> Option<Rect> {
+        if !self.cursor_vfxs.is_empty() {
+            return None;
+        }
+        let corners =
+            self.corners.iter().map(|corner| to_skia_point(corner.current_position)).collect_vec();
+        let cell = Rect::from_point_and_size(
+            to_skia_point(self.destination),
+            (grid_scale.width(), grid_scale.height()),
+        );
+        let bounds = Rect::bounds(&corners).map_or(cell, |corners| Rect::join2(corners, cell));
+        // Antialiasing and the rounding of the corners can touch the surrounding pixels
+        Some(bounds.with_outset((2.0, 2.0)))
+    }
 }
@@ -1,4 +1,4 @@
-use std::{rc::Rc, sync::Arc};
+use std::sync::Arc;
 
 use skia_safe::{
     Canvas, ColorSpace, ColorType, Surface, SurfaceProps, SurfacePropsFlags,
@@ -105,13 +105,13 @@ pub struct D3DSkiaRenderer {
     _composition_device: IDCompositionDevice,
     _target: IDCompositionTarget,
     _visual: IDCompositionVisual,
-    window: Rc<Window>,
+    window: Arc<Window>,
 
     settings: Arc<Settings>,
 }
 
 impl D3DSkiaRenderer {
-    pub fn new(window: Rc<Window>, settings: Arc<Settings>) -> Self {
+    pub fn new(window: Arc<Window>, settings: Arc<Settings>) -> Self {
         tracy_zone!("D3DSkiaRenderer::new");
         #[cfg(feature = "d3d_debug")]
         let dxgi_factory: IDXGIFactory2 = unsafe {
@@ -362,8 +362,8 @@ impl D3DSkiaRenderer {
 }
 
 impl SkiaRenderer for D3DSkiaRenderer {
-    fn window(&self) -> Rc<Window> {
-        Rc::clone(&self.window)
+    fn window(&self) -> Arc<Window> {
+        Arc::clone(&self.window)
     }
 
     fn flush(&mut self) {}
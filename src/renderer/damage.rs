@@ -0,0 +1,183 @@
+//! Damage tracking. Only the parts of the frame that changed since the back buffer was last drawn
+//! are redrawn, the rest of the buffer is kept from the earlier frames.
+//!
+//! The damage is collected on the CPU from what the renderer knows changed, like the redrawn lines
+//! and the cursor. Anything that can't be tracked precisely, like animations or settings changes,
+//! damages the whole frame.
+
+use std::collections::VecDeque;
+
+use skia_safe::{IRect, ISize, Rect, RoundOut};
+
+/// The oldest back buffer that can be partially redrawn, in frames.
+const MAX_BUFFER_AGE: usize = 4;
+
+/// The parts of a frame that changed.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Damage {
+    full: bool,
+    rects: Vec<IRect>,
+}
+
+impl Damage {
+    pub fn add(&mut self, rect: Rect) {
+        if !self.full && !rect.is_empty() {
+            self.rects.push(rect.round_out());
+        }
+    }
+
+    pub fn add_full(&mut self) {
+        self.full = true;
+        self.rects.clear();
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.full
+    }
+
+    pub fn rects(&self) -> &[IRect] {
+        &self.rects
+    }
+
+    pub fn intersects(&self, rect: Rect) -> bool {
+        let rect: IRect = rect.round_out();
+        self.full || self.rects.iter().any(|damaged| IRect::intersects(damaged, &rect))
+    }
+
+    pub fn merge(&mut self, other: &Damage) {
+        if other.full {
+            self.add_full();
+        } else if !self.full {
+            self.rects.extend_from_slice(&other.rects);
+        }
+    }
+
+    /// The bounding rect of the damage inside a surface of the given size, or `None` when nothing
+    /// changed.
+    fn bounds(&self, size: ISize) -> Option<IRect> {
+        let surface = IRect::from_size(size);
+        if self.full {
+            return Some(surface);
+        }
+        let bounds = self.rects.iter().copied().reduce(|a, b| IRect::join(&a, &b))?;
+        IRect::intersect(&bounds, &surface)
+    }
+}
+
+/// What to redraw in a frame.
+pub struct FrameDamage {
+    /// The area of the back buffer to redraw, or `None` when it's already up to date.
+    pub redraw: Option<IRect>,
+    /// What changed since the previous frame, as a hint for the compositor.
+    pub damage: Damage,
+}
+
+impl FrameDamage {
+    /// The redrawn part of the frame, from 0 to 1.
+    pub fn redrawn_fraction(&self, size: ISize) -> f32 {
+        let area = |size: ISize| size.width as f32 * size.height as f32;
+        match self.redraw {
+            Some(redraw) => area(redraw.size()) / area(size).max(1.0),
+            None => 0.0,
+        }
+    }
+}
+
+/// Remembers the damage of the last frames, to find out what to redraw in an older back buffer.
+#[derive(Default)]
+pub struct DamageTracker {
+    /// The damage of the previously drawn frames, the most recent first.
+    history: VecDeque<Damage>,
+}
+
+impl DamageTracker {
+    /// Finishes a frame with the given damage, that's drawn to a buffer containing the frame from
+    /// `buffer_age` frames ago. An age of 0 means that the contents of the buffer are unknown, so
+    /// everything is redrawn.
+    pub fn finish_frame(&mut self, damage: Damage, buffer_age: u32, size: ISize) -> FrameDamage {
+        let age = buffer_age as usize;
+
+        let mut redraw = damage.clone();
+        if age == 0 || age > self.history.len() {
+            redraw.add_full();
+        } else {
+            for previous in self.history.iter().take(age - 1) {
+                redraw.merge(previous);
+            }
+        }
+
+        self.history.push_front(damage.clone());
+        self.history.truncate(MAX_BUFFER_AGE);
+        FrameDamage { redraw: redraw.bounds(size), damage }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZE: ISize = ISize { width: 100, height: 100 };
+
+    fn damage(rects: &[Rect]) -> Damage {
+        let mut damage = Damage::default();
+        for rect in rects {
+            damage.add(*rect);
+        }
+        damage
+    }
+
+    #[test]
+    fn test_redraws_damage_since_buffer_age() {
+        let mut tracker = DamageTracker::default();
+        let first = damage(&[Rect::from_xywh(0.0, 0.0, 10.0, 10.0)]);
+        // The first frame has nothing to build on
+        assert_eq!(tracker.finish_frame(first, 1, SIZE).redraw, Some(IRect::from_size(SIZE)));
+
+        let second = damage(&[Rect::from_xywh(20.5, 20.5, 10.0, 10.0)]);
+        let frame = tracker.finish_frame(second, 1, SIZE);
+        assert_eq!(frame.redraw, Some(IRect::from_ltrb(20, 20, 31, 31)));
+        assert_eq!(frame.damage.rects(), [IRect::from_ltrb(20, 20, 31, 31)]);
+
+        let third = damage(&[Rect::from_xywh(50.0, 50.0, 10.0, 10.0)]);
+        let frame = tracker.finish_frame(third, 2, SIZE);
+        assert_eq!(frame.redraw, Some(IRect::from_ltrb(20, 20, 60, 60)));
+        assert_eq!(frame.redrawn_fraction(SIZE), 0.16);
+
+        // Nothing changed since the buffer was drawn
+        assert_eq!(tracker.finish_frame(Damage::default(), 1, SIZE).redraw, None);
+        assert_eq!(
+            tracker.finish_frame(Damage::default(), 0, SIZE).redraw,
+            Some(IRect::from_size(SIZE))
+        );
+    }
+
+    #[test]
+    fn test_full_damage() {
+        let mut tracker = DamageTracker::default();
+        tracker.finish_frame(Damage::default(), 0, SIZE);
+        let mut full = damage(&[Rect::from_xywh(0.0, 0.0, 10.0, 10.0)]);
+        full.add_full();
+        full.add(Rect::from_xywh(0.0, 0.0, 10.0, 10.0));
+        assert!(full.is_full());
+        assert!(full.rects().is_empty());
+        tracker.finish_frame(full, 1, SIZE);
+
+        let outside = damage(&[Rect::from_xywh(200.0, 200.0, 10.0, 10.0)]);
+        let frame = tracker.finish_frame(outside.clone(), 2, SIZE);
+        assert_eq!(frame.redraw, Some(IRect::from_size(SIZE)));
+        // Damage outside of the surface doesn't need a redraw
+        assert_eq!(tracker.finish_frame(outside, 1, SIZE).redraw, None);
+    }
+
+    #[test]
+    fn test_merges_skipped_frames() {
+        let mut skipped = damage(&[Rect::from_xywh(0.0, 0.0, 10.0, 10.0)]);
+        let mut latest = damage(&[Rect::from_xywh(20.0, 20.0, 10.0, 10.0)]);
+        latest.merge(&skipped);
+        assert_eq!(latest.rects().len(), 2);
+
+        skipped.add_full();
+        latest.merge(&skipped);
+        assert!(latest.is_full());
+    }
+}
@@ -1,10 +1,17 @@
-use std::{num::NonZeroUsize, rc::Rc};
+use std::{
+    borrow::Borrow,
+    collections::{HashMap, HashSet},
+    hash::{Hash, Hasher},
+    num::NonZeroUsize,
+    rc::Rc,
+    thread,
+};
 
 use itertools::Itertools;
-use log::{debug, error, info, trace, warn};
+use log::{debug, error, info, trace};
 use lru::LruCache;
 use skia_safe::{
-    TextBlob, TextBlobBuilder,
+    GlyphId, Point, TextBlob, TextBlobBuilder,
     graphics::{font_cache_limit, font_cache_used, set_font_cache_limit},
 };
 use swash::{
@@ -17,29 +24,108 @@ use swash::{
 };
 
 use crate::{
-    editor::Word,
+    editor::{Style, Word},
     error_msg,
     profiling::tracy_zone,
-    renderer::fonts::{font_loader::*, font_options::*},
+    renderer::fonts::{
+        font_loader::*, font_options::*, highlight_fonts::HighlightFonts, swash_font::SwashFont,
+    },
     units::PixelSize,
 };
 
-#[derive(new, Clone, Hash, PartialEq, Eq, Debug)]
+#[derive(new, Clone, Debug)]
 struct ShapeKey {
     pub text: String,
     pub style: CoarseStyle,
 }
 
+/// The text and style of a word, so that the cache can be searched without allocating a
+/// [`ShapeKey`].
+trait AsShapeKey {
+    fn parts(&self) -> (&str, CoarseStyle);
+}
+
+impl AsShapeKey for ShapeKey {
+    fn parts(&self) -> (&str, CoarseStyle) {
+        (&self.text, self.style)
+    }
+}
+
+impl AsShapeKey for (&str, CoarseStyle) {
+    fn parts(&self) -> (&str, CoarseStyle) {
+        *self
+    }
+}
+
+impl<'a> Borrow<dyn AsShapeKey + 'a> for ShapeKey {
+    fn borrow(&self) -> &(dyn AsShapeKey + 'a) {
+        self
+    }
+}
+
+impl Hash for dyn AsShapeKey + '_ {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.parts().hash(state);
+    }
+}
+
+impl PartialEq for dyn AsShapeKey + '_ {
+    fn eq(&self, other: &Self) -> bool {
+        self.parts() == other.parts()
+    }
+}
+
+impl Eq for dyn AsShapeKey + '_ {}
+
+// Hashed the same way as the borrowed keys
+impl Hash for ShapeKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (self as &dyn AsShapeKey).hash(state);
+    }
+}
+
+impl PartialEq for ShapeKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.parts() == other.parts()
+    }
+}
+
+impl Eq for ShapeKey {}
+
+/// The glyphs of a word that are shaped with the same font, positioned relative to the start of
+/// the word on the baseline.
+pub struct GlyphRun {
+    pub font: Rc<FontPair>,
+    pub glyphs: Vec<GlyphId>,
+    pub positions: Vec<Point>,
+    pub blob: TextBlob,
+}
+
+/// Clusters that are shaped together with the same font.
+struct ShapeJob {
+    font_pair: Rc<FontPair>,
+    features: Vec<(String, u16)>,
+    clusters: Vec<CharCluster>,
+}
+
 const FONT_CACHE_SIZE: usize = 8 * 1024 * 1024;
+/// Starting a worker isn't worth it for fewer words.
+const MIN_WORDS_PER_WORKER: usize = 16;
 
 pub struct CachingShaper {
     options: FontOptions,
     font_loader: FontLoader,
-    blob_cache: LruCache<ShapeKey, Vec<TextBlob>>,
+    blob_cache: LruCache<ShapeKey, Vec<GlyphRun>>,
     shape_context: ShapeContext,
+    /// The shaping contexts of the workers used by `shape_words`.
+    worker_contexts: Vec<ShapeContext>,
     scale_factor: f32,
     linespace: f32,
     font_info: Option<(Metrics, f32)>,
+    /// The fonts of [`FontOverride::Highlight`].
+    highlight_fonts: HighlightFonts,
+    /// The overriding fonts, scaled to fit the cells.
+    scaled_fonts: HashMap<FontKey, Rc<FontPair>>,
 }
 
 impl CachingShaper {
@@ -51,9 +137,12 @@ impl CachingShaper {
             font_loader: FontLoader::new(font_size),
             blob_cache: LruCache::new(NonZeroUsize::new(10000).unwrap()),
             shape_context: ShapeContext::new(),
+            worker_contexts: Vec::new(),
             scale_factor,
             linespace: 0.0,
             font_info: None,
+            highlight_fonts: HighlightFonts::default(),
+            scaled_fonts: HashMap::new(),
         };
         shaper.reset_font_loader();
         shaper
@@ -78,6 +167,11 @@ impl CachingShaper {
         (self.options.size * self.scale_factor).max(min_font_size)
     }
 
+    /// The font size in pixels, without the scale factor.
+    pub fn font_size(&self) -> f32 {
+        self.options.size
+    }
+
     pub fn update_scale_factor(&mut self, scale_factor: f32) {
         debug!("scale_factor changed: {scale_factor:.2}");
         self.scale_factor = scale_factor;
@@ -116,31 +210,75 @@ impl CachingShaper {
             return;
         }
 
-        let failed_fonts =
-            keys.iter().filter(|key| self.font_loader.get_or_load(key).is_none()).collect_vec();
-
-        if failed_fonts.len() == keys.len() {
+        // Only one of the fonts has to exist, the fallback fonts are loaded on first use
+        if !keys.iter().any(|key| self.font_loader.get_or_load(key).is_some()) {
             error_msg!(
                 "Font can't be updated to: {:#?}\n\
                 No candidate fonts could be loaded: {}",
                 options,
-                failed_fonts.iter().join(",\n"),
+                keys.iter().join(",\n"),
             );
             return;
         }
 
-        if !failed_fonts.is_empty() {
-            warn!(
-                "Some configured fonts could not be loaded and will be skipped: {}",
-                failed_fonts.iter().join(", ")
-            );
-        }
-
         debug!("Font updated to: {options:?}");
         self.options = options;
         self.reset_font_loader();
     }
 
+    pub fn update_highlight_fonts(&mut self, highlight_fonts: HighlightFonts) {
+        debug!("Updating highlight fonts: {highlight_fonts:?}");
+        self.highlight_fonts = highlight_fonts;
+        self.blob_cache.clear();
+    }
+
+    /// The style of the fonts for text of the highlight, with the font of its highlight groups or
+    /// of the `altfont` attribute.
+    pub fn coarse_style(&self, style: &Style) -> CoarseStyle {
+        let font = style.font.or_else(|| {
+            (style.altfont && !self.options.altfont.is_empty()).then_some(FontOverride::AltFont)
+        });
+        CoarseStyle::from(style).with_font(font)
+    }
+
+    /// The fonts for the style, paired with whether they are scaled to fit the cells. The fonts
+    /// that override the configured fonts come first, and are scaled.
+    fn font_list(&self, style: CoarseStyle) -> Vec<(FontDescription, bool)> {
+        let overrides: &[FontDescription] = match style.font() {
+            Some(FontOverride::AltFont) => self.options.altfont.as_slice(),
+            Some(FontOverride::Highlight(index)) => self.highlight_fonts.fonts(index),
+            None => &[],
+        };
+        overrides
+            .iter()
+            .map(|font| (font.with_style(style), true))
+            .chain(self.options.font_list(style).into_iter().map(|font| (font, false)))
+            .collect()
+    }
+
+    /// Loads a font that overrides the configured fonts, scaled so that its glyphs fit the cells
+    /// of the normal font.
+    fn get_or_load_scaled(&mut self, font_key: &FontKey) -> Option<Rc<FontPair>> {
+        if let Some(font_pair) = self.scaled_fonts.get(font_key) {
+            return Some(font_pair.clone());
+        }
+
+        let font_pair = self.font_loader.get_or_load(font_key)?;
+        let size = self.current_size();
+        let (base_metrics, base_advance) = self.info();
+        let (metrics, advance) = font_info(&mut self.shape_context, &font_pair.swash_font, size);
+        let scale =
+            (base_advance / advance).min(line_height(&base_metrics) / line_height(&metrics));
+        let scaled = if scale.is_finite() && scale > 0.0 && (scale - 1.0).abs() > f32::EPSILON {
+            debug!("Scaling {font_key} by {scale:.3} to fit the cells");
+            font_pair.with_size(size * scale).map(Rc::new).unwrap_or(font_pair)
+        } else {
+            font_pair
+        };
+        self.scaled_fonts.insert(font_key.clone(), scaled.clone());
+        Some(scaled)
+    }
+
     pub fn update_linespace(&mut self, linespace: f32) {
         debug!("Updating linespace: {linespace}");
 
@@ -164,6 +302,7 @@ impl CachingShaper {
     fn reset_font_loader(&mut self) {
         tracy_zone!("reset_font_loader");
         self.font_info = None;
+        self.scaled_fonts.clear();
         let font_size = self.current_size();
 
         self.font_loader = FontLoader::new(font_size);
@@ -184,16 +323,9 @@ impl CachingShaper {
 
         let font_pair = self.current_font_pair();
         let size = self.current_size();
-        let mut shaper =
-            self.shape_context.builder(font_pair.swash_font.as_ref()).size(size).build();
-        shaper.add_str("M");
-        let metrics = shaper.metrics();
-        let mut advance = metrics.average_width;
-        shaper.shape_with(|cluster| {
-            advance = cluster.glyphs.first().map_or(metrics.average_width, |g| g.advance);
-        });
-        self.font_info = Some((metrics, advance));
-        (metrics, advance)
+        let info = font_info(&mut self.shape_context, &font_pair.swash_font, size);
+        self.font_info = Some(info);
+        info
     }
 
     fn metrics(&mut self) -> Metrics {
@@ -204,7 +336,7 @@ impl CachingShaper {
     pub fn font_base_dimensions(&mut self) -> PixelSize<f32> {
         let (metrics, glyph_advance) = self.info();
 
-        let bare_font_height = metrics.ascent + metrics.descent + metrics.leading;
+        let bare_font_height = line_height(&metrics);
         // assuming that linespace is checked on receive for validity
         let font_height = (bare_font_height + self.linespace).ceil();
         let font_width = glyph_advance + self.options.width;
@@ -266,32 +398,40 @@ impl CachingShaper {
             // Create font fallback list
             let mut font_fallback_keys = Vec::new();
 
-            // Add parsed fonts from guifont or config file
+            // Add the fonts of the highlight and parsed fonts from guifont or config file
             font_fallback_keys.extend(
-                self.options
-                    .font_list(style)
-                    .iter()
-                    .map(|font_desc| FontKey {
-                        font_desc: Some(font_desc.clone()),
-                        hinting: self.options.hinting.clone(),
-                        edging: self.options.edging.clone(),
+                self.font_list(style)
+                    .into_iter()
+                    .map(|(font_desc, scaled)| {
+                        let key = FontKey {
+                            font_desc: Some(font_desc),
+                            hinting: self.options.hinting.clone(),
+                            edging: self.options.edging.clone(),
+                        };
+                        (key, scaled)
                     })
                     .unique(),
             );
 
             // Add default font
-            font_fallback_keys.push(FontKey {
+            let default_key = FontKey {
                 font_desc: None,
                 hinting: self.options.hinting.clone(),
                 edging: self.options.edging.clone(),
-            });
+            };
+            font_fallback_keys.push((default_key, false));
 
             // Use the cluster.map function to select a viable font from the fallback list and loaded fonts
 
             let mut best = None;
             // Search through the configured and default fonts for a match
-            for fallback_key in font_fallback_keys.iter() {
-                if let Some(font_pair) = self.font_loader.get_or_load(fallback_key) {
+            for (fallback_key, scaled) in font_fallback_keys.iter() {
+                let font_pair = if *scaled {
+                    self.get_or_load_scaled(fallback_key)
+                } else {
+                    self.font_loader.get_or_load(fallback_key)
+                };
+                if let Some(font_pair) = font_pair {
                     let charmap = font_pair.swash_font.as_ref().charmap();
                     match cluster.map(|ch| charmap.map(ch)) {
                         Status::Complete => {
@@ -379,74 +519,149 @@ impl CachingShaper {
         }
     }
 
-    pub fn shape(&mut self, word: Word<'_>, style: CoarseStyle) -> Vec<TextBlob> {
-        let current_size = self.current_size();
-        let glyph_width = self.font_base_dimensions().width;
-
-        let mut resulting_blobs = Vec::new();
-
-        for (cluster_group, font_pair) in self.build_clusters(word, style) {
-            let features = self.get_font_features(
-                font_pair.as_ref().key.font_desc.as_ref().map(|desc| desc.family.as_str()),
-            );
-
-            let mut shaper = self
-                .shape_context
-                .builder(font_pair.swash_font.as_ref())
-                .features(features.iter().map(|(name, value)| (name.as_ref(), *value)))
-                .size(current_size)
-                .build();
-
-            let charmap = font_pair.swash_font.as_ref().charmap();
-            for mut cluster in cluster_group {
-                cluster.map(|ch| charmap.map(ch));
-                shaper.add_cluster(&cluster);
-            }
-
-            let mut glyph_data = Vec::new();
+    /// Picks the fonts for the clusters of a word, and groups them so that they can be shaped.
+    fn shape_jobs(&mut self, word: Word<'_>, style: CoarseStyle) -> Vec<ShapeJob> {
+        self.build_clusters(word, style)
+            .into_iter()
+            .map(|(clusters, font_pair)| {
+                let features = self.get_font_features(
+                    font_pair.as_ref().key.font_desc.as_ref().map(|desc| desc.family.as_str()),
+                );
+                ShapeJob { font_pair, features, clusters }
+            })
+            .collect()
+    }
 
-            shaper.shape_with(|glyph_cluster| {
-                //Align to the grid at the start of each cluster
-                let mut x_offset = glyph_width * glyph_cluster.data as f32;
+    pub fn shape(&mut self, word: Word<'_>, style: CoarseStyle) -> Vec<GlyphRun> {
+        let glyph_width = self.font_base_dimensions().width;
 
-                for glyph in glyph_cluster.glyphs {
-                    let position = (x_offset + glyph.x, -glyph.y);
-                    glyph_data.push((glyph.id, position));
-                    x_offset += glyph.advance;
-                }
-            });
+        let jobs = self.shape_jobs(word, style);
+        jobs.into_iter()
+            .filter_map(|mut job| {
+                let glyphs = shape_clusters(
+                    &mut self.shape_context,
+                    &job.font_pair.swash_font,
+                    &job.features,
+                    &mut job.clusters,
+                    job.font_pair.skia_font.size(),
+                    glyph_width,
+                );
+                make_run(job.font_pair, glyphs)
+            })
+            .collect()
+    }
 
-            if glyph_data.is_empty() {
+    /// Shapes the words that aren't cached yet ahead of drawing them, spread over up to `workers`
+    /// threads. The fonts are still picked here, in order, so the result is the same as shaping
+    /// the words one by one.
+    pub fn shape_words<'a>(
+        &mut self,
+        words: impl IntoIterator<Item = (Word<'a>, CoarseStyle)>,
+        workers: usize,
+    ) {
+        tracy_zone!("shape_words");
+        let mut queued = HashSet::new();
+        let mut missing = Vec::new();
+        for (word, style) in words {
+            let parts: &dyn AsShapeKey = &(word.text, style);
+            if self.blob_cache.contains(parts) || queued.contains(parts) {
                 continue;
             }
+            let key = ShapeKey::new(word.text.to_string(), style);
+            queued.insert(key.clone());
+            missing.push((key, word));
+        }
 
-            let mut blob_builder = TextBlobBuilder::new();
-            let (glyphs, positions) =
-                blob_builder.alloc_run_pos(&font_pair.skia_font, glyph_data.len(), None);
-            for (i, (glyph_id, glyph_position)) in glyph_data.iter().enumerate() {
-                glyphs[i] = *glyph_id;
-                positions[i] = (*glyph_position).into();
+        let workers = workers.min(missing.len() / MIN_WORDS_PER_WORKER);
+        if workers <= 1 {
+            for (key, word) in missing {
+                let runs = self.shape(word, key.style);
+                self.blob_cache.put(key, runs);
             }
-
-            let blob = blob_builder.make();
-            resulting_blobs.push(blob.expect("Could not create textblob"));
+            return;
         }
 
-        resulting_blobs
+        let glyph_width = self.font_base_dimensions().width;
+        let mut jobs = missing
+            .into_iter()
+            .map(|(key, word)| {
+                let jobs = self.shape_jobs(word, key.style);
+                (key, jobs)
+            })
+            .collect::<Vec<_>>();
+
+        // Only the font data is shared with the workers, the font pairs stay on this thread
+        let mut inputs = jobs
+            .iter_mut()
+            .map(|(_, jobs)| {
+                jobs.iter_mut()
+                    .map(|ShapeJob { font_pair, features, clusters }| {
+                        let size = font_pair.skia_font.size();
+                        (&font_pair.swash_font, features.as_slice(), clusters.as_mut_slice(), size)
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        if self.worker_contexts.len() < workers {
+            self.worker_contexts.resize_with(workers, ShapeContext::new);
+        }
+        let chunk_size = inputs.len().div_ceil(workers);
+        let shaped = thread::scope(|scope| {
+            let handles = inputs
+                .chunks_mut(chunk_size)
+                .zip(self.worker_contexts.iter_mut())
+                .map(|(words, context)| {
+                    scope.spawn(move || {
+                        tracy_zone!("shape_words_worker");
+                        words
+                            .iter_mut()
+                            .map(|jobs| {
+                                jobs.iter_mut()
+                                    .map(|(font, features, clusters, size)| {
+                                        shape_clusters(
+                                            context,
+                                            font,
+                                            features,
+                                            clusters,
+                                            *size,
+                                            glyph_width,
+                                        )
+                                    })
+                                    .collect::<Vec<_>>()
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().expect("A shaping worker panicked"))
+                .collect::<Vec<_>>()
+        });
+        drop(inputs);
+
+        for ((key, jobs), shaped) in jobs.into_iter().zip(shaped) {
+            let runs = jobs
+                .into_iter()
+                .zip(shaped)
+                .filter_map(|(job, glyphs)| make_run(job.font_pair, glyphs))
+                .collect();
+            self.blob_cache.put(key, runs);
+        }
     }
 
-    pub fn shape_cached(&mut self, word: Word<'_>, style: CoarseStyle) -> &Vec<TextBlob> {
+    pub fn shape_cached(&mut self, word: Word<'_>, style: CoarseStyle) -> &Vec<GlyphRun> {
         tracy_zone!("shape_cached");
         let text = word.text;
-        let key = ShapeKey::new(text.to_string(), style);
+        let parts: &dyn AsShapeKey = &(text, style);
 
-        if !self.blob_cache.contains(&key) {
+        if !self.blob_cache.contains(parts) {
             trace!("Shaping text: {text:?}");
-            let blobs = self.shape(word, style);
-            self.blob_cache.put(key.clone(), blobs);
+            let runs = self.shape(word, style);
+            self.blob_cache.put(ShapeKey::new(text.to_string(), style), runs);
         }
 
-        self.blob_cache.get(&key).unwrap()
+        self.blob_cache.get(parts).unwrap()
     }
 
     fn get_font_features(&self, name: Option<&str>) -> Vec<(String, u16)> {
@@ -466,3 +681,126 @@ impl CachingShaper {
         }
     }
 }
+
+/// The metrics of the font at the size, and the advance of `M` for the width of the cells.
+fn font_info(context: &mut ShapeContext, font: &SwashFont, size: f32) -> (Metrics, f32) {
+    let mut shaper =
+        context.builder(font.as_ref()).variations(font.variations()).size(size).build();
+    shaper.add_str("M");
+    let metrics = shaper.metrics();
+    let mut advance = metrics.average_width;
+    shaper.shape_with(|cluster| {
+        advance = cluster.glyphs.first().map_or(metrics.average_width, |g| g.advance);
+    });
+    (metrics, advance)
+}
+
+fn line_height(metrics: &Metrics) -> f32 {
+    metrics.ascent + metrics.descent + metrics.leading
+}
+
+/// Shapes clusters that use the same font, aligning each cluster to the start of its cell.
+fn shape_clusters(
+    context: &mut ShapeContext,
+    font: &SwashFont,
+    features: &[(String, u16)],
+    clusters: &mut [CharCluster],
+    size: f32,
+    glyph_width: f32,
+) -> (Vec<GlyphId>, Vec<Point>) {
+    let mut shaper = context
+        .builder(font.as_ref())
+        .variations(font.variations())
+        .features(features.iter().map(|(name, value)| (name.as_ref(), *value)))
+        .size(size)
+        .build();
+
+    let charmap = font.as_ref().charmap();
+    for cluster in clusters {
+        cluster.map(|ch| charmap.map(ch));
+        shaper.add_cluster(cluster);
+    }
+
+    let mut glyph_ids = Vec::new();
+    let mut glyph_positions = Vec::new();
+
+    shaper.shape_with(|glyph_cluster| {
+        //Align to the grid at the start of each cluster
+        let mut x_offset = glyph_width * glyph_cluster.data as f32;
+
+        for glyph in glyph_cluster.glyphs {
+            glyph_ids.push(glyph.id);
+            glyph_positions.push(Point::new(x_offset + glyph.x, -glyph.y));
+            x_offset += glyph.advance;
+        }
+    });
+
+    (glyph_ids, glyph_positions)
+}
+
+fn make_run(
+    font_pair: Rc<FontPair>,
+    (glyph_ids, glyph_positions): (Vec<GlyphId>, Vec<Point>),
+) -> Option<GlyphRun> {
+    if glyph_ids.is_empty() {
+        return None;
+    }
+
+    let mut blob_builder = TextBlobBuilder::new();
+    let (glyphs, positions) =
+        blob_builder.alloc_run_pos(&font_pair.skia_font, glyph_ids.len(), None);
+    glyphs.copy_from_slice(&glyph_ids);
+    positions.copy_from_slice(&glyph_positions);
+
+    let blob = blob_builder.make().expect("Could not create textblob");
+    Some(GlyphRun { font: font_pair, glyphs: glyph_ids, positions: glyph_positions, blob })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// What's drawn of the runs of a word.
+    fn shaped(
+        shaper: &mut CachingShaper,
+        word: Word<'_>,
+        style: CoarseStyle,
+    ) -> Vec<(FontKey, Vec<GlyphId>, Vec<Point>)> {
+        shaper
+            .shape_cached(word, style)
+            .iter()
+            .map(|run| (run.font.key.clone(), run.glyphs.clone(), run.positions.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn test_parallel_shaping_matches_sequential() {
+        let texts = (0..200)
+            .map(|index| format!("{index}->={}ffi", "x".repeat(index % 7)))
+            .collect::<Vec<_>>();
+        let cluster_sizes = texts.iter().map(|text| vec![1; text.len()]).collect::<Vec<_>>();
+        let styles = CoarseStyle::permutations().collect::<Vec<_>>();
+        let words = || {
+            texts
+                .iter()
+                .zip(&cluster_sizes)
+                .zip(styles.iter().cycle())
+                .map(|((text, sizes), style)| (Word::new(text, sizes), *style))
+        };
+
+        let mut sequential = CachingShaper::new(1.0);
+        sequential.shape_words(words(), 1);
+        let mut parallel = CachingShaper::new(1.0);
+        parallel.shape_words(words(), 4);
+
+        for (index, text) in texts.iter().enumerate() {
+            let style = styles[index % styles.len()];
+            let parts: &dyn AsShapeKey = &(text.as_str(), style);
+            assert!(sequential.blob_cache.contains(parts));
+            assert!(parallel.blob_cache.contains(parts));
+            let word = || Word::new(text, &cluster_sizes[index]);
+            let expected = shaped(&mut sequential, word(), style);
+            assert_eq!(shaped(&mut parallel, word(), style), expected, "{text}");
+        }
+    }
+}
@@ -1,19 +1,26 @@
 use std::{
+    collections::HashSet,
     fmt::{Display, Formatter},
     num::NonZeroUsize,
     rc::Rc,
+    time::Instant,
 };
 
-use log::trace;
+use log::{trace, warn};
 use lru::LruCache;
-use skia_safe::{Data, Font, FontHinting as SkiaHinting, FontMgr, font::Edging as SkiaEdging};
+use skia_safe::{
+    Data, Font, FontArguments, FontHinting as SkiaHinting, FontMgr, FourByteTag, Typeface,
+    font::Edging as SkiaEdging,
+    font_arguments::{VariationPosition, variation_position::Coordinate},
+};
 
 use crate::{
     profiling::tracy_zone,
     renderer::fonts::{
-        font_options::{CoarseStyle, FontDescription, FontEdging, FontHinting},
+        font_options::{CoarseStyle, FontAxes, FontDescription, FontEdging, FontHinting},
         swash_font::SwashFont,
     },
+    startup_time,
 };
 
 static DEFAULT_FONT: &[u8] = include_bytes!("../../../assets/fonts/FiraCodeNerdFont-Regular.ttf");
@@ -37,10 +44,25 @@ impl FontPair {
         // Only the lower 16 bits are part of the index, the rest indicates named instances. But we
         // don't care about those here, since we are just loading the font, so ignore them
         let index = index & 0xFFFF;
-        let swash_font = SwashFont::from_data(font_data, index)?;
+        // The font data doesn't include the position of a variable font, so it's shaped with the
+        // axes of the typeface instead
+        let variations = typeface
+            .variation_design_position()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|coordinate| (coordinate.axis.to_be_bytes(), coordinate.value))
+            .collect();
+        let swash_font = SwashFont::from_data(font_data, index, variations)?;
 
         Some(Self { key, skia_font, swash_font })
     }
+
+    /// The same font at another size.
+    pub fn with_size(&self, size: f32) -> Option<FontPair> {
+        let mut skia_font = self.skia_font.clone();
+        skia_font.set_size(size);
+        FontPair::new(self.key.clone(), skia_font)
+    }
 }
 
 impl PartialEq for FontPair {
@@ -61,6 +83,8 @@ pub struct FontKey {
 pub struct FontLoader {
     font_mgr: FontMgr,
     cache: LruCache<FontKey, Rc<FontPair>>,
+    /// The fonts that could not be loaded, so that they are only tried once.
+    failed: HashSet<FontKey>,
     font_size: f32,
     last_resort: Option<Rc<FontPair>>,
 }
@@ -80,6 +104,7 @@ impl FontLoader {
         FontLoader {
             font_mgr: FontMgr::new(),
             cache: LruCache::new(NonZeroUsize::new(20).unwrap()),
+            failed: HashSet::new(),
             font_size,
             last_resort: None,
         }
@@ -91,6 +116,7 @@ impl FontLoader {
         if let Some(desc) = &font_key.font_desc {
             let (family, style) = desc.as_family_and_font_style();
             let typeface = self.font_mgr.match_family_style(family, style)?;
+            let typeface = with_axes(typeface, &desc.axes);
             FontPair::new(font_key, Font::from_typeface(typeface, self.font_size))
         } else {
             let data = Data::new_copy(DEFAULT_FONT);
@@ -104,7 +130,17 @@ impl FontLoader {
             return Some(cached.clone());
         }
 
-        let loaded_font = self.load(font_key.clone())?;
+        if self.failed.contains(font_key) {
+            return None;
+        }
+
+        let start = Instant::now();
+        let Some(loaded_font) = self.load(font_key.clone()) else {
+            warn!("Could not load {font_key}, it will be skipped");
+            self.failed.insert(font_key.clone());
+            return None;
+        };
+        startup_time::record(format_args!("load {font_key}"), start);
         let font_rc = Rc::new(loaded_font);
         self.cache.put(font_key.clone(), font_rc.clone());
 
@@ -124,6 +160,7 @@ impl FontLoader {
             font_desc: Some(FontDescription {
                 family: typeface.family_name(),
                 style: coarse_style.name().map(str::to_string),
+                ..Default::default()
             }),
             hinting: FontHinting::default(),
             edging: FontEdging::default(),
@@ -168,6 +205,28 @@ impl FontLoader {
     }
 }
 
+/// Sets the axes of a variable font. Skia clamps the values to the ranges of the font, and ignores
+/// the axes it doesn't have.
+fn with_axes(typeface: Typeface, axes: &FontAxes) -> Typeface {
+    if axes.is_empty() {
+        return typeface;
+    }
+
+    let coordinates = axes
+        .iter()
+        .filter_map(|(tag, value)| {
+            let Ok(tag) = <[u8; 4]>::try_from(tag.as_bytes()) else {
+                warn!("Invalid font axis {tag}, the tag has to be four characters");
+                return None;
+            };
+            Some(Coordinate { axis: FourByteTag::new(u32::from_be_bytes(tag)), value })
+        })
+        .collect::<Vec<_>>();
+    let arguments = FontArguments::new()
+        .set_variation_design_position(VariationPosition { coordinates: &coordinates });
+    typeface.clone_with_arguments(&arguments).unwrap_or(typeface)
+}
+
 fn font_hinting(hinting: &FontHinting) -> SkiaHinting {
     match hinting {
         FontHinting::Full => SkiaHinting::Full,
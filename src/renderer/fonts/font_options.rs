@@ -1,4 +1,11 @@
-use std::{collections::HashMap, fmt, iter, num::ParseFloatError, sync::Arc};
+use std::{
+    collections::HashMap,
+    fmt,
+    hash::{Hash, Hasher},
+    iter,
+    num::ParseFloatError,
+    sync::Arc,
+};
 
 use itertools::Itertools;
 use log::warn;
@@ -9,7 +16,10 @@ use skia_safe::{
     font_style::{Slant, Weight, Width},
 };
 
-use crate::{editor, error_msg, settings::ParseFromValue};
+use crate::{
+    editor, error_msg,
+    settings::{ConfigSchema, ParseFromValue},
+};
 
 pub const DEFAULT_FONT_SIZE: f32 = 14.0;
 const FONT_OPTS_SEPARATOR: char = ':';
@@ -21,23 +31,40 @@ const FONT_WIDTH_PREFIX: char = 'w';
 const FONT_BOLD_OPT: &str = "b";
 const FONT_ITALIC_OPT: &str = "i";
 
+const FONT_AXIS_SEPARATOR: char = '=';
+const FONT_BOLD_AXIS_PREFIX: &str = "b.";
+const FONT_ITALIC_AXIS_PREFIX: &str = "i.";
+const FONT_BOLD_ITALIC_AXIS_PREFIX: &str = "bi.";
+
 const INVALID_SIZE_ERR: &str = "Invalid size";
 const INVALID_WIDTH_ERR: &str = "Invalid width";
+const INVALID_AXIS_ERR: &str = "Invalid font axis";
 
 /// Description of the normal font.
-#[derive(Clone, Debug, Deserialize, PartialEq, Hash, Eq, Default)]
+#[derive(Clone, Debug, Deserialize, PartialEq, Hash, Eq, Default, ConfigSchema)]
 pub struct FontDescription {
     pub family: String,
     pub style: Option<String>,
+    /// Values of the axes of a variable font, like `{ wght = 450 }`.
+    #[serde(default)]
+    pub axes: FontAxes,
 }
 
 /// Description of the italic and bold fonts.
-#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[derive(Clone, Debug, Deserialize, PartialEq, ConfigSchema)]
 pub struct SecondaryFontDescription {
     pub family: Option<String>,
     pub style: Option<String>,
+    /// Values of the axes of a variable font, like `{ wght = 700 }`.
+    #[serde(default)]
+    pub axes: FontAxes,
 }
 
+/// Values of the axes of a variable font, sorted by their four letter tag.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(from = "HashMap<String, f32>")]
+pub struct FontAxes(Vec<(String, f32)>);
+
 #[derive(Clone, Debug, Deserialize, PartialEq)]
 pub struct FontFeature(pub String, pub u16);
 
@@ -47,9 +74,29 @@ pub struct FontFeature(pub String, pub u16);
 pub struct CoarseStyle {
     bold: bool,
     italic: bool,
+    font: Option<FontOverride>,
+}
+
+/// A font that is used instead of the configured fonts, before falling back to them.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum FontOverride {
+    /// The font of the `altfont` highlight attribute.
+    AltFont,
+    /// The font of a highlight group, by its index in the [`HighlightFonts`].
+    ///
+    /// [`HighlightFonts`]: super::highlight_fonts::HighlightFonts
+    Highlight(u32),
 }
 
 impl CoarseStyle {
+    pub fn with_font(self, font: Option<FontOverride>) -> Self {
+        Self { font, ..self }
+    }
+
+    pub fn font(&self) -> Option<FontOverride> {
+        self.font
+    }
+
     /// Returns the textual name of this style.
     pub fn name(&self) -> Option<&'static str> {
         let name = match (self.bold, self.italic) {
@@ -63,14 +110,16 @@ impl CoarseStyle {
 
     /// Iterates through all possible style permutations.
     pub fn permutations() -> impl Iterator<Item = CoarseStyle> {
-        iter::repeat_n([true, false], 2)
-            .multi_cartesian_product()
-            .map(|values| CoarseStyle { bold: values[0], italic: values[1] })
+        iter::repeat_n([true, false], 2).multi_cartesian_product().map(|values| CoarseStyle {
+            bold: values[0],
+            italic: values[1],
+            font: None,
+        })
     }
 }
 
 impl From<CoarseStyle> for FontStyle {
-    fn from(CoarseStyle { bold, italic }: CoarseStyle) -> Self {
+    fn from(CoarseStyle { bold, italic, .. }: CoarseStyle) -> Self {
         match (bold, italic) {
             (true, true) => FontStyle::bold_italic(),
             (true, false) => FontStyle::bold(),
@@ -82,7 +131,7 @@ impl From<CoarseStyle> for FontStyle {
 
 impl From<&editor::Style> for CoarseStyle {
     fn from(fine_style: &editor::Style) -> Self {
-        Self { bold: fine_style.bold, italic: fine_style.italic }
+        Self { bold: fine_style.bold, italic: fine_style.italic, font: None }
     }
 }
 
@@ -100,6 +149,10 @@ pub struct FontOptions {
     pub bold: Option<Vec<SecondaryFontDescription>>,
     pub bold_italic: Option<Vec<SecondaryFontDescription>>,
     pub features: HashMap<String /* family */, Vec<FontFeature> /* features */>,
+    /// The fonts of highlight groups, used before the other fonts.
+    pub highlight: HashMap<String /* group */, Vec<FontDescription>>,
+    /// The fonts of the text with the `altfont` highlight attribute.
+    pub altfont: Vec<FontDescription>,
     pub size: f32,
     pub width: f32,
     pub hinting: FontHinting,
@@ -128,6 +181,91 @@ impl FontFeature {
     }
 }
 
+impl FontDescription {
+    /// The description of the font for text of the style. A style that is set explicitly is kept.
+    pub fn with_style(&self, style: CoarseStyle) -> FontDescription {
+        FontDescription {
+            family: self.family.clone(),
+            style: self.style.clone().or_else(|| style.name().map(str::to_string)),
+            axes: self.axes.for_style(style),
+        }
+    }
+}
+
+impl FontAxes {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, f32)> {
+        self.0.iter().map(|(tag, value)| (tag.as_str(), *value))
+    }
+
+    /// Sets the value of the axis, replacing the previous value.
+    pub fn set(&mut self, tag: &str, value: f32) {
+        match self.0.binary_search_by(|(existing, _)| existing.as_str().cmp(tag)) {
+            Ok(index) => self.0[index].1 = value,
+            Err(index) => self.0.insert(index, (tag.to_string(), value)),
+        }
+    }
+
+    /// Parses an axis of the guifont, like `wght=450`.
+    fn parse_into(&mut self, axis: &str) -> Result<(), &'static str> {
+        let (tag, value) = axis.split_once(FONT_AXIS_SEPARATOR).ok_or(INVALID_AXIS_ERR)?;
+        if tag.len() != 4 || !tag.is_ascii() {
+            return Err(INVALID_AXIS_ERR);
+        }
+        let value = value.parse().map_err(|_| INVALID_AXIS_ERR)?;
+        self.set(tag, value);
+        Ok(())
+    }
+
+    /// Returns the axes without the ones the style sets, so that a bold variant isn't drawn with
+    /// the weight of the normal font.
+    fn for_style(&self, style: CoarseStyle) -> FontAxes {
+        let axes = self
+            .0
+            .iter()
+            .filter(|(tag, _)| !(style.bold && tag == "wght"))
+            .filter(|(tag, _)| !(style.italic && (tag == "slnt" || tag == "ital")))
+            .cloned()
+            .collect();
+        FontAxes(axes)
+    }
+}
+
+impl From<HashMap<String, f32>> for FontAxes {
+    fn from(axes: HashMap<String, f32>) -> Self {
+        FontAxes(axes.into_iter().sorted_by(|a, b| a.0.cmp(&b.0)).collect())
+    }
+}
+
+impl PartialEq for FontAxes {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.len() == other.0.len()
+            && self.0.iter().zip(&other.0).all(|((tag, value), (other_tag, other_value))| {
+                tag == other_tag && value.to_bits() == other_value.to_bits()
+            })
+    }
+}
+
+impl Eq for FontAxes {}
+
+impl Hash for FontAxes {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        for (tag, value) in &self.0 {
+            tag.hash(state);
+            value.to_bits().hash(state);
+        }
+    }
+}
+
+impl ConfigSchema for FontAxes {
+    fn config_schema() -> serde_json::Value {
+        HashMap::<String, f32>::config_schema()
+    }
+}
+
 impl FontOptions {
     pub fn parse(guifont_setting: &str) -> Result<FontOptions, &str> {
         let mut font_options = FontOptions::default();
@@ -145,14 +283,29 @@ impl FontOptions {
             if !parsed_font_list.is_empty() {
                 font_options.normal = parsed_font_list
                     .into_iter()
-                    .map(|family| FontDescription { family, style: None })
+                    .map(|family| FontDescription { family, ..Default::default() })
                     .collect();
             }
         }
 
         let mut style: Vec<String> = vec![];
+        let mut axes = FontAxes::default();
+        let mut bold_axes = FontAxes::default();
+        let mut italic_axes = FontAxes::default();
+        let mut bold_italic_axes = FontAxes::default();
         for part in parts {
-            if let Some(hinting_string) = part.strip_prefix(FONT_HINTING_PREFIX) {
+            // Checked first, since the tags of the axes can start like the other options
+            if part.contains(FONT_AXIS_SEPARATOR) {
+                if let Some(axis) = part.strip_prefix(FONT_BOLD_ITALIC_AXIS_PREFIX) {
+                    bold_italic_axes.parse_into(axis)?;
+                } else if let Some(axis) = part.strip_prefix(FONT_BOLD_AXIS_PREFIX) {
+                    bold_axes.parse_into(axis)?;
+                } else if let Some(axis) = part.strip_prefix(FONT_ITALIC_AXIS_PREFIX) {
+                    italic_axes.parse_into(axis)?;
+                } else {
+                    axes.parse_into(part)?;
+                }
+            } else if let Some(hinting_string) = part.strip_prefix(FONT_HINTING_PREFIX) {
                 font_options.hinting = FontHinting::parse(hinting_string)?;
             } else if let Some(edging_string) = part.strip_prefix(FONT_EDGING_PREFIX) {
                 font_options.edging = FontEdging::parse(edging_string)?;
@@ -173,8 +326,34 @@ impl FontOptions {
         };
         for font in font_options.normal.iter_mut() {
             font.style.clone_from(&style);
+            font.axes.clone_from(&axes);
         }
 
+        // The variants with their own axes use the normal fonts, with those axes on top of the
+        // ones they inherit
+        let variant = |bold: bool, italic: bool, variant_axes: FontAxes| {
+            if variant_axes.is_empty() {
+                return None;
+            }
+            let mut merged_axes = axes.for_style(CoarseStyle { bold, italic, font: None });
+            for (tag, value) in variant_axes.iter() {
+                merged_axes.set(tag, value);
+            }
+            let fonts = font_options
+                .normal
+                .iter()
+                .map(|font| SecondaryFontDescription {
+                    family: Some(font.family.clone()),
+                    style: None,
+                    axes: merged_axes.clone(),
+                })
+                .collect();
+            Some(fonts)
+        };
+        font_options.bold = variant(true, false, bold_axes);
+        font_options.italic = variant(false, true, italic_axes);
+        font_options.bold_italic = variant(true, true, bold_italic_axes);
+
         Ok(font_options)
     }
 
@@ -182,8 +361,9 @@ impl FontOptions {
         self.normal.first().cloned()
     }
 
+    /// The fonts for the style, without the font it overrides them with.
     pub fn font_list(&self, style: CoarseStyle) -> Vec<FontDescription> {
-        if style == CoarseStyle::default() {
+        if !style.bold && !style.italic {
             return self.normal.clone();
         }
 
@@ -198,6 +378,7 @@ impl FontOptions {
             // use current requested font style instead of normal
             style: style.name().map(str::to_string),
             family: font.family.clone(),
+            axes: font.axes.for_style(style),
         });
 
         fonts
@@ -214,6 +395,7 @@ impl FontOptions {
                                 .map(|primary_font| FontDescription {
                                     family: primary_font.family.clone(),
                                     style: font.style.clone(),
+                                    axes: font.axes.clone(),
                                 })
                                 .unwrap()
                         } else {
@@ -223,6 +405,7 @@ impl FontOptions {
                                     .style
                                     .clone()
                                     .or_else(|| style.name().map(str::to_string)),
+                                axes: font.axes.clone(),
                             }
                         }
                     })
@@ -248,6 +431,8 @@ impl Default for FontOptions {
             bold: None,
             bold_italic: None,
             features: HashMap::new(),
+            highlight: HashMap::new(),
+            altfont: Vec::new(),
             size: points_to_pixels(DEFAULT_FONT_SIZE),
             width: 0.0,
             hinting: FontHinting::default(),
@@ -264,6 +449,8 @@ impl PartialEq for FontOptions {
             && self.italic == other.italic
             && self.bold_italic == other.bold_italic
             && self.features == other.features
+            && self.highlight == other.highlight
+            && self.altfont == other.altfont
             && self.edging == other.edging
             && (self.size - other.size).abs() < f32::EPSILON
             && self.hinting == other.hinting
@@ -345,19 +532,29 @@ pub enum PixelGeometry {
 }
 
 impl ParseFromValue for PixelGeometry {
-    fn parse_from_value(&mut self, value: Value) {
-        match value.as_str() {
-            Some("Unknown") => *self = Self::Unknown,
-            Some("RGBH") => *self = Self::RGBH,
-            Some("BGRH") => *self = Self::BGRH,
-            Some("RGBV") => *self = Self::RGBV,
-            Some("BGRV") => *self = Self::BGRV,
+    fn parse_from_value(&mut self, value: Value) -> bool {
+        *self = match value.as_str() {
+            Some("Unknown") => Self::Unknown,
+            Some("RGBH") => Self::RGBH,
+            Some("BGRH") => Self::BGRH,
+            Some("RGBV") => Self::RGBV,
+            Some("BGRV") => Self::BGRV,
             _ => {
                 error_msg!(
                     "Setting expected \"RGBH\", \"BGRH\", \"RGBV\", \"BGRV\", or \"Unknown\", but received {value:?}"
                 );
+                return false;
             }
-        }
+        };
+        true
+    }
+
+    fn value_kind() -> &'static str {
+        "string"
+    }
+
+    fn allowed_values() -> &'static [&'static str] {
+        &["Unknown", "RGBH", "BGRH", "RGBV", "BGRV"]
     }
 }
 
@@ -406,6 +603,10 @@ pub fn points_to_pixels(value: f32) -> f32 {
     }
 }
 
+pub fn pixels_to_points(value: f32) -> f32 {
+    value / points_to_pixels(1.0)
+}
+
 impl FontDescription {
     pub fn as_family_and_font_style(&self) -> (&str, FontStyle) {
         // support font weights:
@@ -622,6 +823,64 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_font_axes_from_guifont_setting() {
+        let guifont_setting = "Recursive:h12:wght=450:CASL=1:wdth=87.5";
+        let font_options = FontOptions::parse(guifont_setting).unwrap();
+
+        assert_eq!(font_options.size, points_to_pixels(12.0));
+        assert_eq!(font_options.width, 0.0);
+        assert_eq!(
+            font_options.normal[0].axes.iter().collect_vec(),
+            vec![("CASL", 1.0), ("wdth", 87.5), ("wght", 450.0)],
+        );
+    }
+
+    #[test]
+    fn test_parse_variant_font_axes_from_guifont_setting() {
+        let guifont_setting = "Recursive:wght=450:slnt=0:CASL=1:b.wght=800";
+        let font_options = FontOptions::parse(guifont_setting).unwrap();
+        let bold = CoarseStyle { bold: true, italic: false, font: None };
+        let italic = CoarseStyle { bold: false, italic: true, font: None };
+
+        // The bold variant inherits the axes that aren't about the weight
+        assert_eq!(
+            font_options.font_list(bold)[0].axes.iter().collect_vec(),
+            vec![("CASL", 1.0), ("slnt", 0.0), ("wght", 800.0)],
+        );
+        // And the italic variant leaves the slant to the style
+        assert_eq!(
+            font_options.font_list(italic)[0].axes.iter().collect_vec(),
+            vec![("CASL", 1.0), ("wght", 450.0)],
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid_font_axis_from_guifont_setting() {
+        for guifont_setting in ["Recursive:weight=450", "Recursive:wght=heavy", "Recursive:b.=1"] {
+            let err = FontOptions::parse(guifont_setting).unwrap_err();
+            assert_eq!(err, INVALID_AXIS_ERR, "{guifont_setting} should be invalid");
+        }
+    }
+
+    #[test]
+    fn test_font_list_of_font_override() {
+        let font_options = FontOptions::parse("Fira Code Mono:wght=450").unwrap();
+        let style = CoarseStyle::default().with_font(Some(FontOverride::Highlight(0)));
+        // The overridden fonts fall back to the normal fonts
+        assert_eq!(font_options.font_list(style), font_options.normal);
+
+        let cursive = FontDescription { family: "Victor Mono".to_string(), ..Default::default() };
+        let bold = CoarseStyle { bold: true, italic: false, font: Some(FontOverride::AltFont) };
+        let bold_cursive = cursive.with_style(bold);
+        assert_eq!(bold_cursive.family, "Victor Mono");
+        assert_eq!(bold_cursive.style.as_deref(), Some("Bold"));
+
+        let italic_cursive =
+            FontDescription { style: Some("Light Italic".to_string()), ..cursive.clone() };
+        assert_eq!(italic_cursive.with_style(bold).style.as_deref(), Some("Light Italic"));
+    }
+
     #[test]
     fn test_parse_font_name_with_escapes() {
         let without_escapes_or_specials_chars = parse_font_name("Fira Code Mono");
@@ -666,6 +925,7 @@ mod tests {
         let font_style = FontDescription {
             family: "Fira Code Mono".to_string(),
             style: Some("Bold Italic".to_string()),
+            ..Default::default()
         };
 
         let (family, style) = font_style.as_family_and_font_style();
@@ -685,6 +945,7 @@ mod tests {
         let font_style = FontDescription {
             family: "Fira Code Mono".to_string(),
             style: Some("SemiBold".to_string()),
+            ..Default::default()
         };
 
         let (family, style) = font_style.as_family_and_font_style();
@@ -704,6 +965,7 @@ mod tests {
         let font_style = FontDescription {
             family: "Fira Code Mono".to_string(),
             style: Some("W100".to_string()),
+            ..Default::default()
         };
 
         let (family, style) = font_style.as_family_and_font_style();
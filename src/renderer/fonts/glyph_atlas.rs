@@ -0,0 +1,474 @@
+//! An alternative text pipeline, where every glyph is rasterized once into a shared atlas and the
+//! text is drawn as textured quads from it, instead of drawing text blobs.
+//!
+//! The atlas is split into pages that are filled with shelves of glyphs. The glyphs are rasterized
+//! on the CPU, and the new glyphs of a run are copied into an image of just the rectangle they
+//! cover, so only those are uploaded to the GPU. The images are never changed after that, since
+//! the lines are recorded into pictures that keep them. Once a page has too many of them, they're
+//! replaced by a single image of the page. When the memory cap is reached, the least recently
+//! used page is evicted and its glyphs are rasterized again when needed.
+
+use std::collections::HashMap;
+
+use skia_safe::{
+    BlendMode, Canvas, Color, Data, Font, GlyphId, IRect, ISize, Image, ImageInfo, Paint, Point,
+    RSXform, Rect, RoundOut, SamplingOptions, Surface, font::Edging, images, surfaces,
+    typeface::TypefaceId,
+};
+
+use crate::{
+    profiling::tracy_zone,
+    renderer::fonts::{
+        caching_shaper::GlyphRun,
+        font_options::{FontEdging, FontHinting},
+    },
+};
+
+/// The width and height of a page, so that its pixels use 1 MiB.
+const PAGE_SIZE: i32 = 512;
+/// A page uses its pixels twice, once where the glyphs are rasterized and once in the images.
+const PAGE_BYTES: usize = PAGE_SIZE as usize * PAGE_SIZE as usize * 4 * 2;
+/// How many images a page can have before they're merged, so that a text run doesn't need too
+/// many draws.
+const MAX_CHUNKS: usize = 16;
+/// The number of horizontal subpixel positions the glyphs are rasterized at.
+const SUBPIXEL_STEPS: f32 = 4.0;
+/// Empty space around each glyph, so that neighbours don't bleed into each other.
+const PADDING: i32 = 1;
+
+#[derive(Hash, PartialEq, Eq)]
+struct GlyphKey {
+    typeface: TypefaceId,
+    size: u32,
+    /// The same typeface is rasterized differently with other hinting and edging options.
+    hinting: FontHinting,
+    edging: FontEdging,
+    glyph: GlyphId,
+    subpixel: u8,
+}
+
+#[derive(Clone, Copy)]
+struct AtlasGlyph {
+    page: usize,
+    /// Where the glyph is in the page, or `None` for glyphs without any pixels, like spaces.
+    source: Option<IRect>,
+    /// The top left corner of the glyph relative to the pen position.
+    offset: Point,
+    /// Color glyphs, like emojis, are drawn as is instead of being tinted by the text color.
+    colored: bool,
+}
+
+/// Packs rectangles into rows of similar height, from the top of the page to the bottom.
+#[derive(Default)]
+struct ShelfAllocator {
+    /// The top, height and used width of every shelf.
+    shelves: Vec<(i32, i32, i32)>,
+}
+
+impl ShelfAllocator {
+    fn allocate(&mut self, size: ISize) -> Option<IRect> {
+        if size.width > PAGE_SIZE || size.height > PAGE_SIZE {
+            return None;
+        }
+        // Don't waste more than a third of a shelf on smaller glyphs
+        let fits = |height: i32| size.height <= height && size.height * 3 >= height * 2;
+        for (top, height, used) in self.shelves.iter_mut() {
+            if fits(*height) && *used + size.width <= PAGE_SIZE {
+                let rect = IRect::from_xywh(*used, *top, size.width, size.height);
+                *used += size.width;
+                return Some(rect);
+            }
+        }
+
+        let top = self.used_height();
+        if top + size.height > PAGE_SIZE {
+            return None;
+        }
+        self.shelves.push((top, size.height, size.width));
+        Some(IRect::from_xywh(0, top, size.width, size.height))
+    }
+
+    /// The height of the shelves, from the top of the page.
+    fn used_height(&self) -> i32 {
+        self.shelves.last().map_or(0, |(top, height, _)| top + height)
+    }
+}
+
+/// An image of a part of a page, that's uploaded to the GPU when it's first drawn.
+struct Chunk {
+    rect: IRect,
+    image: Image,
+}
+
+struct Page {
+    /// Where the glyphs are rasterized.
+    surface: Surface,
+    /// The images the glyphs are drawn from.
+    chunks: Vec<Chunk>,
+    /// The glyphs that aren't in an image yet.
+    dirty: Option<IRect>,
+    allocator: ShelfAllocator,
+    last_used: u64,
+}
+
+impl Page {
+    fn new() -> Option<Self> {
+        Some(Self {
+            surface: surfaces::raster_n32_premul((PAGE_SIZE, PAGE_SIZE))?,
+            chunks: Vec::new(),
+            dirty: None,
+            allocator: ShelfAllocator::default(),
+            last_used: 0,
+        })
+    }
+
+    fn add_dirty(&mut self, rect: IRect) {
+        self.dirty = Some(match self.dirty {
+            Some(dirty) => IRect::join(&dirty, &rect),
+            None => rect,
+        });
+    }
+
+    /// Copies the new glyphs into an image, or the whole used part of the page when it has too
+    /// many images already.
+    fn upload(&mut self) {
+        let Some(dirty) = self.dirty.take() else {
+            return;
+        };
+        tracy_zone!("glyph_atlas_upload");
+        let rect = if self.chunks.len() < MAX_CHUNKS {
+            dirty
+        } else {
+            self.chunks.clear();
+            IRect::from_wh(PAGE_SIZE, self.allocator.used_height())
+        };
+        if let Some(image) = self.copy_image(rect) {
+            self.chunks.push(Chunk { rect, image });
+        }
+    }
+
+    /// Copies the pixels into a new image, instead of taking a snapshot that shares them with the
+    /// surface, which would copy the whole page when the next glyph is drawn to it.
+    fn copy_image(&mut self, rect: IRect) -> Option<Image> {
+        let info = ImageInfo::new_n32_premul(rect.size(), None);
+        let row_bytes = info.min_row_bytes();
+        let mut pixels = vec![0; info.compute_min_byte_size()];
+        if !self.surface.read_pixels(&info, &mut pixels, row_bytes, (rect.left, rect.top)) {
+            return None;
+        }
+        images::raster_from_data(&info, Data::new_copy(&pixels), row_bytes)
+    }
+
+    /// The newest image that contains `source`.
+    fn chunk(&self, source: IRect) -> Option<(usize, &Chunk)> {
+        self.chunks.iter().enumerate().rev().find(|(_, chunk)| {
+            chunk.rect.left <= source.left
+                && chunk.rect.top <= source.top
+                && chunk.rect.right >= source.right
+                && chunk.rect.bottom >= source.bottom
+        })
+    }
+}
+
+/// The quads to draw from a single page.
+#[derive(Default)]
+struct Batch {
+    transforms: Vec<RSXform>,
+    sources: Vec<Rect>,
+    colors: Vec<Color>,
+}
+
+pub struct GlyphAtlas {
+    glyphs: HashMap<GlyphKey, AtlasGlyph>,
+    pages: Vec<Option<Page>>,
+    /// Increased for every drawn run, so that the pages in use aren't evicted.
+    generation: u64,
+}
+
+impl GlyphAtlas {
+    pub fn new() -> Self {
+        Self { glyphs: HashMap::new(), pages: Vec::new(), generation: 0 }
+    }
+
+    /// Forgets all glyphs, for example when the font changes.
+    pub fn clear(&mut self) {
+        self.glyphs.clear();
+        self.pages.clear();
+    }
+
+    /// Draws a shaped run at `origin`. `max_memory` is the memory the pages can use, in bytes.
+    pub fn draw_run(
+        &mut self,
+        canvas: &Canvas,
+        run: &GlyphRun,
+        origin: Point,
+        paint: &Paint,
+        max_memory: usize,
+    ) {
+        tracy_zone!("glyph_atlas_draw");
+        self.generation += 1;
+        let max_pages = (max_memory / PAGE_BYTES).max(1);
+        let mut font = run.font.skia_font.clone();
+        // The glyphs are stored as coverage, which can't hold subpixel antialiasing
+        if font.edging() == Edging::SubpixelAntiAlias {
+            font.set_edging(Edging::AntiAlias);
+        }
+        let typeface = font.typeface().unique_id();
+        let size = font.size().to_bits();
+
+        // The new glyphs are rasterized first, so that they're uploaded together
+        let mut quads = Vec::with_capacity(run.glyphs.len());
+        for (glyph, position) in run.glyphs.iter().zip(&run.positions) {
+            let position = origin + *position;
+            let (x, subpixel) = quantize_subpixel(position.x);
+            let y = position.y.round();
+            let key = GlyphKey {
+                typeface,
+                size,
+                hinting: run.font.key.hinting.clone(),
+                edging: run.font.key.edging.clone(),
+                glyph: *glyph,
+                subpixel,
+            };
+            let Some(atlas_glyph) = self.get_or_rasterize(key, &font, max_pages) else {
+                // Too large for the atlas
+                canvas.draw_glyphs_at(&[*glyph], &[Point::default()][..], position, &font, paint);
+                continue;
+            };
+            if atlas_glyph.source.is_some() {
+                quads.push((atlas_glyph, x, y));
+            }
+        }
+        for page in self.pages.iter_mut().flatten() {
+            page.upload();
+        }
+
+        // In the order of their first glyph, so that overlapping glyphs are drawn the same way
+        let mut batches: Vec<((usize, usize), Batch)> = Vec::new();
+        for (atlas_glyph, x, y) in quads {
+            let (Some(source), Some(Some(page))) =
+                (atlas_glyph.source, self.pages.get(atlas_glyph.page))
+            else {
+                continue;
+            };
+            let Some((chunk_index, chunk)) = page.chunk(source) else {
+                continue;
+            };
+            let source = source.with_offset((-chunk.rect.left, -chunk.rect.top));
+
+            let batch_key = (atlas_glyph.page, chunk_index);
+            let index =
+                batches.iter().position(|(key, _)| *key == batch_key).unwrap_or_else(|| {
+                    batches.push((batch_key, Batch::default()));
+                    batches.len() - 1
+                });
+            let batch = &mut batches[index].1;
+            batch.transforms.push(RSXform::new(
+                1.0,
+                0.0,
+                (x as f32 + atlas_glyph.offset.x, y + atlas_glyph.offset.y),
+            ));
+            batch.sources.push(Rect::from(source));
+            batch.colors.push(if atlas_glyph.colored { Color::WHITE } else { paint.color() });
+        }
+
+        for ((page, chunk), batch) in batches {
+            let Some(Some(page)) = self.pages.get(page) else {
+                continue;
+            };
+            canvas.draw_atlas(
+                &page.chunks[chunk].image,
+                &batch.transforms,
+                &batch.sources,
+                &batch.colors[..],
+                BlendMode::Modulate,
+                SamplingOptions::default(),
+                None,
+                paint,
+            );
+        }
+    }
+
+    fn get_or_rasterize(
+        &mut self,
+        key: GlyphKey,
+        font: &Font,
+        max_pages: usize,
+    ) -> Option<AtlasGlyph> {
+        if let Some(glyph) = self.glyphs.get(&key).copied() {
+            if let Some(Some(page)) = self.pages.get_mut(glyph.page) {
+                page.last_used = self.generation;
+            }
+            return Some(glyph);
+        }
+
+        let mut bounds = [Rect::default()];
+        font.get_bounds(&[key.glyph], &mut bounds, None);
+        let subpixel = key.subpixel as f32 / SUBPIXEL_STEPS;
+        let bounds: IRect = bounds[0].with_offset((subpixel, 0.0)).round_out();
+        if bounds.is_empty() {
+            let glyph =
+                AtlasGlyph { page: 0, source: None, offset: Point::default(), colored: false };
+            self.glyphs.insert(key, glyph);
+            return Some(glyph);
+        }
+
+        let padded = ISize::new(bounds.width() + PADDING * 2, bounds.height() + PADDING * 2);
+        let (page_index, slot) = self.allocate(padded, max_pages)?;
+        let source = slot.with_inset((PADDING, PADDING));
+        let page = self.pages[page_index].as_mut()?;
+        page.last_used = self.generation;
+        page.add_dirty(source);
+
+        tracy_zone!("glyph_atlas_rasterize");
+        let canvas = page.surface.canvas();
+        let mut paint = Paint::default();
+        paint.set_color(Color::WHITE);
+        let pen = Point::new(
+            (source.left - bounds.left) as f32 + subpixel,
+            (source.top - bounds.top) as f32,
+        );
+        canvas.draw_glyphs_at(&[key.glyph], &[Point::default()][..], pen, font, &paint);
+
+        let info = ImageInfo::new_n32_premul(source.size(), None);
+        let mut pixels = vec![0; info.compute_min_byte_size()];
+        let colored = page.surface.read_pixels(
+            &info,
+            &mut pixels,
+            info.min_row_bytes(),
+            (source.left, source.top),
+        ) && is_colored(&pixels);
+
+        let glyph = AtlasGlyph {
+            page: page_index,
+            source: Some(source),
+            offset: Point::new(bounds.left as f32, bounds.top as f32),
+            colored,
+        };
+        self.glyphs.insert(key, glyph);
+        Some(glyph)
+    }
+
+    /// Finds space for a glyph, adding a page or evicting the least recently used one when all
+    /// pages are full.
+    fn allocate(&mut self, size: ISize, max_pages: usize) -> Option<(usize, IRect)> {
+        for (index, page) in self.pages.iter_mut().enumerate() {
+            if let Some(slot) = page.as_mut().and_then(|page| page.allocator.allocate(size)) {
+                return Some((index, slot));
+            }
+        }
+
+        let live_pages = self.pages.iter().flatten().count();
+        let index = if live_pages < max_pages {
+            self.pages.iter().position(Option::is_none).unwrap_or_else(|| {
+                self.pages.push(None);
+                self.pages.len() - 1
+            })
+        } else {
+            // The pages used for the current run are kept, even if that goes over the cap
+            let (index, _) = self
+                .pages
+                .iter()
+                .enumerate()
+                .filter_map(|(index, page)| Some((index, page.as_ref()?.last_used)))
+                .filter(|(_, last_used)| *last_used < self.generation)
+                .min_by_key(|(_, last_used)| *last_used)
+                .unwrap_or_else(|| {
+                    self.pages.push(None);
+                    (self.pages.len() - 1, 0)
+                });
+            self.glyphs.retain(|_, glyph| glyph.page != index || glyph.source.is_none());
+            index
+        };
+
+        let mut page = Page::new()?;
+        let slot = page.allocator.allocate(size)?;
+        self.pages[index] = Some(page);
+        Some((index, slot))
+    }
+}
+
+/// Splits a horizontal position into the whole pixel and the subpixel step it's rasterized at.
+fn quantize_subpixel(x: f32) -> (i32, u8) {
+    let steps = (x * SUBPIXEL_STEPS).round() as i32;
+    let steps_per_pixel = SUBPIXEL_STEPS as i32;
+    (steps.div_euclid(steps_per_pixel), steps.rem_euclid(steps_per_pixel) as u8)
+}
+
+/// Whether a glyph rasterized in white has any color, in premultiplied pixels.
+fn is_colored(pixels: &[u8]) -> bool {
+    pixels.chunks_exact(4).any(|pixel| {
+        let alpha = pixel.iter().copied().max().unwrap_or(0);
+        pixel.iter().any(|channel| *channel != alpha)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shelf_allocator() {
+        let mut allocator = ShelfAllocator::default();
+        assert_eq!(allocator.allocate(ISize::new(10, 20)), Some(IRect::from_xywh(0, 0, 10, 20)));
+        assert_eq!(allocator.allocate(ISize::new(10, 16)), Some(IRect::from_xywh(10, 0, 10, 16)));
+        // Too small for the first shelf
+        assert_eq!(allocator.allocate(ISize::new(10, 8)), Some(IRect::from_xywh(0, 20, 10, 8)));
+        assert_eq!(
+            allocator.allocate(ISize::new(PAGE_SIZE, 20)),
+            Some(IRect::from_xywh(0, 28, PAGE_SIZE, 20))
+        );
+        assert_eq!(allocator.allocate(ISize::new(PAGE_SIZE + 1, 1)), None);
+        assert_eq!(allocator.allocate(ISize::new(1, PAGE_SIZE - 47)), None);
+    }
+
+    #[test]
+    fn test_page_uploads_new_glyphs() {
+        let mut page = Page::new().unwrap();
+        let first = page.allocator.allocate(ISize::new(10, 20)).unwrap();
+        let second = page.allocator.allocate(ISize::new(10, 20)).unwrap();
+        page.add_dirty(first);
+        page.add_dirty(second);
+        page.upload();
+        assert_eq!(page.chunks.len(), 1);
+        assert_eq!(page.chunks[0].rect, IRect::from_xywh(0, 0, 20, 20));
+        assert_eq!(page.chunks[0].image.dimensions(), ISize::new(20, 20));
+        assert_eq!(page.chunk(second).map(|(index, _)| index), Some(0));
+
+        // Only the new glyph is copied
+        let third = page.allocator.allocate(ISize::new(10, 20)).unwrap();
+        page.add_dirty(third);
+        page.upload();
+        assert_eq!(page.chunks.len(), 2);
+        assert_eq!(page.chunks[1].rect, third);
+        assert_eq!(page.chunk(third).map(|(index, _)| index), Some(1));
+
+        // Too many images are merged into one
+        while page.chunks.len() < MAX_CHUNKS {
+            let rect = page.allocator.allocate(ISize::new(10, 20)).unwrap();
+            page.add_dirty(rect);
+            page.upload();
+        }
+        let last = page.allocator.allocate(ISize::new(10, 8)).unwrap();
+        page.add_dirty(last);
+        page.upload();
+        assert_eq!(page.chunks.len(), 1);
+        assert_eq!(page.chunks[0].rect, IRect::from_wh(PAGE_SIZE, 28));
+        assert_eq!(page.chunk(first).map(|(index, _)| index), Some(0));
+        assert_eq!(page.chunk(last).map(|(index, _)| index), Some(0));
+    }
+
+    #[test]
+    fn test_quantize_subpixel() {
+        assert_eq!(quantize_subpixel(10.0), (10, 0));
+        assert_eq!(quantize_subpixel(10.3), (10, 1));
+        assert_eq!(quantize_subpixel(10.9), (11, 0));
+        assert_eq!(quantize_subpixel(-0.25), (-1, 3));
+    }
+
+    #[test]
+    fn test_is_colored() {
+        assert!(!is_colored(&[0, 0, 0, 0, 128, 128, 128, 128, 255, 255, 255, 255]));
+        assert!(is_colored(&[0, 0, 0, 0, 200, 40, 40, 255]));
+    }
+}
@@ -0,0 +1,131 @@
+use std::collections::HashMap;
+
+use super::font_options::{FontDescription, FontOverride};
+
+/// The fonts of highlight groups, from `[font.highlight]` in the config file and from
+/// `neovide.set_hl_font`. A single copy is kept in the settings, so that every window uses the
+/// same fonts.
+///
+/// Each group gets a fixed index when it's first given a font, which the styles refer to with
+/// [`FontOverride::Highlight`]. Changing the fonts of a group therefore doesn't change the styles,
+/// only giving a font to a group or removing it does.
+#[derive(Clone, Debug, Default)]
+pub struct HighlightFonts {
+    config: HashMap<String, Vec<FontDescription>>,
+    /// The fonts set with `neovide.set_hl_font`, which take precedence over the config file.
+    runtime: HashMap<String, Vec<FontDescription>>,
+    indices: HashMap<String, u32>,
+    /// The fonts by index, empty for the groups that don't have a font anymore.
+    fonts: Vec<Vec<FontDescription>>,
+}
+
+impl HighlightFonts {
+    /// Replaces the fonts of the config file. Returns whether any font changed.
+    pub fn set_config(&mut self, config: HashMap<String, Vec<FontDescription>>) -> bool {
+        if self.config == config {
+            return false;
+        }
+        self.config = config;
+        self.update();
+        true
+    }
+
+    /// Sets the fonts of a group, or goes back to the fonts of the config file with `None`.
+    pub fn set(&mut self, group: String, fonts: Option<Vec<FontDescription>>) {
+        match fonts {
+            Some(fonts) => self.runtime.insert(group, fonts),
+            None => self.runtime.remove(&group),
+        };
+        self.update();
+    }
+
+    fn update(&mut self) {
+        for fonts in &mut self.fonts {
+            fonts.clear();
+        }
+        let groups = self
+            .config
+            .iter()
+            .filter(|(group, _)| !self.runtime.contains_key(*group))
+            .chain(&self.runtime);
+        for (group, fonts) in groups {
+            if fonts.is_empty() {
+                continue;
+            }
+            let index = *self.indices.entry(group.clone()).or_insert_with(|| {
+                self.fonts.push(Vec::new());
+                self.fonts.len() as u32 - 1
+            });
+            self.fonts[index as usize] = fonts.clone();
+        }
+    }
+
+    /// Whether no group has a font, so the highlight groups of the styles aren't needed.
+    pub fn is_empty(&self) -> bool {
+        self.fonts.iter().all(Vec::is_empty)
+    }
+
+    pub fn fonts(&self, index: u32) -> &[FontDescription] {
+        self.fonts.get(index as usize).map_or(&[], Vec::as_slice)
+    }
+
+    /// The font of a highlight made of `groups`, in the order they are combined.
+    pub fn resolve(&self, groups: &[String]) -> Option<FontOverride> {
+        // The groups that are combined last are on top
+        groups.iter().rev().find_map(|group| self.group_index(group)).map(FontOverride::Highlight)
+    }
+
+    fn group_index(&self, mut group: &str) -> Option<u32> {
+        // Like their highlights, the captures of treesitter fall back to their parents, so
+        // `@string.lua` uses the font of `@string`
+        loop {
+            if let Some(&index) = self.indices.get(group) {
+                if !self.fonts[index as usize].is_empty() {
+                    return Some(index);
+                }
+            }
+            group = &group[..group.rfind('.')?];
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn font(family: &str) -> Vec<FontDescription> {
+        vec![FontDescription { family: family.to_owned(), ..Default::default() }]
+    }
+
+    fn groups(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn test_resolve_highlight_fonts() {
+        let mut fonts = HighlightFonts::default();
+        assert!(fonts.is_empty());
+        assert!(fonts.set_config(HashMap::from([("@string".to_owned(), font("Config"))])));
+        fonts.set("Comment".to_owned(), Some(font("Comment")));
+
+        let string = fonts.resolve(&groups(&["@string.lua"]));
+        assert_eq!(string, Some(FontOverride::Highlight(0)));
+        assert_eq!(fonts.fonts(0), font("Config"));
+        let comment = fonts.resolve(&groups(&["@string", "Comment"]));
+        assert_eq!(comment, Some(FontOverride::Highlight(1)));
+        assert_eq!(fonts.resolve(&groups(&["Normal"])), None);
+
+        // The fonts set at runtime are used instead, with the same index
+        fonts.set("@string".to_owned(), Some(font("Runtime")));
+        assert_eq!(fonts.resolve(&groups(&["@string"])), string);
+        assert_eq!(fonts.fonts(0), font("Runtime"));
+        fonts.set("@string".to_owned(), None);
+        assert_eq!(fonts.fonts(0), font("Config"));
+
+        assert!(!fonts.set_config(HashMap::from([("@string".to_owned(), font("Config"))])));
+        assert!(fonts.set_config(HashMap::new()));
+        assert_eq!(fonts.resolve(&groups(&["@string"])), None);
+        fonts.set("Comment".to_owned(), None);
+        assert!(fonts.is_empty());
+    }
+}
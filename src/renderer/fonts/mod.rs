@@ -1,4 +1,6 @@
 pub mod caching_shaper;
 pub mod font_loader;
 pub mod font_options;
+pub mod glyph_atlas;
+pub mod highlight_fonts;
 mod swash_font;
@@ -4,16 +4,22 @@ pub struct SwashFont {
     data: Vec<u8>,
     offset: u32,
     pub key: CacheKey,
+    /// The position of a variable font, as the tags and values of its axes.
+    variations: Vec<([u8; 4], f32)>,
 }
 
 impl SwashFont {
-    pub fn from_data(data: Vec<u8>, index: usize) -> Option<Self> {
+    pub fn from_data(data: Vec<u8>, index: usize, variations: Vec<([u8; 4], f32)>) -> Option<Self> {
         let font = FontRef::from_index(&data, index)?;
         let (offset, key) = (font.offset, font.key);
-        Some(Self { data, offset, key })
+        Some(Self { data, offset, key, variations })
     }
 
     pub fn as_ref(&self) -> FontRef<'_> {
         FontRef { data: &self.data, offset: self.offset, key: self.key }
     }
+
+    pub fn variations(&self) -> &[([u8; 4], f32)] {
+        &self.variations
+    }
 }
@@ -1,4 +1,4 @@
-use std::{ops::Range, sync::Arc};
+use std::{ops::Range, sync::Arc, thread};
 
 use log::trace;
 use skia_safe::{
@@ -6,7 +6,7 @@ use skia_safe::{
 };
 
 use crate::{
-    editor::{Colors, LineFragment, Style, UnderlineStyle},
+    editor::{Colors, Line, LineFragment, Style, UnderlineStyle},
     profiling::tracy_zone,
     renderer::{
         CachingShaper, RendererSettings,
@@ -19,7 +19,13 @@ use crate::{
     window::WindowSettings,
 };
 
-use super::{box_drawing::BoxDrawingSettings, fonts::font_options::FontOptions};
+use super::{
+    box_drawing::BoxDrawingSettings,
+    fonts::{font_options::FontOptions, glyph_atlas::GlyphAtlas, highlight_fonts::HighlightFonts},
+};
+
+/// The most shaping workers used when the number isn't configured.
+const MAX_AUTOMATIC_SHAPING_WORKERS: usize = 4;
 
 pub struct GridRenderer {
     pub shaper: CachingShaper,
@@ -29,6 +35,7 @@ pub struct GridRenderer {
     pub box_char_renderer: box_drawing::Renderer,
     pub is_ready: bool,
 
+    glyph_atlas: GlyphAtlas,
     settings: Arc<Settings>,
 }
 
@@ -65,6 +72,7 @@ impl GridRenderer {
             ),
             is_ready: false,
 
+            glyph_atlas: GlyphAtlas::new(),
             settings,
         }
     }
@@ -88,6 +96,10 @@ impl GridRenderer {
         self.update_font_dimensions();
     }
 
+    pub fn update_highlight_fonts(&mut self, highlight_fonts: HighlightFonts) {
+        self.shaper.update_highlight_fonts(highlight_fonts);
+    }
+
     pub fn update_linespace(&mut self, linespace_setting: f32) {
         self.shaper.update_linespace(linespace_setting);
         self.update_font_dimensions();
@@ -102,10 +114,32 @@ impl GridRenderer {
         self.grid_scale = GridScale::new(self.shaper.font_base_dimensions());
         let new_cell_size = GridSize::new(1, 1) * self.grid_scale;
         self.box_char_renderer.update_dimensions(new_cell_size, self.em_size);
+        self.glyph_atlas.clear();
         self.is_ready = true;
         trace!("Updated font dimensions: {:?}", self.grid_scale);
     }
 
+    /// Shapes the text of the lines before they are drawn, in parallel when there's a lot of it.
+    pub fn shape_lines<'a>(&mut self, lines: impl IntoIterator<Item = &'a Line>) {
+        let workers = match self.settings.get::<RendererSettings>().shaping_workers {
+            0 => thread::available_parallelism()
+                .map_or(1, |count| count.get())
+                .min(MAX_AUTOMATIC_SHAPING_WORKERS),
+            workers => workers as usize,
+        };
+        let fragments = lines.into_iter().flat_map(Line::fragments).collect::<Vec<_>>();
+        let default_style = &self.default_style;
+        let shaper = &self.shaper;
+        let words = fragments
+            .iter()
+            .flat_map(|fragment| {
+                let style = shaper.coarse_style(fragment.style.as_ref().unwrap_or(default_style));
+                fragment.words().map(move |word| (word, style))
+            })
+            .collect::<Vec<_>>();
+        self.shaper.shape_words(words, workers);
+    }
+
     fn compute_text_region(&self, cells: &Range<u32>) -> PixelRect<f32> {
         let grid_position = GridPos::new(cells.start, 0);
         let pos = grid_position * self.grid_scale;
@@ -259,19 +293,24 @@ impl GridRenderer {
             } else {
                 paint.set_color(style.foreground(&self.default_style.colors).to_color());
             }
+            let RendererSettings { glyph_atlas: use_glyph_atlas, glyph_atlas_size, .. } =
+                self.settings.get::<RendererSettings>();
+            let coarse_style = self.shaper.coarse_style(style);
             for word in fragment.words() {
                 let adjustment = PixelVec::new(
                     word.cell as f32 * self.grid_scale.width(),
                     self.shaper.baseline_offset(),
                 );
-
-                for blob in self.shaper.shape_cached(word, style.into()).iter() {
-                    tracy_zone!("draw_text_blob");
-                    text_canvas.draw_text_blob(
-                        blob,
-                        to_skia_point(region.min + adjustment),
-                        &paint,
-                    );
+                let origin = to_skia_point(region.min + adjustment);
+
+                for run in self.shaper.shape_cached(word, coarse_style).iter() {
+                    if use_glyph_atlas {
+                        let max_memory = glyph_atlas_size as usize * 1024 * 1024;
+                        self.glyph_atlas.draw_run(text_canvas, run, origin, &paint, max_memory);
+                    } else {
+                        tracy_zone!("draw_text_blob");
+                        text_canvas.draw_text_blob(&run.blob, origin, &paint);
+                    }
                     text_drawn = true;
                 }
             }
@@ -0,0 +1,252 @@
+//! An overlay showing the recent input events received from winit, and what was sent to Neovim for
+//! them, to find out why a mapping doesn't fire.
+//!
+//! The log is global, since the commands are recorded where they are sent to Neovim, far away from
+//! the renderer. Nothing is recorded while the inspector is hidden.
+
+use std::{
+    collections::VecDeque,
+    sync::{
+        Arc, LazyLock, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Instant,
+};
+
+use skia_safe::{Canvas, Color, Paint, Rect};
+use winit::{event::WindowEvent, keyboard::ModifiersState};
+
+use crate::{
+    bridge::SerialCommand,
+    profiling::tracy_zone,
+    renderer::{RendererSettings, overlay_font::OverlayFont},
+    settings::Settings,
+};
+
+const MAX_ENTRIES: usize = 200;
+const VISIBLE_ENTRIES: usize = 30;
+const WIDTH: f32 = 560.0;
+const MARGIN: f32 = 32.0;
+const PADDING: f32 = 8.0;
+
+static LOG: InputLog = InputLog::new();
+static START: LazyLock<Instant> = LazyLock::new(Instant::now);
+
+struct LogEntry {
+    seconds: f32,
+    /// Sent to Neovim, instead of received from winit.
+    sent: bool,
+    text: String,
+}
+
+impl LogEntry {
+    fn line(&self) -> String {
+        let direction = if self.sent { "->" } else { "<-" };
+        format!("{:9.3} {direction} {}", self.seconds, self.text)
+    }
+}
+
+/// The recorded entries, and whether anything is recorded at all.
+struct InputLog {
+    recording: AtomicBool,
+    entries: Mutex<VecDeque<LogEntry>>,
+}
+
+impl InputLog {
+    const fn new() -> Self {
+        Self { recording: AtomicBool::new(false), entries: Mutex::new(VecDeque::new()) }
+    }
+
+    /// Starts or stops recording. The entries are cleared when it stops.
+    fn set_recording(&self, recording: bool) {
+        if self.recording.swap(recording, Ordering::Relaxed) && !recording {
+            self.entries.lock().unwrap().clear();
+        }
+    }
+
+    fn is_recording(&self) -> bool {
+        self.recording.load(Ordering::Relaxed)
+    }
+
+    fn record(&self, sent: bool, text: String) {
+        if !self.is_recording() {
+            return;
+        }
+        let entry = LogEntry { seconds: START.elapsed().as_secs_f32(), sent, text };
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() == MAX_ENTRIES {
+            entries.pop_front();
+        }
+        entries.push_back(entry);
+    }
+
+    fn text(&self) -> String {
+        let entries = self.entries.lock().unwrap();
+        entries.iter().map(LogEntry::line).collect::<Vec<_>>().join("\n")
+    }
+}
+
+/// Records a raw winit input event, with the modifiers held while it happened.
+pub fn record_event(event: &WindowEvent, modifiers: ModifiersState) {
+    if !LOG.is_recording() {
+        return;
+    }
+    let description = match event {
+        WindowEvent::KeyboardInput { event, is_synthetic, .. } => format!(
+            "key {:?} logical={:?} physical={:?} text={:?} location={:?}{}{} mods={modifiers:?}",
+            event.state,
+            event.logical_key,
+            event.physical_key,
+            event.text,
+            event.location,
+            if event.repeat { " repeat" } else { "" },
+            if *is_synthetic { " synthetic" } else { "" },
+        ),
+        WindowEvent::ModifiersChanged(modifiers) => format!("modifiers {:?}", modifiers.state()),
+        WindowEvent::Ime(ime) => format!("ime {ime:?}"),
+        WindowEvent::MouseInput { state, button, .. } => {
+            format!("mouse {button:?} {state:?} mods={modifiers:?}")
+        }
+        WindowEvent::MouseWheel { delta, phase, .. } => {
+            format!("wheel {delta:?} {phase:?} mods={modifiers:?}")
+        }
+        WindowEvent::PinchGesture { delta, phase, .. } => format!("pinch {delta:.3} {phase:?}"),
+        WindowEvent::Touch(touch) => format!(
+            "touch {} {:?} at ({:.0}, {:.0})",
+            touch.id, touch.phase, touch.location.x, touch.location.y
+        ),
+        _ => return,
+    };
+    LOG.record(false, description);
+}
+
+/// Records an input command sent to Neovim.
+pub fn record_sent(command: &SerialCommand) {
+    if LOG.is_recording() {
+        LOG.record(true, describe_sent(command));
+    }
+}
+
+fn describe_sent(command: &SerialCommand) -> String {
+    match command {
+        SerialCommand::Keyboard(keys) => format!("nvim_input({keys:?})"),
+        SerialCommand::KeyboardImeCommit { formatted, .. } => format!("ime commit {formatted:?}"),
+        SerialCommand::KeyboardImePreedit { raw, .. } => format!("ime preedit {raw:?}"),
+        SerialCommand::MouseButton { button, action, grid_id, position, modifier_string } => {
+            input_mouse(button, action, modifier_string, *grid_id, *position)
+        }
+        SerialCommand::Scroll { direction, grid_id, position, modifier_string } => {
+            input_mouse("wheel", direction, modifier_string, *grid_id, *position)
+        }
+        SerialCommand::Drag { button, grid_id, position, modifier_string } => {
+            input_mouse(button, "drag", modifier_string, *grid_id, *position)
+        }
+        SerialCommand::Paste(text) => format!("nvim_paste({} bytes)", text.len()),
+        #[cfg(target_os = "macos")]
+        SerialCommand::ForceClickCommand => "NeovideForceClick".to_owned(),
+    }
+}
+
+fn input_mouse(
+    button: &str,
+    action: &str,
+    modifier: &str,
+    grid_id: u64,
+    (col, row): (u32, u32),
+) -> String {
+    format!("nvim_input_mouse({button:?}, {action:?}, {modifier:?}, {grid_id}, {row}, {col})")
+}
+
+/// The whole log as text, for bug reports.
+pub fn log_text() -> String {
+    LOG.text()
+}
+
+pub struct InputInspector {
+    font: OverlayFont,
+    settings: Arc<Settings>,
+}
+
+impl InputInspector {
+    pub fn new(font_size: f32, settings: Arc<Settings>) -> Self {
+        Self { font: OverlayFont::new(font_size), settings }
+    }
+
+    pub fn draw(&self, root_canvas: &Canvas) {
+        tracy_zone!("input_inspector_draw");
+        let enabled = self.settings.get::<RendererSettings>().input_inspector;
+        LOG.set_recording(enabled);
+        if !enabled {
+            return;
+        }
+        let Some(font) = self.font.get() else {
+            return;
+        };
+
+        let log = LOG.entries.lock().unwrap();
+        let line_height = font.spacing();
+        let lines = log.len().clamp(1, VISIBLE_ENTRIES);
+        let width = root_canvas.base_layer_size().width as f32;
+        let rect = Rect::from_xywh(
+            (width - WIDTH - MARGIN).max(0.0),
+            MARGIN,
+            WIDTH,
+            lines as f32 * line_height + PADDING * 2.0,
+        );
+
+        root_canvas.save();
+        root_canvas.clip_rect(rect, None, Some(false));
+        let mut paint = Paint::default();
+        paint.set_color(Color::from_argb(200, 30, 30, 30));
+        root_canvas.draw_paint(&paint);
+
+        let mut y = rect.top + PADDING - font.metrics().1.ascent;
+        let skip = log.len().saturating_sub(VISIBLE_ENTRIES);
+        if log.is_empty() {
+            paint.set_color(Color::from_argb(255, 160, 160, 160));
+            root_canvas.draw_str("Waiting for input", (rect.left + PADDING, y), font, &paint);
+        }
+        for entry in log.iter().skip(skip) {
+            let color = if entry.sent {
+                Color::from_argb(255, 0, 255, 0)
+            } else {
+                Color::from_argb(255, 220, 220, 220)
+            };
+            paint.set_color(color);
+            root_canvas.draw_str(entry.line(), (rect.left + PADDING, y), font, &paint);
+            y += line_height;
+        }
+        root_canvas.restore();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_sent() {
+        // A log of its own, since the global one is written by everything sent to Neovim
+        let log = InputLog::new();
+        let record = |command| log.record(true, describe_sent(&command));
+        log.set_recording(true);
+        record(SerialCommand::Keyboard("<C-i>".to_owned()));
+        record(SerialCommand::Scroll {
+            direction: "down".to_owned(),
+            grid_id: 2,
+            position: (4, 7),
+            modifier_string: "C-".to_owned(),
+        });
+        assert_eq!(
+            log.text().lines().map(|line| &line[10..]).collect::<Vec<_>>(),
+            [
+                "-> nvim_input(\"<C-i>\")",
+                "-> nvim_input_mouse(\"wheel\", \"down\", \"C-\", 2, 7, 4)"
+            ]
+        );
+
+        log.set_recording(false);
+        record(SerialCommand::Keyboard("ignored".to_owned()));
+        assert!(log.text().is_empty());
+    }
+}
@@ -1,4 +1,4 @@
-use std::{rc::Rc, sync::Arc};
+use std::sync::Arc;
 
 use objc2::{rc::Retained, runtime::ProtocolObject};
 use objc2_app_kit::NSColorSpace;
@@ -75,7 +75,7 @@ impl MetalDrawableSurface {
 }
 
 pub struct MetalSkiaRenderer {
-    window: Rc<Window>,
+    window: Arc<Window>,
     _device: Retained<ProtocolObject<dyn MTLDevice>>,
     command_queue: Retained<ProtocolObject<dyn MTLCommandQueue>>,
     metal_layer: Retained<CAMetalLayer>,
@@ -86,7 +86,7 @@ pub struct MetalSkiaRenderer {
 }
 
 impl MetalSkiaRenderer {
-    pub fn new(window: Rc<Window>, srgb: bool, vsync: bool, settings: Arc<Settings>) -> Self {
+    pub fn new(window: Arc<Window>, srgb: bool, vsync: bool, settings: Arc<Settings>) -> Self {
         log::info!("Initialize MetalSkiaRenderer...");
 
         let draw_size = window.inner_size();
@@ -152,8 +152,8 @@ impl MetalSkiaRenderer {
 }
 
 impl SkiaRenderer for MetalSkiaRenderer {
-    fn window(&self) -> Rc<Window> {
-        Rc::clone(&self.window)
+    fn window(&self) -> Arc<Window> {
+        Arc::clone(&self.window)
     }
 
     fn flush(&mut self) {
@@ -1,14 +1,24 @@
 pub mod animation_utils;
 pub mod box_drawing;
+pub mod command_palette;
 pub mod cursor_renderer;
+pub mod damage;
 pub mod fonts;
 pub mod grid_renderer;
+pub mod input_inspector;
 pub mod opengl;
+mod overlay_font;
 pub mod profiler;
 pub mod progress_bar;
+pub mod render_thread;
 mod rendered_layer;
 pub mod rendered_window;
+pub mod screencast;
+pub mod screenshot;
+pub mod selection;
+pub mod settings_panel;
 mod vsync;
+pub mod zoom;
 
 #[cfg(target_os = "windows")]
 pub mod d3d;
@@ -19,13 +29,18 @@ mod metal;
 use std::{
     cmp::Ordering,
     collections::{HashMap, hash_map::Entry},
-    rc::Rc,
     sync::Arc,
 };
 
+use command_palette::CommandPalette;
+use damage::Damage;
+use input_inspector::InputInspector;
 use itertools::Itertools;
 use progress_bar::{ProgressBar, ProgressBarSettings};
-use skia_safe::{Canvas, Color4f, Paint};
+use screencast::{Screencast, ScreencastSettings};
+use settings_panel::SettingsPanel;
+use skia_safe::{Canvas, Color4f, Paint, Rect};
+use zoom::ZoomAnimation;
 
 use winit::{
     event::WindowEvent,
@@ -38,13 +53,13 @@ use crate::{
     bridge::EditorMode,
     cmd_line::CmdLineSettings,
     editor::{Cursor, Style, WindowType},
-    profiling::{tracy_create_gpu_context, tracy_named_frame, tracy_zone},
+    profiling::{tracy_named_frame, tracy_zone},
     renderer::{
         fonts::font_options::PixelGeometry,
         rendered_layer::{FloatingLayer, group_windows},
     },
     settings::*,
-    units::{GridPos, GridRect, GridScale, GridSize, PixelPos, PixelRect, to_skia_rect},
+    units::{GridRect, GridScale, GridSize, PixelPos, PixelRect, to_skia_rect},
     window::{EventPayload, ShouldRender},
 };
 
@@ -64,19 +79,16 @@ use cursor_renderer::CursorRenderer;
 pub use fonts::caching_shaper::CachingShaper;
 pub use grid_renderer::GridRenderer;
 pub use rendered_window::{RenderedWindow, WindowDrawCommand, WindowDrawDetails};
+pub use selection::{GuiSelection, SelectionMode};
 
 pub use vsync::VSync;
 
-use self::fonts::font_options::FontOptions;
-
-const MESSAGE_SELECTION_ALPHA: f32 = 0.35;
+use self::fonts::{
+    font_options::{FontOptions, points_to_pixels},
+    highlight_fonts::HighlightFonts,
+};
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
-pub struct MessageSelection {
-    pub grid_id: u64,
-    pub start: GridPos<u32>,
-    pub end: GridPos<u32>,
-}
+const SELECTION_ALPHA: f32 = 0.35;
 
 #[cfg(feature = "profiling")]
 fn plot_skia_cache() {
@@ -94,24 +106,55 @@ fn plot_skia_cache() {
 
 #[derive(SettingGroup, Clone)]
 pub struct RendererSettings {
+    /// How long windows take to move to their new position, in seconds.
+    #[local]
     position_animation_length: f32,
+    /// How long the scroll animation takes, in seconds.
+    #[local]
     scroll_animation_length: f32,
+    /// How many lines are animated when scrolling more than one screen at a time.
+    #[local]
     scroll_animation_far_lines: u32,
+    /// Blur the background behind floating windows.
     floating_blur: bool,
+    /// The horizontal blur radius of floating windows.
     floating_blur_amount_x: f32,
+    /// The vertical blur radius of floating windows.
     floating_blur_amount_y: f32,
+    /// Draw a shadow below floating windows.
     floating_shadow: bool,
+    /// The virtual height of floating windows, which determines the shadow size.
     floating_z_height: f32,
+    /// The corner radius of floating windows, relative to the line height.
     floating_corner_radius: f32,
+    /// The angle of the light source that casts the shadows.
     light_angle_degrees: f32,
+    /// The radius of the light source that casts the shadows.
     light_radius: f32,
+    /// Draw debug information about the renderer.
     debug_renderer: bool,
+    /// Show a frametime graph in the upper left corner.
     profiler: bool,
+    /// Show the recent input events and what was sent to Neovim for them.
+    input_inspector: bool,
+    /// Multiplier for the thickness of underlines and undercurls.
     underline_stroke_scale: f32,
+    /// The gamma adjustment of the text rendering.
     text_gamma: f32,
+    /// The contrast adjustment of the text rendering.
     text_contrast: f32,
+    /// Group consecutive layers so they are shadowed and blurred together.
     experimental_layer_grouping: bool,
+    /// The subpixel layout of the display, used for subpixel antialiasing.
     pixel_geometry: PixelGeometry,
+    /// Only redraw the parts of the window that changed since the last frame.
+    damage_tracking: bool,
+    /// Draw the text from a shared atlas of rasterized glyphs, instead of drawing text blobs.
+    glyph_atlas: bool,
+    /// How much memory the glyph atlas can use, in MiB.
+    glyph_atlas_size: u32,
+    /// How many threads shape the text of the lines in parallel, or 0 to pick automatically.
+    shaping_workers: u32,
 }
 
 impl Default for RendererSettings {
@@ -130,11 +173,16 @@ impl Default for RendererSettings {
             light_radius: 5.,
             debug_renderer: false,
             profiler: false,
+            input_inspector: false,
             underline_stroke_scale: 1.,
             text_gamma: 0.0,
             text_contrast: 0.5,
             experimental_layer_grouping: false,
             pixel_geometry: PixelGeometry::default(),
+            damage_tracking: true,
+            glyph_atlas: false,
+            glyph_atlas_size: 16,
+            shaping_workers: 0,
         }
     }
 }
@@ -161,16 +209,27 @@ pub struct Renderer {
     current_mode: EditorMode,
 
     pub progress_bar: ProgressBar,
+    pub settings_panel: SettingsPanel,
+    pub command_palette: CommandPalette,
+    pub screencast: Screencast,
 
     pub rendered_windows: HashMap<u64, RenderedWindow>,
     pub window_regions: Vec<WindowDrawDetails>,
 
     profiler: profiler::Profiler,
+    input_inspector: InputInspector,
     pub os_scale_factor: f64,
     pub user_scale_factor: f64,
+    pub zoom: ZoomAnimation,
 
     settings: Arc<Settings>,
-    message_selection: Option<MessageSelection>,
+    selection: Option<GuiSelection>,
+
+    damage: Damage,
+    // What the last frame was drawn with, to find out what changed since
+    drawn_cursor_bounds: Option<Rect>,
+    drawn_content_region: Option<PixelRect<f32>>,
+    drew_full_frame_effects: bool,
 }
 
 /// Results of processing the draw commands from the command channel.
@@ -184,13 +243,22 @@ impl Renderer {
         let window_settings = settings.get::<WindowSettings>();
 
         let user_scale_factor = window_settings.scale_factor.into();
+        let zoom = ZoomAnimation::new(window_settings.scale_factor);
         let scale_factor = user_scale_factor * os_scale_factor;
         let cursor_renderer = CursorRenderer::new(settings.clone());
         let mut grid_renderer = GridRenderer::new(scale_factor, settings.clone());
         let mut font_config_state = settings.get::<FontConfigState>();
         font_config_state.has_font = init_config.font.is_some();
         settings.set(&font_config_state);
-        grid_renderer.update_font_options(init_config.font.map(|x| x.into()).unwrap_or_default());
+        let mut font_options: FontOptions = init_config.font.map(|x| x.into()).unwrap_or_default();
+        if let Some(size) = font_config_state.restored_size {
+            font_options.size = points_to_pixels(size);
+        }
+        let mut highlight_fonts = settings.get::<HighlightFonts>();
+        highlight_fonts.set_config(font_options.highlight.clone());
+        settings.set(&highlight_fonts);
+        grid_renderer.update_highlight_fonts(highlight_fonts);
+        grid_renderer.update_font_options(font_options);
         grid_renderer.handle_box_drawing_update(init_config.box_drawing.unwrap_or_default());
         let current_mode = EditorMode::Unknown(String::from(""));
 
@@ -198,8 +266,12 @@ impl Renderer {
         let window_regions = Vec::new();
 
         let profiler = profiler::Profiler::new(12.0, settings.clone());
+        let input_inspector = InputInspector::new(12.0, settings.clone());
 
         let progress_bar = ProgressBar::new();
+        let settings_panel = SettingsPanel::new(settings.clone());
+        let command_palette = CommandPalette::new();
+        let screencast = Screencast::new();
 
         Renderer {
             rendered_windows,
@@ -208,11 +280,20 @@ impl Renderer {
             current_mode,
             window_regions,
             profiler,
+            input_inspector,
             progress_bar,
+            settings_panel,
+            command_palette,
+            screencast,
             os_scale_factor,
             user_scale_factor,
+            zoom,
             settings,
-            message_selection: None,
+            selection: None,
+            damage: Damage::default(),
+            drawn_cursor_bounds: None,
+            drawn_content_region: None,
+            drew_full_frame_effects: false,
         }
     }
 
@@ -224,21 +305,152 @@ impl Renderer {
         self.grid_renderer.font_names()
     }
 
-    pub fn set_message_selection(&mut self, selection: Option<MessageSelection>) {
-        self.message_selection = selection;
+    /// Returns the settings of the given group with the `w:` and `b:` overrides of the window
+    /// shown in the grid applied.
+    pub fn settings_for_grid<T: SettingGroup + Clone + Send + Sync + 'static>(
+        &self,
+        grid_id: u64,
+    ) -> T {
+        let global = self.settings.get::<T>();
+        match self.rendered_windows.get(&grid_id) {
+            Some(window) => window.local_overrides.resolve(&global),
+            None => global,
+        }
+    }
+
+    pub fn set_selection(&mut self, selection: Option<GuiSelection>) {
+        if selection != self.selection {
+            self.damage.add_full();
+        }
+        self.selection = selection;
+    }
+
+    /// Redraws the whole frame, for changes that aren't tracked, like settings.
+    pub fn invalidate(&mut self) {
+        self.damage.add_full();
+    }
+
+    /// Returns the text covered by `selection`, with trailing whitespace removed from each row.
+    pub fn selection_text(&self, selection: &GuiSelection) -> Option<String> {
+        let window = self.rendered_windows.get(&selection.grid_id)?;
+        let (row_start, row_end) = selection.rows(window.grid_size)?;
+
+        let mut lines = Vec::new();
+        for row in row_start..=row_end {
+            let Some((col_start, col_end)) = selection.row_span(row, window.grid_size) else {
+                continue;
+            };
+            if let Some(line) = window.line_text_range(row, col_start, col_end) {
+                lines.push(line);
+            }
+        }
+
+        if lines.is_empty() || lines.iter().all(|line| line.is_empty()) {
+            return None;
+        }
+
+        Some(lines.join("\n"))
     }
 
     pub fn prepare_frame(&mut self) -> ShouldRender {
         self.cursor_renderer.prepare_frame()
     }
 
+    /// Returns what changed since the previous frame. Called before the frame is drawn.
+    pub fn take_damage(&mut self, content_region: Option<&PixelRect<f32>>) -> Damage {
+        let settings = self.settings.get::<RendererSettings>();
+        self.collect_damage(content_region, &settings);
+        std::mem::take(&mut self.damage)
+    }
+
+    /// Draws the frame. `redrawn_fraction` is how much of the previous frame was redrawn, shown by
+    /// the profiler.
     pub fn draw_frame(
         &mut self,
         root_canvas: &Canvas,
         content_region: Option<&PixelRect<f32>>,
         dt: f32,
+        redrawn_fraction: f32,
     ) {
         tracy_zone!("renderer_draw_frame");
+        self.draw_contents(root_canvas, content_region, dt, redrawn_fraction);
+
+        #[cfg(feature = "profiling")]
+        plot_skia_cache();
+    }
+
+    /// Collects what changed since the last frame was drawn.
+    fn collect_damage(
+        &mut self,
+        content_region: Option<&PixelRect<f32>>,
+        settings: &RendererSettings,
+    ) {
+        let grid_scale = self.grid_renderer.grid_scale;
+        if content_region != self.drawn_content_region.as_ref() {
+            self.damage.add_full();
+        }
+        self.drawn_content_region = content_region.copied();
+
+        for window in self.rendered_windows.values_mut() {
+            window.take_damage(grid_scale, settings, &mut self.damage);
+        }
+
+        let cursor_bounds = self.cursor_renderer.damage_bounds(grid_scale);
+        match (cursor_bounds, self.drawn_cursor_bounds) {
+            (Some(bounds), Some(drawn_bounds)) => {
+                self.damage.add(bounds);
+                self.damage.add(drawn_bounds);
+            }
+            // The cursor effects can draw anywhere
+            _ => self.damage.add_full(),
+        }
+        self.drawn_cursor_bounds = cursor_bounds;
+
+        if settings.profiler {
+            self.damage.add(self.profiler.get_rect());
+        }
+
+        // The overlays and the zoom animation are redrawn fully, and once more when they are gone
+        let full_frame_effects = self.zoom.is_zoomed()
+            || settings.debug_renderer
+            || settings.input_inspector
+            || self.progress_bar.is_animating()
+            || self.settings_panel.is_open()
+            || self.command_palette.is_open()
+            || self.screencast.is_visible();
+        if full_frame_effects || self.drew_full_frame_effects {
+            self.damage.add_full();
+        }
+        self.drew_full_frame_effects = full_frame_effects;
+
+        // The blur behind floating windows reads from around the damage, so when anything below
+        // one changes, the whole window is redrawn
+        if self.damage.is_full() {
+            return;
+        }
+        let blur_outset =
+            (settings.floating_blur_amount_x * 3.0, settings.floating_blur_amount_y * 3.0);
+        for window in self.rendered_windows.values() {
+            if window.hidden || window.anchor_info.is_none() {
+                continue;
+            }
+            if !settings.floating_blur && !window.has_transparency() {
+                continue;
+            }
+            let region = to_skia_rect(&window.pixel_region(grid_scale)).with_outset(blur_outset);
+            if self.damage.intersects(region) {
+                self.damage.add(region);
+            }
+        }
+    }
+
+    fn draw_contents(
+        &mut self,
+        root_canvas: &Canvas,
+        content_region: Option<&PixelRect<f32>>,
+        dt: f32,
+        redrawn_fraction: f32,
+    ) {
         let window_settings = self.settings.get::<WindowSettings>();
         let opacity = if window_settings.normal_opacity < 1.0 {
             window_settings.normal_opacity
@@ -259,6 +471,8 @@ impl Renderer {
             let clip_rect = to_skia_rect(&root_window.pixel_region(grid_scale));
             root_canvas.clip_rect(clip_rect, None, Some(false));
         }
+        root_canvas.save();
+        self.zoom.apply(root_canvas);
 
         let (root_windows, floating_layers) = {
             let (mut root_windows, mut floating_windows): (
@@ -362,10 +576,12 @@ impl Renderer {
 
         self.window_regions =
             root_window_regions.into_iter().chain(floating_window_regions).collect();
-        self.draw_message_selection(root_canvas, grid_scale);
+        self.draw_selection(root_canvas, grid_scale);
         self.cursor_renderer.draw(&mut self.grid_renderer, root_canvas);
+        root_canvas.restore();
 
-        self.profiler.draw(root_canvas, dt);
+        self.profiler.draw(root_canvas, dt, redrawn_fraction);
+        self.input_inspector.draw(root_canvas);
 
         let grid_size = self.get_grid_size();
 
@@ -373,43 +589,25 @@ impl Renderer {
 
         let progress_bar_settings = self.settings.get::<ProgressBarSettings>();
         self.progress_bar.draw(&progress_bar_settings, root_canvas, &self.grid_renderer, grid_size);
-
-        #[cfg(feature = "profiling")]
-        plot_skia_cache();
+        self.settings_panel.draw(root_canvas, &self.grid_renderer);
+        self.command_palette.draw(root_canvas, &self.grid_renderer);
+        let screencast_settings = self.settings.get::<ScreencastSettings>();
+        self.screencast.draw(&screencast_settings, root_canvas, &self.grid_renderer);
     }
 
-    fn message_selection_window(&self) -> Option<(&MessageSelection, &RenderedWindow)> {
-        let selection = self.message_selection.as_ref()?;
-        let window = self.rendered_windows.get(&selection.grid_id)?;
-        if !matches!(window.window_type, WindowType::Message { .. }) {
-            return None;
-        }
-
-        Some((selection, window))
-    }
-
-    fn draw_message_selection(&mut self, root_canvas: &Canvas, grid_scale: GridScale) {
-        let Some((selection, window)) = self.message_selection_window() else {
+    fn draw_selection(&mut self, root_canvas: &Canvas, grid_scale: GridScale) {
+        let Some(selection) = self.selection.as_ref() else {
             return;
         };
-
-        let height = window.grid_size.height;
-        let width = window.grid_size.width;
-        if height == 0 || width == 0 {
+        let Some(window) = self.rendered_windows.get(&selection.grid_id) else {
+            return;
+        };
+        if window.hidden {
             return;
         }
-
-        let max_row = height - 1;
-        let max_col = width - 1;
-        let start_row = selection.start.y.min(max_row);
-        let end_row = selection.end.y.min(max_row);
-        let (row_start, row_end) =
-            if start_row <= end_row { (start_row, end_row) } else { (end_row, start_row) };
-
-        let start_col = selection.start.x.min(max_col);
-        let end_col = selection.end.x.min(max_col);
-        let (col_start, col_end) =
-            if start_col <= end_col { (start_col, end_col) } else { (end_col, start_col) };
+        let Some((row_start, row_end)) = selection.rows(window.grid_size) else {
+            return;
+        };
 
         let pixel_region = window.pixel_region(grid_scale);
         root_canvas.save();
@@ -425,12 +623,13 @@ impl Renderer {
 
         let mut paint = Paint::new(selection_color, None);
         paint.set_anti_alias(false);
-        paint.set_alpha_f(MESSAGE_SELECTION_ALPHA);
+        paint.set_alpha_f(SELECTION_ALPHA);
 
         for row in row_start..=row_end {
-            let row_start_col = if row == row_start { col_start } else { 0 };
-            let row_end_col = if row == row_end { col_end } else { max_col };
-            if let Some(rect) = window.grid_row_rect(row, row_start_col, row_end_col, grid_scale) {
+            let Some((col_start, col_end)) = selection.row_span(row, window.grid_size) else {
+                continue;
+            };
+            if let Some(rect) = window.grid_row_rect(row, col_start, col_end, grid_scale) {
                 root_canvas.draw_rect(rect, &paint);
             }
         }
@@ -473,6 +672,12 @@ impl Renderer {
         self.progress_bar.animate(&progress_bar_settings, dt);
         animating |= self.progress_bar.is_animating();
 
+        let screencast_settings = self.settings.get::<ScreencastSettings>();
+        animating |= self.screencast.animate(&screencast_settings);
+
+        let zoom_animation_length = self.settings.get::<WindowSettings>().zoom_animation_length;
+        animating |= self.zoom.animate(dt, zoom_animation_length);
+
         animating
     }
 
@@ -522,12 +727,16 @@ impl Renderer {
         self.rendered_windows
             .iter_mut()
             .for_each(|(_, w)| w.prepare_lines(&mut self.grid_renderer, opacity, force));
+        if force {
+            self.damage.add_full();
+        }
     }
 
     fn handle_draw_command(&mut self, draw_command: DrawCommand, result: &mut DrawCommandResult) {
         match draw_command {
             DrawCommand::Window { grid_id, command: WindowDrawCommand::Close } => {
                 self.rendered_windows.remove(&grid_id);
+                self.damage.add_full();
             }
             DrawCommand::Window { grid_id, command } => {
                 match self.rendered_windows.entry(grid_id) {
@@ -559,14 +768,17 @@ impl Renderer {
             }
             DrawCommand::FontChanged(new_font) => {
                 self.grid_renderer.update_font(&new_font);
+                self.damage.add_full();
                 result.font_changed = true;
             }
             DrawCommand::LineSpaceChanged(new_linespace) => {
                 self.grid_renderer.update_linespace(new_linespace);
+                self.damage.add_full();
                 result.font_changed = true;
             }
             DrawCommand::DefaultStyleChanged(new_style) => {
                 self.grid_renderer.default_style = Arc::new(new_style);
+                self.damage.add_full();
             }
             DrawCommand::ModeChanged(new_mode) => {
                 self.current_mode = new_mode;
@@ -587,7 +799,8 @@ impl Renderer {
         self.cursor_renderer = CursorRenderer::new(self.settings.clone());
         self.progress_bar = ProgressBar::new();
         self.current_mode = EditorMode::Unknown(String::new());
-        self.message_selection = None;
+        self.selection = None;
+        self.damage.add_full();
     }
 
     pub fn get_cursor_destination(&self) -> PixelPos<f32> {
@@ -632,7 +845,7 @@ pub enum WindowConfigType {
 
 #[derive(Clone)]
 pub struct WindowConfig {
-    pub window: Rc<Window>,
+    pub window: Arc<Window>,
     pub config: WindowConfigType,
 }
 
@@ -678,11 +891,28 @@ pub fn build_window_config(
 }
 
 pub trait SkiaRenderer {
-    fn window(&self) -> Rc<Window>;
+    fn window(&self) -> Arc<Window>;
     fn flush(&mut self);
     fn swap_buffers(&mut self);
+    /// How many frames ago the back buffer was drawn, 0 when the contents are undefined, or `None`
+    /// when the backend can't tell.
+    fn buffer_age(&self) -> Option<u32> {
+        None
+    }
+    /// Swaps the buffers, telling the compositor which parts of the frame changed.
+    fn swap_buffers_with_damage(&mut self, _damage: &Damage) {
+        self.swap_buffers();
+    }
     fn canvas(&mut self) -> &Canvas;
     fn resize(&mut self);
+    /// Releases the graphics context from the thread that created the renderer, so that it can be
+    /// moved to a render thread. Returns `false` when the backend can't be used from another
+    /// thread, and then the frames are drawn on the event loop.
+    fn release_context(&mut self) -> bool {
+        false
+    }
+    /// Makes the graphics context current on the render thread.
+    fn acquire_context(&mut self) {}
     fn create_vsync(&self, proxy: EventLoopProxy<EventPayload>) -> VSync;
     #[cfg(feature = "gpu_profiling")]
     fn tracy_create_gpu_context(&self, name: &str) -> Box<dyn GpuCtx>;
@@ -694,7 +924,7 @@ pub fn create_skia_renderer(
     vsync: bool,
     settings: Arc<Settings>,
 ) -> Box<dyn SkiaRenderer> {
-    let renderer: Box<dyn SkiaRenderer> = match &window.config {
+    match &window.config {
         WindowConfigType::OpenGL(..) => {
             Box::new(opengl::OpenGLSkiaRenderer::new(window.clone(), srgb, vsync, settings.clone()))
         }
@@ -709,7 +939,5 @@ pub fn create_skia_renderer(
             vsync,
             settings.clone(),
         )),
-    };
-    tracy_create_gpu_context("main_render_context", renderer.as_ref());
-    renderer
+    }
 }
@@ -3,7 +3,6 @@ use std::{
     env::{self, consts::OS},
     ffi::{CStr, CString, c_void},
     num::NonZeroU32,
-    rc::Rc,
     sync::Arc,
 };
 
@@ -12,14 +11,14 @@ use glutin::surface::SwapInterval;
 use glutin::{
     config::{Config, ConfigTemplateBuilder},
     context::{ContextAttributesBuilder, GlProfile, PossiblyCurrentContext},
-    display::GetGlDisplay,
+    display::{GetDisplayExtensions, GetGlDisplay},
     prelude::*,
-    surface::{Surface, SurfaceAttributesBuilder, WindowSurface},
+    surface::{Rect, Surface, SurfaceAttributesBuilder, WindowSurface},
 };
 use glutin_winit::DisplayBuilder;
 use raw_window_handle::HasWindowHandle;
 use skia_safe::{
-    ColorSpace, ColorType, SurfaceProps, SurfacePropsFlags,
+    ColorSpace, ColorType, IRect, SurfaceProps, SurfacePropsFlags,
     canvas::Canvas,
     gpu::{
         DirectContext, SurfaceOrigin, backend_render_targets::make_gl, gl::FramebufferInfo,
@@ -38,7 +37,9 @@ pub use super::vsync::VSyncWinDwm;
 #[cfg(target_os = "macos")]
 pub use super::vsync::VSyncMacosDisplayLink;
 
-use super::{RendererSettings, SkiaRenderer, VSync, WindowConfig, WindowConfigType};
+use super::{
+    RendererSettings, SkiaRenderer, VSync, WindowConfig, WindowConfigType, damage::Damage,
+};
 
 use crate::{profiling::tracy_gpu_zone, settings::Settings, window::EventPayload};
 
@@ -54,7 +55,7 @@ pub struct OpenGLSkiaRenderer {
     context: PossiblyCurrentContext,
     window_surface: Surface<WindowSurface>,
     config: Config,
-    window: Option<Rc<Window>>,
+    window: Option<Arc<Window>>,
 
     settings: Arc<Settings>,
 }
@@ -160,8 +161,8 @@ impl OpenGLSkiaRenderer {
 }
 
 impl SkiaRenderer for OpenGLSkiaRenderer {
-    fn window(&self) -> Rc<Window> {
-        Rc::clone(self.window.as_ref().unwrap())
+    fn window(&self) -> Arc<Window> {
+        Arc::clone(self.window.as_ref().unwrap())
     }
 
     fn flush(&mut self) {
@@ -179,6 +180,54 @@ impl SkiaRenderer for OpenGLSkiaRenderer {
         }
     }
 
+    fn buffer_age(&self) -> Option<u32> {
+        let supported = match self.window_surface.display() {
+            #[cfg(not(target_os = "macos"))]
+            glutin::display::Display::Egl(display) => {
+                display.extensions().contains("EGL_EXT_buffer_age")
+            }
+            #[cfg(target_os = "linux")]
+            glutin::display::Display::Glx(display) => {
+                display.extensions().contains("GLX_EXT_buffer_age")
+            }
+            #[allow(unreachable_patterns)]
+            _ => false,
+        };
+        supported.then(|| self.window_surface.buffer_age())
+    }
+
+    fn swap_buffers_with_damage(&mut self, damage: &Damage) {
+        #[cfg(not(target_os = "macos"))]
+        if let (Surface::Egl(surface), PossiblyCurrentContext::Egl(context)) =
+            (&self.window_surface, &self.context)
+        {
+            tracy_gpu_zone!("swap buffers with damage");
+            self.window().pre_present_notify();
+            // The damage rects have a bottom left origin, and no rects mean that everything changed
+            let bounds = IRect::from_wh(self.skia_surface.width(), self.skia_surface.height());
+            let rects = if damage.is_full() {
+                Vec::new()
+            } else {
+                damage
+                    .rects()
+                    .iter()
+                    .filter_map(|rect| IRect::intersect(rect, &bounds))
+                    .map(|rect| {
+                        Rect::new(
+                            rect.left,
+                            bounds.bottom - rect.bottom,
+                            rect.width(),
+                            rect.height(),
+                        )
+                    })
+                    .collect()
+            };
+            let _ = surface.swap_buffers_with_damage(context, &rects);
+            return;
+        }
+        self.swap_buffers();
+    }
+
     fn canvas(&mut self) -> &Canvas {
         self.skia_surface.canvas()
     }
@@ -195,6 +244,24 @@ impl SkiaRenderer for OpenGLSkiaRenderer {
         );
     }
 
+    fn release_context(&mut self) -> bool {
+        // Winit runs the window methods used while drawing on the main thread on macOS, which
+        // would deadlock while the event loop waits for the render thread
+        if cfg!(target_os = "macos") {
+            return false;
+        }
+        self.context.make_not_current_in_place().expect("Failed to release the OpenGL context");
+        true
+    }
+
+    fn acquire_context(&mut self) {
+        self.context
+            .make_current(&self.window_surface)
+            .expect("Failed to make the OpenGL context current");
+        // The GL state tracked by Skia doesn't carry over to the new thread
+        self.gr_context.reset(None);
+    }
+
     #[allow(unused_variables)]
     fn create_vsync(&self, proxy: EventLoopProxy<EventPayload>) -> VSync {
         #[cfg(target_os = "linux")]
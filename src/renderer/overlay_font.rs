@@ -0,0 +1,28 @@
+use std::cell::OnceCell;
+
+use skia_safe::Font;
+
+use crate::renderer::fonts::font_loader::{FontKey, FontLoader};
+
+/// The bundled font of the overlays drawn on top of the editor, like the profiler and the settings
+/// panel. It's loaded the first time an overlay is drawn, so that startup doesn't wait for it.
+pub struct OverlayFont {
+    size: f32,
+    font: OnceCell<Option<Font>>,
+}
+
+impl OverlayFont {
+    pub fn new(size: f32) -> Self {
+        Self { size, font: OnceCell::new() }
+    }
+
+    /// The font, or `None` when it couldn't be loaded, in which case the overlay isn't drawn.
+    pub fn get(&self) -> Option<&Font> {
+        self.font
+            .get_or_init(|| {
+                let font = FontLoader::new(self.size).get_or_load(&FontKey::default());
+                font.map(|font| font.skia_font.clone())
+            })
+            .as_ref()
+    }
+}
@@ -1,44 +1,54 @@
 use std::collections::VecDeque;
-use std::{rc::Rc, sync::Arc};
+use std::sync::Arc;
 
 use crate::{
     profiling::tracy_zone,
-    renderer::{RendererSettings, animation_utils::lerp, fonts::font_loader::*},
+    renderer::{RendererSettings, animation_utils::lerp, overlay_font::OverlayFont},
     settings::Settings,
 };
-use skia_safe::{Canvas, Color, Paint, Point, Rect, Size};
+use skia_safe::{Canvas, Color, Font, Paint, Point, Rect, Size};
 
 const FRAMETIMES_COUNT: usize = 48;
 
 pub struct Profiler {
-    pub font: Rc<FontPair>,
+    font: OverlayFont,
     pub position: Point,
     pub size: Size,
     pub frametimes: VecDeque<f32>,
+    /// The frames drawn since the profiler was enabled and how long they took, in seconds. The
+    /// average frame rate is logged when it's disabled again.
+    measured_frames: u32,
+    measured_time: f32,
 
     settings: Arc<Settings>,
 }
 
 impl Profiler {
     pub fn new(font_size: f32, settings: Arc<Settings>) -> Self {
-        let font_key = FontKey::default();
-        let mut font_loader = FontLoader::new(font_size);
-        let font = font_loader.get_or_load(&font_key).unwrap();
         Self {
-            font,
+            font: OverlayFont::new(font_size),
             position: Point::new(32.0, 32.0),
             size: Size::new(200.0, 120.0),
             frametimes: VecDeque::with_capacity(FRAMETIMES_COUNT),
+            measured_frames: 0,
+            measured_time: 0.0,
 
             settings,
         }
     }
 
-    pub fn draw(&mut self, root_canvas: &Canvas, dt: f32) {
+    /// Draws the frametimes, and how much of the frame was redrawn, from 0 to 1.
+    pub fn draw(&mut self, root_canvas: &Canvas, dt: f32, redrawn_fraction: f32) {
         tracy_zone!("profiler_draw");
         if !self.settings.get::<RendererSettings>().profiler {
+            self.log_frame_rate();
             return;
         }
+        self.measured_frames += 1;
+        self.measured_time += dt;
+        let Some(font) = self.font.get() else {
+            return;
+        };
 
         root_canvas.save();
         let rect = self.get_rect();
@@ -55,11 +65,15 @@ impl Profiler {
         let color = Color::from_argb(255, 0, 255, 0);
         paint.set_color(color);
         let mut text_position = self.position;
-        text_position.y += self.font.skia_font.size();
+        text_position.y += font.size();
         root_canvas.draw_str(
-            format!("{:.0}FPS", 1.0 / dt.max(f32::EPSILON)),
+            format!(
+                "{:.0}FPS  {:.0}% redrawn",
+                1.0 / dt.max(f32::EPSILON),
+                redrawn_fraction * 100.0
+            ),
             text_position,
-            &self.font.skia_font,
+            font,
             &paint,
         );
 
@@ -68,12 +82,26 @@ impl Profiler {
             self.frametimes.pop_front();
         }
 
-        self.draw_graph(root_canvas);
+        self.draw_graph(root_canvas, font);
 
         root_canvas.restore();
     }
 
-    fn draw_graph(&self, root_canvas: &Canvas) {
+    fn log_frame_rate(&mut self) {
+        if self.measured_frames == 0 {
+            return;
+        }
+        log::info!(
+            "Profiler: {} frames in {:.2}s, {:.1} FPS on average",
+            self.measured_frames,
+            self.measured_time,
+            self.measured_frames as f32 / self.measured_time.max(f32::EPSILON)
+        );
+        self.measured_frames = 0;
+        self.measured_time = 0.0;
+    }
+
+    fn draw_graph(&self, root_canvas: &Canvas, font: &Font) {
         let mut paint = Paint::default();
         let color = Color::from_argb(255, 0, 100, 200);
         paint.set_color(color);
@@ -113,27 +141,22 @@ impl Profiler {
         paint.set_anti_alias(false);
 
         // Show min, max, avg (average).
-        root_canvas.draw_str(
-            format!("min: {min_ft:.1}ms"),
-            (rect.left, rect.bottom),
-            &self.font.skia_font,
-            &paint,
-        );
+        root_canvas.draw_str(format!("min: {min_ft:.1}ms"), (rect.left, rect.bottom), font, &paint);
         root_canvas.draw_str(
             format!("avg: {avg:.1}ms"),
             (rect.left, rect.bottom - graph_height * 0.5),
-            &self.font.skia_font,
+            font,
             &paint,
         );
         root_canvas.draw_str(
             format!("max: {max_ft:.1}ms"),
             (rect.left, rect.bottom - graph_height),
-            &self.font.skia_font,
+            font,
             &paint,
         );
     }
 
-    fn get_rect(&self) -> Rect {
+    pub fn get_rect(&self) -> Rect {
         Rect::new(
             self.position.x,
             self.position.y,
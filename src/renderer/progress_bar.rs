@@ -7,9 +7,13 @@ use skia_safe::{Canvas, Color4f, Paint, Rect};
 #[derive(Clone, SettingGroup)]
 #[setting_prefix = "progress_bar"]
 pub struct ProgressBarSettings {
+    /// Show a progress bar for progress messages.
     pub enabled: bool,
+    /// The height of the progress bar, in pixels.
     pub height: f32,
+    /// How fast the progress bar animates, in percent per second.
     pub animation_speed: f32,
+    /// How long the progress bar stays visible after reaching 100%, in seconds.
     pub hide_delay: f32,
 }
 
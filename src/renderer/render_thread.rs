@@ -0,0 +1,359 @@
+//! Presents the frames of a window. When the backend's graphics context can be moved to another
+//! thread, every window gets a render thread that owns its [`SkiaRenderer`], so that playing the
+//! frames back on the GPU, waiting for the vsync and swapping the buffers doesn't block the event
+//! loop. The other backends present the frames on the event loop.
+//!
+//! Only the presentation moves. Handling the draw command batches, the animations, shaping the
+//! lines and recording the frame still run on the event loop, since the input handling reads the
+//! same renderer state, so a large redraw still delays the input for that long. Moving them would
+//! need the renderer state to be split between the threads, which isn't done.
+//!
+//! The event loop records each frame into a [`Picture`], and the render thread plays it back to
+//! the back buffer, only redrawing the damaged part. When it falls behind, only the newest frame is
+//! drawn, with the damage of the skipped frames merged into it.
+
+use std::{
+    cell::RefCell,
+    sync::{
+        Arc, Condvar, Mutex,
+        atomic::{AtomicU32, Ordering},
+    },
+    thread::{self, JoinHandle},
+};
+
+use skia_safe::{Canvas, ClipOp, IRect, Picture, PictureRecorder, Rect};
+use winit::{event_loop::EventLoopProxy, window::WindowId};
+
+use crate::{
+    error_msg,
+    profiling::{
+        tracy_create_gpu_context, tracy_frame, tracy_gpu_collect, tracy_gpu_zone, tracy_plot,
+        tracy_zone,
+    },
+    renderer::{
+        Renderer, RendererSettings, SkiaRenderer,
+        damage::{Damage, DamageTracker},
+        screenshot,
+        vsync::VSyncTimer,
+    },
+    settings::Settings,
+    units::PixelRect,
+    window::{EventPayload, UserEvent},
+};
+
+/// Presents the frames of a window, on a render thread when the backend allows it.
+pub enum FramePresenter {
+    Thread(RenderThread),
+    /// The backend can't be used from another thread, so the frames are drawn on the event loop.
+    EventLoop(RefCell<FrameDrawer>),
+}
+
+impl FramePresenter {
+    /// Takes the renderer of the window. The frames are paced by `timer` when there's no vsync.
+    pub fn new(
+        mut skia_renderer: Box<dyn SkiaRenderer>,
+        timer: Option<VSyncTimer>,
+        settings: Arc<Settings>,
+        proxy: EventLoopProxy<EventPayload>,
+        window_id: WindowId,
+    ) -> Self {
+        if skia_renderer.release_context() {
+            let skia_renderer = SendRenderer(skia_renderer);
+            Self::Thread(RenderThread::new(skia_renderer, timer, settings, proxy, window_id))
+        } else {
+            tracy_create_gpu_context("main_render_context", skia_renderer.as_ref());
+            let drawer = FrameDrawer::new(skia_renderer, timer, settings, proxy, window_id);
+            Self::EventLoop(RefCell::new(drawer))
+        }
+    }
+
+    /// Draws the frame of `renderer`, sized `width` by `height`, and presents it. `show_window` shows
+    /// the window once the frame is presented, so that it doesn't flash empty.
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_frame(
+        &self,
+        renderer: &mut Renderer,
+        width: u32,
+        height: u32,
+        content_region: &PixelRect<f32>,
+        dt: f32,
+        screenshot: bool,
+        show_window: bool,
+    ) {
+        let redrawn_fraction = self.redrawn_fraction();
+        let damage = renderer.take_damage(Some(content_region));
+        match self {
+            Self::Thread(render_thread) => {
+                // The frame is only recorded here, the render thread draws it
+                let mut recorder = PictureRecorder::new();
+                let canvas =
+                    recorder.begin_recording(Rect::from_iwh(width as i32, height as i32), false);
+                renderer.draw_frame(canvas, Some(content_region), dt, redrawn_fraction);
+                let Some(picture) = recorder.finish_recording_as_picture(None) else {
+                    return;
+                };
+                render_thread.submit(Frame { picture, damage, screenshot, show_window });
+            }
+            Self::EventLoop(drawer) => {
+                drawer.borrow_mut().present(damage, screenshot, show_window, |canvas| {
+                    renderer.draw_frame(canvas, Some(content_region), dt, redrawn_fraction)
+                });
+            }
+        }
+    }
+
+    /// Resizes the surface to the window size before the next frame is drawn.
+    pub fn resize(&self) {
+        match self {
+            Self::Thread(render_thread) => render_thread.resize(),
+            Self::EventLoop(drawer) => drawer.borrow_mut().resize(),
+        }
+    }
+
+    /// How much of the last presented frame was redrawn, from 0 to 1.
+    pub fn redrawn_fraction(&self) -> f32 {
+        match self {
+            Self::Thread(render_thread) => render_thread.redrawn_fraction(),
+            Self::EventLoop(drawer) => drawer.borrow().redrawn_fraction,
+        }
+    }
+}
+
+/// A frame recorded on the event loop thread.
+struct Frame {
+    picture: Picture,
+    /// What changed since the previous frame.
+    damage: Damage,
+    /// Save a screenshot once the frame is drawn.
+    screenshot: bool,
+    show_window: bool,
+}
+
+#[derive(Default)]
+struct Pending {
+    frame: Option<Frame>,
+    resize: bool,
+    quit: bool,
+}
+
+struct Shared {
+    pending: Mutex<Pending>,
+    condvar: Condvar,
+    /// How much of the last frame was redrawn, as the bits of an `f32`.
+    redrawn_fraction: AtomicU32,
+}
+
+/// Moves the renderer to the render thread.
+struct SendRenderer(Box<dyn SkiaRenderer>);
+
+// SAFETY: Only built for renderers whose `release_context` returned `true`, which means that the
+// graphics context is no longer current on the event loop thread and is made current again on the
+// render thread, and that the window methods used while drawing can be called from there. The
+// renderer is created on the event loop thread, since some backends need the window there, and is
+// only used by the render thread after that.
+unsafe impl Send for SendRenderer {}
+
+impl SendRenderer {
+    fn into_inner(self) -> Box<dyn SkiaRenderer> {
+        self.0
+    }
+}
+
+pub struct RenderThread {
+    shared: Arc<Shared>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl RenderThread {
+    fn new(
+        skia_renderer: SendRenderer,
+        timer: Option<VSyncTimer>,
+        settings: Arc<Settings>,
+        proxy: EventLoopProxy<EventPayload>,
+        window_id: WindowId,
+    ) -> Self {
+        let shared = Arc::new(Shared {
+            pending: Mutex::new(Pending::default()),
+            condvar: Condvar::new(),
+            redrawn_fraction: AtomicU32::new(1.0f32.to_bits()),
+        });
+
+        let thread = {
+            let shared = Arc::clone(&shared);
+            thread::Builder::new()
+                .name("render".to_owned())
+                .spawn(move || {
+                    let mut skia_renderer = skia_renderer.into_inner();
+                    skia_renderer.acquire_context();
+                    tracy_create_gpu_context("main_render_context", skia_renderer.as_ref());
+                    let drawer = FrameDrawer::new(skia_renderer, timer, settings, proxy, window_id);
+                    drawer.run(&shared);
+                })
+                .expect("Failed to start the render thread")
+        };
+
+        Self { shared, thread: Some(thread) }
+    }
+
+    /// Queues the frame to be drawn, replacing the frame that's still waiting.
+    fn submit(&self, mut frame: Frame) {
+        let mut pending = self.shared.pending.lock().unwrap();
+        if let Some(skipped) = pending.frame.take() {
+            frame.damage.merge(&skipped.damage);
+            frame.screenshot |= skipped.screenshot;
+            frame.show_window |= skipped.show_window;
+        }
+        pending.frame = Some(frame);
+        self.shared.condvar.notify_one();
+    }
+
+    fn resize(&self) {
+        self.shared.pending.lock().unwrap().resize = true;
+        self.shared.condvar.notify_one();
+    }
+
+    fn redrawn_fraction(&self) -> f32 {
+        f32::from_bits(self.shared.redrawn_fraction.load(Ordering::Relaxed))
+    }
+}
+
+impl Drop for RenderThread {
+    fn drop(&mut self) {
+        self.shared.pending.lock().unwrap().quit = true;
+        self.shared.condvar.notify_one();
+        if let Some(thread) = self.thread.take() {
+            if thread.join().is_err() {
+                log::error!("The render thread panicked");
+            }
+        }
+    }
+}
+
+/// Draws the frames to the window's back buffer and presents them, on the render thread or on the
+/// event loop.
+pub struct FrameDrawer {
+    skia_renderer: Box<dyn SkiaRenderer>,
+    timer: Option<VSyncTimer>,
+    damage: DamageTracker,
+    redrawn_fraction: f32,
+    settings: Arc<Settings>,
+    /// Tells the event loop when the first frame is presented.
+    proxy: EventLoopProxy<EventPayload>,
+    window_id: WindowId,
+}
+
+impl FrameDrawer {
+    fn new(
+        skia_renderer: Box<dyn SkiaRenderer>,
+        timer: Option<VSyncTimer>,
+        settings: Arc<Settings>,
+        proxy: EventLoopProxy<EventPayload>,
+        window_id: WindowId,
+    ) -> Self {
+        Self {
+            skia_renderer,
+            timer,
+            damage: DamageTracker::default(),
+            redrawn_fraction: 1.0,
+            settings,
+            proxy,
+            window_id,
+        }
+    }
+
+    fn run(mut self, shared: &Shared) {
+        loop {
+            let (frame, resize) = {
+                let pending = shared.pending.lock().unwrap();
+                let mut pending = shared
+                    .condvar
+                    .wait_while(pending, |pending| {
+                        pending.frame.is_none() && !pending.resize && !pending.quit
+                    })
+                    .unwrap();
+                if pending.quit {
+                    return;
+                }
+                (pending.frame.take(), std::mem::take(&mut pending.resize))
+            };
+
+            if resize {
+                self.resize();
+            }
+            if let Some(frame) = frame {
+                let Frame { picture, damage, screenshot, show_window } = frame;
+                self.present(damage, screenshot, show_window, |canvas| {
+                    canvas.draw_picture(&picture, None, None);
+                });
+                shared.redrawn_fraction.store(self.redrawn_fraction.to_bits(), Ordering::Relaxed);
+            }
+        }
+    }
+
+    fn resize(&mut self) {
+        self.skia_renderer.resize();
+    }
+
+    /// Draws the damaged part of the frame with `draw` and presents it.
+    fn present(
+        &mut self,
+        damage: Damage,
+        screenshot: bool,
+        show_window: bool,
+        draw: impl FnOnce(&Canvas),
+    ) {
+        tracy_zone!("present_frame");
+        // Without the age of the back buffer, its contents are unknown and everything is redrawn.
+        // Keeping the frame in another surface instead would copy the whole frame every time.
+        let damage_tracking = self.settings.get::<RendererSettings>().damage_tracking;
+        let buffer_age =
+            if damage_tracking { self.skia_renderer.buffer_age().unwrap_or(0) } else { 0 };
+
+        let canvas = self.skia_renderer.canvas();
+        let size = canvas.base_layer_size();
+        let frame_damage = self.damage.finish_frame(damage, buffer_age, size);
+        self.redrawn_fraction = frame_damage.redrawn_fraction(size);
+        tracy_plot!("redrawn_fraction", self.redrawn_fraction as f64);
+
+        draw_clipped(canvas, frame_damage.redraw, draw);
+
+        if screenshot {
+            match screenshot::save_screenshot(canvas) {
+                Ok(path) => log::info!("Saved screenshot to {}", path.display()),
+                Err(error) => {
+                    error_msg!("{error}");
+                }
+            }
+        }
+
+        self.skia_renderer.flush();
+
+        if let Some(timer) = self.timer.as_mut() {
+            tracy_gpu_zone!("wait for vsync");
+            timer.wait_for_vsync();
+        }
+
+        self.skia_renderer.swap_buffers_with_damage(&frame_damage.damage);
+        if show_window {
+            // The window is shown by the event loop, since some platforms only allow that there
+            let _ = self.proxy.send_event(EventPayload::for_window(
+                UserEvent::FirstFramePresented,
+                self.window_id,
+            ));
+        }
+
+        tracy_frame();
+        tracy_gpu_collect();
+    }
+}
+
+/// Draws the part of the frame inside `redraw`, when something changed.
+fn draw_clipped(canvas: &Canvas, redraw: Option<IRect>, draw: impl FnOnce(&Canvas)) {
+    let Some(redraw) = redraw else {
+        return;
+    };
+    canvas.save();
+    canvas.clip_irect(redraw, ClipOp::Intersect);
+    draw(canvas);
+    canvas.restore();
+}
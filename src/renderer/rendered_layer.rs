@@ -1,9 +1,9 @@
 use itertools::Itertools;
 use skia_safe::{
-    BlendMode, Canvas, ClipOp, Color, Paint, Path, PathOp, Point3, RRect, Rect,
+    BlendMode, Canvas, ClipOp, Color, Matrix, Paint, Path, PathOp, Point3, RRect, Rect,
     canvas::SaveLayerRec,
     image_filters::blur,
-    utils::shadow_utils::{ShadowFlags, draw_shadow},
+    utils::shadow_utils::{ShadowFlags, draw_shadow, local_bounds},
 };
 
 use glamour::Intersection;
@@ -143,20 +143,13 @@ impl FloatingLayer<'_> {
         // We clip using the Difference op to make sure that the shadow isn't rendered inside
         // the window itself.
         root_canvas.clip_path(path, Some(ClipOp::Difference), None);
-        // The light angle is specified in degrees from the vertical, so we first convert them
-        // to radians and then use sin/cos to get the y and z components of the light
-        let light_angle_radians = settings.light_angle_degrees.to_radians();
+        let (z_plane, light_position) = shadow_light(settings);
         draw_shadow(
             root_canvas,
             path,
-            // Specifies how far from the root canvas the shadow casting rect is. We just use
-            // the z component here to set it a constant distance away.
-            Point3::new(0., 0., settings.floating_z_height),
-            // Because we use the DIRECTIONAL_LIGHT shadow flag, this specifies the angle that
-            // the light is coming from.
-            Point3::new(0., -light_angle_radians.sin(), light_angle_radians.cos()),
-            // This is roughly equal to the apparent radius of the light .
-            5.,
+            z_plane,
+            light_position,
+            SHADOW_LIGHT_RADIUS,
             Color::from_argb((0.03 * 255.) as u8, 0, 0, 0),
             Color::from_argb((0.35 * 255.) as u8, 0, 0, 0),
             // Directional Light flag is necessary to make the shadow render consistently
@@ -168,6 +161,40 @@ impl FloatingLayer<'_> {
     }
 }
 
+// This is roughly equal to the apparent radius of the light.
+const SHADOW_LIGHT_RADIUS: f32 = 5.;
+
+fn shadow_light(settings: &RendererSettings) -> (Point3, Point3) {
+    // The light angle is specified in degrees from the vertical, so we first convert them
+    // to radians and then use sin/cos to get the y and z components of the light
+    let light_angle_radians = settings.light_angle_degrees.to_radians();
+    (
+        // Specifies how far from the root canvas the shadow casting rect is. We just use
+        // the z component here to set it a constant distance away.
+        Point3::new(0., 0., settings.floating_z_height),
+        // Because we use the DIRECTIONAL_LIGHT shadow flag, this specifies the angle that
+        // the light is coming from.
+        Point3::new(0., -light_angle_radians.sin(), light_angle_radians.cos()),
+    )
+}
+
+/// The area covered by a floating window and its shadow.
+pub fn shadow_bounds(rect: Rect, settings: &RendererSettings) -> Rect {
+    if !settings.floating_shadow {
+        return rect;
+    }
+    let (z_plane, light_position) = shadow_light(settings);
+    local_bounds(
+        &Matrix::new_identity(),
+        &Path::rect(rect, None),
+        z_plane,
+        light_position,
+        SHADOW_LIGHT_RADIUS,
+        ShadowFlags::DIRECTIONAL_LIGHT.bits(),
+    )
+    .map_or(rect, |bounds| Rect::join2(bounds, rect))
+}
+
 fn get_window_group(windows: &mut Vec<LayerWindow>, index: usize) -> usize {
     if windows[index].group != index {
         windows[index].group = get_window_group(windows, windows[index].group);
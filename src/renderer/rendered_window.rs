@@ -5,12 +5,15 @@ use skia_safe::{
 };
 
 use crate::{
-    bridge::WindowAnchor,
+    bridge::{CellText, WindowAnchor},
     cmd_line::CmdLineSettings,
     editor::{AnchorInfo, Line, LineFragment, SortOrder, WindowType},
     profiling::{tracy_plot, tracy_zone},
-    renderer::{GridRenderer, RendererSettings, animation_utils::*},
-    settings::Settings,
+    renderer::{
+        GridRenderer, RendererSettings, animation_utils::*, damage::Damage,
+        rendered_layer::shadow_bounds,
+    },
+    settings::{LocalOverrides, Settings},
     units::{GridPos, GridRect, GridScale, GridSize, PixelPos, PixelRect, PixelVec, to_skia_rect},
     utils::RingBuffer,
 };
@@ -68,6 +71,7 @@ pub enum WindowDrawCommand {
         right: u64,
     },
     SortOrder(SortOrder),
+    LocalOverrides(LocalOverrides),
 }
 
 struct RenderedLine {
@@ -105,6 +109,14 @@ pub struct RenderedWindow {
     position_t: f32,
 
     pub scroll_animation: CriticallyDampedSpringAnimation,
+
+    pub local_overrides: LocalOverrides,
+
+    // What changed since the window was last drawn, see `take_damage`
+    damaged_rows: Vec<u32>,
+    region_damaged: bool,
+    fully_damaged: bool,
+    drawn_state: Option<(PixelRect<f32>, f32)>,
 }
 
 #[derive(Clone, Debug)]
@@ -145,6 +157,13 @@ impl RenderedWindow {
             position_t: 2.0, // 2.0 is out of the 0.0 to 1.0 range and stops animation.
 
             scroll_animation: CriticallyDampedSpringAnimation::new(),
+
+            local_overrides: LocalOverrides::default(),
+
+            damaged_rows: Vec::new(),
+            region_damaged: false,
+            fully_damaged: false,
+            drawn_state: None,
         }
     }
 
@@ -192,6 +211,7 @@ impl RenderedWindow {
         grid_rect: &GridRect<f32>,
         dt: f32,
     ) -> bool {
+        let settings = self.local_overrides.resolve(settings);
         let mut animating = false;
 
         if self.position_t > 1.0 - f32::EPSILON {
@@ -539,6 +559,12 @@ impl RenderedWindow {
         self.scrollback_lines[scroll_offset + inner_row].as_ref().cloned()
     }
 
+    pub fn row_cells(&self, row: u32) -> Option<Vec<CellText>> {
+        let line = self.line_for_row(row)?;
+        let line = line.borrow();
+        line.line.cells().map(|cells| cells.to_vec())
+    }
+
     pub fn line_text_range(&self, row: u32, start_col: u32, end_col: u32) -> Option<String> {
         let line = self.line_for_row(row)?;
         let line = line.borrow();
@@ -608,6 +634,14 @@ impl RenderedWindow {
     }
 
     pub fn handle_window_draw_command(&mut self, draw_command: WindowDrawCommand) {
+        match &draw_command {
+            WindowDrawCommand::DrawLine { row, .. } => self.damaged_rows.push(*row as u32),
+            WindowDrawCommand::Viewport { scroll_delta } if scroll_delta.round() == 0.0 => {}
+            // Only changes which window is on top
+            WindowDrawCommand::SortOrder(..) => self.region_damaged = true,
+            _ => self.fully_damaged = true,
+        }
+
         match draw_command {
             WindowDrawCommand::Position { grid_position, grid_size, anchor_info, window_type } => {
                 tracy_zone!("position_cmd", 0);
@@ -724,14 +758,50 @@ impl RenderedWindow {
                     anchor_info.sort_order = sort_order;
                 }
             }
+            WindowDrawCommand::LocalOverrides(local_overrides) => {
+                self.local_overrides = local_overrides;
+            }
             _ => {}
         };
     }
 
+    /// Adds what changed since the window was last drawn to the damage. Moving, resizing or
+    /// scrolling the window damages the whole frame, since it can uncover the windows below.
+    pub fn take_damage(
+        &mut self,
+        grid_scale: GridScale,
+        settings: &RendererSettings,
+        damage: &mut Damage,
+    ) {
+        let state = (self.valid && !self.hidden)
+            .then(|| (self.pixel_region(grid_scale), self.scroll_animation.position));
+        let region_damaged = std::mem::take(&mut self.region_damaged);
+        if std::mem::take(&mut self.fully_damaged) || state != self.drawn_state {
+            damage.add_full();
+        } else if let Some((region, _)) = state {
+            let region = to_skia_rect(&region);
+            if region_damaged {
+                damage.add(region);
+                if self.anchor_info.is_some() {
+                    damage.add(shadow_bounds(region, settings));
+                }
+            }
+            // The trailing fill can extend the rows past the window
+            let right = region.right + grid_scale.width() * MAX_TRAILING_FILL_CELLS;
+            for row in &self.damaged_rows {
+                let top = region.top + *row as f32 * grid_scale.height();
+                damage.add(Rect::new(region.left, top, right, top + grid_scale.height()));
+            }
+        }
+        self.damaged_rows.clear();
+        self.drawn_state = state;
+    }
+
     pub fn flush(&mut self, renderer_settings: &RendererSettings) {
         if !self.valid {
             return;
         }
+        let renderer_settings = self.local_overrides.resolve(renderer_settings);
         // If the borders are changed, reset the scrollback to only fit the inner view
         let inner_range = self.viewport_margins.top as isize
             ..(self.actual_lines.len() - self.viewport_margins.bottom as usize) as isize;
@@ -869,20 +939,48 @@ impl RenderedWindow {
             return;
         }
         let grid_scale = grid_renderer.grid_scale;
+        let position = self.grid_destination * grid_scale;
 
-        let mut prepare_line = |line: &Rc<RefCell<RenderedLine>>| {
-            let mut line = line.borrow_mut();
-            let position = self.grid_destination * grid_renderer.grid_scale;
+        let needs_preparing = |line: &RenderedLine| {
             let boxchar_moved = match line.boxchar_picture {
                 None => false,
                 Some((_, p)) if p == position => false,
                 _ => true,
             };
             // This can be optimized, only the boxchars need to be redrawn when the window moves
-            if line.is_valid && !force && !boxchar_moved {
-                return;
-            }
+            !line.is_valid || force || boxchar_moved
+        };
 
+        let mut lines = Vec::new();
+        if !self.scrollback_lines.is_empty() {
+            lines.extend(
+                self.scrollback_lines
+                    .iter_range(scroll_offset_lines..scroll_offset_lines + height + 1)
+                    .flatten()
+                    .cloned(),
+            );
+        }
+        lines.extend(
+            self.actual_lines.iter_range(0..self.viewport_margins.top as isize).flatten().cloned(),
+        );
+        let actual_line_count = self.actual_lines.len() as isize;
+        lines.extend(
+            self.actual_lines
+                .iter_range(
+                    actual_line_count - self.viewport_margins.bottom as isize..actual_line_count,
+                )
+                .flatten()
+                .cloned(),
+        );
+        lines.retain(|line| needs_preparing(&line.borrow()));
+
+        {
+            let lines = lines.iter().map(|line| line.borrow()).collect::<Vec<_>>();
+            grid_renderer.shape_lines(lines.iter().map(|line| &line.line));
+        }
+
+        for line in lines {
+            let mut line = line.borrow_mut();
             let mut recorder = PictureRecorder::new();
 
             let line_size = GridSize::new(self.grid_size.width, 1) * grid_scale;
@@ -935,32 +1033,6 @@ impl RenderedWindow {
             line.trailing_background = trailing_background;
             line.has_transparency = has_transparency;
             line.is_valid = true;
-        };
-
-        if !self.scrollback_lines.is_empty() {
-            for line in self
-                .scrollback_lines
-                .iter_range_mut(scroll_offset_lines..scroll_offset_lines + height + 1)
-                .flatten()
-            {
-                prepare_line(line)
-            }
-        }
-
-        for line in
-            self.actual_lines.iter_range_mut(0..self.viewport_margins.top as isize).flatten()
-        {
-            prepare_line(line)
-        }
-        let actual_line_count = self.actual_lines.len() as isize;
-        for line in self
-            .actual_lines
-            .iter_range_mut(
-                actual_line_count - self.viewport_margins.bottom as isize..actual_line_count,
-            )
-            .flatten()
-        {
-            prepare_line(line)
         }
     }
 }
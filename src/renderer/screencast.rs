@@ -0,0 +1,295 @@
+//! Shows the recently pressed keys in a corner of the window, for presentations and screencasts.
+//! Repeated keys are grouped, and the keys fade out after a while.
+
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use neovide_derive::SettingGroup;
+use rmpv::Value;
+use skia_safe::{Canvas, Color4f, Paint, RRect, Rect, colors};
+
+use crate::{
+    bridge::EditorMode,
+    error_msg,
+    profiling::tracy_zone,
+    renderer::{GridRenderer, overlay_font::OverlayFont, settings_panel::draw_text},
+    settings::ParseFromValue,
+};
+
+const MAX_ENTRIES: usize = 8;
+/// How long the keys take to fade out at the end of the timeout, in seconds.
+const FADE_TIME: f32 = 0.5;
+/// Shown instead of the text typed in insert mode when it's hidden.
+const HIDDEN_TEXT: &str = "…";
+
+#[derive(Clone, SettingGroup)]
+#[setting_prefix = "screencast"]
+pub struct ScreencastSettings {
+    /// Show the pressed keys in a corner of the window.
+    pub enabled: bool,
+    /// The corner of the window the keys are shown in.
+    pub position: ScreencastPosition,
+    /// Hide the text typed in insert, replace and command-line mode, only showing the other keys.
+    pub hide_insert_text: bool,
+    /// How long the keys stay visible, in seconds.
+    pub timeout: f32,
+    /// The size of the keys, relative to the editor font.
+    pub scale: f32,
+}
+
+impl Default for ScreencastSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            position: ScreencastPosition::BottomRight,
+            hide_insert_text: true,
+            timeout: 2.0,
+            scale: 1.5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScreencastPosition {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl ParseFromValue for ScreencastPosition {
+    fn parse_from_value(&mut self, value: Value) -> bool {
+        if value.is_str() {
+            *self = match value.as_str().unwrap() {
+                "top_left" => ScreencastPosition::TopLeft,
+                "top_right" => ScreencastPosition::TopRight,
+                "bottom_left" => ScreencastPosition::BottomLeft,
+                "bottom_right" => ScreencastPosition::BottomRight,
+                value => {
+                    error_msg!(
+                        "Setting ScreencastPosition expected one of `top_left`, `top_right`, `bottom_left`, `bottom_right`, but received {value:?}"
+                    );
+                    return false;
+                }
+            };
+            true
+        } else {
+            error_msg!("Setting ScreencastPosition expected string, but received {value:?}");
+            false
+        }
+    }
+
+    fn value_kind() -> &'static str {
+        "string"
+    }
+
+    fn allowed_values() -> &'static [&'static str] {
+        &["top_left", "top_right", "bottom_left", "bottom_right"]
+    }
+}
+
+impl From<ScreencastPosition> for Value {
+    fn from(value: ScreencastPosition) -> Self {
+        match value {
+            ScreencastPosition::TopLeft => Value::from("top_left"),
+            ScreencastPosition::TopRight => Value::from("top_right"),
+            ScreencastPosition::BottomLeft => Value::from("bottom_left"),
+            ScreencastPosition::BottomRight => Value::from("bottom_right"),
+        }
+    }
+}
+
+struct KeyEntry {
+    keys: String,
+    count: usize,
+    last_pressed: Instant,
+}
+
+impl KeyEntry {
+    fn label(&self) -> String {
+        if self.count > 1 { format!("{} ×{}", self.keys, self.count) } else { self.keys.clone() }
+    }
+}
+
+/// The recently pressed keys, oldest first.
+#[derive(Default)]
+struct KeyHistory {
+    entries: VecDeque<KeyEntry>,
+}
+
+impl KeyHistory {
+    fn push(&mut self, keys: &str, now: Instant, timeout: f32) {
+        self.remove_expired(now, timeout);
+        if let Some(last) = self.entries.back_mut().filter(|last| last.keys == keys) {
+            last.count += 1;
+            last.last_pressed = now;
+            return;
+        }
+        if self.entries.len() == MAX_ENTRIES {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(KeyEntry { keys: keys.to_owned(), count: 1, last_pressed: now });
+    }
+
+    fn remove_expired(&mut self, now: Instant, timeout: f32) {
+        let timeout = Duration::from_secs_f32(timeout.max(0.0));
+        self.entries.retain(|entry| now.duration_since(entry.last_pressed) < timeout);
+    }
+}
+
+pub struct Screencast {
+    font: OverlayFont,
+    history: KeyHistory,
+}
+
+impl Screencast {
+    pub fn new() -> Self {
+        Self { font: OverlayFont::new(12.0), history: KeyHistory::default() }
+    }
+
+    /// Records keys sent to Neovim, in Vim notation, as pressed in the mode.
+    pub fn record(&mut self, settings: &ScreencastSettings, keys: &str, mode: &EditorMode) {
+        if settings.enabled {
+            let keys = displayed_keys(settings, keys, mode);
+            self.history.push(keys, Instant::now(), settings.timeout);
+        }
+    }
+
+    /// Returns true while keys are shown, since they are fading out.
+    pub fn animate(&mut self, settings: &ScreencastSettings) -> bool {
+        if !settings.enabled {
+            self.history.entries.clear();
+        }
+        self.history.remove_expired(Instant::now(), settings.timeout);
+        !self.history.entries.is_empty()
+    }
+
+    pub fn is_visible(&self) -> bool {
+        !self.history.entries.is_empty()
+    }
+
+    pub fn draw(
+        &self,
+        settings: &ScreencastSettings,
+        canvas: &Canvas,
+        grid_renderer: &GridRenderer,
+    ) {
+        if !settings.enabled || self.history.entries.is_empty() {
+            return;
+        }
+        tracy_zone!("screencast_draw");
+        let Some(font) = self.font.get() else {
+            return;
+        };
+
+        let mut font = font.clone();
+        font.set_size(grid_renderer.em_size * settings.scale);
+        let padding = font.size() * 0.4;
+        let margin = grid_renderer.grid_scale.height();
+        let key_height = font.spacing() + padding;
+
+        let default_colors = &grid_renderer.default_style.colors;
+        let foreground = default_colors.foreground.unwrap_or(colors::WHITE);
+        let background = default_colors.background.unwrap_or(colors::BLACK);
+
+        let now = Instant::now();
+        let keys = self
+            .history
+            .entries
+            .iter()
+            .map(|entry| {
+                let label = entry.label();
+                let width = font.measure_str(&label, None).0 + padding * 2.0;
+                let remaining = settings.timeout - (now - entry.last_pressed).as_secs_f32();
+                (label, width, (remaining / FADE_TIME).clamp(0.0, 1.0))
+            })
+            .collect::<Vec<_>>();
+        let total_width =
+            keys.iter().map(|(_, width, _)| width).sum::<f32>() + padding * (keys.len() - 1) as f32;
+
+        let size = canvas.base_layer_size();
+        let (canvas_width, canvas_height) = (size.width as f32, size.height as f32);
+        let mut left = match settings.position {
+            ScreencastPosition::TopLeft | ScreencastPosition::BottomLeft => margin,
+            ScreencastPosition::TopRight | ScreencastPosition::BottomRight => {
+                canvas_width - margin - total_width
+            }
+        };
+        let top = match settings.position {
+            ScreencastPosition::TopLeft | ScreencastPosition::TopRight => margin,
+            ScreencastPosition::BottomLeft | ScreencastPosition::BottomRight => {
+                canvas_height - margin - key_height
+            }
+        };
+
+        let mut paint = Paint::default();
+        paint.set_anti_alias(true);
+        for (label, width, alpha) in keys {
+            let rect = Rect::from_xywh(left, top, width, key_height);
+            paint.set_color4f(Color4f { a: 0.85 * alpha, ..background }, None);
+            canvas.draw_rrect(RRect::new_rect_xy(rect, padding, padding), &paint);
+            paint.set_color4f(Color4f { a: alpha, ..foreground }, None);
+            draw_text(canvas, &label, left + padding, &rect, &font, &paint);
+            left += width + padding;
+        }
+    }
+}
+
+fn displayed_keys<'a>(settings: &ScreencastSettings, keys: &'a str, mode: &EditorMode) -> &'a str {
+    if settings.hide_insert_text && is_text_mode(mode) && is_text(keys) {
+        HIDDEN_TEXT
+    } else {
+        keys
+    }
+}
+
+/// The modes in which the typed keys are inserted as text.
+fn is_text_mode(mode: &EditorMode) -> bool {
+    match mode {
+        EditorMode::Insert | EditorMode::Replace | EditorMode::CmdLine => true,
+        EditorMode::Unknown(name) => name.starts_with("cmdline"),
+        EditorMode::Normal | EditorMode::Visual => false,
+    }
+}
+
+/// Whether the keys insert text, instead of being a special key or having modifiers.
+fn is_text(keys: &str) -> bool {
+    !keys.starts_with('<') || matches!(keys, "<Space>" | "<lt>" | "<Bslash>" | "<Bar>")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(history: &KeyHistory) -> Vec<String> {
+        history.entries.iter().map(KeyEntry::label).collect()
+    }
+
+    #[test]
+    fn test_groups_repeats_and_expires() {
+        let mut history = KeyHistory::default();
+        let start = Instant::now();
+        for keys in ["j", "j", "j", "<C-w>", "j"] {
+            history.push(keys, start, 2.0);
+        }
+        assert_eq!(labels(&history), ["j ×3", "<C-w>", "j"]);
+
+        history.push("<Esc>", start + Duration::from_secs(3), 2.0);
+        assert_eq!(labels(&history), ["<Esc>"]);
+    }
+
+    #[test]
+    fn test_hides_insert_text() {
+        let settings = ScreencastSettings::default();
+        let insert = EditorMode::Insert;
+        assert_eq!(displayed_keys(&settings, "a", &insert), HIDDEN_TEXT);
+        assert_eq!(displayed_keys(&settings, "<Space>", &insert), HIDDEN_TEXT);
+        assert_eq!(displayed_keys(&settings, "<C-o>", &insert), "<C-o>");
+        assert_eq!(displayed_keys(&settings, "a", &EditorMode::Normal), "a");
+
+        let settings = ScreencastSettings { hide_insert_text: false, ..settings };
+        assert_eq!(displayed_keys(&settings, "a", &insert), "a");
+    }
+}
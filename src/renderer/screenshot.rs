@@ -0,0 +1,36 @@
+//! Saves the contents of a window as a PNG image, for the `screenshot` GUI action.
+
+use std::{fs, path::PathBuf, time::SystemTime};
+
+use skia_safe::{AlphaType, Canvas, ColorType, ImageInfo};
+use time::{OffsetDateTime, macros::format_description};
+
+use crate::settings::neovide_std_datapath;
+
+/// Saves the canvas to the pictures directory, or the Neovide data directory when there's none,
+/// and returns the path of the image.
+pub fn save_screenshot(canvas: &Canvas) -> Result<PathBuf, String> {
+    let size = canvas.base_layer_size();
+    let info = ImageInfo::new(size, ColorType::RGBA8888, AlphaType::Unpremul, None);
+    let row_bytes = info.min_row_bytes();
+    let mut pixels = vec![0; info.compute_min_byte_size()];
+    if !canvas.read_pixels(&info, &mut pixels, row_bytes, (0, 0)) {
+        return Err("Could not read the window contents for the screenshot".to_owned());
+    }
+    let image = image::RgbaImage::from_raw(size.width as u32, size.height as u32, pixels)
+        .ok_or("The screenshot has an invalid size")?;
+
+    let directory = dirs::picture_dir().unwrap_or_else(neovide_std_datapath);
+    fs::create_dir_all(&directory)
+        .map_err(|error| format!("Could not create {}: {error}", directory.display()))?;
+    let path = directory.join(screenshot_file_name(SystemTime::now().into()));
+    image.save(&path).map_err(|error| format!("Could not save {}: {error}", path.display()))?;
+    Ok(path)
+}
+
+fn screenshot_file_name(time: OffsetDateTime) -> String {
+    let timestamp = time
+        .format(format_description!("[year]-[month]-[day] [hour].[minute].[second]"))
+        .unwrap_or_default();
+    format!("Neovide {timestamp}.png")
+}
@@ -0,0 +1,217 @@
+//! Client side text selection.
+//!
+//! The selection is tracked and drawn by Neovide itself, so it never touches Neovim's Visual mode
+//! and works for grids that can't be entered, like hover popups or the message area.
+
+use crate::{
+    bridge::CellText,
+    units::{GridPos, GridSize},
+};
+
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum SelectionMode {
+    /// Selects the text flowing from the start to the end position, like a terminal would.
+    #[default]
+    Character,
+    /// Selects the rectangle spanned by the start and end positions.
+    Block,
+    /// Selects whole rows.
+    Line,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct GuiSelection {
+    pub grid_id: u64,
+    pub start: GridPos<u32>,
+    pub end: GridPos<u32>,
+    pub mode: SelectionMode,
+}
+
+impl GuiSelection {
+    pub fn new(grid_id: u64, start: GridPos<u32>, end: GridPos<u32>, mode: SelectionMode) -> Self {
+        Self { grid_id, start, end, mode }
+    }
+
+    /// Returns the first and last selected row, clamped to the grid.
+    pub fn rows(&self, grid_size: GridSize<u32>) -> Option<(u32, u32)> {
+        if grid_size.width == 0 || grid_size.height == 0 {
+            return None;
+        }
+
+        let max_row = grid_size.height - 1;
+        let start_row = self.start.y.min(max_row);
+        let end_row = self.end.y.min(max_row);
+        Some(if start_row <= end_row { (start_row, end_row) } else { (end_row, start_row) })
+    }
+
+    /// Returns the inclusive column range selected on `row`, or `None` when the row is not part
+    /// of the selection.
+    pub fn row_span(&self, row: u32, grid_size: GridSize<u32>) -> Option<(u32, u32)> {
+        let (row_start, row_end) = self.rows(grid_size)?;
+        if row < row_start || row > row_end {
+            return None;
+        }
+
+        let max_col = grid_size.width - 1;
+        let start_col = self.start.x.min(max_col);
+        let end_col = self.end.x.min(max_col);
+
+        match self.mode {
+            SelectionMode::Line => Some((0, max_col)),
+            SelectionMode::Block => Some((start_col.min(end_col), start_col.max(end_col))),
+            SelectionMode::Character => {
+                // The anchor of the flow is whichever end comes first in reading order
+                let ((first_row, first_col), (last_row, last_col)) = {
+                    let start = (self.start.y.min(grid_size.height - 1), start_col);
+                    let end = (self.end.y.min(grid_size.height - 1), end_col);
+                    if start <= end { (start, end) } else { (end, start) }
+                };
+                let span_start = if row == first_row { first_col } else { 0 };
+                let span_end = if row == last_row { last_col } else { max_col };
+                Some((span_start, span_end))
+            }
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum CellClass {
+    Whitespace,
+    Word,
+    Punctuation,
+}
+
+fn cell_class(cell: &str) -> CellClass {
+    match cell.chars().next() {
+        None => CellClass::Whitespace,
+        Some(c) if c.is_whitespace() => CellClass::Whitespace,
+        Some(c) if c.is_alphanumeric() || c == '_' => CellClass::Word,
+        Some(_) => CellClass::Punctuation,
+    }
+}
+
+/// Returns the inclusive column range of the word under `col`. A word is a run of cells of the
+/// same class (keyword characters, punctuation or whitespace), like `iw` in Neovim.
+///
+/// The empty continuation cell of a double width character belongs to the character before it.
+pub fn word_bounds(cells: &[CellText], col: u32) -> (u32, u32) {
+    if cells.is_empty() {
+        return (col, col);
+    }
+
+    let mut col = (col as usize).min(cells.len() - 1);
+    while col > 0 && cells[col].is_empty() {
+        col -= 1;
+    }
+    let class = cell_class(&cells[col]);
+    let same_class = |cell: &CellText| cell.is_empty() || cell_class(cell) == class;
+
+    let mut start = col;
+    while start > 0 && same_class(&cells[start - 1]) {
+        start -= 1;
+    }
+    // Don't let the selection start on a continuation cell
+    while start < col && cells[start].is_empty() {
+        start += 1;
+    }
+
+    let mut end = col;
+    while end + 1 < cells.len() && same_class(&cells[end + 1]) {
+        end += 1;
+    }
+
+    (start as u32, end as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cells(text: &str) -> Vec<CellText> {
+        text.chars().map(|c| c.to_string().into()).collect()
+    }
+
+    fn selection(start: (u32, u32), end: (u32, u32), mode: SelectionMode) -> GuiSelection {
+        GuiSelection::new(1, GridPos::new(start.0, start.1), GridPos::new(end.0, end.1), mode)
+    }
+
+    #[test]
+    fn character_selection_flows_across_rows() {
+        let grid_size = GridSize::new(10, 5);
+        let selection = selection((7, 1), (2, 3), SelectionMode::Character);
+
+        assert_eq!(selection.row_span(0, grid_size), None);
+        assert_eq!(selection.row_span(1, grid_size), Some((7, 9)));
+        assert_eq!(selection.row_span(2, grid_size), Some((0, 9)));
+        assert_eq!(selection.row_span(3, grid_size), Some((0, 2)));
+        assert_eq!(selection.row_span(4, grid_size), None);
+    }
+
+    #[test]
+    fn character_selection_backwards_is_normalized() {
+        let grid_size = GridSize::new(10, 5);
+        let selection = selection((2, 3), (7, 1), SelectionMode::Character);
+
+        assert_eq!(selection.row_span(1, grid_size), Some((7, 9)));
+        assert_eq!(selection.row_span(3, grid_size), Some((0, 2)));
+    }
+
+    #[test]
+    fn block_selection_uses_the_same_columns_on_all_rows() {
+        let grid_size = GridSize::new(10, 5);
+        let selection = selection((7, 1), (2, 3), SelectionMode::Block);
+
+        assert_eq!(selection.row_span(1, grid_size), Some((2, 7)));
+        assert_eq!(selection.row_span(2, grid_size), Some((2, 7)));
+        assert_eq!(selection.row_span(3, grid_size), Some((2, 7)));
+    }
+
+    #[test]
+    fn line_selection_covers_full_rows() {
+        let grid_size = GridSize::new(10, 5);
+        let selection = selection((7, 1), (2, 2), SelectionMode::Line);
+
+        assert_eq!(selection.row_span(1, grid_size), Some((0, 9)));
+        assert_eq!(selection.row_span(2, grid_size), Some((0, 9)));
+    }
+
+    #[test]
+    fn selection_is_clamped_to_the_grid() {
+        let grid_size = GridSize::new(4, 2);
+        let selection = selection((1, 0), (20, 20), SelectionMode::Character);
+
+        assert_eq!(selection.rows(grid_size), Some((0, 1)));
+        assert_eq!(selection.row_span(1, grid_size), Some((0, 3)));
+        assert_eq!(selection.rows(GridSize::new(0, 0)), None);
+    }
+
+    #[test]
+    fn word_bounds_selects_keyword_runs() {
+        let line = cells("let foo_bar = 42;");
+        assert_eq!(word_bounds(&line, 5), (4, 10));
+        assert_eq!(word_bounds(&line, 0), (0, 2));
+        assert_eq!(word_bounds(&line, 15), (14, 15));
+        assert_eq!(word_bounds(&line, 16), (16, 16));
+    }
+
+    #[test]
+    fn word_bounds_selects_whitespace_runs() {
+        let line = cells("a   b");
+        assert_eq!(word_bounds(&line, 2), (1, 3));
+    }
+
+    #[test]
+    fn word_bounds_handles_double_width_characters() {
+        let line: Vec<CellText> =
+            ["x", " ", "一", "", "二", "", " "].iter().map(|s| CellText::from(*s)).collect();
+        assert_eq!(word_bounds(&line, 3), (2, 5));
+        assert_eq!(word_bounds(&line, 2), (2, 5));
+    }
+
+    #[test]
+    fn word_bounds_clamps_the_column() {
+        let line = cells("abc");
+        assert_eq!(word_bounds(&line, 10), (0, 2));
+        assert_eq!(word_bounds(&[], 3), (3, 3));
+    }
+}
@@ -0,0 +1,567 @@
+//! The `:NeovideSettings` panel. It's drawn by Neovide on top of the grid, lists all the settings
+//! that are backed by a `g:neovide_*` variable and changes them with a live preview.
+
+use std::{path::PathBuf, sync::Arc};
+
+use rmpv::Value;
+use skia_safe::{Canvas, Color4f, Font, Paint, PaintStyle, Point, Rect, colors};
+use winit::{
+    event::{ElementState, MouseButton, MouseScrollDelta, WindowEvent},
+    keyboard::{Key, NamedKey},
+};
+
+use crate::{
+    profiling::tracy_zone,
+    renderer::{GridRenderer, overlay_font::OverlayFont},
+    settings::{SettingMetadata, Settings},
+};
+
+const HELP: &str = "↑↓ select  ←→ change  enter toggle  r reset  w save  esc close";
+const MAX_WIDTH_CELLS: f32 = 90.0;
+// Width of the value next to a slider
+const SLIDER_LABEL_CELLS: f32 = 8.0;
+const STEPS: f64 = 20.0;
+
+/// What the window should do with an input event.
+pub enum SettingsPanelInput {
+    /// The panel is closed, so the event goes to Neovim as usual.
+    Ignored,
+    Consumed,
+    Set {
+        name: String,
+        value: Value,
+    },
+    /// Save the current values of these settings to the config file.
+    Save(Vec<String>),
+}
+
+struct RowGeometry {
+    index: usize,
+    row: Rect,
+    value: Rect,
+    slider: Option<Rect>,
+}
+
+pub struct SettingsPanel {
+    settings: Arc<Settings>,
+    font: OverlayFont,
+    entries: Vec<SettingMetadata>,
+    open: bool,
+    selected: usize,
+    scroll: usize,
+    visible_rows: usize,
+    // Settings changed since the panel was opened or last saved
+    modified: Vec<String>,
+    status: Option<String>,
+    dragging: Option<usize>,
+    mouse_position: Point,
+    // The geometry of the last frame, for mouse input
+    panel_rect: Rect,
+    rows: Vec<RowGeometry>,
+}
+
+impl SettingsPanel {
+    pub fn new(settings: Arc<Settings>) -> Self {
+        Self {
+            settings,
+            font: OverlayFont::new(12.0),
+            entries: Vec::new(),
+            open: false,
+            selected: 0,
+            scroll: 0,
+            visible_rows: 1,
+            modified: Vec::new(),
+            status: None,
+            dragging: None,
+            mouse_position: Point::default(),
+            panel_rect: Rect::default(),
+            rows: Vec::new(),
+        }
+    }
+
+    pub fn open(&mut self) {
+        self.entries = self.settings.setting_metadata();
+        self.selected = self.selected.min(self.entries.len().saturating_sub(1));
+        self.status = None;
+        self.open = true;
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn close(&mut self) {
+        self.open = false;
+        self.dragging = None;
+        self.rows.clear();
+    }
+
+    /// Shows the result of a [`SettingsPanelInput::Save`].
+    pub fn handle_saved(&mut self, result: Result<PathBuf, String>) {
+        self.status = Some(match result {
+            Ok(path) => {
+                self.modified.clear();
+                format!("Saved to {}", path.display())
+            }
+            Err(error) => error,
+        });
+    }
+
+    pub fn handle_event(&mut self, event: &WindowEvent) -> SettingsPanelInput {
+        if !self.open {
+            return SettingsPanelInput::Ignored;
+        }
+
+        match event {
+            WindowEvent::KeyboardInput { event, .. } => {
+                if event.state != ElementState::Pressed {
+                    return SettingsPanelInput::Consumed;
+                }
+                self.handle_key(event.logical_key.as_ref())
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                self.mouse_position = Point::new(position.x as f32, position.y as f32);
+                match self.dragging {
+                    Some(index) => self.drag(index),
+                    None => SettingsPanelInput::Consumed,
+                }
+            }
+            WindowEvent::MouseInput { state, button: MouseButton::Left, .. } => match state {
+                ElementState::Pressed => self.click(),
+                ElementState::Released => {
+                    self.dragging = None;
+                    SettingsPanelInput::Consumed
+                }
+            },
+            WindowEvent::MouseInput { .. } => SettingsPanelInput::Consumed,
+            WindowEvent::MouseWheel { delta, .. } => {
+                let lines = match delta {
+                    MouseScrollDelta::LineDelta(_, lines) => *lines,
+                    MouseScrollDelta::PixelDelta(position) => position.y.signum() as f32,
+                };
+                if lines > 0.0 {
+                    self.select(self.selected.saturating_sub(1));
+                } else if lines < 0.0 {
+                    self.select(self.selected + 1);
+                }
+                SettingsPanelInput::Consumed
+            }
+            // Otherwise the keys typed with an input method would reach Neovim behind the panel
+            WindowEvent::Ime(_) => SettingsPanelInput::Consumed,
+            _ => SettingsPanelInput::Ignored,
+        }
+    }
+
+    fn handle_key(&mut self, key: Key<&str>) -> SettingsPanelInput {
+        match key {
+            Key::Named(NamedKey::Escape) | Key::Character("q") => self.close(),
+            Key::Named(NamedKey::ArrowUp) | Key::Character("k") => {
+                self.select(self.selected.saturating_sub(1))
+            }
+            Key::Named(NamedKey::ArrowDown) | Key::Character("j") => self.select(self.selected + 1),
+            Key::Named(NamedKey::PageUp) => {
+                self.select(self.selected.saturating_sub(self.visible_rows))
+            }
+            Key::Named(NamedKey::PageDown) => self.select(self.selected + self.visible_rows),
+            Key::Named(NamedKey::Home) | Key::Character("g") => self.select(0),
+            Key::Named(NamedKey::End) | Key::Character("G") => self.select(usize::MAX),
+            Key::Named(NamedKey::ArrowLeft) | Key::Character("h") => return self.step(-1),
+            Key::Named(NamedKey::ArrowRight) | Key::Character("l") => return self.step(1),
+            Key::Named(NamedKey::Enter | NamedKey::Space) => return self.activate(),
+            Key::Character("r") => return self.reset(),
+            Key::Character("w") => return self.save(),
+            _ => {}
+        }
+        SettingsPanelInput::Consumed
+    }
+
+    fn select(&mut self, index: usize) {
+        self.selected = index.min(self.entries.len().saturating_sub(1));
+    }
+
+    fn set(&mut self, name: String, value: Value) -> SettingsPanelInput {
+        if !self.modified.contains(&name) {
+            self.modified.push(name.clone());
+        }
+        self.status = None;
+        SettingsPanelInput::Set { name, value }
+    }
+
+    fn step(&mut self, direction: i32) -> SettingsPanelInput {
+        let Some(metadata) = self.entries.get(self.selected) else {
+            return SettingsPanelInput::Consumed;
+        };
+        let value = self
+            .settings
+            .value(&metadata.name)
+            .and_then(|current| step_value(metadata, &current, direction));
+        match value {
+            Some(value) => self.set(metadata.name.clone(), value),
+            None => SettingsPanelInput::Consumed,
+        }
+    }
+
+    /// Toggles booleans and cycles through the allowed values of strings.
+    fn activate(&mut self) -> SettingsPanelInput {
+        match self.entries.get(self.selected) {
+            Some(metadata) if slider_range(metadata).is_none() => self.step(1),
+            _ => SettingsPanelInput::Consumed,
+        }
+    }
+
+    fn reset(&mut self) -> SettingsPanelInput {
+        let Some(metadata) = self.entries.get(self.selected) else {
+            return SettingsPanelInput::Consumed;
+        };
+        match self.settings.default_value(&metadata.name) {
+            Some(value) => self.set(metadata.name.clone(), value),
+            None => SettingsPanelInput::Consumed,
+        }
+    }
+
+    fn save(&mut self) -> SettingsPanelInput {
+        if self.modified.is_empty() {
+            self.status = Some("No changes to save".to_owned());
+            return SettingsPanelInput::Consumed;
+        }
+        SettingsPanelInput::Save(self.modified.clone())
+    }
+
+    fn click(&mut self) -> SettingsPanelInput {
+        let position = self.mouse_position;
+        if !contains(&self.panel_rect, position) {
+            self.close();
+            return SettingsPanelInput::Consumed;
+        }
+        let Some(row) = self.rows.iter().find(|row| contains(&row.row, position)) else {
+            return SettingsPanelInput::Consumed;
+        };
+        let (index, on_value, on_slider) =
+            (row.index, contains(&row.value, position), row.slider.is_some());
+
+        self.selected = index;
+        if on_value && on_slider {
+            self.dragging = Some(index);
+            self.drag(index)
+        } else if on_value {
+            self.activate()
+        } else {
+            SettingsPanelInput::Consumed
+        }
+    }
+
+    fn drag(&mut self, index: usize) -> SettingsPanelInput {
+        let slider = self.rows.iter().find(|row| row.index == index).and_then(|row| row.slider);
+        let (Some(slider), Some(metadata)) = (slider, self.entries.get(index)) else {
+            return SettingsPanelInput::Consumed;
+        };
+        let fraction = ((self.mouse_position.x - slider.left) / slider.width()).clamp(0.0, 1.0);
+        match slider_value(metadata, fraction as f64) {
+            Some(value) if Some(&value) != self.settings.value(&metadata.name).as_ref() => {
+                self.set(metadata.name.clone(), value)
+            }
+            _ => SettingsPanelInput::Consumed,
+        }
+    }
+
+    pub fn draw(&mut self, canvas: &Canvas, grid_renderer: &GridRenderer) {
+        if !self.open {
+            return;
+        }
+        tracy_zone!("settings_panel_draw");
+        let Some(font) = self.font.get() else {
+            return;
+        };
+
+        let mut font = font.clone();
+        font.set_size(grid_renderer.em_size);
+        let cell_width = grid_renderer.grid_scale.width();
+        let row_height = grid_renderer.grid_scale.height() * 1.4;
+        let padding = cell_width * 2.0;
+
+        let default_colors = &grid_renderer.default_style.colors;
+        let foreground = default_colors.foreground.unwrap_or(colors::WHITE);
+        let background = default_colors.background.unwrap_or(colors::BLACK);
+        let faded = |alpha: f32| Color4f { a: alpha, ..foreground };
+
+        // Centered, leaving some of the grid visible around the panel
+        let size = canvas.base_layer_size();
+        let (canvas_width, canvas_height) = (size.width as f32, size.height as f32);
+        let width = (canvas_width - cell_width * 8.0).min(cell_width * MAX_WIDTH_CELLS).max(0.0);
+        let height = (canvas_height - row_height * 4.0).max(0.0);
+        self.panel_rect =
+            Rect::from_xywh((canvas_width - width) / 2.0, row_height * 2.0, width, height);
+        let panel = self.panel_rect;
+
+        canvas.save();
+        canvas.reset_matrix();
+        canvas.clip_rect(panel, None, Some(true));
+
+        let mut paint = Paint::new(Color4f { a: 0.95, ..background }, None);
+        paint.set_anti_alias(true);
+        canvas.draw_rect(panel, &paint);
+        paint.set_color4f(faded(0.3), None);
+        paint.set_style(PaintStyle::Stroke);
+        canvas.draw_rect(panel.with_inset((0.5, 0.5)), &paint);
+        paint.set_style(PaintStyle::Fill);
+
+        let text_left = panel.left + padding;
+        let text_right = panel.right - padding;
+        let header = Rect::new(panel.left, panel.top, panel.right, panel.top + row_height * 1.5);
+        paint.set_color4f(foreground, None);
+        draw_text(canvas, "Neovide Settings", text_left, &header, &font, &paint);
+
+        // The documentation of the selected setting and the status or help below the list
+        let footer_top = panel.bottom - row_height * 3.0;
+        let doc = self.entries.get(self.selected).map(|metadata| metadata.doc.as_str());
+        let doc_row = Rect::new(panel.left, footer_top, panel.right, footer_top + row_height);
+        draw_text(canvas, doc.unwrap_or_default(), text_left, &doc_row, &font, &paint);
+        let status_row = doc_row.with_offset((0.0, row_height));
+        paint.set_color4f(faded(0.6), None);
+        let status = self.status.as_deref().unwrap_or(HELP);
+        draw_text(canvas, status, text_left, &status_row, &font, &paint);
+
+        let list_top = header.bottom;
+        self.visible_rows = (((footer_top - list_top) / row_height).floor() as usize).max(1);
+        if self.selected < self.scroll {
+            self.scroll = self.selected;
+        } else if self.selected >= self.scroll + self.visible_rows {
+            self.scroll = self.selected + 1 - self.visible_rows;
+        }
+
+        self.rows.clear();
+        let value_left = panel.left + width * 0.55;
+        let visible = self.scroll..(self.scroll + self.visible_rows).min(self.entries.len());
+        for (row_index, index) in visible.enumerate() {
+            let metadata = &self.entries[index];
+            let top = list_top + row_index as f32 * row_height;
+            let row = Rect::new(panel.left, top, panel.right, top + row_height);
+            let value_rect = Rect::new(value_left, top, text_right, top + row_height);
+            let value = self.settings.value(&metadata.name).unwrap_or_default();
+
+            if index == self.selected {
+                paint.set_color4f(faded(0.12), None);
+                canvas.draw_rect(row, &paint);
+            }
+
+            // Changed settings are highlighted, the rest is faded
+            let changed = Some(&value) != self.settings.default_value(&metadata.name).as_ref();
+            let modified = if self.modified.contains(&metadata.name) { "*" } else { "" };
+            paint.set_color4f(if changed { foreground } else { faded(0.7) }, None);
+            let name = format!("{}{modified}", metadata.name);
+            draw_text(canvas, &name, text_left, &row, &font, &paint);
+
+            let slider = slider_range(metadata).map(|range| {
+                let label_width = cell_width * SLIDER_LABEL_CELLS;
+                let slider = Rect::new(value_left, top, text_right - label_width, top + row_height);
+                draw_slider(canvas, &slider, range, &value, foreground, &paint);
+                slider
+            });
+
+            paint.set_color4f(foreground, None);
+            let label = format_value(metadata, &value);
+            match (slider, metadata.kind) {
+                (Some(slider), _) => {
+                    let left = slider.right + cell_width;
+                    draw_text(canvas, &label, left, &row, &font, &paint);
+                }
+                (None, "boolean") => {
+                    let checked = value.as_bool().unwrap_or_default();
+                    let size = row_height * 0.45;
+                    let box_rect =
+                        Rect::from_xywh(value_left, row.center_y() - size / 2.0, size, size);
+                    paint.set_style(if checked { PaintStyle::Fill } else { PaintStyle::Stroke });
+                    canvas.draw_rect(box_rect, &paint);
+                    paint.set_style(PaintStyle::Fill);
+                    draw_text(canvas, &label, value_left + size + cell_width, &row, &font, &paint);
+                }
+                (None, _) if !metadata.allowed_values.is_empty() => {
+                    let label = format!("‹ {label} ›");
+                    draw_text(canvas, &label, value_left, &row, &font, &paint);
+                }
+                (None, _) => draw_text(canvas, &label, value_left, &row, &font, &paint),
+            }
+
+            self.rows.push(RowGeometry { index, row, value: value_rect, slider });
+        }
+
+        canvas.restore();
+    }
+}
+
+pub(super) fn contains(rect: &Rect, point: Point) -> bool {
+    point.x >= rect.left && point.x < rect.right && point.y >= rect.top && point.y < rect.bottom
+}
+
+pub(super) fn draw_text(
+    canvas: &Canvas,
+    text: &str,
+    left: f32,
+    row: &Rect,
+    font: &Font,
+    paint: &Paint,
+) {
+    let (_, metrics) = font.metrics();
+    // Vertically centered in the row
+    let baseline = row.center_y() - (metrics.ascent + metrics.descent) / 2.0;
+    canvas.draw_str(text, Point::new(left, baseline), font, paint);
+}
+
+fn draw_slider(
+    canvas: &Canvas,
+    slider: &Rect,
+    (min, max): (f64, f64),
+    value: &Value,
+    foreground: Color4f,
+    paint: &Paint,
+) {
+    let mut paint = paint.clone();
+    let fraction = ((value.as_f64().unwrap_or(min) - min) / (max - min)).clamp(0.0, 1.0) as f32;
+    let y = slider.center_y();
+    let knob_x = slider.left + slider.width() * fraction;
+
+    paint.set_stroke_width(2.0);
+    paint.set_color4f(Color4f { a: 0.3, ..foreground }, None);
+    canvas.draw_line((slider.left, y), (slider.right, y), &paint);
+    paint.set_color4f(foreground, None);
+    canvas.draw_line((slider.left, y), (knob_x, y), &paint);
+    canvas.draw_circle((knob_x, y), slider.height() * 0.2, &paint);
+}
+
+/// The range of the slider of a numeric setting. The settings don't declare their range, so it's
+/// guessed from the name, with a fallback based on the default value.
+fn slider_range(metadata: &SettingMetadata) -> Option<(f64, f64)> {
+    if !matches!(metadata.kind, "number" | "integer") {
+        return None;
+    }
+    let name = metadata.name.as_str();
+    let range = match name {
+        "cursor_vfx_opacity" => (0.0, 256.0),
+        "scale_factor" => (0.25, 4.0),
+        "refresh_rate" | "refresh_rate_idle" => (1.0, 240.0),
+        _ if name.ends_with("opacity") => (0.0, 1.0),
+        _ if name.ends_with("degrees") => (0.0, 360.0),
+        _ if name.starts_with("padding_") => (0.0, 100.0),
+        _ if ["_length", "lifetime", "timeout", "delay"].iter().any(|s| name.ends_with(s)) => {
+            (0.0, 2.0)
+        }
+        _ => {
+            let minimum = if metadata.kind == "integer" { 10.0 } else { 1.0 };
+            let default = metadata.default.as_f64().unwrap_or_default().abs();
+            (0.0, (default * 2.0).max(minimum))
+        }
+    };
+    Some(range)
+}
+
+fn numeric_value(metadata: &SettingMetadata, value: f64) -> Value {
+    if metadata.kind == "integer" {
+        Value::from(value.round() as i64)
+    } else {
+        Value::from((value * 100.0).round() / 100.0)
+    }
+}
+
+fn slider_value(metadata: &SettingMetadata, fraction: f64) -> Option<Value> {
+    let (min, max) = slider_range(metadata)?;
+    Some(numeric_value(metadata, min + (max - min) * fraction))
+}
+
+/// The value after pressing left (`direction` -1) or right (1) on a setting.
+fn step_value(metadata: &SettingMetadata, current: &Value, direction: i32) -> Option<Value> {
+    if let Some((min, max)) = slider_range(metadata) {
+        let step = ((max - min) / STEPS).max(if metadata.kind == "integer" { 1.0 } else { 0.0 });
+        let value = current.as_f64()? + step * direction as f64;
+        return Some(numeric_value(metadata, value.clamp(min, max)));
+    }
+
+    match (metadata.kind, current) {
+        ("boolean", current) => current.as_bool().map(|checked| Value::from(!checked)),
+        (_, current) if !metadata.allowed_values.is_empty() => {
+            // Lists, like the cursor vfx modes, are cycled as a single value
+            let current = match current {
+                Value::Array(values) => values.first().and_then(Value::as_str),
+                value => value.as_str(),
+            };
+            let allowed_values = metadata.allowed_values;
+            let position = allowed_values.iter().position(|value| Some(*value) == current);
+            let next = match position {
+                Some(position) => {
+                    (position as i32 + direction).rem_euclid(allowed_values.len() as i32) as usize
+                }
+                None => 0,
+            };
+            Some(Value::from(allowed_values[next]))
+        }
+        _ => None,
+    }
+}
+
+fn format_value(metadata: &SettingMetadata, value: &Value) -> String {
+    match value {
+        Value::Boolean(true) => "on".to_owned(),
+        Value::Boolean(false) => "off".to_owned(),
+        Value::F32(_) | Value::F64(_) => format!("{:.2}", value.as_f64().unwrap_or_default()),
+        Value::String(string) => match string.as_str() {
+            Some("") if !metadata.allowed_values.is_empty() => "none".to_owned(),
+            Some(string) => string.to_owned(),
+            None => value.to_string(),
+        },
+        Value::Array(values) if values.is_empty() => "none".to_owned(),
+        Value::Array(values) => {
+            let values: Vec<_> = values.iter().map(|value| format_value(metadata, value)).collect();
+            values.join(", ")
+        }
+        value => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata(
+        name: &str,
+        kind: &'static str,
+        allowed_values: &'static [&'static str],
+        default: Value,
+    ) -> SettingMetadata {
+        SettingMetadata { name: name.to_owned(), kind, allowed_values, default, doc: String::new() }
+    }
+
+    #[test]
+    fn test_step_value() {
+        let opacity = metadata("opacity", "number", &[], Value::from(1.0f32));
+        assert_eq!(step_value(&opacity, &Value::from(0.8f32), -1), Some(Value::from(0.75)));
+        assert_eq!(step_value(&opacity, &Value::from(1.0f32), 1), Some(Value::from(1.0)));
+
+        let padding = metadata("padding_top", "integer", &[], Value::from(0));
+        assert_eq!(step_value(&padding, &Value::from(0), 1), Some(Value::from(5)));
+        assert_eq!(step_value(&padding, &Value::from(0), -1), Some(Value::from(0)));
+
+        let border = metadata("show_border", "boolean", &[], Value::from(true));
+        assert_eq!(step_value(&border, &Value::from(true), 1), Some(Value::from(false)));
+
+        let vfx_mode =
+            metadata("cursor_vfx_mode", "string|string[]", &["", "railgun", "torpedo"], "".into());
+        let current = Value::Array(vec!["railgun".into(), "ripple".into()]);
+        assert_eq!(step_value(&vfx_mode, &current, 1), Some(Value::from("torpedo")));
+        assert_eq!(step_value(&vfx_mode, &Value::from(""), -1), Some(Value::from("torpedo")));
+
+        let title = metadata("title_text_color", "string", &[], "".into());
+        assert_eq!(step_value(&title, &Value::from(""), 1), None);
+    }
+
+    #[test]
+    fn test_slider_value() {
+        let animation_length =
+            metadata("cursor_animation_length", "number", &[], Value::from(0.15f32));
+        assert_eq!(slider_value(&animation_length, 0.25), Some(Value::from(0.5)));
+
+        let refresh_rate = metadata("refresh_rate", "integer", &[], Value::from(60));
+        assert_eq!(slider_value(&refresh_rate, 1.0), Some(Value::from(240)));
+
+        let theme = metadata("theme", "string", &["auto", "dark"], "auto".into());
+        assert_eq!(slider_value(&theme, 0.5), None);
+    }
+}
@@ -15,7 +15,7 @@ use crate::{
     settings::Settings,
     window::{EventPayload, WindowSettings},
 };
-use vsync_timer::VSyncTimer;
+pub use vsync_timer::VSyncTimer;
 
 #[cfg(target_os = "windows")]
 pub use vsync_win_dwm::VSyncWinDwm;
@@ -29,7 +29,7 @@ pub use vsync_macos_display_link::VSyncMacosDisplayLink;
 pub enum VSync {
     Opengl(),
     WinitThrottling(),
-    Timer(VSyncTimer),
+    Timer(),
     #[cfg(target_os = "windows")]
     WindowsDwm(VSyncWinDwm),
     #[cfg(target_os = "windows")]
@@ -45,26 +45,14 @@ impl VSync {
         vsync_enabled: bool,
         renderer: &dyn SkiaRenderer,
         proxy: EventLoopProxy<EventPayload>,
-        settings: Arc<Settings>,
     ) -> Self {
-        if vsync_enabled {
-            renderer.create_vsync(proxy)
-        } else {
-            VSync::Timer(VSyncTimer::new(settings))
-        }
+        if vsync_enabled { renderer.create_vsync(proxy) } else { VSync::Timer() }
     }
 
-    pub fn wait_for_vsync(&mut self) {
-        match self {
-            VSync::Timer(vsync) => vsync.wait_for_vsync(),
-            #[cfg(target_os = "windows")]
-            VSync::WindowsDwm(vsync) => vsync.wait_for_vsync(),
-            #[cfg(target_os = "windows")]
-            VSync::WindowsSwapChain(vsync) => vsync.wait_for_vsync(),
-            #[cfg(target_os = "macos")]
-            VSync::MacosDisplayLink(vsync) => vsync.wait_for_vsync(),
-            _ => {}
-        }
+    /// Creates the timer that paces the frames on the render thread, when there's no vsync that
+    /// does it.
+    pub fn create_timer(&self, settings: Arc<Settings>) -> Option<VSyncTimer> {
+        matches!(self, VSync::Timer()).then(|| VSyncTimer::new(settings))
     }
 
     pub fn uses_winit_throttling(&self) -> bool {
@@ -93,7 +81,7 @@ impl VSync {
         let settings_refresh_rate = 1.0 / settings.get::<WindowSettings>().refresh_rate as f32;
 
         match self {
-            VSync::Timer(_) => settings_refresh_rate,
+            VSync::Timer() => settings_refresh_rate,
             _ => {
                 let monitor = window.current_monitor();
                 monitor
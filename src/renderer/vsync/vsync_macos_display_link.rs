@@ -164,8 +164,6 @@ impl VSyncMacosDisplayLink {
         }
     }
 
-    pub fn wait_for_vsync(&mut self) {}
-
     pub fn request_redraw(&mut self) {
         self.redraw_requested.store(true, Ordering::Relaxed);
     }
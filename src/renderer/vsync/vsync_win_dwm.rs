@@ -99,8 +99,6 @@ impl VSyncWinDwm {
         Self { should_exit, vsync_thread, redraw_requested }
     }
 
-    pub fn wait_for_vsync(&mut self) {}
-
     pub fn request_redraw(&mut self) {
         self.redraw_requested.store(true, Ordering::Relaxed);
     }
@@ -50,8 +50,6 @@ impl VSyncWinSwapChain {
         Self { vsync_thread: Some(vsync_thread), sender }
     }
 
-    pub fn wait_for_vsync(&mut self) {}
-
     pub fn request_redraw(&mut self) {
         self.sender.send(Message::RequestRedraw).ok();
     }
@@ -0,0 +1,120 @@
+//! Animated zoom. While the animation runs the rendered frame is scaled around an anchor point, and
+//! the new scale factor is only applied to the fonts and the Neovim grid once it settles.
+
+use skia_safe::Canvas;
+
+use super::animation_utils::CriticallyDampedSpringAnimation;
+use crate::units::PixelPos;
+
+/// The scale factor multiplier of one zoom step.
+pub const ZOOM_STEP: f32 = 1.1;
+const MIN_SCALE_FACTOR: f32 = 0.25;
+const MAX_SCALE_FACTOR: f32 = 4.0;
+// The spring tolerance is absolute, so the offset is stored in percent of the scale factor to
+// avoid a visible jump at the end of the animation
+const SPRING_UNITS: f32 = 100.0;
+
+pub struct ZoomAnimation {
+    /// The scale factor the fonts are laid out with.
+    applied: f32,
+    /// The scale factor the animation moves towards.
+    target: f32,
+    /// The distance from the target.
+    spring: CriticallyDampedSpringAnimation,
+    anchor: PixelPos<f32>,
+}
+
+impl ZoomAnimation {
+    pub fn new(scale_factor: f32) -> Self {
+        Self {
+            applied: scale_factor,
+            target: scale_factor,
+            spring: CriticallyDampedSpringAnimation::new(),
+            anchor: PixelPos::ZERO,
+        }
+    }
+
+    pub fn target(&self) -> f32 {
+        self.target
+    }
+
+    fn current(&self) -> f32 {
+        self.target + self.spring.position / SPRING_UNITS
+    }
+
+    /// Starts animating towards the scale factor, keeping the anchor at the same position.
+    pub fn zoom_to(&mut self, scale_factor: f32, anchor: PixelPos<f32>) {
+        let scale_factor = scale_factor.clamp(MIN_SCALE_FACTOR, MAX_SCALE_FACTOR);
+        // Moving the anchor mid-animation would make the content jump
+        if self.scale() == 1.0 {
+            self.anchor = anchor;
+        }
+        self.spring.position = (self.current() - scale_factor) * SPRING_UNITS;
+        self.target = scale_factor;
+    }
+
+    /// Called when the scale factor was changed without animation, for example by a setting.
+    pub fn set_scale_factor(&mut self, scale_factor: f32) {
+        self.applied = scale_factor;
+        self.target = scale_factor;
+        self.spring.reset();
+    }
+
+    pub fn animate(&mut self, dt: f32, animation_length: f32) -> bool {
+        self.spring.update(dt, animation_length) || self.target != self.applied
+    }
+
+    /// Returns the new scale factor when the animation has settled and it needs to be applied.
+    pub fn take_settled(&mut self) -> Option<f32> {
+        if self.spring.position != 0.0 || self.target == self.applied {
+            return None;
+        }
+        self.applied = self.target;
+        Some(self.target)
+    }
+
+    /// Whether the frame is scaled, while the zoom animation hasn't settled.
+    pub fn is_zoomed(&self) -> bool {
+        self.scale() != 1.0
+    }
+
+    /// How much the frame is scaled compared to the applied scale factor.
+    fn scale(&self) -> f32 {
+        self.current() / self.applied
+    }
+
+    /// Scales the canvas around the anchor, the caller saves and restores the canvas.
+    pub fn apply(&self, canvas: &Canvas) {
+        let scale = self.scale();
+        if scale == 1.0 {
+            return;
+        }
+        canvas.translate((self.anchor.x, self.anchor.y));
+        canvas.scale((scale, scale));
+        canvas.translate((-self.anchor.x, -self.anchor.y));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zoom_settles() {
+        let mut zoom = ZoomAnimation::new(1.0);
+        zoom.zoom_to(zoom.target() * ZOOM_STEP, PixelPos::new(10.0, 10.0));
+        assert!(zoom.animate(0.01, 0.15));
+        assert_eq!(zoom.take_settled(), None);
+        assert!(zoom.scale() > 1.0 && zoom.scale() < ZOOM_STEP);
+
+        while zoom.spring.position != 0.0 {
+            zoom.animate(0.01, 0.15);
+        }
+        assert_eq!(zoom.take_settled(), Some(ZOOM_STEP));
+        assert_eq!(zoom.scale(), 1.0);
+        assert!(!zoom.animate(0.01, 0.15));
+
+        zoom.zoom_to(100.0, PixelPos::ZERO);
+        assert_eq!(zoom.target(), MAX_SCALE_FACTOR);
+    }
+}
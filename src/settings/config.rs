@@ -1,9 +1,17 @@
 //! Config file handling
 
-use std::{env, fs, sync::mpsc, time::Duration};
+use std::{
+    collections::HashMap,
+    env, fs, io,
+    sync::{OnceLock, mpsc},
+    time::Duration,
+};
 
-use notify_debouncer_full::{new_debouncer, notify::RecursiveMode};
+use notify_debouncer_full::{DebounceEventResult, new_debouncer, notify::RecursiveMode};
+use parking_lot::RwLock;
+use rmpv::Value;
 use serde::Deserialize;
+use serde_json::json;
 use winit::event_loop::EventLoopProxy;
 
 use crate::{
@@ -16,10 +24,18 @@ use crate::{
 
 use std::path::{Path, PathBuf};
 
-use super::font::FontSettings;
+use super::{
+    ConfigSchema, Settings,
+    config_schema::{object, without_required},
+    font::FontSettings,
+};
 
 const CONFIG_FILE: &str = "config.toml";
 
+static ACTIVE_PROFILE: RwLock<Option<String>> = RwLock::new(None);
+// Lets a profile switch trigger a reload through the config file watcher
+static RELOAD_SENDER: OnceLock<mpsc::Sender<DebounceEventResult>> = OnceLock::new();
+
 #[cfg(unix)]
 fn neovide_config_dir() -> PathBuf {
     let xdg_dirs = xdg::BaseDirectories::with_prefix("neovide");
@@ -45,7 +61,7 @@ pub fn config_path() -> PathBuf {
         })
 }
 
-#[derive(Debug, Deserialize, Default, Clone)]
+#[derive(Debug, Deserialize, Default, Clone, ConfigSchema)]
 #[serde(rename_all = "kebab-case")]
 pub struct Config {
     pub font: Option<FontSettings>,
@@ -77,6 +93,52 @@ pub struct Config {
     pub wayland_app_id: Option<String>,
     pub x11_wm_class: Option<String>,
     pub x11_wm_class_instance: Option<String>,
+    pub keys: Option<HashMap<String, String>>,
+    #[serde(flatten)]
+    pub settings: SettingTables,
+}
+
+/// The `[window]`, `[renderer]`, `[cursor]`, `[progress_bar]` and `[screencast]` tables, which can
+/// contain any of the `g:neovide_*` settings of the corresponding setting group, without the prefix.
+#[derive(Debug, Deserialize, Default, Clone, PartialEq, ConfigSchema)]
+pub struct SettingTables {
+    pub window: Option<toml::Table>,
+    pub renderer: Option<toml::Table>,
+    pub cursor: Option<toml::Table>,
+    #[serde(alias = "progress-bar")]
+    pub progress_bar: Option<toml::Table>,
+    pub screencast: Option<toml::Table>,
+}
+
+impl SettingTables {
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, &toml::Table)> {
+        [
+            ("window", &self.window),
+            ("renderer", &self.renderer),
+            ("cursor", &self.cursor),
+            ("progress_bar", &self.progress_bar),
+            ("screencast", &self.screencast),
+        ]
+        .into_iter()
+        .filter_map(|(section, table)| Some((section, table.as_ref()?)))
+    }
+}
+
+pub(super) fn toml_to_value(value: &toml::Value) -> Value {
+    match value {
+        toml::Value::String(string) => Value::from(string.as_str()),
+        toml::Value::Integer(integer) => Value::from(*integer),
+        toml::Value::Float(float) => Value::from(*float),
+        toml::Value::Boolean(boolean) => Value::from(*boolean),
+        toml::Value::Datetime(datetime) => Value::from(datetime.to_string()),
+        toml::Value::Array(array) => Value::Array(array.iter().map(toml_to_value).collect()),
+        toml::Value::Table(table) => Value::Map(
+            table
+                .iter()
+                .map(|(key, value)| (Value::from(key.as_str()), toml_to_value(value)))
+                .collect(),
+        ),
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -84,6 +146,8 @@ pub struct Config {
 pub enum HotReloadConfigs {
     Renderer(RendererHotReloadConfigs),
     Window(WindowHotReloadConfigs),
+    Settings(SettingTables),
+    Profile(Option<String>),
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -97,6 +161,151 @@ pub enum WindowHotReloadConfigs {
     TitleHidden(Option<bool>),
     MouseCursorIcon(MouseCursorIcon),
     Geometry(GeometryArgs),
+    Keys(Option<HashMap<String, String>>),
+}
+
+pub fn active_profile() -> Option<String> {
+    ACTIVE_PROFILE.read().clone()
+}
+
+/// Selects the `[profile.<name>]` table that is overlaid on the base config. Must be called before
+/// the config is first loaded.
+pub fn set_active_profile(profile: Option<String>) {
+    *ACTIVE_PROFILE.write() = profile.filter(|profile| !profile.is_empty());
+}
+
+/// Switches to another profile at runtime, reloading the config file the same way as when it
+/// changes on disk. An empty name switches back to the base config.
+pub fn switch_profile(profile: &str) {
+    if !profile.is_empty() && !profile_names().iter().any(|name| name == profile) {
+        error_msg!("Profile {profile} not found in config file {}", config_path().display());
+        return;
+    }
+
+    set_active_profile(Some(profile.to_owned()));
+    reload_config();
+}
+
+/// Reloads the config file the same way as when it changes on disk.
+pub fn reload_config() {
+    match RELOAD_SENDER.get() {
+        Some(sender) => {
+            let _ = sender.send(Ok(Vec::new()));
+        }
+        None => log::warn!("Config file watcher not running, the config file is not reloaded"),
+    }
+}
+
+/// The names of all profiles in the config file.
+pub fn profile_names() -> Vec<String> {
+    fs::read_to_string(config_path())
+        .ok()
+        .and_then(|toml| toml::from_str::<toml::Table>(&toml).ok())
+        .and_then(|mut table| match table.remove("profile") {
+            Some(toml::Value::Table(profiles)) => Some(profiles.keys().cloned().collect()),
+            _ => None,
+        })
+        .unwrap_or_default()
+}
+
+/// Recursively overlays `overlay` on top of `base`. Tables are merged, all other values replaced.
+fn merge_tables(base: &mut toml::Table, overlay: toml::Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(overlay)) => {
+                merge_tables(base, overlay);
+            }
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Writes `(table, key, value)` setting values to the config file, or to the active profile in it.
+/// The formatting and comments of the rest of the file are kept.
+///
+/// Returns the path of the config file.
+pub fn save_setting_values(values: &[(String, String, Value)]) -> Result<PathBuf, String> {
+    let path = config_path();
+    let source = match fs::read_to_string(&path) {
+        Ok(source) => source,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(error) => return Err(format!("Could not read {}: {error}", path.display())),
+    };
+    let source = set_setting_values(&source, active_profile().as_deref(), values)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|error| format!("Could not create {}: {error}", parent.display()))?;
+    }
+    fs::write(&path, source)
+        .map_err(|error| format!("Could not write {}: {error}", path.display()))?;
+    Ok(path)
+}
+
+fn set_setting_values(
+    source: &str,
+    profile: Option<&str>,
+    values: &[(String, String, Value)],
+) -> Result<String, String> {
+    let mut document: toml_edit::DocumentMut =
+        source.parse().map_err(|error| format!("Could not parse the config file: {error}"))?;
+    let mut root = document.as_table_mut();
+    if let Some(profile) = profile {
+        root = sub_table(sub_table(root, "profile", true)?, profile, true)?;
+    }
+
+    for (section, key, value) in values {
+        let Some(value) = value_to_toml(value) else {
+            return Err(format!("Can't save {value} for `{key}` in the config file"));
+        };
+        let table = sub_table(root, section, false)?;
+        // Keep using the kebab-case key if the file already uses it
+        let kebab_key = key.replace('_', "-");
+        let key = if table.contains_key(&kebab_key) { &kebab_key } else { key };
+        match table.get_mut(key).and_then(toml_edit::Item::as_value_mut) {
+            Some(existing) => {
+                // The decor holds the comments and whitespace around the value
+                let decor = existing.decor().clone();
+                *existing = value;
+                *existing.decor_mut() = decor;
+            }
+            None => {
+                table.insert(key, toml_edit::Item::Value(value));
+            }
+        }
+    }
+    Ok(document.to_string())
+}
+
+fn sub_table<'a>(
+    table: &'a mut toml_edit::Table,
+    key: &str,
+    implicit: bool,
+) -> Result<&'a mut toml_edit::Table, String> {
+    let item = table.entry(key).or_insert_with(|| {
+        let mut table = toml_edit::Table::new();
+        table.set_implicit(implicit);
+        toml_edit::Item::Table(table)
+    });
+    item.as_table_mut().ok_or_else(|| format!("`{key}` in the config file is not a table"))
+}
+
+fn value_to_toml(value: &Value) -> Option<toml_edit::Value> {
+    match value {
+        Value::Boolean(boolean) => Some((*boolean).into()),
+        Value::Integer(integer) => integer.as_i64().map(Into::into),
+        // Go through the shortest representation, so that 0.8 isn't saved as 0.800000011920929
+        Value::F32(float) => float.to_string().parse::<f64>().ok().map(Into::into),
+        Value::F64(float) => Some((*float).into()),
+        Value::String(string) => string.as_str().map(Into::into),
+        Value::Array(array) => array
+            .iter()
+            .map(value_to_toml)
+            .collect::<Option<toml_edit::Array>>()
+            .map(toml_edit::Value::Array),
+        _ => None,
+    }
 }
 
 impl Config {
@@ -208,15 +417,68 @@ impl Config {
                 e
             )
         })?;
-        let config = toml::from_str(&toml).map_err(|e| {
+        let parse_error = |e: toml::de::Error| {
             format!(
                 "Error while parsing config file {}:\n{}\nContinuing with default config.",
                 path.to_string_lossy(),
                 e
             )
+        };
+        let table: toml::Table = toml::from_str(&toml).map_err(parse_error)?;
+        let config = Config::from_table(table, active_profile().as_deref()).map_err(|e| {
+            format!(
+                "Error while loading config file {}:\n{}\nContinuing with default config.",
+                path.to_string_lossy(),
+                e
+            )
         })?;
         Ok(config)
     }
+
+    /// The JSON schema of the config file, including the setting tables of all registered
+    /// setting groups.
+    pub fn schema(settings: &Settings) -> serde_json::Value {
+        let mut schema = Config::config_schema();
+        for (section, properties) in settings.config_table_schemas() {
+            let table = object(properties, Vec::new());
+            let alias = section.replace('_', "-");
+            if alias != section && schema["properties"].get(&alias).is_some() {
+                schema["properties"][alias] = table.clone();
+            }
+            schema["properties"][section] = table;
+        }
+        let profile = json!({
+            "type": "object",
+            "description": "Named profiles, which are overlaid on the rest of the config",
+            "additionalProperties": without_required(schema.clone()),
+        });
+        schema["properties"]["profile"] = profile;
+        schema["$schema"] = json!("https://json-schema.org/draft/2020-12/schema");
+        schema["title"] = json!("Neovide config file");
+        schema
+    }
+
+    /// Builds the config from the base table with the `[profile.<name>]` table of `profile`
+    /// overlaid on it. When there's no such profile, the base config is used alone.
+    pub(super) fn from_table(
+        mut table: toml::Table,
+        profile: Option<&str>,
+    ) -> Result<Self, String> {
+        let profiles = table.remove("profile");
+        if let Some(profile) = profile {
+            match profiles.as_ref().and_then(|profiles| profiles.get(profile)) {
+                Some(toml::Value::Table(overlay)) => merge_tables(&mut table, overlay.clone()),
+                Some(_) => return Err(format!("Profile {profile} is not a table")),
+                None => {
+                    // Printed as well, since the config is loaded before the logger is set up
+                    let warning = format!("Profile {profile} not found, using the base config.");
+                    eprintln!("{warning}");
+                    log::warn!("{warning}");
+                }
+            }
+        }
+        table.try_into().map_err(|e: toml::de::Error| e.to_string())
+    }
 }
 
 fn watcher_thread(init_config: Config, event_loop_proxy: EventLoopProxy<EventPayload>) {
@@ -226,6 +488,7 @@ fn watcher_thread(init_config: Config, event_loop_proxy: EventLoopProxy<EventPay
         None => return,
     };
     let (tx, rx) = mpsc::channel();
+    let _ = RELOAD_SENDER.set(tx.clone());
     let mut debouncer = new_debouncer(Duration::from_millis(500), None, tx).unwrap();
 
     if let Err(e) = debouncer.watch(
@@ -238,6 +501,7 @@ fn watcher_thread(init_config: Config, event_loop_proxy: EventLoopProxy<EventPay
     }
 
     let mut previous_config = init_config;
+    let mut previous_profile = active_profile();
     loop {
         if let Err(e) = rx.recv() {
             eprintln!("Error while watching config file: {e}");
@@ -319,6 +583,94 @@ fn watcher_thread(init_config: Config, event_loop_proxy: EventLoopProxy<EventPay
                 }
             }
         }
+        if config.keys != previous_config.keys {
+            event_loop_proxy
+                .send_event(EventPayload::all(UserEvent::ConfigsChanged(Box::new(
+                    HotReloadConfigs::Window(WindowHotReloadConfigs::Keys(config.keys.clone())),
+                ))))
+                .unwrap();
+        }
+        let profile = active_profile();
+        if profile != previous_profile {
+            event_loop_proxy
+                .send_event(EventPayload::all(UserEvent::ConfigsChanged(Box::new(
+                    HotReloadConfigs::Profile(profile.clone()),
+                ))))
+                .unwrap();
+            previous_profile = profile;
+        }
+        if config.settings != previous_config.settings {
+            event_loop_proxy
+                .send_event(EventPayload::all(UserEvent::ConfigsChanged(Box::new(
+                    HotReloadConfigs::Settings(config.settings.clone()),
+                ))))
+                .unwrap();
+        }
         previous_config = config;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"
+        vsync = true
+
+        [window]
+        opacity = 1.0
+        padding_top = 2
+
+        [profile.presentation.font]
+        normal = ["Fira Code"]
+        size = 24
+
+        [profile.presentation.window]
+        opacity = 0.8
+    "#;
+
+    #[test]
+    fn test_profile_overlay() {
+        let table: toml::Table = toml::from_str(CONFIG).unwrap();
+
+        let config = Config::from_table(table.clone(), None).unwrap();
+        assert_eq!(config.vsync, Some(true));
+        assert!(config.font.is_none());
+        let window = config.settings.window.unwrap();
+        assert_eq!(window["opacity"].as_float(), Some(1.0));
+
+        let config = Config::from_table(table.clone(), Some("presentation")).unwrap();
+        assert_eq!(config.vsync, Some(true));
+        assert_eq!(config.font.unwrap().size, 24.0);
+        let window = config.settings.window.unwrap();
+        assert_eq!(window["opacity"].as_float(), Some(0.8));
+        assert_eq!(window["padding_top"].as_integer(), Some(2));
+
+        // An unknown profile keeps the base config
+        let config = Config::from_table(table, Some("pairing")).unwrap();
+        assert_eq!(config.vsync, Some(true));
+        assert!(config.font.is_none());
+        let window = config.settings.window.unwrap();
+        assert_eq!(window["opacity"].as_float(), Some(1.0));
+    }
+
+    #[test]
+    fn test_set_setting_values() {
+        let source = "vsync = true\n\n[window]\n# Slightly transparent\nopacity = 0.9 # or 1.0\n";
+        let values = vec![
+            ("window".to_owned(), "opacity".to_owned(), Value::from(0.8f32)),
+            ("window".to_owned(), "show_border".to_owned(), Value::from(false)),
+            ("cursor".to_owned(), "vfx_mode".to_owned(), Value::Array(vec!["railgun".into()])),
+        ];
+
+        assert_eq!(
+            set_setting_values(source, None, &values).unwrap(),
+            "vsync = true\n\n[window]\n# Slightly transparent\nopacity = 0.8 # or 1.0\n\
+             show_border = false\n\n[cursor]\nvfx_mode = [\"railgun\"]\n"
+        );
+        assert_eq!(
+            set_setting_values(source, Some("work"), &values[1..2]).unwrap(),
+            format!("{source}\n[profile.work.window]\nshow_border = false\n")
+        );
+    }
+}
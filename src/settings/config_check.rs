@@ -0,0 +1,424 @@
+//! `--check-config`, reports all problems in a config file together with their location.
+
+use std::{fs, ops::Range, path::Path};
+
+use serde_json::Value as JsonValue;
+use skia_safe::{FontMgr, FontStyle};
+use toml::{
+    Spanned,
+    de::{DeTable, DeValue},
+};
+
+use super::{Config, Settings};
+use crate::cmd_line::{GeometryArgs, MouseCursorIcon};
+
+const FONT_KEYS: &[&str] = &["normal", "bold", "italic", "bold_italic", "altfont"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub span: Option<Range<usize>>,
+    pub message: String,
+}
+
+impl Diagnostic {
+    fn new(span: impl Into<Option<Range<usize>>>, message: impl Into<String>) -> Self {
+        Self { span: span.into(), message: message.into() }
+    }
+}
+
+/// Checks the config file at `path` and prints the problems found. Returns whether the config
+/// file is valid.
+pub fn check_config_file(path: &Path, settings: &Settings) -> bool {
+    let source = match fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(error) => {
+            eprintln!("{}: could not read config file: {error}", path.display());
+            return false;
+        }
+    };
+
+    let diagnostics = check_config(&source, settings, &font_exists);
+    for diagnostic in &diagnostics {
+        match &diagnostic.span {
+            Some(span) => {
+                let (line, column) = line_column(&source, span.start);
+                eprintln!("{}:{line}:{column}: {}", path.display(), diagnostic.message);
+            }
+            None => eprintln!("{}: {}", path.display(), diagnostic.message),
+        }
+    }
+
+    if diagnostics.is_empty() {
+        println!("{}: no problems found", path.display());
+    }
+    diagnostics.is_empty()
+}
+
+pub fn check_config(
+    source: &str,
+    settings: &Settings,
+    font_exists: &dyn Fn(&str) -> bool,
+) -> Vec<Diagnostic> {
+    let document = match DeTable::parse(source) {
+        Ok(document) => document,
+        Err(error) => return vec![Diagnostic::new(error.span(), error.message())],
+    };
+
+    let mut diagnostics = Vec::new();
+    let root = Spanned::new(document.span(), DeValue::Table(document.into_inner()));
+    validate(&root, &Config::schema(settings), "", &mut diagnostics);
+
+    // The values can only be checked further when they have the right types
+    if diagnostics.is_empty() {
+        let DeValue::Table(root) = root.get_ref() else {
+            unreachable!();
+        };
+        check_values(source, root, &mut diagnostics);
+        check_fonts(root, font_exists, &mut diagnostics);
+    }
+
+    diagnostics.sort_by_key(|diagnostic| diagnostic.span.as_ref().map(|span| span.start));
+    diagnostics
+}
+
+/// Validates `value` against the subset of JSON schema generated by `ConfigSchema`.
+fn validate(
+    value: &Spanned<DeValue>,
+    schema: &JsonValue,
+    path: &str,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    if let Some(schemas) = schema.get("anyOf").and_then(JsonValue::as_array) {
+        // Report the problems of the closest match
+        let best_match = schemas
+            .iter()
+            .map(|schema| {
+                let mut diagnostics = Vec::new();
+                validate(value, schema, path, &mut diagnostics);
+                diagnostics
+            })
+            .min_by_key(Vec::len)
+            .unwrap_or_default();
+        diagnostics.extend(best_match);
+        return;
+    }
+
+    let found = type_name(value.get_ref());
+    let expected: Vec<&str> = match schema.get("type") {
+        Some(JsonValue::String(expected)) => vec![expected.as_str()],
+        Some(JsonValue::Array(expected)) => expected.iter().filter_map(JsonValue::as_str).collect(),
+        _ => Vec::new(),
+    };
+    let type_matches = expected
+        .iter()
+        .any(|expected| *expected == found || (*expected == "number" && found == "integer"));
+    if !expected.is_empty() && !type_matches {
+        diagnostics.push(Diagnostic::new(
+            value.span(),
+            format!("expected {} for `{path}`, found {found}", expected.join(" or ")),
+        ));
+        return;
+    }
+
+    match value.get_ref() {
+        DeValue::String(string) => {
+            let Some(variants) = schema.get("enum").and_then(JsonValue::as_array) else {
+                return;
+            };
+            if !variants.iter().any(|variant| variant.as_str() == Some(&**string)) {
+                let variants = variants.iter().filter_map(JsonValue::as_str).collect::<Vec<_>>();
+                diagnostics.push(Diagnostic::new(
+                    value.span(),
+                    format!(
+                        "unknown value `{string}` for `{path}`, expected one of: {}",
+                        variants.join(", ")
+                    ),
+                ));
+            }
+        }
+        DeValue::Array(array) => {
+            let prefix_items = schema.get("prefixItems").and_then(JsonValue::as_array);
+            let min_items = schema.get("minItems").and_then(JsonValue::as_u64);
+            let max_items = schema.get("maxItems").and_then(JsonValue::as_u64);
+            let length = array.len() as u64;
+            if min_items.is_some_and(|min| length < min)
+                || max_items.is_some_and(|max| length > max)
+            {
+                diagnostics.push(Diagnostic::new(
+                    value.span(),
+                    format!("unexpected number of items in `{path}`"),
+                ));
+                return;
+            }
+            for (i, item) in array.iter().enumerate() {
+                let item_schema =
+                    prefix_items.and_then(|items| items.get(i)).or(schema.get("items"));
+                if let Some(item_schema) = item_schema {
+                    validate(item, item_schema, &format!("{path}[{i}]"), diagnostics);
+                }
+            }
+        }
+        DeValue::Table(table) => {
+            let properties = schema.get("properties").and_then(JsonValue::as_object);
+            let additional = schema.get("additionalProperties");
+            for (key, value) in table.iter() {
+                let key_path = if path.is_empty() {
+                    key.get_ref().to_string()
+                } else {
+                    format!("{path}.{}", key.get_ref())
+                };
+                let schema = properties.and_then(|properties| properties.get(&**key.get_ref()));
+                match (schema, additional) {
+                    (Some(schema), _) => validate(value, schema, &key_path, diagnostics),
+                    (None, Some(JsonValue::Bool(false))) => diagnostics
+                        .push(Diagnostic::new(key.span(), format!("unknown key `{key_path}`"))),
+                    (None, Some(schema)) if schema.is_object() => {
+                        validate(value, schema, &key_path, diagnostics)
+                    }
+                    _ => {}
+                }
+            }
+
+            let required = schema.get("required").and_then(JsonValue::as_array);
+            for key in required.into_iter().flatten().filter_map(JsonValue::as_str) {
+                if get(table, key).is_none() {
+                    let message = if path.is_empty() {
+                        format!("missing required key `{key}`")
+                    } else {
+                        format!("missing required key `{key}` in `{path}`")
+                    };
+                    diagnostics.push(Diagnostic::new(value.span(), message));
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Checks the values that are parsed after deserialization, for the base config and each profile.
+fn check_values(source: &str, root: &DeTable, diagnostics: &mut Vec<Diagnostic>) {
+    let Ok(table) = toml::from_str::<toml::Table>(source) else {
+        return;
+    };
+    let profiles = match get(root, "profile").map(Spanned::get_ref) {
+        Some(DeValue::Table(profiles)) => profiles
+            .iter()
+            .filter_map(|(name, profile)| match profile.get_ref() {
+                DeValue::Table(profile) => Some((&**name.get_ref(), profile)),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    let configs =
+        std::iter::once((None, root)).chain(profiles.into_iter().map(|(n, p)| (Some(n), p)));
+    for (profile, overlay) in configs {
+        let context = profile.map(|profile| format!(" (profile {profile})")).unwrap_or_default();
+        // Values set by the profile are reported at the profile, the others at the base config
+        let span_of = |key: &str| get(overlay, key).or_else(|| get(root, key)).map(Spanned::span);
+
+        let config = match Config::from_table(table.clone(), profile) {
+            Ok(config) => config,
+            Err(error) => {
+                diagnostics.push(Diagnostic::new(None, format!("{error}{context}")));
+                continue;
+            }
+        };
+        if let Err(error) = GeometryArgs::from_config(
+            config.size.as_deref(),
+            config.grid.as_deref(),
+            config.maximized,
+        ) {
+            let span = span_of("size").or_else(|| span_of("grid")).or_else(|| span_of("maximized"));
+            diagnostics.push(Diagnostic::new(span, format!("{error}{context}")));
+        }
+        if let Err(error) = MouseCursorIcon::from_config(config.mouse_cursor_icon.as_deref()) {
+            diagnostics.push(Diagnostic::new(
+                span_of("mouse-cursor-icon"),
+                format!("invalid mouse-cursor-icon: {error}{context}"),
+            ));
+        }
+    }
+}
+
+fn check_fonts(
+    root: &DeTable,
+    font_exists: &dyn Fn(&str) -> bool,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    let mut fonts = get(root, "font").into_iter().collect::<Vec<_>>();
+    if let Some(DeValue::Table(profiles)) = get(root, "profile").map(Spanned::get_ref) {
+        for (_, profile) in profiles.iter() {
+            if let DeValue::Table(profile) = profile.get_ref() {
+                fonts.extend(get(profile, "font"));
+            }
+        }
+    }
+
+    let mut families = Vec::new();
+    for font in fonts {
+        if let DeValue::Table(font) = font.get_ref() {
+            for key in FONT_KEYS {
+                if let Some(value) = get(font, key) {
+                    font_families(value, &mut families);
+                }
+            }
+            if let Some(DeValue::Table(highlight)) = get(font, "highlight").map(Spanned::get_ref) {
+                for (_, value) in highlight.iter() {
+                    font_families(value, &mut families);
+                }
+            }
+        }
+    }
+
+    for (family, span) in families {
+        if !font_exists(family) {
+            diagnostics.push(Diagnostic::new(span, format!("font `{family}` is not installed")));
+        }
+    }
+}
+
+/// Collects the families of a font description, which is either a family name, a table with a
+/// family or a list of those.
+fn font_families<'a>(value: &'a Spanned<DeValue>, families: &mut Vec<(&'a str, Range<usize>)>) {
+    match value.get_ref() {
+        DeValue::String(family) => families.push((family, value.span())),
+        DeValue::Table(table) => {
+            if let Some(family) = get(table, "family") {
+                font_families(family, families);
+            }
+        }
+        DeValue::Array(array) => {
+            for value in array.iter() {
+                font_families(value, families);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn font_exists(family: &str) -> bool {
+    FontMgr::new().match_family_style(family, FontStyle::normal()).is_some()
+}
+
+fn get<'a, 'i>(table: &'a DeTable<'i>, key: &str) -> Option<&'a Spanned<DeValue<'i>>> {
+    table.iter().find(|(name, _)| name.get_ref() == key).map(|(_, value)| value)
+}
+
+fn type_name(value: &DeValue) -> &'static str {
+    match value {
+        DeValue::String(_) => "string",
+        DeValue::Integer(_) => "integer",
+        DeValue::Float(_) => "number",
+        DeValue::Boolean(_) => "boolean",
+        DeValue::Datetime(_) => "datetime",
+        DeValue::Array(_) => "array",
+        DeValue::Table(_) => "object",
+    }
+}
+
+/// One based line and column of a byte offset.
+fn line_column(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset.min(source.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().map_or(0, |line| line.chars().count()) + 1;
+    (line, column)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{renderer::RendererSettings, window::WindowSettings};
+
+    fn check(source: &str) -> Vec<(usize, usize, String)> {
+        let settings = Settings::new();
+        settings.register::<WindowSettings>();
+        settings.register::<RendererSettings>();
+        check_config(source, &settings, &|family| family != "Missing Font")
+            .into_iter()
+            .map(|diagnostic| {
+                let (line, column) = line_column(source, diagnostic.span.unwrap().start);
+                (line, column, diagnostic.message)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_valid_config() {
+        let source = indoc::indoc! {r#"
+            vsync = true
+            frame = "none"
+
+            [font]
+            normal = ["Fira Code", { family = "Noto Sans", style = "Bold" }]
+            altfont = "Fira Code"
+            size = 14
+
+            [font.highlight]
+            Comment = { family = "Noto Sans", style = "Italic" }
+
+            [box-drawing.sizes]
+            default = [2, 4]
+
+            [window]
+            opacity = 0.9
+            padding-top = 2
+
+            [profile.presentation]
+            size = "1200x800"
+        "#};
+        assert_eq!(check(source), vec![]);
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        let source = indoc::indoc! {r#"
+            vsyncc = true
+            size = "big"
+
+            [box-drawing]
+            mode = "round"
+
+            [font]
+            normal = "Missing Font"
+
+            [window]
+            opacity = "high"
+
+            [profile.presentation.font]
+            size = "large"
+        "#};
+        assert_eq!(
+            check(source),
+            vec![
+                (1, 1, "unknown key `vsyncc`".to_owned()),
+                (
+                    5,
+                    8,
+                    "unknown value `round` for `box-drawing.mode`, expected one of: font-glyph, \
+                     native, selected-native"
+                        .to_owned()
+                ),
+                (7, 1, "missing required key `size` in `font`".to_owned()),
+                (11, 11, "expected number for `window.opacity`, found string".to_owned()),
+                (
+                    14,
+                    8,
+                    "expected number for `profile.presentation.font.size`, found string".to_owned()
+                ),
+            ]
+        );
+
+        let source = "size = \"big\"\n[font]\nnormal = \"Missing Font\"\nsize = 12\n\
+                      [font.highlight]\nComment = [\"Missing Font\"]\n";
+        assert_eq!(
+            check(source),
+            vec![
+                (1, 8, "Invalid geometry: big\nValid format: <width>x<height>".to_owned()),
+                (3, 10, "font `Missing Font` is not installed".to_owned()),
+                (6, 12, "font `Missing Font` is not installed".to_owned()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,167 @@
+//! JSON schema of the config file, used by `--print-config-schema` and `--check-config`.
+
+use std::{collections::HashMap, path::PathBuf};
+
+use rmpv::Value;
+use serde_json::{Map, Value as JsonValue, json};
+
+/// Types that can be read from the config file. Usually derived with `#[derive(ConfigSchema)]`,
+/// which follows the serde attributes of the type.
+pub trait ConfigSchema {
+    fn config_schema() -> JsonValue;
+}
+
+macro_rules! impl_config_schema {
+    ($schema:tt, $($ty:ty),+) => {
+        $(
+            impl ConfigSchema for $ty {
+                fn config_schema() -> JsonValue {
+                    json!($schema)
+                }
+            }
+        )+
+    };
+}
+
+impl_config_schema!({ "type": "boolean" }, bool);
+impl_config_schema!({ "type": "string" }, String, PathBuf);
+impl_config_schema!({ "type": "number" }, f32, f64);
+impl_config_schema!({ "type": "integer", "minimum": 0 }, u16, u32, u64);
+impl_config_schema!({ "type": "integer" }, i32, i64);
+impl_config_schema!({ "type": "object" }, toml::Table);
+
+// TOML has no null, so an optional value is just not required
+impl<T: ConfigSchema> ConfigSchema for Option<T> {
+    fn config_schema() -> JsonValue {
+        T::config_schema()
+    }
+}
+
+impl<T: ConfigSchema> ConfigSchema for Vec<T> {
+    fn config_schema() -> JsonValue {
+        json!({ "type": "array", "items": T::config_schema() })
+    }
+}
+
+impl<T: ConfigSchema> ConfigSchema for HashMap<String, T> {
+    fn config_schema() -> JsonValue {
+        json!({ "type": "object", "additionalProperties": T::config_schema() })
+    }
+}
+
+impl<A: ConfigSchema, B: ConfigSchema> ConfigSchema for (A, B) {
+    fn config_schema() -> JsonValue {
+        json!({
+            "type": "array",
+            "prefixItems": [A::config_schema(), B::config_schema()],
+            "minItems": 2,
+            "maxItems": 2,
+        })
+    }
+}
+
+pub fn object(properties: Map<String, JsonValue>, required: Vec<&str>) -> JsonValue {
+    let mut schema = json!({
+        "type": "object",
+        "properties": properties,
+        "additionalProperties": false,
+    });
+    if !required.is_empty() {
+        schema["required"] = json!(required);
+    }
+    schema
+}
+
+pub fn string_enum(variants: Vec<&str>) -> JsonValue {
+    json!({ "type": "string", "enum": variants })
+}
+
+pub fn any_of(schemas: Vec<JsonValue>) -> JsonValue {
+    json!({ "anyOf": schemas })
+}
+
+pub fn describe(mut schema: JsonValue, description: Option<&str>) -> JsonValue {
+    if let (Some(description), Some(schema)) = (description, schema.as_object_mut()) {
+        schema.insert("description".to_owned(), json!(description));
+    }
+    schema
+}
+
+/// Adds the properties of a `#[serde(flatten)]` field to the parent object.
+pub fn flatten(properties: &mut Map<String, JsonValue>, schema: JsonValue) {
+    if let JsonValue::Object(mut schema) = schema {
+        if let Some(JsonValue::Object(flattened)) = schema.remove("properties") {
+            properties.extend(flattened);
+        }
+    }
+}
+
+/// The same schema, but with all properties optional, also in nested tables. Used for profiles,
+/// which only contain the values that differ from the base config.
+pub fn without_required(mut schema: JsonValue) -> JsonValue {
+    remove_required(&mut schema);
+    schema
+}
+
+fn remove_required(schema: &mut JsonValue) {
+    let Some(schema) = schema.as_object_mut() else {
+        return;
+    };
+    schema.remove("required");
+    for (key, value) in schema.iter_mut() {
+        match (key.as_str(), value) {
+            ("properties", JsonValue::Object(properties)) => {
+                properties.values_mut().for_each(remove_required);
+            }
+            ("anyOf" | "prefixItems", JsonValue::Array(schemas)) => {
+                schemas.iter_mut().for_each(remove_required);
+            }
+            ("items" | "additionalProperties", schema) => remove_required(schema),
+            _ => {}
+        }
+    }
+}
+
+/// Schema of a setting in one of the setting tables. The settings are dynamically typed, so the
+/// type is derived from the default value.
+pub fn setting_schema(default: &Value) -> JsonValue {
+    match default {
+        Value::Boolean(_) => json!({ "type": "boolean" }),
+        Value::Integer(integer) if integer.is_u64() => json!({ "type": "integer", "minimum": 0 }),
+        Value::Integer(_) => json!({ "type": "integer" }),
+        Value::F32(_) | Value::F64(_) => json!({ "type": "number" }),
+        Value::String(_) => json!({ "type": "string" }),
+        // Lists also accept a single value
+        Value::Array(_) => json!({ "type": ["array", "string"] }),
+        _ => json!({}),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::{Config, Settings};
+
+    #[test]
+    fn test_config_schema() {
+        let settings = Settings::new();
+        settings.register::<crate::renderer::progress_bar::ProgressBarSettings>();
+        let schema = Config::schema(&settings);
+
+        assert_eq!(schema["additionalProperties"], json!(false));
+        assert_eq!(schema["properties"]["title-hidden"]["type"], "boolean");
+        assert_eq!(schema["properties"]["font"]["required"], json!(["normal", "size"]));
+        assert_eq!(
+            schema["properties"]["box-drawing"]["properties"]["mode"]["enum"],
+            json!(["font-glyph", "native", "selected-native"])
+        );
+        assert_eq!(
+            schema["properties"]["progress_bar"]["properties"]["enabled"]["type"],
+            "boolean"
+        );
+        assert_eq!(
+            schema["properties"]["profile"]["additionalProperties"]["properties"]["vsync"]["type"],
+            "boolean"
+        );
+    }
+}
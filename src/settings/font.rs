@@ -3,39 +3,39 @@ use std::collections::HashMap;
 use serde::Deserialize;
 
 use crate::renderer::fonts::font_options::{
-    FontDescription, FontEdging, FontFeature, FontHinting, FontOptions, SecondaryFontDescription,
-    points_to_pixels,
+    FontAxes, FontDescription, FontEdging, FontFeature, FontHinting, FontOptions,
+    SecondaryFontDescription, points_to_pixels,
 };
 
-#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Deserialize, PartialEq, ConfigSchema)]
 #[serde(untagged)]
 pub enum SimpleFontDescription {
     String(String),
     Details(FontDescription),
 }
 
-#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Deserialize, PartialEq, ConfigSchema)]
 #[serde(untagged)]
 pub enum FontDescriptionSettings {
     Vec(Vec<SimpleFontDescription>),
     Single(SimpleFontDescription),
 }
 
-#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Deserialize, PartialEq, ConfigSchema)]
 #[serde(untagged)]
 pub enum SimpleSecondaryFontDescription {
     String(String),
     Details(SecondaryFontDescription),
 }
 
-#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Deserialize, PartialEq, ConfigSchema)]
 #[serde(untagged)]
 pub enum SecondaryFontDescriptionSettings {
     Vec(Vec<SimpleSecondaryFontDescription>),
     Single(SimpleSecondaryFontDescription),
 }
 
-#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Deserialize, PartialEq, ConfigSchema)]
 pub struct FontSettings {
     /// Font family to use for the normal font.
     pub normal: FontDescriptionSettings,
@@ -45,6 +45,10 @@ pub struct FontSettings {
     pub size: f32,
     pub width: Option<f32>,
     pub features: Option<HashMap<String /* family */, Vec<String> /* features */>>,
+    /// Fonts of highlight groups, like `Comment`, scaled to fit the cells.
+    pub highlight: Option<HashMap<String /* group */, FontDescriptionSettings>>,
+    /// Font of the text with the `altfont` highlight attribute, scaled to fit the cells.
+    pub altfont: Option<FontDescriptionSettings>,
     pub allow_float_size: Option<bool>,
     pub hinting: Option<String>,
     pub edging: Option<String>,
@@ -74,7 +78,9 @@ impl From<SecondaryFontDescriptionSettings> for Vec<SecondaryFontDescription> {
 impl From<SimpleFontDescription> for FontDescription {
     fn from(value: SimpleFontDescription) -> Self {
         match value {
-            SimpleFontDescription::String(value) => FontDescription { family: value, style: None },
+            SimpleFontDescription::String(value) => {
+                FontDescription { family: value, ..Default::default() }
+            }
             SimpleFontDescription::Details(value) => value,
         }
     }
@@ -83,9 +89,11 @@ impl From<SimpleFontDescription> for FontDescription {
 impl From<SimpleSecondaryFontDescription> for SecondaryFontDescription {
     fn from(value: SimpleSecondaryFontDescription) -> Self {
         match value {
-            SimpleSecondaryFontDescription::String(value) => {
-                SecondaryFontDescription { family: Some(value), style: None }
-            }
+            SimpleSecondaryFontDescription::String(value) => SecondaryFontDescription {
+                family: Some(value),
+                style: None,
+                axes: FontAxes::default(),
+            },
             SimpleSecondaryFontDescription::Details(value) => value,
         }
     }
@@ -116,6 +124,13 @@ impl From<FontSettings> for FontOptions {
                         .collect()
                 })
                 .unwrap_or_default(),
+            highlight: value
+                .highlight
+                .map(|highlight| {
+                    highlight.into_iter().map(|(group, fonts)| (group, fonts.into())).collect()
+                })
+                .unwrap_or_default(),
+            altfont: value.altfont.map(|fonts| fonts.into()).unwrap_or_default(),
             size: points_to_pixels(value.size),
             width: points_to_pixels(value.width.unwrap_or_default()),
             hinting: value
@@ -178,6 +193,63 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_font_axes() {
+        let settings = r#"
+        {
+            "normal": { "family": "Recursive", "axes": { "wght": 450, "CASL": 1 } },
+            "bold": { "family": "Recursive", "axes": { "wght": 800 } },
+            "size": 20
+        }
+        "#;
+
+        let settings: FontSettings = serde_json::from_str(settings).unwrap();
+        let options = FontOptions::from(settings);
+        let bold = CoarseStyle::permutations().find(|style| style.name() == Some("Bold")).unwrap();
+
+        let normal = options.font_list(CoarseStyle::default());
+        assert_eq!(normal[0].axes.iter().collect::<Vec<_>>(), vec![("CASL", 1.0), ("wght", 450.0)]);
+        let bold = options.font_list(bold);
+        assert_eq!(bold[0].axes.iter().collect::<Vec<_>>(), vec![("wght", 800.0)]);
+        // The normal font the bold variant falls back to doesn't keep the normal weight
+        assert_eq!(bold[1].axes.iter().collect::<Vec<_>>(), vec![("CASL", 1.0)]);
+    }
+
+    #[test]
+    fn test_highlight_fonts() {
+        let settings = r#"
+        {
+            "normal": "Consolas",
+            "highlight": {
+                "Comment": "Victor Mono",
+                "String": ["Iosevka", { "family": "Recursive", "axes": { "CASL": 1 } }]
+            },
+            "altfont": { "family": "Monaspace Radon", "style": "Italic" },
+            "size": 20
+        }
+        "#;
+
+        let settings: FontSettings = serde_json::from_str(settings).unwrap();
+        let options = FontOptions::from(settings);
+
+        assert_eq!(
+            options.highlight["Comment"],
+            vec![FontDescription { family: "Victor Mono".into(), ..Default::default() }]
+        );
+        let string = &options.highlight["String"];
+        assert_eq!(string.len(), 2);
+        assert_eq!(string[1].family, "Recursive");
+        assert_eq!(string[1].axes.iter().collect::<Vec<_>>(), vec![("CASL", 1.0)]);
+        assert_eq!(
+            options.altfont,
+            vec![FontDescription {
+                family: "Monaspace Radon".into(),
+                style: Some("Italic".into()),
+                ..Default::default()
+            }]
+        );
+    }
+
     #[test]
     fn test_secondary_font_single() {
         let settings = r#"
@@ -242,9 +314,21 @@ mod tests {
         assert_eq!(
             fonts,
             vec![
-                FontDescription { family: "NotFound".into(), style: Some("Bold".into()) },
-                FontDescription { family: "Consolas".into(), style: Some("Bold".into()) },
-                FontDescription { family: "Noto Emoji".into(), style: Some("Bold".into()) }
+                FontDescription {
+                    family: "NotFound".into(),
+                    style: Some("Bold".into()),
+                    ..Default::default()
+                },
+                FontDescription {
+                    family: "Consolas".into(),
+                    style: Some("Bold".into()),
+                    ..Default::default()
+                },
+                FontDescription {
+                    family: "Noto Emoji".into(),
+                    style: Some("Bold".into()),
+                    ..Default::default()
+                }
             ]
         );
     }
@@ -270,10 +354,26 @@ mod tests {
         assert_eq!(
             fonts,
             vec![
-                FontDescription { family: "NotFound".into(), style: Some("Bold".into()) },
-                FontDescription { family: "Menlo".into(), style: Some("Bold".into()) },
-                FontDescription { family: "Consolas".into(), style: Some("Bold".into()) },
-                FontDescription { family: "Noto Emoji".into(), style: Some("Bold".into()) }
+                FontDescription {
+                    family: "NotFound".into(),
+                    style: Some("Bold".into()),
+                    ..Default::default()
+                },
+                FontDescription {
+                    family: "Menlo".into(),
+                    style: Some("Bold".into()),
+                    ..Default::default()
+                },
+                FontDescription {
+                    family: "Consolas".into(),
+                    style: Some("Bold".into()),
+                    ..Default::default()
+                },
+                FontDescription {
+                    family: "Noto Emoji".into(),
+                    style: Some("Bold".into()),
+                    ..Default::default()
+                }
             ]
         );
     }
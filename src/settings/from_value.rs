@@ -4,84 +4,147 @@ use log::error;
 // Trait to allow for conversion from rmpv::Value to any other data type.
 // Note: Feel free to implement this trait for custom types in each subsystem.
 pub trait ParseFromValue {
-    fn parse_from_value(&mut self, value: Value);
+    /// Parses `value` into `self`. Returns `false` and leaves `self` unchanged when it's invalid,
+    /// after reporting the error.
+    fn parse_from_value(&mut self, value: Value) -> bool;
+
+    /// The kind of value that is accepted, one of `boolean`, `integer`, `number`, `string` or
+    /// `string|string[]`. Used for type checking settings from Lua.
+    fn value_kind() -> &'static str
+    where
+        Self: Sized,
+    {
+        "any"
+    }
+
+    /// The accepted values, for settings that are a choice between a few strings.
+    fn allowed_values() -> &'static [&'static str]
+    where
+        Self: Sized,
+    {
+        &[]
+    }
 }
 
 // FromValue implementations for most typical types
 impl ParseFromValue for f32 {
-    fn parse_from_value(&mut self, value: Value) {
-        if value.is_f64() {
-            *self = value.as_f64().unwrap() as f32;
-        } else if value.is_i64() {
-            *self = value.as_i64().unwrap() as f32;
-        } else if value.is_u64() {
-            *self = value.as_u64().unwrap() as f32;
+    fn parse_from_value(&mut self, value: Value) -> bool {
+        // Also accepts integers, and the F32 values produced by the setting readers
+        if let Some(value) = value.as_f64() {
+            *self = value as f32;
+            true
         } else {
             error!("Setting expected an f32, but received {value:?}");
+            false
         }
     }
+
+    fn value_kind() -> &'static str {
+        "number"
+    }
 }
 
 impl ParseFromValue for u64 {
-    fn parse_from_value(&mut self, value: Value) {
+    fn parse_from_value(&mut self, value: Value) -> bool {
         if value.is_u64() {
             *self = value.as_u64().unwrap();
+            true
         } else {
             error!("Setting expected a u64, but received {value:?}");
+            false
         }
     }
+
+    fn value_kind() -> &'static str {
+        "integer"
+    }
 }
 
 impl ParseFromValue for u32 {
-    fn parse_from_value(&mut self, value: Value) {
+    fn parse_from_value(&mut self, value: Value) -> bool {
         if value.is_u64() {
             *self = value.as_u64().unwrap() as u32;
+            true
         } else {
             error!("Setting expected a u32, but received {value:?}");
+            false
         }
     }
+
+    fn value_kind() -> &'static str {
+        "integer"
+    }
 }
 
 impl ParseFromValue for i32 {
-    fn parse_from_value(&mut self, value: Value) {
+    fn parse_from_value(&mut self, value: Value) -> bool {
         if value.is_i64() {
             *self = value.as_i64().unwrap() as i32;
+            true
         } else {
             error!("Setting expected an i32, but received {value:?}");
+            false
         }
     }
+
+    fn value_kind() -> &'static str {
+        "integer"
+    }
 }
 
 impl ParseFromValue for String {
-    fn parse_from_value(&mut self, value: Value) {
+    fn parse_from_value(&mut self, value: Value) -> bool {
         if value.is_str() {
             *self = String::from(value.as_str().unwrap());
+            true
         } else {
             error!("Setting expected a string, but received {value:?}");
+            false
         }
     }
+
+    fn value_kind() -> &'static str {
+        "string"
+    }
 }
 
 impl ParseFromValue for bool {
-    fn parse_from_value(&mut self, value: Value) {
+    fn parse_from_value(&mut self, value: Value) -> bool {
         if value.is_bool() {
             *self = value.as_bool().unwrap();
         } else if value.is_u64() {
             *self = value.as_u64().unwrap() != 0;
         } else {
             error!("Setting expected a bool or 0/1, but received {value:?}");
+            return false;
         }
+        true
+    }
+
+    fn value_kind() -> &'static str {
+        "boolean"
     }
 }
 
 impl<T: ParseFromValue + Default> ParseFromValue for Option<T> {
-    fn parse_from_value(&mut self, value: Value) {
+    fn value_kind() -> &'static str {
+        T::value_kind()
+    }
+
+    fn allowed_values() -> &'static [&'static str] {
+        T::allowed_values()
+    }
+
+    fn parse_from_value(&mut self, value: Value) -> bool {
         match self.as_mut() {
             Some(inner) => inner.parse_from_value(value),
             None => {
                 let mut inner = T::default();
-                inner.parse_from_value(value);
-                *self = Some(inner);
+                let valid = inner.parse_from_value(value);
+                if valid {
+                    *self = Some(inner);
+                }
+                valid
             }
         }
     }
@@ -108,9 +171,12 @@ mod tests {
         assert_eq!(v0, v2p, "v0 should equal {v2p} but is actually {v0}");
         v0.parse_from_value(v3);
         assert_eq!(v0, v3p, "v0 should equal {v3p} but is actually {v0}");
+        v0.parse_from_value(Value::from(0.5f32));
+        assert_eq!(v0, 0.5, "v0 should equal 0.5 but is actually {v0}");
+        v0.parse_from_value(Value::from(u64::MAX));
 
         // This is a noop and prints an error
-        v0.parse_from_value(Value::from("asd"));
+        assert!(!v0.parse_from_value(Value::from("asd")));
         assert_eq!(v0, v3p, "v0 should equal {v3p} but is actually {v0}");
     }
 
@@ -124,7 +190,7 @@ mod tests {
         assert_eq!(v0, v1p, "v0 should equal {v1p} but is actually {v0}");
 
         // This is a noop and prints an error
-        v0.parse_from_value(Value::from(-1));
+        assert!(!v0.parse_from_value(Value::from(-1)));
         assert_eq!(v0, v1p, "v0 should equal {v1p} but is actually {v0}");
     }
 
@@ -138,7 +204,7 @@ mod tests {
         assert_eq!(v0, v1p, "v0 should equal {v1p} but is actually {v0}");
 
         // This is a noop and prints an error
-        v0.parse_from_value(Value::from(-1));
+        assert!(!v0.parse_from_value(Value::from(-1)));
         assert_eq!(v0, v1p, "v0 should equal {v1p} but is actually {v0}");
     }
 
@@ -166,7 +232,7 @@ mod tests {
         assert_eq!(v0, v1p, "v0 should equal {v1p} but is actually {v0}");
 
         // This is a noop and prints an error
-        v0.parse_from_value(Value::from(-1));
+        assert!(!v0.parse_from_value(Value::from(-1)));
         assert_eq!(v0, v1p, "v0 should equal {v1p} but is actually {v0}");
     }
 
@@ -188,7 +254,7 @@ mod tests {
         assert_eq!(v0, v3p, "v0 should equal {v3p} but is actually {v0}");
 
         // This is a noop and prints an error
-        v0.parse_from_value(Value::from(-1));
+        assert!(!v0.parse_from_value(Value::from(-1)));
         assert_eq!(v0, v3p, "v0 should equal {v3p} but is actually {v0}");
     }
 }
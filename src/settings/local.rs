@@ -0,0 +1,27 @@
+use super::{SettingGroup, SettingsChanged};
+
+/// Window and buffer local (`w:neovide_*` and `b:neovide_*`) overrides for a single Neovim
+/// window. Only settings marked with `#[local]` in their `SettingGroup` can be overridden.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LocalOverrides {
+    overrides: Vec<SettingsChanged>,
+}
+
+impl LocalOverrides {
+    pub fn new(overrides: Vec<SettingsChanged>) -> Self {
+        Self { overrides }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.overrides.is_empty()
+    }
+
+    /// Returns a copy of the global settings with the local overrides applied.
+    pub fn resolve<T: SettingGroup + Clone>(&self, global: &T) -> T {
+        let mut settings = global.clone();
+        for changed in &self.overrides {
+            settings.apply_local_override(changed);
+        }
+        settings
+    }
+}
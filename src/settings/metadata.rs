@@ -0,0 +1,151 @@
+//! Metadata of the settings, which is sent to Neovim for the typed `require("neovide")` API and
+//! used to generate the LuaLS annotations printed by `--emit-lua-types`.
+
+use std::fmt::Write;
+
+use rmpv::Value;
+
+use crate::bridge::nvim_dict;
+
+/// Describes a setting that is backed by a `g:neovide_*` variable.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SettingMetadata {
+    /// The name without the `neovide_` prefix.
+    pub name: String,
+    /// One of `boolean`, `integer`, `number`, `string`, `string|string[]` or `any`.
+    pub kind: &'static str,
+    /// The accepted strings, empty when any string is accepted.
+    pub allowed_values: &'static [&'static str],
+    pub default: Value,
+    pub doc: String,
+}
+
+impl From<&SettingMetadata> for Value {
+    fn from(metadata: &SettingMetadata) -> Self {
+        let allowed_values = metadata.allowed_values.iter().map(|value| Value::from(*value));
+        Value::Map(nvim_dict! {
+            "name" => metadata.name.as_str(),
+            "type" => metadata.kind,
+            "allowed_values" => Value::Array(allowed_values.collect()),
+            "default" => metadata.default.clone(),
+            "doc" => metadata.doc.as_str(),
+        })
+    }
+}
+
+const LUA_FUNCTIONS: &str = r#"---@class neovide
+local neovide = {}
+
+--- Validates and applies all the given settings.
+---@param settings neovide.Settings
+function neovide.setup(settings) end
+
+--- Returns the value of a setting, or all settings when no name is given.
+---@overload fun(): neovide.Settings
+---@param name neovide.SettingName
+---@return any
+function neovide.get(name) end
+
+--- Validates and applies a single setting.
+---@param name neovide.SettingName
+---@param value any
+function neovide.set(name, value) end
+
+--- Binds a key chord to a GUI action, or removes the binding when the action is nil.
+---@param keys string
+---@param action? "toggle_fullscreen"|"zoom_in"|"zoom_out"|"zoom_reset"|"new_window"|"paste"|"toggle_profiler"|"show_settings"|"screenshot"|"reload_config"|"list_fonts"|"switch_theme"|"show_command_palette"|"toggle_input_inspector"|"copy_input_log"
+function neovide.bind(keys, action) end
+
+---@alias neovide.FontDescription string|{ family: string, style?: string, axes?: table<string, number> }
+
+--- Sets the font of a highlight group, or goes back to the font of the config file when it's nil.
+---@param group string
+---@param font? neovide.FontDescription|neovide.FontDescription[]
+function neovide.set_hl_font(group, font) end
+
+--- Adds an entry to the command palette, or removes it when the callback is nil.
+---@param title string
+---@param callback? string|fun()
+function neovide.register_command(title, callback) end
+
+return neovide
+"#;
+
+fn lua_type(metadata: &SettingMetadata) -> String {
+    let string_type = if metadata.allowed_values.is_empty() {
+        "string".to_owned()
+    } else {
+        let literals: Vec<_> =
+            metadata.allowed_values.iter().map(|value| format!("{value:?}")).collect();
+        literals.join("|")
+    };
+    match metadata.kind {
+        "string" => string_type,
+        "string|string[]" if metadata.allowed_values.is_empty() => "string|string[]".to_owned(),
+        "string|string[]" => format!("{string_type}|({string_type})[]"),
+        kind => kind.to_owned(),
+    }
+}
+
+/// Generates a LuaLS meta file describing `require("neovide")`.
+pub fn lua_types(metadata: &[SettingMetadata]) -> String {
+    let mut output = String::new();
+    output.push_str("---@meta neovide\n\n");
+    output.push_str("-- Generated by `neovide --emit-lua-types`, do not edit.\n\n");
+
+    output.push_str("---@class neovide.Settings\n");
+    for setting in metadata {
+        let mut description = setting.doc.clone();
+        if !setting.default.is_nil() {
+            if !description.is_empty() {
+                description.push(' ');
+            }
+            let _ = write!(description, "(Default: `{}`)", setting.default);
+        }
+        let field = format!("---@field {}? {} {description}", setting.name, lua_type(setting));
+        output.push_str(field.trim_end());
+        output.push('\n');
+    }
+
+    let names: Vec<_> = metadata.iter().map(|setting| format!("{:?}", setting.name)).collect();
+    let _ = writeln!(output, "\n---@alias neovide.SettingName {}\n", names.join("|"));
+
+    output.push_str(LUA_FUNCTIONS);
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lua_types() {
+        let metadata = vec![
+            SettingMetadata {
+                name: "cursor_vfx_mode".to_owned(),
+                kind: "string|string[]",
+                allowed_values: &["", "railgun"],
+                default: Value::from(""),
+                doc: "The cursor particle effect.".to_owned(),
+            },
+            SettingMetadata {
+                name: "scale_factor".to_owned(),
+                kind: "number",
+                allowed_values: &[],
+                default: Value::from(1.0f32),
+                doc: String::new(),
+            },
+        ];
+        let types = lua_types(&metadata);
+
+        assert!(types.starts_with("---@meta neovide\n"));
+        assert!(types.contains(
+            "---@field cursor_vfx_mode? \"\"|\"railgun\"|(\"\"|\"railgun\")[] \
+             The cursor particle effect. (Default: `\"\"`)\n"
+        ));
+        assert!(types.contains("---@field scale_factor? number (Default: `1`)\n"));
+        assert!(
+            types.contains("---@alias neovide.SettingName \"cursor_vfx_mode\"|\"scale_factor\"\n")
+        );
+    }
+}
@@ -1,5 +1,7 @@
 pub mod font;
 mod from_value;
+mod local;
+mod metadata;
 mod window_size;
 
 use anyhow::{Context, Result};
@@ -9,54 +11,77 @@ use parking_lot::RwLock;
 use rmpv::Value;
 use std::{
     any::{Any, TypeId},
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     convert::TryInto,
     fmt::Debug,
 };
 use winit::event_loop::EventLoopProxy;
 
-use crate::{bridge::NeovimWriter, window::EventPayload, window::RouteId};
+use crate::{
+    bridge::NeovimWriter, error_msg, renderer::fonts::highlight_fonts::HighlightFonts,
+    window::EventPayload, window::RouteId,
+};
 pub use from_value::ParseFromValue;
+pub use local::LocalOverrides;
+pub use metadata::{SettingMetadata, lua_types};
 pub use window_size::{
     DEFAULT_GRID_SIZE, MIN_GRID_SIZE, PersistentWindowSettings, clamped_grid_size,
-    load_last_window_settings, neovide_std_datapath, save_window_size,
+    load_last_window_settings, neovide_std_datapath, restore_runtime_settings, save_window_size,
 };
 
 pub mod config;
-pub use config::{Config, HotReloadConfigs, RendererHotReloadConfigs, WindowHotReloadConfigs};
+pub mod config_check;
+pub mod config_schema;
+pub use config::{
+    Config, HotReloadConfigs, RendererHotReloadConfigs, SettingTables, WindowHotReloadConfigs,
+};
+pub use config_schema::ConfigSchema;
 
 pub trait SettingGroup {
     type ChangedEvent: Debug + Clone + Send + Sync + Any;
     fn register(settings: &Settings);
+    fn apply_local_override(&mut self, changed: &SettingsChanged);
 }
 
 #[derive(Clone, Debug)]
 pub struct FontConfigState {
     pub has_font: bool,
+    /// The font size in points remembered from the previous session.
+    pub restored_size: Option<f32>,
 }
 
 impl FontConfigState {
     pub fn new() -> Self {
-        Self { has_font: false }
+        Self { has_font: false, restored_size: None }
     }
 }
 
 // Function types to handle settings updates
 type UpdateHandlerFunc = fn(&Settings, Value) -> SettingsChanged;
 type ReaderHandlerFunc = fn(&Settings) -> Option<Value>;
+// Returns `None` when the value is invalid, so that no override is recorded
+type LocalUpdateHandlerFunc = fn(&Settings, Value) -> Option<SettingsChanged>;
 
 // The Settings struct acts as a global container where each of Neovide's subsystems can store
 // their own settings. It will also coordinate updates between Neovide and nvim to make sure the
 // settings remain consistent on both sides.
 // Note: As right now we're only sending new setting values to Neovide during the
 // read_initial_values call, after that point we should not modify the contents of the Settings
-// struct except when prompted by an update event from nvim. Otherwise, the settings in Neovide and
-// nvim will get out of sync.
+// struct except when prompted by an update event from nvim, or when the config file changes. In
+// the latter case the new values have to be sent to nvim as well. Otherwise, the settings in
+// Neovide and nvim will get out of sync.
 #[derive(Default, Debug)]
 pub struct Settings {
     settings: RwLock<HashMap<TypeId, Box<dyn Any + Send + Sync>>>,
     updaters: RwLock<HashMap<SettingLocation, UpdateHandlerFunc>>,
     readers: RwLock<HashMap<SettingLocation, ReaderHandlerFunc>>,
+    local_updaters: RwLock<HashMap<String, LocalUpdateHandlerFunc>>,
+    // (table, key) in the config file to setting name
+    config_keys: RwLock<HashMap<(String, String), String>>,
+    config_defaults: RwLock<HashMap<String, Value>>,
+    // Settings that have been set from nvim, these take precedence over the config file
+    neovim_values: RwLock<HashSet<String>>,
+    metadata: RwLock<Vec<SettingMetadata>>,
 }
 
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
@@ -71,6 +96,7 @@ impl Settings {
     pub fn new() -> Self {
         let settings = Self::default();
         settings.set(&FontConfigState::new());
+        settings.set(&HighlightFonts::default());
         settings
     }
 
@@ -85,6 +111,33 @@ impl Settings {
         self.readers.write().insert(setting_location.clone(), reader_func);
     }
 
+    pub fn set_local_setting_handler(&self, name: String, update_func: LocalUpdateHandlerFunc) {
+        self.local_updaters.write().insert(name, update_func);
+    }
+
+    /// Makes the setting `name` configurable through `key` in the `[section]` table of the config
+    /// file. The current value is remembered as the default, which is restored when the key is
+    /// removed from the config file again.
+    pub fn set_config_key(&self, section: &str, key: &str, name: &str) {
+        let location = SettingLocation::NeovideGlobal(name.to_owned());
+        let reader = self.readers.read().get(&location).copied();
+        if let Some(value) = reader.and_then(|reader| reader(self)) {
+            self.config_defaults.write().insert(name.to_owned(), value);
+        }
+        self.config_keys.write().insert((section.to_owned(), key.to_owned()), name.to_owned());
+    }
+
+    pub fn set_setting_metadata(&self, metadata: SettingMetadata) {
+        self.metadata.write().push(metadata);
+    }
+
+    /// The metadata of all settings backed by a `g:neovide_*` variable, sorted by name.
+    pub fn setting_metadata(&self) -> Vec<SettingMetadata> {
+        let mut metadata = self.metadata.read().clone();
+        metadata.sort_by(|a, b| a.name.cmp(&b.name));
+        metadata
+    }
+
     pub fn set<T: Clone + Send + Sync + 'static>(&self, t: &T) {
         let type_id: TypeId = TypeId::of::<T>();
         let t: T = (*t).clone();
@@ -107,6 +160,135 @@ impl Settings {
         self.updaters.read().keys().cloned().collect()
     }
 
+    pub fn local_setting_names(&self) -> Vec<String> {
+        self.local_updaters.read().keys().cloned().collect()
+    }
+
+    /// Parses the raw `w:`/`b:` values of a window. The values are parsed once here, so that
+    /// invalid values are only reported when they are set. Invalid values are dropped, so the
+    /// global value keeps applying.
+    pub fn parse_local_overrides(&self, values: Vec<(String, Value)>) -> LocalOverrides {
+        let local_updaters = self.local_updaters.read();
+        let overrides = values
+            .into_iter()
+            .filter_map(|(name, value)| match local_updaters.get(&name) {
+                Some(update_handler) => update_handler(self, value),
+                None => {
+                    warn!("Ignoring local override for setting that can't be overridden: {name}");
+                    None
+                }
+            })
+            .collect();
+        LocalOverrides::new(overrides)
+    }
+
+    /// The schemas of the settings in each of the setting tables of the config file, by table.
+    pub fn config_table_schemas(
+        &self,
+    ) -> HashMap<String, serde_json::Map<String, serde_json::Value>> {
+        let config_defaults = self.config_defaults.read();
+        let mut tables: HashMap<String, serde_json::Map<_, _>> = HashMap::new();
+        for ((section, key), name) in self.config_keys.read().iter() {
+            let schema = config_defaults
+                .get(name)
+                .map(config_schema::setting_schema)
+                .unwrap_or_else(|| serde_json::json!({}));
+            let properties = tables.entry(section.clone()).or_default();
+            properties.insert(key.replace('_', "-"), schema.clone());
+            properties.insert(key.clone(), schema);
+        }
+        tables
+    }
+
+    /// Applies the setting tables of the config file. Settings that have been set from nvim are
+    /// left alone, and settings missing from the config file are reset to their defaults.
+    ///
+    /// Returns the name, new value and change event of every setting that changed.
+    pub fn apply_config_tables<'a>(
+        &self,
+        tables: impl IntoIterator<Item = (&'a str, &'a toml::Table)>,
+    ) -> Vec<(String, Value, SettingsChanged)> {
+        let tables: HashMap<&str, &toml::Table> = tables.into_iter().collect();
+        let config_keys = self.config_keys.read().clone();
+
+        for (section, table) in &tables {
+            for key in table.keys() {
+                let config_key = (section.to_string(), key.replace('-', "_"));
+                if !config_keys.contains_key(&config_key) {
+                    error_msg!(
+                        "Unknown setting `{key}` in the [{section}] table of the config file"
+                    );
+                }
+            }
+        }
+
+        let mut changes = Vec::new();
+        for ((section, key), name) in config_keys {
+            if self.neovim_values.read().contains(&name) {
+                continue;
+            }
+
+            let value = tables
+                .get(section.as_str())
+                .and_then(|table| table.get(&key).or_else(|| table.get(&key.replace('_', "-"))))
+                .map(config::toml_to_value)
+                .or_else(|| self.config_defaults.read().get(&name).cloned());
+            let Some(value) = value else {
+                continue;
+            };
+
+            let location = SettingLocation::NeovideGlobal(name.clone());
+            let update_handler = *self.updaters.read().get(&location).unwrap();
+            let reader = *self.readers.read().get(&location).unwrap();
+
+            let previous = reader(self);
+            let event = update_handler(self, value);
+            if let Some(value) = reader(self).filter(|value| Some(value) != previous.as_ref()) {
+                changes.push((name, value, event));
+            }
+        }
+        changes
+    }
+
+    /// The current value of a setting backed by a `g:neovide_*` variable.
+    pub fn value(&self, name: &str) -> Option<Value> {
+        let location = SettingLocation::NeovideGlobal(name.to_owned());
+        let reader = self.readers.read().get(&location).copied()?;
+        reader(self)
+    }
+
+    /// The default value of a setting, before the config file or nvim changed it.
+    pub fn default_value(&self, name: &str) -> Option<Value> {
+        self.config_defaults.read().get(name).cloned()
+    }
+
+    /// Changes a setting from Neovide itself, for example from the settings panel. Like values
+    /// set from nvim, these take precedence over the config file.
+    ///
+    /// Returns the new value and the change event, or `None` when the setting doesn't exist.
+    pub fn set_value(&self, name: &str, value: Value) -> Option<(Value, SettingsChanged)> {
+        let event = self.apply_value(name, value)?;
+        self.neovim_values.write().insert(name.to_owned());
+        Some((self.value(name)?, event))
+    }
+
+    /// Changes a setting the same way as the config file does, so nvim and config reloads can
+    /// still override it.
+    pub fn apply_value(&self, name: &str, value: Value) -> Option<SettingsChanged> {
+        let location = SettingLocation::NeovideGlobal(name.to_owned());
+        let update_handler = self.updaters.read().get(&location).copied()?;
+        Some(update_handler(self, value))
+    }
+
+    /// The table and key of a setting in the config file.
+    pub fn config_key(&self, name: &str) -> Option<(String, String)> {
+        self.config_keys
+            .read()
+            .iter()
+            .find(|(_, setting)| *setting == name)
+            .map(|(key, _)| key.clone())
+    }
+
     pub async fn read_initial_values(&self, nvim: &Neovim<NeovimWriter>) -> Result<()> {
         let deprecated_settings = ["transparency".to_owned()];
         let keys: Vec<SettingLocation> = self
@@ -124,6 +306,7 @@ impl Settings {
                     match nvim.get_var(&variable_name).await {
                         Ok(value) => {
                             self.updaters.read().get(&location).unwrap()(self, value);
+                            self.neovim_values.write().insert(name.clone());
                         }
                         Err(error) => {
                             trace!("Initial value load failed for {name}: {error}");
@@ -179,7 +362,15 @@ impl Settings {
             }
         };
 
+        // Our own updates are echoed back by nvim, so only values that actually differ count as
+        // set from nvim
+        let previous = self.readers.read().get(&location).and_then(|reader| reader(self));
         let event = update_handler(self, value);
+        let current = self.readers.read().get(&location).and_then(|reader| reader(self));
+        if current != previous {
+            self.neovim_values.write().insert(name);
+        }
+
         let _ = event_loop_proxy.send_event(EventPayload::for_route(event.into(), route_id));
     }
 
@@ -229,6 +420,7 @@ pub enum SettingsChanged {
     Cursor(crate::renderer::cursor_renderer::CursorSettingsChanged),
     Renderer(crate::renderer::RendererSettingsChanged),
     ProgressBar(crate::renderer::progress_bar::ProgressBarSettingsChanged),
+    Screencast(crate::renderer::screencast::ScreencastSettingsChanged),
     #[cfg(test)]
     Test(tests::TestSettingsChanged),
 }
@@ -237,8 +429,10 @@ pub enum SettingsChanged {
 mod tests {
     #[derive(Clone, SettingGroup)]
     struct TestSettings {
+        /// The foo setting.
         foo: String,
         bar: String,
+        #[local]
         baz: String,
         #[option = "mousemoveevent"]
         mousemoveevent_option: Option<bool>,
@@ -358,6 +552,76 @@ mod tests {
         assert_eq!(v2, r2);
     }
 
+    #[test]
+    fn test_local_overrides() {
+        let settings = Settings::new();
+        settings.register::<TestSettings>();
+
+        assert_eq!(settings.local_setting_names(), vec!["baz".to_owned()]);
+
+        let overrides = settings.parse_local_overrides(vec![
+            ("baz".to_owned(), Value::from("baz_local")),
+            ("foo".to_owned(), Value::from("foo_local")),
+        ]);
+        let global = settings.get::<TestSettings>();
+        let resolved = overrides.resolve(&global);
+        assert_eq!(resolved.foo, "foo");
+        assert_eq!(resolved.baz, "baz_local");
+        assert_eq!(settings.get::<TestSettings>().baz, "baz");
+
+        let resolved = LocalOverrides::default().resolve(&global);
+        assert_eq!(resolved.baz, "baz");
+
+        let overrides = settings.parse_local_overrides(vec![("baz".to_owned(), Value::from(1))]);
+        assert!(overrides.is_empty());
+        let resolved = overrides.resolve(&global);
+        assert_eq!(resolved.baz, "baz");
+    }
+
+    #[test]
+    fn test_apply_config_tables() {
+        let settings = Settings::new();
+        settings.register::<TestSettings>();
+
+        let table: toml::Table = toml::from_str(r#"foo = "foo_config""#).unwrap();
+        let changes = settings.apply_config_tables([("test", &table)]);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].0, "foo");
+        assert_eq!(changes[0].1, Value::from("foo_config"));
+        assert_eq!(settings.get::<TestSettings>().foo, "foo_config");
+
+        // Values set from nvim take precedence
+        settings.neovim_values.write().insert("bar".to_owned());
+        let table: toml::Table = toml::from_str(r#"bar = "bar_config""#).unwrap();
+        let changes = settings.apply_config_tables([("test", &table)]);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].0, "foo");
+        assert_eq!(settings.get::<TestSettings>().foo, "foo");
+        assert_eq!(settings.get::<TestSettings>().bar, "bar");
+
+        assert!(settings.apply_config_tables(Vec::new()).is_empty());
+    }
+
+    #[test]
+    fn test_setting_metadata() {
+        let settings = Settings::new();
+        settings.register::<TestSettings>();
+
+        let metadata = settings.setting_metadata();
+        let names: Vec<_> = metadata.iter().map(|setting| setting.name.as_str()).collect();
+        assert_eq!(names, vec!["bar", "baz", "foo"]);
+        assert_eq!(
+            metadata[2],
+            SettingMetadata {
+                name: "foo".to_owned(),
+                kind: "string",
+                allowed_values: &[],
+                default: Value::from("foo"),
+                doc: "The foo setting.".to_owned(),
+            }
+        );
+    }
+
     #[tokio::test]
     async fn test_read_initial_values() {
         let settings = Settings::new();
@@ -368,9 +632,10 @@ mod tests {
 
         let command = create_nvim_command(&settings);
         let instance = NeovimInstance::Embedded(command);
-        let NeovimSession { neovim: nvim, .. } = NeovimSession::new(instance, NeovimHandler())
-            .await
-            .unwrap_or_explained_panic("Could not locate or start the neovim process");
+        let NeovimSession { neovim: nvim, .. } =
+            NeovimSession::new(instance, NeovimHandler(), None)
+                .await
+                .unwrap_or_explained_panic("Could not locate or start the neovim process");
         nvim.set_var("neovide_bar", Value::from("bar_set".to_owned()))
             .await
             .expect("Could not set neovide_bar variable");
@@ -1,20 +1,31 @@
-use std::path::PathBuf;
+use std::{
+    collections::HashMap,
+    env,
+    path::{Path, PathBuf},
+};
 
 use log::{error, warn};
+use rmpv::Value;
 use serde::{Deserialize, Serialize};
 use winit::dpi::{PhysicalPosition, PhysicalSize};
 
 use crate::{
-    settings::Settings, units::GridSize, window::WindowSettings, window::WinitWindowWrapper,
+    cmd_line::CmdLineSettings,
+    renderer::fonts::font_options::pixels_to_points,
+    settings::{FontConfigState, Settings, config::active_profile},
+    units::GridSize,
+    window::{RememberSettings, WindowSettings, WinitWindowWrapper},
 };
 
 const SETTINGS_FILE: &str = "neovide-settings.json";
+/// How many projects get their own window geometry, the least recently used ones are dropped.
+const MAX_PROJECTS: usize = 32;
 
 pub const DEFAULT_GRID_SIZE: GridSize<u32> = GridSize { width: 100, height: 50 };
 pub const MIN_GRID_SIZE: GridSize<u32> = GridSize { width: 20, height: 6 };
 pub const MAX_GRID_SIZE: GridSize<u32> = GridSize { width: 10000, height: 1000 };
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub enum PersistentWindowSettings {
     Maximized {
         #[serde(default)]
@@ -30,9 +41,39 @@ pub enum PersistentWindowSettings {
     },
 }
 
+/// Values chosen during a session, restored on the next launch when `remember_settings` is
+/// enabled.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct PersistentRuntimeSettings {
+    #[serde(default)]
+    pub scale_factor: Option<f32>,
+    #[serde(default)]
+    pub opacity: Option<f32>,
+    /// The font size in points.
+    #[serde(default)]
+    pub font_size: Option<f32>,
+    #[serde(default)]
+    pub fullscreen: Option<bool>,
+    #[serde(default)]
+    pub theme: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct ProjectWindowSettings {
+    directory: PathBuf,
+    window: PersistentWindowSettings,
+}
+
 #[derive(Serialize, Deserialize)]
 struct PersistentSettings {
+    /// The window of the last session, used for projects without their own entry.
     window: PersistentWindowSettings,
+    /// The window of each project, the most recently used first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    projects: Vec<ProjectWindowSettings>,
+    /// Keyed by `profile:<name>` or `directory:<path>`, depending on `remember_settings`.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    runtime: HashMap<String, PersistentRuntimeSettings>,
 }
 
 fn settings_path() -> PathBuf {
@@ -56,14 +97,98 @@ pub fn neovide_std_datapath() -> PathBuf {
         .join("neovide")
 }
 
-pub fn load_last_window_settings() -> Result<PersistentWindowSettings, String> {
-    let settings = load_settings()?;
-    let loaded_settings = settings.window;
+/// Loads the window of the current project, or the one of the last session when the project
+/// has none.
+pub fn load_last_window_settings(settings: &Settings) -> Result<PersistentWindowSettings, String> {
+    let mut persistent_settings = load_settings()?;
+    let project = project_directory(settings);
+    let loaded_settings = project
+        .and_then(|project| {
+            let index =
+                persistent_settings.projects.iter().position(|entry| entry.directory == project)?;
+            Some(persistent_settings.projects.swap_remove(index).window)
+        })
+        .unwrap_or(persistent_settings.window);
     log::debug!("Loaded window settings: {loaded_settings:?}");
 
     Ok(loaded_settings)
 }
 
+/// The `--chdir` directory, or the current one.
+fn working_directory(settings: &Settings) -> Option<PathBuf> {
+    let directory = settings
+        .get::<CmdLineSettings>()
+        .chdir
+        .map(PathBuf::from)
+        .or_else(|| env::current_dir().ok())?;
+    Some(directory.canonicalize().unwrap_or(directory))
+}
+
+/// The git root of the working directory, or the working directory itself outside of a repository.
+fn project_directory(settings: &Settings) -> Option<PathBuf> {
+    working_directory(settings).map(|directory| git_root(&directory).unwrap_or(directory))
+}
+
+fn git_root(directory: &Path) -> Option<PathBuf> {
+    directory.ancestors().find(|ancestor| ancestor.join(".git").exists()).map(Path::to_path_buf)
+}
+
+/// Moves the project to the front of the list, dropping the least recently used ones.
+fn remember_project(
+    projects: &mut Vec<ProjectWindowSettings>,
+    directory: PathBuf,
+    window: PersistentWindowSettings,
+) {
+    projects.retain(|entry| entry.directory != directory);
+    projects.insert(0, ProjectWindowSettings { directory, window });
+    projects.truncate(MAX_PROJECTS);
+}
+
+/// The key of the remembered runtime settings, or `None` when they are not remembered.
+fn runtime_settings_key(settings: &Settings) -> Option<String> {
+    match settings.get::<WindowSettings>().remember_settings {
+        RememberSettings::Never => None,
+        RememberSettings::Profile => {
+            Some(format!("profile:{}", active_profile().unwrap_or_default()))
+        }
+        RememberSettings::Directory => {
+            let directory = working_directory(settings)?;
+            Some(format!("directory:{}", directory.display()))
+        }
+    }
+}
+
+/// Applies the runtime settings remembered from the previous session. Called after the config file
+/// is loaded and before the first window is created, Neovim can still override them.
+pub fn restore_runtime_settings(settings: &Settings) {
+    let Some(key) = runtime_settings_key(settings) else {
+        return;
+    };
+    let Some(runtime) = load_settings().ok().and_then(|mut loaded| loaded.runtime.remove(&key))
+    else {
+        return;
+    };
+    log::debug!("Restoring runtime settings for {key}: {runtime:?}");
+
+    if let Some(scale_factor) = runtime.scale_factor {
+        settings.apply_value("scale_factor", Value::from(scale_factor));
+    }
+    if let Some(opacity) = runtime.opacity {
+        settings.apply_value("opacity", Value::from(opacity));
+    }
+    if let Some(fullscreen) = runtime.fullscreen {
+        settings.apply_value("fullscreen", Value::from(fullscreen));
+    }
+    if let Some(theme) = runtime.theme {
+        settings.apply_value("theme", Value::from(theme));
+    }
+    if let Some(font_size) = runtime.font_size {
+        let mut font_config_state = settings.get::<FontConfigState>();
+        font_config_state.restored_size = Some(font_size);
+        settings.set(&font_config_state);
+    }
+}
+
 pub fn save_window_size(window_wrapper: &WinitWindowWrapper, settings: &Settings) {
     if window_wrapper.routes.is_empty() {
         return;
@@ -83,25 +208,44 @@ pub fn save_window_size(window_wrapper: &WinitWindowWrapper, settings: &Settings
     let position = window.outer_position().ok();
     let window_settings = settings.get::<WindowSettings>();
 
-    let settings = PersistentSettings {
-        window: if maximized && window_settings.remember_window_size {
-            PersistentWindowSettings::Maximized {
-                grid_size: { window_settings.remember_window_size.then_some(grid_size) },
-            }
-        } else {
-            PersistentWindowSettings::Windowed {
-                pixel_size: { window_settings.remember_window_size.then_some(pixel_size) },
-                grid_size: { window_settings.remember_window_size.then_some(grid_size) },
-                position: {
-                    window_settings
-                        .remember_window_position
-                        .then_some(position)
-                        .flatten()
-                        .unwrap_or_default()
-                },
-            }
-        },
+    let (mut projects, mut runtime) =
+        load_settings().map(|loaded| (loaded.projects, loaded.runtime)).unwrap_or_default();
+    if let Some(key) = runtime_settings_key(settings) {
+        let font_size = route.window.renderer.borrow().grid_renderer.shaper.font_size();
+        runtime.insert(
+            key,
+            PersistentRuntimeSettings {
+                scale_factor: Some(window_settings.scale_factor),
+                opacity: Some(window_settings.opacity),
+                font_size: Some(pixels_to_points(font_size)),
+                fullscreen: Some(window.fullscreen().is_some()),
+                theme: settings.value("theme").and_then(|theme| theme.as_str().map(str::to_owned)),
+            },
+        );
+    }
+
+    let persistent_window = if maximized && window_settings.remember_window_size {
+        PersistentWindowSettings::Maximized {
+            grid_size: { window_settings.remember_window_size.then_some(grid_size) },
+        }
+    } else {
+        PersistentWindowSettings::Windowed {
+            pixel_size: { window_settings.remember_window_size.then_some(pixel_size) },
+            grid_size: { window_settings.remember_window_size.then_some(grid_size) },
+            position: {
+                window_settings
+                    .remember_window_position
+                    .then_some(position)
+                    .flatten()
+                    .unwrap_or_default()
+            },
+        }
     };
+    if let Some(project) = project_directory(settings) {
+        remember_project(&mut projects, project, persistent_window.clone());
+    }
+
+    let settings = PersistentSettings { window: persistent_window, projects, runtime };
 
     let settings_path = settings_path();
 
@@ -127,3 +271,67 @@ pub fn save_window_size(window_wrapper: &WinitWindowWrapper, settings: &Settings
 pub fn clamped_grid_size(grid_size: &GridSize<u32>) -> GridSize<u32> {
     grid_size.clamp(MIN_GRID_SIZE, MAX_GRID_SIZE)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_without_runtime_settings() {
+        let json = r#"{"window":{"Maximized":{"grid_size":null}}}"#;
+        let settings: PersistentSettings = serde_json::from_str(json).unwrap();
+        assert!(settings.runtime.is_empty());
+
+        let serialized = serde_json::to_string(&settings).unwrap();
+        assert!(!serialized.contains("runtime"));
+        assert!(!serialized.contains("projects"));
+    }
+
+    #[test]
+    fn test_remember_project() {
+        let maximized = PersistentWindowSettings::Maximized { grid_size: None };
+        let mut projects = Vec::new();
+        for i in 0..MAX_PROJECTS + 1 {
+            remember_project(
+                &mut projects,
+                PathBuf::from(format!("/project{i}")),
+                maximized.clone(),
+            );
+        }
+        assert_eq!(projects.len(), MAX_PROJECTS);
+        assert_eq!(projects[0].directory, PathBuf::from(format!("/project{MAX_PROJECTS}")));
+        assert!(!projects.iter().any(|entry| entry.directory == Path::new("/project0")));
+
+        let windowed = PersistentWindowSettings::Windowed {
+            position: PhysicalPosition::new(10, 20),
+            pixel_size: None,
+            grid_size: None,
+        };
+        remember_project(&mut projects, PathBuf::from("/project5"), windowed.clone());
+        assert_eq!(projects.len(), MAX_PROJECTS);
+        assert_eq!(projects[0].directory, PathBuf::from("/project5"));
+        assert_eq!(projects[0].window, windowed);
+        assert_eq!(
+            projects.iter().filter(|entry| entry.directory == Path::new("/project5")).count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_runtime_settings_round_trip() {
+        let runtime = PersistentRuntimeSettings {
+            scale_factor: Some(1.25),
+            font_size: Some(14.0),
+            theme: Some("dark".to_owned()),
+            ..Default::default()
+        };
+        let settings = PersistentSettings {
+            window: PersistentWindowSettings::Maximized { grid_size: None },
+            projects: Vec::new(),
+            runtime: HashMap::from([("profile:".to_owned(), runtime.clone())]),
+        };
+        let json = serde_json::to_string(&settings).unwrap();
+        let mut loaded: PersistentSettings = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.runtime.remove("profile:"), Some(runtime));
+    }
+}
@@ -0,0 +1,117 @@
+//! The startup timeline written by `--startuptime`.
+//!
+//! The steps of the startup are recorded from the start of the process until the first frame is
+//! presented, since the command line is only parsed after the config file is loaded. The timeline
+//! is then written to the file, or thrown away when the option isn't used.
+
+use std::{
+    fmt::{Display, Write as _},
+    fs,
+    sync::{
+        LazyLock, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+static PROCESS_START: LazyLock<Instant> = LazyLock::new(Instant::now);
+static RECORDING: AtomicBool = AtomicBool::new(true);
+static ENTRIES: Mutex<Vec<Entry>> = Mutex::new(Vec::new());
+
+#[derive(Debug, Clone, PartialEq)]
+struct Entry {
+    label: String,
+    /// When the step finished, since the start of the process.
+    clock: Duration,
+    /// How long the step took, `None` for events without a duration.
+    duration: Option<Duration>,
+}
+
+/// Starts the clock, this should be the first thing the process does.
+pub fn start() {
+    LazyLock::force(&PROCESS_START);
+}
+
+/// Records a step of the startup, that started at `start` and finished now.
+pub fn record(label: impl Display, start: Instant) {
+    push(label, Some(start));
+}
+
+/// Records an event of the startup, without a duration.
+pub fn mark(label: impl Display) {
+    push(label, None);
+}
+
+fn push(label: impl Display, start: Option<Instant>) {
+    if !RECORDING.load(Ordering::Relaxed) {
+        return;
+    }
+    let now = Instant::now();
+    ENTRIES.lock().unwrap().push(Entry {
+        label: label.to_string(),
+        clock: now.saturating_duration_since(*PROCESS_START),
+        duration: start.map(|start| now.saturating_duration_since(start)),
+    });
+}
+
+/// Stops recording and writes the timeline to `path`, if there is one. Only the first call does
+/// anything.
+pub fn finish(path: Option<&str>) {
+    if !RECORDING.swap(false, Ordering::Relaxed) {
+        return;
+    }
+    let entries = std::mem::take(&mut *ENTRIES.lock().unwrap());
+    let Some(path) = path else {
+        return;
+    };
+    if let Err(error) = fs::write(path, format_timeline(entries)) {
+        log::warn!("Could not write the startup time to {path}: {error}");
+    }
+}
+
+/// Formats the entries like the `--startuptime` output of Neovim, ordered by when they finished.
+fn format_timeline(mut entries: Vec<Entry>) -> String {
+    entries.sort_by_key(|entry| entry.clock);
+
+    let mut output = String::from("times in msec\n clock   self: event\n\n");
+    for entry in entries {
+        let clock = entry.clock.as_secs_f64() * 1000.0;
+        let _ = match entry.duration {
+            Some(duration) => {
+                let duration = duration.as_secs_f64() * 1000.0;
+                writeln!(output, "{clock:07.3}  {duration:07.3}: {}", entry.label)
+            }
+            None => writeln!(output, "{clock:07.3}         : {}", entry.label),
+        };
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(label: &str, clock_ms: u64, duration_ms: Option<u64>) -> Entry {
+        Entry {
+            label: label.to_string(),
+            clock: Duration::from_millis(clock_ms),
+            duration: duration_ms.map(Duration::from_millis),
+        }
+    }
+
+    #[test]
+    fn test_format_timeline() {
+        let entries = vec![
+            entry("first frame", 250, None),
+            entry("config load", 3, Some(2)),
+            entry("neovim spawn", 40, Some(30)),
+        ];
+        assert_eq!(
+            format_timeline(entries),
+            "times in msec\n clock   self: event\n\n\
+             003.000  002.000: config load\n\
+             040.000  030.000: neovim spawn\n\
+             250.000         : first frame\n"
+        );
+    }
+}
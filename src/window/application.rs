@@ -1,4 +1,5 @@
 use std::{
+    collections::HashMap,
     sync::{Arc, Mutex},
     time::{Duration, Instant},
 };
@@ -133,6 +134,7 @@ impl Application {
         _initial_window_size: WindowSize,
         initial_grid_size: Option<Size2<Grid<u32>>>,
         initial_font_settings: Option<FontSettings>,
+        initial_key_bindings: Option<HashMap<String, String>>,
         proxy: EventLoopProxy<EventPayload>,
         settings: Arc<Settings>,
         clipboard: Arc<Mutex<Clipboard>>,
@@ -145,6 +147,7 @@ impl Application {
 
         let window_wrapper = WinitWindowWrapper::new(
             initial_font_settings,
+            initial_key_bindings,
             settings.clone(),
             runtime_tracker.clone(),
             clipboard_handle,
@@ -697,6 +700,12 @@ impl ApplicationHandler<EventPayload> for Application {
                 self.window_wrapper.handle_mac_shortcut(command);
                 self.mark_should_render_all();
             }
+            UserEvent::FirstFramePresented => match target {
+                EventTarget::Window(window_id) => {
+                    self.window_wrapper.handle_first_frame_presented(window_id);
+                }
+                _ => log::warn!("FirstFramePresented event missing window target"),
+            },
             UserEvent::NeovimRestart(details) => {
                 let route_id = self.route_id_for_target(target);
                 let Some(route_id) = route_id else {
@@ -735,12 +744,14 @@ impl ApplicationHandler<EventPayload> for Application {
     fn resumed(&mut self, event_loop: &ActiveEventLoop) {
         tracy_zone!("resumed");
         self.create_window_allowed = true;
-        self.window_wrapper.request_window_creation(&self.proxy);
+        self.window_wrapper.create_initial_window(event_loop, &self.proxy);
         self.schedule_next_event(event_loop);
     }
 
     fn exiting(&mut self, event_loop: &ActiveEventLoop) {
         tracy_zone!("exiting");
+        // Neovide is quitting with open windows, for example with Cmd+Q on macOS
+        self.window_wrapper.save_session();
         self.teardown();
         self.window_wrapper.exit();
         self.schedule_next_event(event_loop);
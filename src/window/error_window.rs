@@ -23,6 +23,7 @@ use winit::{
 use crate::{
     clipboard::{Clipboard, ClipboardHandle},
     cmd_line::{CmdLineSettings, SRGB_DEFAULT},
+    profiling::tracy_create_gpu_context,
     renderer::{SkiaRenderer, WindowConfig, build_window_config, create_skia_renderer},
     settings::Settings,
     window::load_icon,
@@ -143,6 +144,7 @@ impl State {
         let vsync = true;
         let window = create_window(event_loop, &settings);
         let skia_renderer = create_skia_renderer(&window, srgb, vsync, settings);
+        tracy_create_gpu_context("main_render_context", skia_renderer.as_ref());
         window.window.set_visible(true);
         let scale_factor = window.window.scale_factor();
         let size = window.window.inner_size();
@@ -0,0 +1,319 @@
+//! Key chords that trigger built-in GUI actions, configured through the `[keys]` table of the config
+//! file and `require("neovide").bind()`.
+//!
+//! The bindings are handled before the keys are sent to Neovim, so they keep working while Neovim
+//! is busy or waiting in a blocking prompt.
+
+use std::{collections::HashMap, str::FromStr};
+
+use super::keyboard_manager::{KeyModifiers, encode_key};
+use crate::error_msg;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GuiAction {
+    ToggleFullscreen,
+    ZoomIn,
+    ZoomOut,
+    ZoomReset,
+    NewWindow,
+    Paste,
+    ToggleProfiler,
+    ShowSettings,
+    Screenshot,
+    ReloadConfig,
+    ListFonts,
+    SwitchTheme,
+    ShowCommandPalette,
+    ToggleInputInspector,
+    CopyInputLog,
+}
+
+impl GuiAction {
+    pub const ALL: &[GuiAction] = &[
+        GuiAction::ToggleFullscreen,
+        GuiAction::ZoomIn,
+        GuiAction::ZoomOut,
+        GuiAction::ZoomReset,
+        GuiAction::NewWindow,
+        GuiAction::Paste,
+        GuiAction::ToggleProfiler,
+        GuiAction::ShowSettings,
+        GuiAction::Screenshot,
+        GuiAction::ReloadConfig,
+        GuiAction::ListFonts,
+        GuiAction::SwitchTheme,
+        GuiAction::ShowCommandPalette,
+        GuiAction::ToggleInputInspector,
+        GuiAction::CopyInputLog,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            GuiAction::ToggleFullscreen => "toggle_fullscreen",
+            GuiAction::ZoomIn => "zoom_in",
+            GuiAction::ZoomOut => "zoom_out",
+            GuiAction::ZoomReset => "zoom_reset",
+            GuiAction::NewWindow => "new_window",
+            GuiAction::Paste => "paste",
+            GuiAction::ToggleProfiler => "toggle_profiler",
+            GuiAction::ShowSettings => "show_settings",
+            GuiAction::Screenshot => "screenshot",
+            GuiAction::ReloadConfig => "reload_config",
+            GuiAction::ListFonts => "list_fonts",
+            GuiAction::SwitchTheme => "switch_theme",
+            GuiAction::ShowCommandPalette => "show_command_palette",
+            GuiAction::ToggleInputInspector => "toggle_input_inspector",
+            GuiAction::CopyInputLog => "copy_input_log",
+        }
+    }
+
+    /// The name shown in the command palette.
+    pub fn title(self) -> &'static str {
+        match self {
+            GuiAction::ToggleFullscreen => "Toggle Fullscreen",
+            GuiAction::ZoomIn => "Zoom In",
+            GuiAction::ZoomOut => "Zoom Out",
+            GuiAction::ZoomReset => "Reset Zoom",
+            GuiAction::NewWindow => "New Window",
+            GuiAction::Paste => "Paste",
+            GuiAction::ToggleProfiler => "Toggle Profiler",
+            GuiAction::ShowSettings => "Show Settings",
+            GuiAction::Screenshot => "Save Screenshot",
+            GuiAction::ReloadConfig => "Reload Config File",
+            GuiAction::ListFonts => "List Available Fonts",
+            GuiAction::SwitchTheme => "Switch Theme",
+            GuiAction::ShowCommandPalette => "Show Command Palette",
+            GuiAction::ToggleInputInspector => "Toggle Input Inspector",
+            GuiAction::CopyInputLog => "Copy Input Log",
+        }
+    }
+}
+
+impl FromStr for GuiAction {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.replace('-', "_");
+        GuiAction::ALL.iter().copied().find(|action| action.name() == value).ok_or_else(|| {
+            let names: Vec<_> = GuiAction::ALL.iter().map(|action| action.name()).collect();
+            format!("unknown action `{value}`, expected one of {}", names.join(", "))
+        })
+    }
+}
+
+/// The special key names sent by the keyboard manager, and the aliases Neovim accepts for them.
+const SPECIAL_KEYS: &[(&str, &[&str])] = &[
+    ("Down", &[]),
+    ("Left", &[]),
+    ("Right", &[]),
+    ("Up", &[]),
+    ("BS", &["backspace"]),
+    ("Del", &["delete"]),
+    ("End", &[]),
+    ("Enter", &["cr", "return"]),
+    ("Esc", &["escape"]),
+    ("Help", &[]),
+    ("Home", &[]),
+    ("Insert", &[]),
+    ("PageDown", &[]),
+    ("PageUp", &[]),
+    ("Space", &[]),
+    ("Tab", &[]),
+    ("Undo", &[]),
+    ("kComma", &[]),
+    ("kDel", &[]),
+    ("kDivide", &[]),
+    ("kDown", &[]),
+    ("kEnd", &[]),
+    ("kEnter", &[]),
+    ("kEqual", &[]),
+    ("kHome", &[]),
+    ("kLeft", &[]),
+    ("kMinus", &[]),
+    ("kMultiply", &[]),
+    ("kOrigin", &[]),
+    ("kPageDown", &[]),
+    ("kPageUp", &[]),
+    ("kPlus", &[]),
+    ("kPoint", &[]),
+    ("kRight", &[]),
+    ("kUp", &[]),
+];
+
+fn special_key_name(name: &str) -> Option<String> {
+    let lowercase = name.to_ascii_lowercase();
+    for (key, aliases) in SPECIAL_KEYS {
+        if key.eq_ignore_ascii_case(&lowercase) || aliases.contains(&lowercase.as_str()) {
+            return Some((*key).to_owned());
+        }
+    }
+    if let Some(digit) = lowercase.strip_prefix('k').filter(|digit| digit.len() == 1) {
+        return digit.parse::<u32>().ok().map(|digit| format!("k{digit}"));
+    }
+    let number = lowercase.strip_prefix('f')?.parse::<u32>().ok()?;
+    (1..=35).contains(&number).then(|| format!("F{number}"))
+}
+
+/// Converts a key chord in Neovim notation, like `<C-S-f>` or `<d-=>`, to the form the keyboard
+/// manager sends to Neovim, so that it can be compared with the pressed keys.
+pub fn normalize_chord(keys: &str) -> Option<String> {
+    let inner = match keys.strip_prefix('<').and_then(|keys| keys.strip_suffix('>')) {
+        Some(inner) if !inner.is_empty() => inner,
+        _ if keys.chars().count() == 1 => {
+            return Some(encode_key(keys, false, KeyModifiers::default()));
+        }
+        _ => return None,
+    };
+
+    let mut modifiers = KeyModifiers::default();
+    let mut rest = inner;
+    while let Some((modifier, key)) = rest.split_once('-') {
+        if key.is_empty() || modifier.chars().count() != 1 {
+            break;
+        }
+        match modifier.to_ascii_uppercase().as_str() {
+            "S" => modifiers.shift = true,
+            "C" => modifiers.control = true,
+            "M" | "A" => modifiers.alt = true,
+            "D" => modifiers.super_key = true,
+            "T" => modifiers.meta = true,
+            _ => return None,
+        }
+        rest = key;
+    }
+
+    if rest.eq_ignore_ascii_case("lt") {
+        Some(encode_key("<", false, modifiers))
+    } else if rest.chars().count() == 1 {
+        // <C-A> is the same as <C-a> in Neovim
+        let key = if modifiers.control && !modifiers.shift {
+            rest.to_ascii_lowercase()
+        } else {
+            rest.to_owned()
+        };
+        Some(encode_key(&key, false, modifiers))
+    } else {
+        Some(encode_key(&special_key_name(rest)?, true, modifiers))
+    }
+}
+
+/// Opens the command palette, unless the chord is bound to something else.
+#[cfg(target_os = "macos")]
+const COMMAND_PALETTE_KEYS: &str = "<D-S-p>";
+#[cfg(not(target_os = "macos"))]
+const COMMAND_PALETTE_KEYS: &str = "<C-S-p>";
+
+fn default_bindings() -> HashMap<String, GuiAction> {
+    let chord = normalize_chord(COMMAND_PALETTE_KEYS).unwrap();
+    HashMap::from([(chord, GuiAction::ShowCommandPalette)])
+}
+
+/// The bound key chords, stored in the normalized form of `normalize_chord`.
+#[derive(Debug)]
+pub struct KeyBindings {
+    /// The default bindings, overlaid with the `[keys]` table of the config file.
+    config: HashMap<String, GuiAction>,
+    /// Bindings from `neovide.bind()`, which take precedence over the config file. `None` removes
+    /// the binding of the config file.
+    lua: HashMap<String, Option<GuiAction>>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self { config: default_bindings(), lua: HashMap::new() }
+    }
+}
+
+impl KeyBindings {
+    /// Replaces the bindings of the `[keys]` table. Invalid entries are reported and skipped.
+    pub fn set_config(&mut self, keys: Option<&HashMap<String, String>>) {
+        self.config = default_bindings();
+        for (keys, action) in keys.into_iter().flatten() {
+            let binding = parse_chord(keys).and_then(|chord| {
+                let action = (!action.is_empty()).then(|| action.parse()).transpose()?;
+                Ok((chord, action))
+            });
+            match binding {
+                Ok((chord, Some(action))) => {
+                    self.config.insert(chord, action);
+                }
+                // An empty action removes a default binding
+                Ok((chord, None)) => {
+                    self.config.remove(&chord);
+                }
+                Err(error) => error_msg!("Invalid key binding in the config file: {error}"),
+            }
+        }
+    }
+
+    /// Binds the chord to the action, or unbinds it when there's no action.
+    pub fn bind(&mut self, keys: &str, action: Option<&str>) {
+        let action = action.map(str::parse).transpose();
+        match parse_chord(keys).and_then(|chord| Ok((chord, action?))) {
+            Ok((chord, action)) => {
+                self.lua.insert(chord, action);
+            }
+            Err(error) => error_msg!("neovide.bind: {error}"),
+        }
+    }
+
+    pub fn action(&self, chord: &str) -> Option<GuiAction> {
+        match self.lua.get(chord) {
+            Some(action) => *action,
+            None => self.config.get(chord).copied(),
+        }
+    }
+}
+
+fn parse_chord(keys: &str) -> Result<String, String> {
+    normalize_chord(keys).ok_or_else(|| format!("invalid key chord `{keys}`"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_chord() {
+        assert_eq!(normalize_chord("<C-S-f>").as_deref(), Some("<S-C-F>"));
+        assert_eq!(normalize_chord("<c-A>").as_deref(), Some("<C-a>"));
+        assert_eq!(normalize_chord("<D-=>").as_deref(), Some("<D-=>"));
+        assert_eq!(normalize_chord("<D-->").as_deref(), Some("<D-->"));
+        assert_eq!(normalize_chord("<A-S-a>").as_deref(), Some("<M-A>"));
+        assert_eq!(normalize_chord("<S-M-$>").as_deref(), Some("<M-$>"));
+        assert_eq!(normalize_chord("<f11>").as_deref(), Some("<F11>"));
+        assert_eq!(normalize_chord("<s-cr>").as_deref(), Some("<S-Enter>"));
+        assert_eq!(normalize_chord("<C-lt>").as_deref(), Some("<C-lt>"));
+        assert_eq!(normalize_chord("<T-D-x>").as_deref(), Some("<D-T-x>"));
+        assert_eq!(normalize_chord("<C-kplus>").as_deref(), Some("<C-kPlus>"));
+        assert_eq!(normalize_chord("<k5>").as_deref(), Some("<k5>"));
+        assert_eq!(normalize_chord("x").as_deref(), Some("x"));
+        assert_eq!(normalize_chord("<C-Nope>"), None);
+        assert_eq!(normalize_chord("<X-a>"), None);
+        assert_eq!(normalize_chord("ab"), None);
+    }
+
+    #[test]
+    fn test_key_bindings() {
+        let mut bindings = KeyBindings::default();
+        let palette = normalize_chord(COMMAND_PALETTE_KEYS).unwrap();
+        assert_eq!(bindings.action(&palette), Some(GuiAction::ShowCommandPalette));
+
+        let keys = HashMap::from([
+            ("<F11>".to_owned(), "toggle_fullscreen".to_owned()),
+            ("<C-=>".to_owned(), "zoom-in".to_owned()),
+            (COMMAND_PALETTE_KEYS.to_owned(), String::new()),
+        ]);
+        bindings.set_config(Some(&keys));
+        assert_eq!(bindings.action("<F11>"), Some(GuiAction::ToggleFullscreen));
+        assert_eq!(bindings.action("<C-=>"), Some(GuiAction::ZoomIn));
+        assert_eq!(bindings.action(&palette), None);
+
+        bindings.bind("<f11>", None);
+        bindings.bind("<C-->", Some("zoom_out"));
+        assert_eq!(bindings.action("<F11>"), None);
+        assert_eq!(bindings.action("<C-->"), Some(GuiAction::ZoomOut));
+
+        assert!("unknown".parse::<GuiAction>().is_err());
+    }
+}
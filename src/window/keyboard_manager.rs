@@ -1,8 +1,12 @@
 use std::sync::Arc;
 
 use crate::{
-    bridge::{NeovimHandler, SerialCommand, send_ui},
+    bridge::{EditorMode, NeovimHandler, SerialCommand, send_ui},
     settings::Settings,
+    window::{
+        WindowSettings,
+        key_bindings::{GuiAction, KeyBindings},
+    },
 };
 
 #[allow(unused_imports)]
@@ -12,10 +16,7 @@ use winit::{
     keyboard::{Key, KeyCode, KeyLocation, NamedKey, PhysicalKey},
 };
 #[cfg(target_os = "macos")]
-use {
-    crate::{window::WindowSettings, window::settings::OptionAsMeta},
-    winit::keyboard::ModifiersKeyState,
-};
+use {crate::window::settings::OptionAsMeta, winit::keyboard::ModifiersKeyState};
 
 use crate::profiling::tracy_named_frame;
 
@@ -23,11 +24,22 @@ fn is_ascii_alphabetic_char(text: &str) -> bool {
     text.len() == 1 && text.chars().next().unwrap().is_ascii_alphabetic()
 }
 
+#[derive(Default)]
+pub struct KeyboardEventResult {
+    /// The GUI action the keys are bound to, instead of being sent to Neovim.
+    pub action: Option<GuiAction>,
+    /// The pressed keys in Vim notation, for the screencast overlay.
+    pub keys: Option<String>,
+}
+
 pub struct KeyboardManager {
     modifiers: Modifiers,
     ime_preedit: (String, Option<(usize, usize)>),
     meta_is_pressed: bool, // see note on 'meta' below
-    #[allow(dead_code)]
+    // The Meta and Hyper keys are not winit modifiers, so their state is tracked from the key events
+    meta_key_is_pressed: bool,
+    hyper_key_is_pressed: bool,
+    key_bindings: KeyBindings,
     settings: Arc<Settings>,
 }
 
@@ -37,38 +49,67 @@ impl KeyboardManager {
             modifiers: Modifiers::default(),
             ime_preedit: ("".to_string(), None),
             meta_is_pressed: false,
+            meta_key_is_pressed: false,
+            hyper_key_is_pressed: false,
+            key_bindings: KeyBindings::default(),
             settings,
         }
     }
 
-    #[cfg(target_os = "macos")]
     pub fn current_modifiers(&self) -> Modifiers {
         self.modifiers
     }
 
-    pub fn handle_event(&mut self, event: &WindowEvent, neovim_handler: &NeovimHandler) {
+    pub fn key_bindings_mut(&mut self) -> &mut KeyBindings {
+        &mut self.key_bindings
+    }
+
+    /// Sends the keys to Neovim, unless they are bound to a GUI action, which is returned instead.
+    /// The mode is the current Neovim mode of the window.
+    pub fn handle_event(
+        &mut self,
+        event: &WindowEvent,
+        mode: &EditorMode,
+        neovim_handler: &NeovimHandler,
+    ) -> KeyboardEventResult {
         match event {
             WindowEvent::KeyboardInput { event: key_event, is_synthetic: false, .. }
                 if self.ime_preedit.0.is_empty() =>
             {
                 log::trace!("{key_event:#?}");
+                match key_event.logical_key {
+                    Key::Named(NamedKey::Meta) => {
+                        self.meta_key_is_pressed = key_event.state.is_pressed();
+                    }
+                    Key::Named(NamedKey::Hyper) => {
+                        self.hyper_key_is_pressed = key_event.state.is_pressed();
+                    }
+                    _ => {}
+                }
                 if key_event.state == ElementState::Pressed {
-                    if let Some(text) = self.format_key(key_event) {
+                    if let Some(text) = self.format_key(key_event, mode) {
                         log::trace!("Key pressed {} {:?}", text, self.modifiers.state());
+                        if let Some(action) = self.key_bindings.action(&text) {
+                            log::trace!("Key {text} bound to {action:?}");
+                            return KeyboardEventResult { action: Some(action), keys: Some(text) };
+                        }
                         tracy_named_frame!("keyboard input");
-                        send_ui(SerialCommand::Keyboard(text), neovim_handler);
+                        send_ui(SerialCommand::Keyboard(text.clone()), neovim_handler);
+                        return KeyboardEventResult { action: None, keys: Some(text) };
                     }
                 }
             }
             WindowEvent::Ime(Ime::Commit(text)) => {
                 log::trace!("Ime commit {text}");
+                let formatted = self.format_key_text(text, false);
                 send_ui(
                     SerialCommand::KeyboardImeCommit {
-                        formatted: self.format_key_text(text, false),
+                        formatted: formatted.clone(),
                         raw: text.to_owned(),
                     },
                     neovim_handler,
                 );
+                return KeyboardEventResult { action: None, keys: Some(formatted) };
             }
             WindowEvent::Ime(Ime::Preedit(text, cursor_offset)) => {
                 self.ime_preedit = (text.to_string(), *cursor_offset);
@@ -80,6 +121,11 @@ impl KeyboardManager {
                     neovim_handler,
                 );
             }
+            WindowEvent::Focused(false) => {
+                // The key releases are not received while unfocused
+                self.meta_key_is_pressed = false;
+                self.hyper_key_is_pressed = false;
+            }
             WindowEvent::ModifiersChanged(modifiers) => {
                 // Record the modifier states so that we can properly add them to the keybinding text
                 log::trace!("{:?}", *modifiers);
@@ -107,118 +153,112 @@ impl KeyboardManager {
             }
             _ => {}
         }
+        KeyboardEventResult::default()
     }
 
-    fn handle_numpad_numkey<'a>(
-        is_numlock_enabled: bool,
-        numlock_str: &'a str,
-        non_numlock_str: &'a str,
-    ) -> Option<&'a str> {
-        if is_numlock_enabled {
-            return Some(numlock_str);
+    fn format_key(&self, key_event: &KeyEvent, mode: &EditorMode) -> Option<String> {
+        if let Some(text) = get_special_key(key_event) {
+            Some(self.format_key_text(text, true))
+        } else {
+            self.format_normal_key(key_event, mode)
         }
-        Some(non_numlock_str)
     }
 
-    fn handle_numpad_key(key_event: &KeyEvent) -> Option<&str> {
-        let PhysicalKey::Code(physical_key_code) = key_event.physical_key else {
+    /// The US QWERTY character of the physical key, which is sent instead of the character of a
+    /// non-Latin layout when `input_physical_keys` applies, so that commands like `dw` and `<C-w>`
+    /// work without switching the layout.
+    fn physical_key_text(
+        &self,
+        logical_key: &Key,
+        physical_key: PhysicalKey,
+        mode: &EditorMode,
+    ) -> Option<&'static str> {
+        let Key::Character(text) = logical_key else {
             return None;
         };
-        let is_numlock_key = key_event.text.is_some();
-        match physical_key_code {
-            KeyCode::NumpadDivide => Some("kDivide"),
-            KeyCode::NumpadStar => Some("kMultiply"),
-            KeyCode::NumpadSubtract => Some("kMinus"),
-            KeyCode::NumpadAdd => Some("kPlus"),
-            KeyCode::NumpadEnter => Some("kEnter"),
-            KeyCode::NumpadEqual => Some("kEqual"),
-            KeyCode::NumpadComma => match key_event.logical_key.as_ref() {
-                Key::Character(",") => Some("kComma"),
-                Key::Character(".") => Some("kPoint"),
-                _ => None,
-            },
-            KeyCode::NumpadDecimal => {
-                if is_numlock_key {
-                    match key_event.logical_key.as_ref() {
-                        Key::Character(",") => Some("kComma"),
-                        Key::Character(".") => Some("kPoint"),
-                        _ => None,
-                    }
-                } else {
-                    Some("kDel")
-                }
-            }
-            KeyCode::Numpad9 => {
-                KeyboardManager::handle_numpad_numkey(is_numlock_key, "k9", "kPageUp")
-            }
-            KeyCode::Numpad8 => KeyboardManager::handle_numpad_numkey(is_numlock_key, "k8", "kUp"),
-            KeyCode::Numpad7 => {
-                KeyboardManager::handle_numpad_numkey(is_numlock_key, "k7", "kHome")
-            }
-            KeyCode::Numpad6 => {
-                KeyboardManager::handle_numpad_numkey(is_numlock_key, "k6", "kRight")
-            }
-            KeyCode::Numpad5 => {
-                KeyboardManager::handle_numpad_numkey(is_numlock_key, "k5", "kOrigin")
-            }
-            KeyCode::Numpad4 => {
-                KeyboardManager::handle_numpad_numkey(is_numlock_key, "k4", "kLeft")
-            }
-            KeyCode::Numpad3 => {
-                KeyboardManager::handle_numpad_numkey(is_numlock_key, "k3", "kPageDown")
-            }
-            KeyCode::Numpad2 => {
-                KeyboardManager::handle_numpad_numkey(is_numlock_key, "k2", "kDown")
-            }
-            KeyCode::Numpad1 => KeyboardManager::handle_numpad_numkey(is_numlock_key, "k1", "kEnd"),
-            KeyCode::Numpad0 => {
-                KeyboardManager::handle_numpad_numkey(is_numlock_key, "k0", "Insert")
-            }
-            _ => None,
+        // The IME composes its text from the characters of the layout
+        if text.is_ascii() || !self.ime_preedit.0.is_empty() {
+            return None;
         }
+        let modes = self.settings.get::<WindowSettings>().input_physical_keys;
+        let modifiers = self.key_modifiers(false);
+        let has_modifier =
+            modifiers.control || modifiers.alt || modifiers.super_key || modifiers.meta;
+        if !modes.is_enabled() || !(has_modifier || modes.contains(mode.name())) {
+            return None;
+        }
+        let PhysicalKey::Code(code) = physical_key else {
+            return None;
+        };
+        us_qwerty_text(code, modifiers.shift)
     }
 
-    fn format_key(&self, key_event: &KeyEvent) -> Option<String> {
-        if let Some(text) = get_special_key(key_event) {
-            Some(self.format_key_text(text, true))
-        } else {
-            self.format_normal_key(key_event)
+    fn format_normal_key(&self, key_event: &KeyEvent, mode: &EditorMode) -> Option<String> {
+        let physical_text =
+            self.physical_key_text(&key_event.logical_key, key_event.physical_key, mode);
+        let text = match (physical_text, &key_event.text, &key_event.logical_key) {
+            (Some(text), _, _) => text,
+            // Some platforms report the control character with Ctrl, like a tab for <C-i>, which
+            // would be indistinguishable from the key with the same code
+            (None, Some(text), Key::Character(key)) if text.chars().all(char::is_control) => {
+                key.as_str()
+            }
+            (None, Some(text), _) => text.as_str(),
+            (None, None, Key::Character(key)) => key.as_str(),
+            (None, None, _) => return None,
+        };
+        let modifiers = self.key_modifiers(false);
+        if self.settings.get::<WindowSettings>().input_legacy_keys {
+            if let Some(key) = legacy_control_key(text, modifiers) {
+                return Some(encode_key(key, true, KeyModifiers::default()));
+            }
         }
+        Some(encode_key(text, false, modifiers))
     }
 
-    fn format_normal_key(&self, key_event: &KeyEvent) -> Option<String> {
-        key_event
-            .text
-            .as_ref()
-            .or(match &key_event.logical_key {
-                Key::Character(text) => Some(text),
-                _ => None,
-            })
-            .map(|text| self.format_key_text(text.as_str(), false))
+    fn format_key_text(&self, text: &str, is_special: bool) -> String {
+        encode_key(text, is_special, self.key_modifiers(is_special))
     }
 
-    fn format_key_text(&self, text: &str, is_special: bool) -> String {
-        // Neovim always converts shifted ascii alpha characters to uppercase, so do it here already
-        // This fixes some bugs where winit does not report the uppercase text as it should
-        let text = if self.modifiers.state().shift_key() && is_ascii_alphabetic_char(text) {
-            text.to_uppercase()
-        } else {
-            text.to_string()
-        };
+    #[cfg_attr(not(target_os = "macos"), allow(unused_variables))]
+    fn key_modifiers(&self, is_special: bool) -> KeyModifiers {
+        let state = self.modifiers.state();
 
-        let modifiers = self.format_modifier_string(&text, is_special);
-        // < needs to be formatted as a special character, but note that it's not treated as a
-        // special key for the modifier formatting, so S- and -M are still potentially stripped
-        let (text, is_special) =
-            if text == "<" { ("lt".to_string(), true) } else { (text, is_special) };
-        if modifiers.is_empty() {
-            if is_special { format!("<{text}>") } else { text }
-        } else {
-            format!("<{modifiers}{text}>")
+        #[cfg(target_os = "macos")]
+        let have_meta = self.meta_is_pressed || is_special && state.alt_key(); // e.g. non-meta 'option' with <F1> yeilds <M-F1>
+
+        #[cfg(not(target_os = "macos"))]
+        let have_meta = self.meta_is_pressed;
+
+        KeyModifiers {
+            shift: state.shift_key(),
+            control: state.control_key(),
+            alt: have_meta,
+            super_key: state.super_key(),
+            meta: self.meta_key_is_pressed || self.hyper_key_is_pressed,
         }
     }
 
     pub fn format_modifier_string(&self, text: &str, is_special: bool) -> String {
+        self.key_modifiers(is_special).prefix(text, is_special)
+    }
+}
+
+/// The modifiers held together with a key.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct KeyModifiers {
+    pub shift: bool,
+    pub control: bool,
+    /// Alt, or Option when it's used as meta on macOS, sent as `M-`.
+    pub alt: bool,
+    /// Super, Windows or Command, sent as `D-`.
+    pub super_key: bool,
+    /// The Meta or Hyper key of X11 and Wayland keyboards, sent as `T-`.
+    pub meta: bool,
+}
+
+impl KeyModifiers {
+    fn prefix(self, text: &str, is_special: bool) -> String {
         // Shift should always be sent together with special keys (Enter, Space, F keys and so on).
         // And as a special case together with CTRL and standard a-z characters.
         // In all other cases the resulting character is enough.
@@ -230,31 +270,160 @@ impl KeyboardManager {
         // uppercase without shift, or <M-A> .
         // But in combination with other characters, such as <M-S-$> they are not,
         // so we don't want to send shift when that's the case.
-        let state = self.modifiers.state();
-        let include_shift = is_special || (state.control_key() && is_ascii_alphabetic_char(text));
-
-        #[cfg(target_os = "macos")]
-        let have_meta = self.meta_is_pressed || is_special && state.alt_key(); // e.g. non-meta 'option' with <F1> yeilds <M-F1>
-
-        #[cfg(not(target_os = "macos"))]
-        let have_meta = self.meta_is_pressed;
+        let include_shift = is_special || (self.control && is_ascii_alphabetic_char(text));
 
         let mut ret = String::new();
-        (state.shift_key() && include_shift).then(|| ret += "S-");
-        state.control_key().then(|| ret += "C-");
-        (have_meta).then(|| ret += "M-");
-        state.super_key().then(|| ret += "D-");
+        (self.shift && include_shift).then(|| ret += "S-");
+        self.control.then(|| ret += "C-");
+        self.alt.then(|| ret += "M-");
+        self.super_key.then(|| ret += "D-");
+        self.meta.then(|| ret += "T-");
         ret
     }
 }
 
-fn get_special_key(key_event: &KeyEvent) -> Option<&str> {
+/// Encodes a key in Neovim's key notation, for example `<C-S-Tab>`, `<D-a>` or `x`.
+pub fn encode_key(text: &str, is_special: bool, modifiers: KeyModifiers) -> String {
+    // Neovim always converts shifted ascii alpha characters to uppercase, so do it here already
+    // This fixes some bugs where winit does not report the uppercase text as it should
+    let text = if modifiers.shift && is_ascii_alphabetic_char(text) {
+        text.to_uppercase()
+    } else {
+        text.to_string()
+    };
+
+    let prefix = modifiers.prefix(&text, is_special);
+    // < needs to be formatted as a special character, but note that it's not treated as a
+    // special key for the modifier formatting, so S- and -M are still potentially stripped
+    let (text, is_special) =
+        if text == "<" { ("lt".to_string(), true) } else { (text, is_special) };
+    if prefix.is_empty() {
+        if is_special { format!("<{text}>") } else { text }
+    } else {
+        format!("<{prefix}{text}>")
+    }
+}
+
+/// With `input_legacy_keys`, the keys that terminals can't tell apart from Tab, Enter and Escape
+/// are sent as those keys.
+fn legacy_control_key(text: &str, modifiers: KeyModifiers) -> Option<&'static str> {
+    if modifiers != (KeyModifiers { control: true, ..Default::default() }) {
+        return None;
+    }
+    match text {
+        "i" => Some("Tab"),
+        "m" => Some("Enter"),
+        "[" => Some("Esc"),
+        _ => None,
+    }
+}
+
+fn numpad_key_name(code: KeyCode, logical_key: &Key, is_numlock_key: bool) -> Option<&'static str> {
+    let numlock = |numlock_name, name| Some(if is_numlock_key { numlock_name } else { name });
+    let decimal_name = || match logical_key.as_ref() {
+        Key::Character(",") => Some("kComma"),
+        Key::Character(".") => Some("kPoint"),
+        _ => None,
+    };
+    match code {
+        KeyCode::NumpadDivide => Some("kDivide"),
+        KeyCode::NumpadStar | KeyCode::NumpadMultiply => Some("kMultiply"),
+        KeyCode::NumpadSubtract => Some("kMinus"),
+        KeyCode::NumpadAdd => Some("kPlus"),
+        KeyCode::NumpadEnter => Some("kEnter"),
+        KeyCode::NumpadEqual => Some("kEqual"),
+        KeyCode::NumpadComma => decimal_name(),
+        KeyCode::NumpadDecimal if is_numlock_key => decimal_name(),
+        KeyCode::NumpadDecimal => Some("kDel"),
+        KeyCode::Numpad9 => numlock("k9", "kPageUp"),
+        KeyCode::Numpad8 => numlock("k8", "kUp"),
+        KeyCode::Numpad7 => numlock("k7", "kHome"),
+        KeyCode::Numpad6 => numlock("k6", "kRight"),
+        KeyCode::Numpad5 => numlock("k5", "kOrigin"),
+        KeyCode::Numpad4 => numlock("k4", "kLeft"),
+        KeyCode::Numpad3 => numlock("k3", "kPageDown"),
+        KeyCode::Numpad2 => numlock("k2", "kDown"),
+        KeyCode::Numpad1 => numlock("k1", "kEnd"),
+        KeyCode::Numpad0 => numlock("k0", "Insert"),
+        _ => None,
+    }
+}
+
+fn us_qwerty_text(code: KeyCode, shift: bool) -> Option<&'static str> {
+    let (text, shifted) = match code {
+        KeyCode::KeyA => ("a", "A"),
+        KeyCode::KeyB => ("b", "B"),
+        KeyCode::KeyC => ("c", "C"),
+        KeyCode::KeyD => ("d", "D"),
+        KeyCode::KeyE => ("e", "E"),
+        KeyCode::KeyF => ("f", "F"),
+        KeyCode::KeyG => ("g", "G"),
+        KeyCode::KeyH => ("h", "H"),
+        KeyCode::KeyI => ("i", "I"),
+        KeyCode::KeyJ => ("j", "J"),
+        KeyCode::KeyK => ("k", "K"),
+        KeyCode::KeyL => ("l", "L"),
+        KeyCode::KeyM => ("m", "M"),
+        KeyCode::KeyN => ("n", "N"),
+        KeyCode::KeyO => ("o", "O"),
+        KeyCode::KeyP => ("p", "P"),
+        KeyCode::KeyQ => ("q", "Q"),
+        KeyCode::KeyR => ("r", "R"),
+        KeyCode::KeyS => ("s", "S"),
+        KeyCode::KeyT => ("t", "T"),
+        KeyCode::KeyU => ("u", "U"),
+        KeyCode::KeyV => ("v", "V"),
+        KeyCode::KeyW => ("w", "W"),
+        KeyCode::KeyX => ("x", "X"),
+        KeyCode::KeyY => ("y", "Y"),
+        KeyCode::KeyZ => ("z", "Z"),
+        KeyCode::Digit1 => ("1", "!"),
+        KeyCode::Digit2 => ("2", "@"),
+        KeyCode::Digit3 => ("3", "#"),
+        KeyCode::Digit4 => ("4", "$"),
+        KeyCode::Digit5 => ("5", "%"),
+        KeyCode::Digit6 => ("6", "^"),
+        KeyCode::Digit7 => ("7", "&"),
+        KeyCode::Digit8 => ("8", "*"),
+        KeyCode::Digit9 => ("9", "("),
+        KeyCode::Digit0 => ("0", ")"),
+        KeyCode::Backquote => ("`", "~"),
+        KeyCode::Minus => ("-", "_"),
+        KeyCode::Equal => ("=", "+"),
+        KeyCode::BracketLeft => ("[", "{"),
+        KeyCode::BracketRight => ("]", "}"),
+        KeyCode::Backslash => ("\\", "|"),
+        KeyCode::Semicolon => (";", ":"),
+        KeyCode::Quote => ("'", "\""),
+        KeyCode::Comma => (",", "<"),
+        KeyCode::Period => (".", ">"),
+        KeyCode::Slash => ("/", "?"),
+        _ => return None,
+    };
+    Some(if shift { shifted } else { text })
+}
+
+fn get_special_key(key_event: &KeyEvent) -> Option<&'static str> {
     if key_event.location == KeyLocation::Numpad {
-        return KeyboardManager::handle_numpad_key(key_event);
+        let PhysicalKey::Code(code) = key_event.physical_key else {
+            return None;
+        };
+        return numpad_key_name(code, &key_event.logical_key, key_event.text.is_some());
     }
     let Key::Named(key) = &key_event.logical_key else {
         return None;
     };
+    if *key == NamedKey::Space {
+        // Space can finish a dead key sequence, so treat space as a special key only when
+        // that doesn't happen.
+        return (key_event.text == Some(" ".into()) || key_event.text.is_none()).then_some("Space");
+    }
+    named_key_name(*key)
+}
+
+/// The Neovim names of the named keys. Keys without a Neovim equivalent, like media and volume
+/// keys, are left to the system.
+fn named_key_name(key: NamedKey) -> Option<&'static str> {
     match key {
         NamedKey::ArrowDown => Some("Down"),
         NamedKey::ArrowLeft => Some("Left"),
@@ -300,20 +469,14 @@ fn get_special_key(key_event: &KeyEvent) -> Option<&str> {
         NamedKey::F33 => Some("F33"),
         NamedKey::F34 => Some("F34"),
         NamedKey::F35 => Some("F35"),
+        NamedKey::Help => Some("Help"),
         NamedKey::Home => Some("Home"),
         NamedKey::Insert => Some("Insert"),
         NamedKey::PageDown => Some("PageDown"),
         NamedKey::PageUp => Some("PageUp"),
-        NamedKey::Space => {
-            // Space can finish a dead key sequence, so treat space as a special key only when
-            // that doesn't happen.
-            if key_event.text == Some(" ".into()) || key_event.text.is_none() {
-                Some("Space")
-            } else {
-                None
-            }
-        }
+        NamedKey::Space => Some("Space"),
         NamedKey::Tab => Some("Tab"),
+        NamedKey::Undo => Some("Undo"),
         _ => None,
     }
 }
@@ -372,3 +535,130 @@ fn get_special_key(key_event: &KeyEvent) -> Option<&str> {
 // avoid confusing users who have a post-2017 keyboard and are not aware of this
 // history, it is probably best to refer to this physical key as the 'option'
 // key, and not as the 'alt' key.
+
+#[cfg(test)]
+mod tests {
+    use rmpv::Value;
+    use winit::keyboard::ModifiersState;
+
+    use super::*;
+    use crate::{settings::ParseFromValue, window::settings::PhysicalKeyModes};
+
+    const CONTROL: KeyModifiers =
+        KeyModifiers { shift: false, control: true, alt: false, super_key: false, meta: false };
+
+    #[test]
+    fn test_encode_key() {
+        let shift = KeyModifiers { shift: true, ..Default::default() };
+        let alt_shift = KeyModifiers { alt: true, ..shift };
+        let super_meta = KeyModifiers { super_key: true, meta: true, ..Default::default() };
+
+        assert_eq!(encode_key("a", false, KeyModifiers::default()), "a");
+        assert_eq!(encode_key("a", false, shift), "A");
+        assert_eq!(encode_key("a", false, KeyModifiers { shift: true, ..CONTROL }), "<S-C-A>");
+        assert_eq!(encode_key("a", false, alt_shift), "<M-A>");
+        assert_eq!(encode_key("$", false, alt_shift), "<M-$>");
+        assert_eq!(encode_key("Tab", true, shift), "<S-Tab>");
+        assert_eq!(encode_key("<", false, KeyModifiers::default()), "<lt>");
+        assert_eq!(encode_key("<", false, CONTROL), "<C-lt>");
+        assert_eq!(encode_key("x", false, super_meta), "<D-T-x>");
+    }
+
+    #[test]
+    fn test_control_keys_are_distinct() {
+        for key in ["i", "m", "["] {
+            assert_eq!(encode_key(key, false, CONTROL), format!("<C-{key}>"));
+        }
+        assert_eq!(legacy_control_key("i", CONTROL), Some("Tab"));
+        assert_eq!(legacy_control_key("m", CONTROL), Some("Enter"));
+        assert_eq!(legacy_control_key("[", CONTROL), Some("Esc"));
+        assert_eq!(legacy_control_key("i", KeyModifiers { shift: true, ..CONTROL }), None);
+        assert_eq!(legacy_control_key("i", KeyModifiers::default()), None);
+    }
+
+    #[test]
+    fn test_named_keys() {
+        assert_eq!(named_key_name(NamedKey::Tab), Some("Tab"));
+        assert_eq!(named_key_name(NamedKey::Escape), Some("Esc"));
+        assert_eq!(named_key_name(NamedKey::F13), Some("F13"));
+        assert_eq!(named_key_name(NamedKey::F35), Some("F35"));
+        assert_eq!(named_key_name(NamedKey::Help), Some("Help"));
+        assert_eq!(named_key_name(NamedKey::MediaPlayPause), None);
+        assert_eq!(named_key_name(NamedKey::AudioVolumeUp), None);
+        assert_eq!(named_key_name(NamedKey::Meta), None);
+    }
+
+    #[test]
+    fn test_numpad_keys() {
+        let point = Key::Character(".".into());
+        assert_eq!(numpad_key_name(KeyCode::Numpad5, &point, true), Some("k5"));
+        assert_eq!(numpad_key_name(KeyCode::Numpad5, &point, false), Some("kOrigin"));
+        assert_eq!(numpad_key_name(KeyCode::NumpadDecimal, &point, true), Some("kPoint"));
+        assert_eq!(numpad_key_name(KeyCode::NumpadDecimal, &point, false), Some("kDel"));
+        assert_eq!(numpad_key_name(KeyCode::NumpadEnter, &point, true), Some("kEnter"));
+        assert_eq!(numpad_key_name(KeyCode::KeyA, &point, true), None);
+    }
+
+    fn keyboard_manager(physical_key_modes: &[&str]) -> KeyboardManager {
+        let mut input_physical_keys = PhysicalKeyModes::default();
+        let modes = physical_key_modes.iter().map(|&mode| Value::from(mode)).collect();
+        assert!(input_physical_keys.parse_from_value(Value::Array(modes)));
+        let settings = Settings::new();
+        settings.set(&WindowSettings { input_physical_keys, ..Default::default() });
+        KeyboardManager::new(Arc::new(settings))
+    }
+
+    fn physical_text(manager: &KeyboardManager, text: &str, mode: EditorMode) -> Option<&str> {
+        let key = Key::Character(text.into());
+        manager.physical_key_text(&key, PhysicalKey::Code(KeyCode::KeyW), &mode)
+    }
+
+    #[test]
+    fn test_physical_keys_in_insert_mode() {
+        let manager = keyboard_manager(&["normal", "visual"]);
+        // The text of the layout is typed
+        assert_eq!(physical_text(&manager, "ц", EditorMode::Insert), None);
+        assert_eq!(physical_text(&manager, "ц", EditorMode::CmdLine), None);
+        assert_eq!(physical_text(&manager, "w", EditorMode::Normal), None);
+    }
+
+    #[test]
+    fn test_physical_keys_in_normal_mode() {
+        let mut manager = keyboard_manager(&["normal", "visual"]);
+        assert_eq!(physical_text(&manager, "ц", EditorMode::Normal), Some("w"));
+        assert_eq!(physical_text(&manager, "ц", EditorMode::Visual), Some("w"));
+
+        manager.modifiers = ModifiersState::SHIFT.into();
+        assert_eq!(physical_text(&manager, "Ц", EditorMode::Normal), Some("W"));
+
+        // Keys with modifiers are translated in every mode, like <C-w> in insert mode
+        manager.modifiers = ModifiersState::CONTROL.into();
+        assert_eq!(physical_text(&manager, "ц", EditorMode::Insert), Some("w"));
+        manager.modifiers = ModifiersState::SUPER.into();
+        assert_eq!(physical_text(&manager, "ц", EditorMode::Insert), Some("w"));
+
+        let manager = keyboard_manager(&[]);
+        assert_eq!(physical_text(&manager, "ц", EditorMode::Normal), None);
+    }
+
+    #[test]
+    fn test_physical_keys_during_ime_preedit() {
+        let mut manager = keyboard_manager(&["normal"]);
+        manager.ime_preedit = ("ц".to_owned(), Some((2, 2)));
+        assert_eq!(physical_text(&manager, "ц", EditorMode::Normal), None);
+        manager.modifiers = ModifiersState::CONTROL.into();
+        assert_eq!(physical_text(&manager, "ц", EditorMode::Normal), None);
+
+        // Committing the text ends the preedit with an empty one
+        manager.ime_preedit = (String::new(), None);
+        assert_eq!(physical_text(&manager, "ц", EditorMode::Normal), Some("w"));
+    }
+
+    #[test]
+    fn test_us_qwerty_text() {
+        assert_eq!(us_qwerty_text(KeyCode::KeyW, false), Some("w"));
+        assert_eq!(us_qwerty_text(KeyCode::KeyW, true), Some("W"));
+        assert_eq!(us_qwerty_text(KeyCode::Digit4, true), Some("$"));
+        assert_eq!(us_qwerty_text(KeyCode::Enter, false), None);
+    }
+}
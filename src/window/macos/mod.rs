@@ -4,5 +4,5 @@ pub mod tab_navigation;
 pub use crate::platform::macos::{
     MacosWindowFeature, TouchpadStage, get_last_host_window, get_ns_window, hide_application,
     is_focus_suppressed, is_tab_overview_active, native_tab_bar_enabled, register_file_handler,
-    trigger_tab_overview, window_identifier,
+    request_new_window, trigger_tab_overview, window_identifier,
 };
@@ -1,9 +1,11 @@
 mod application;
 mod error_window;
+mod key_bindings;
 mod keyboard_manager;
 #[cfg(target_os = "macos")]
 pub mod macos;
 mod mouse_manager;
+mod session;
 mod settings;
 mod window_wrapper;
 
@@ -39,13 +41,16 @@ use keyboard_manager::KeyboardManager;
 use mouse_manager::MouseManager;
 use std::fs::File;
 use std::io::Read;
+use std::path::PathBuf;
 use std::sync::atomic::{AtomicU64, Ordering};
 
 use crate::{
-    bridge::RestartDetails,
+    bridge::{EditorMode, RestartDetails},
     cmd_line::{CmdLineSettings, GeometryArgs},
     frame::Frame,
-    renderer::{DrawCommand, WindowConfig, build_window_config},
+    renderer::{
+        DrawCommand, WindowConfig, build_window_config, fonts::font_options::FontDescription,
+    },
     settings::{
         HotReloadConfigs, PersistentWindowSettings, Settings, SettingsChanged, clamped_grid_size,
         load_last_window_settings, save_window_size,
@@ -56,8 +61,12 @@ use crate::{
 pub use application::Application;
 pub use application::ShouldRender;
 pub use error_window::show_error_window;
-pub use mouse_manager::{MessageSelectionEvent, OverlayEvent};
-pub use settings::{ThemeSettings, WindowSettings, WindowSettingsChanged};
+pub use key_bindings::GuiAction;
+pub use mouse_manager::{OverlayEvent, SelectionEvent};
+pub use session::{session_enabled, session_file};
+pub use settings::{
+    RememberSettings, SelectionModifier, ThemeSettings, WindowSettings, WindowSettingsChanged,
+};
 pub use window_wrapper::WinitWindowWrapper;
 
 static DEFAULT_ICON: &[u8] = include_bytes!("../../assets/neovide.ico");
@@ -99,6 +108,24 @@ pub enum WindowCommand {
     SetMouseEnabled(bool),
     ListAvailableFonts,
     FocusWindow,
+    ShowSettingsPanel,
+    /// Binds a key chord to a GUI action, or unbinds it when there's no action.
+    BindKey {
+        keys: String,
+        action: Option<String>,
+    },
+    /// Sets the fonts of a highlight group, or goes back to the configured fonts without fonts.
+    SetHighlightFont {
+        group: String,
+        fonts: Option<Vec<FontDescription>>,
+    },
+    /// The Neovim mode changed.
+    ModeChanged(EditorMode),
+    /// Adds a Lua command to the command palette, or removes it.
+    RegisterPaletteCommand {
+        title: String,
+        registered: bool,
+    },
     #[cfg(target_os = "macos")]
     TouchpadPressure {
         col: i64,
@@ -116,6 +143,11 @@ pub enum WindowCommand {
     },
     Minimize,
     ThemeChanged(Option<Theme>),
+    /// The `:mksession` file of the window was written, because its Neovim is about to quit.
+    SessionSaved {
+        session_file: PathBuf,
+        directory: PathBuf,
+    },
     #[cfg(windows)]
     RegisterRightClick,
     #[cfg(windows)]
@@ -142,6 +174,8 @@ pub enum UserEvent {
     ShowProgressBar {
         percent: f32,
     },
+    /// The first frame of the window was presented, so it can be shown.
+    FirstFramePresented,
     #[cfg(target_os = "macos")]
     CreateWindow,
     #[cfg(target_os = "macos")]
@@ -150,7 +184,6 @@ pub enum UserEvent {
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum EventTarget {
-    #[cfg_attr(not(target_os = "macos"), allow(dead_code))]
     Window(winit::window::WindowId),
     Route(RouteId),
     Focused,
@@ -174,7 +207,6 @@ pub struct EventPayload {
 }
 
 impl EventPayload {
-    #[cfg_attr(not(target_os = "macos"), allow(dead_code))]
     pub fn for_window(payload: UserEvent, window_id: winit::window::WindowId) -> Self {
         Self { payload, target: EventTarget::Window(window_id) }
     }
@@ -240,7 +272,7 @@ pub fn create_window(
     let cmd_line_settings = settings.get::<CmdLineSettings>();
     let icon = load_icon(cmd_line_settings.icon.as_ref());
 
-    let persistent_window_settings = load_last_window_settings().ok();
+    let persistent_window_settings = load_last_window_settings(settings).ok();
 
     let previous_position = match persistent_window_settings {
         Some(PersistentWindowSettings::Windowed { position, .. }) => Some(position),
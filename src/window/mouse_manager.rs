@@ -16,7 +16,10 @@ use glamour::{Contains, Point2};
 use crate::{
     bridge::{NeovimHandler, SerialCommand, send_ui},
     editor::WindowType,
-    renderer::{MessageSelection, Renderer, WindowDrawDetails},
+    renderer::{
+        GuiSelection, Renderer, SelectionMode, WindowDrawDetails, selection::word_bounds,
+        zoom::ZOOM_STEP,
+    },
     settings::Settings,
     units::{GridPos, GridScale, GridSize, GridVec, PixelPos, PixelRect, PixelSize, PixelVec},
     window::{WindowSettings, keyboard_manager::KeyboardManager},
@@ -38,18 +41,32 @@ struct DragDetails {
     button: MouseButton,
 }
 
+// Presses closer together than this on the same cell count as double and triple clicks
+const MULTI_CLICK_INTERVAL: Duration = Duration::from_millis(500);
+
 #[derive(Clone, Debug)]
-struct MessageSelectionState {
-    draw_details: WindowDrawDetails,
-    start: GridPos<u32>,
-    end: GridPos<u32>,
+struct SelectionState {
+    selection: GuiSelection,
+    // The cells selected by the initial press, which stay selected while dragging
+    anchor_start: GridPos<u32>,
+    anchor_end: GridPos<u32>,
+    click_count: u32,
+    dragging: bool,
+}
+
+#[derive(Clone, Debug)]
+struct LastClick {
+    time: Instant,
+    grid_id: u64,
+    position: GridPos<u32>,
+    count: u32,
 }
 
 #[derive(Clone, Debug)]
-pub enum MessageSelectionEvent {
+pub enum SelectionEvent {
     Outside,
-    Update(MessageSelection),
-    Finish(MessageSelection),
+    Update(GuiSelection),
+    Finish(GuiSelection),
     Clear,
 }
 
@@ -57,7 +74,7 @@ pub enum MessageSelectionEvent {
 pub enum OverlayEvent {
     #[default]
     Unchanged,
-    MessageSelection(MessageSelectionEvent),
+    Selection(SelectionEvent),
 }
 
 pub struct PointerTransitionResult {
@@ -66,6 +83,8 @@ pub struct PointerTransitionResult {
 
 pub struct MouseEventResult {
     pub overlay_event: OverlayEvent,
+    /// The scale factor multiplier requested by pinching or Ctrl+scrolling, anchored at the mouse.
+    pub zoom: Option<f32>,
 }
 
 pub struct EditorState<'a> {
@@ -74,6 +93,7 @@ pub struct EditorState<'a> {
     pub full_region: WindowDrawDetails,
     pub window: &'a Window,
     pub keyboard_manager: &'a KeyboardManager,
+    pub renderer: &'a Renderer,
 }
 
 #[derive(Debug)]
@@ -105,7 +125,8 @@ pub struct MouseManager {
     pub enabled: bool,
 
     settings: Arc<Settings>,
-    message_selection: Option<MessageSelectionState>,
+    selection: Option<SelectionState>,
+    last_click: Option<LastClick>,
 }
 
 impl MouseManager {
@@ -121,7 +142,8 @@ impl MouseManager {
             cursor_resync_needed: false,
             enabled: true,
             settings,
-            message_selection: None,
+            selection: None,
+            last_click: None,
         }
     }
 
@@ -220,8 +242,8 @@ impl MouseManager {
         Self::get_relative_position_at(self.window_position, window_details, editor_state)
     }
 
-    pub fn clear_message_selection(&mut self) -> bool {
-        let had_selection = self.message_selection.take().is_some();
+    pub fn clear_selection(&mut self) -> bool {
+        let had_selection = self.selection.take().is_some();
         if had_selection {
             self.drag_details = None;
             self.has_moved = false;
@@ -230,8 +252,40 @@ impl MouseManager {
         had_selection
     }
 
-    fn message_area_drag_selection_enabled(&self) -> bool {
-        self.settings.get::<WindowSettings>().message_area_drag_selection
+    fn word_at(
+        editor_state: &EditorState,
+        grid_id: u64,
+        position: GridPos<u32>,
+    ) -> (GridPos<u32>, GridPos<u32>) {
+        let cells = editor_state
+            .renderer
+            .rendered_windows
+            .get(&grid_id)
+            .and_then(|window| window.row_cells(position.y))
+            .unwrap_or_default();
+        let (start, end) = word_bounds(&cells, position.x);
+        (GridPos::new(start, position.y), GridPos::new(end, position.y))
+    }
+
+    fn extend_selection(
+        state: &mut SelectionState,
+        position: GridPos<u32>,
+        editor_state: &EditorState,
+    ) {
+        let (start, end) = if state.click_count == 2 {
+            // Extend word by word, keeping the initially selected word
+            let (word_start, word_end) =
+                Self::word_at(editor_state, state.selection.grid_id, position);
+            if (position.y, position.x) < (state.anchor_start.y, state.anchor_start.x) {
+                (state.anchor_end, word_start)
+            } else {
+                (state.anchor_start, word_end)
+            }
+        } else {
+            (state.anchor_start, position)
+        };
+        state.selection.start = start;
+        state.selection.end = end;
     }
 
     fn handle_pointer_motion(
@@ -239,32 +293,24 @@ impl MouseManager {
         position: PixelPos<f32>,
         editor_state: &EditorState,
         neovim_handler: &NeovimHandler,
-    ) -> MessageSelectionEvent {
+    ) -> SelectionEvent {
         let window_size = editor_state.window.inner_size();
         let window_size = PixelSize::new(window_size.width as f32, window_size.height as f32);
         let relative_window_rect = PixelRect::from_size(window_size);
 
         self.window_position = position;
 
-        let message_selection_enabled = self.message_area_drag_selection_enabled();
-        if !message_selection_enabled && self.clear_message_selection() {
-            return MessageSelectionEvent::Clear;
-        }
-
-        if let Some(selection) = &mut self.message_selection {
-            let window_position = self.window_position;
-            let end = Self::get_relative_position_at(
-                window_position,
-                &selection.draw_details,
-                editor_state,
-            );
-            selection.end = end;
-            self.grid_position = end;
-            return MessageSelectionEvent::Update(MessageSelection {
-                grid_id: selection.draw_details.id,
-                start: selection.start,
-                end,
-            });
+        if let Some(state) = self.selection.as_mut().filter(|state| state.dragging) {
+            let draw_details = editor_state
+                .window_regions
+                .iter()
+                .find(|details| details.id == state.selection.grid_id);
+            if let Some(draw_details) = draw_details {
+                let position = Self::get_relative_position_at(position, draw_details, editor_state);
+                Self::extend_selection(state, position, editor_state);
+                self.grid_position = position;
+            }
+            return SelectionEvent::Update(state.selection);
         }
 
         // If dragging, the relevant window (the one which we send all commands to) is the one
@@ -280,7 +326,7 @@ impl MouseManager {
             }
         } else {
             if !relative_window_rect.contains(&position) {
-                return MessageSelectionEvent::Outside;
+                return SelectionEvent::Outside;
             }
 
             self.get_window_details_under_mouse(editor_state)
@@ -326,66 +372,88 @@ impl MouseManager {
             self.has_moved = self.drag_details.is_some() && (self.has_moved || has_moved);
         }
 
-        MessageSelectionEvent::Outside
+        SelectionEvent::Outside
     }
 
-    fn update_message_selection_on_button(
+    fn update_selection_on_button(
         &mut self,
         mouse_button: MouseButton,
         down: bool,
         editor_state: &EditorState,
-    ) -> Option<MessageSelectionEvent> {
-        if !self.message_area_drag_selection_enabled() {
-            return self.clear_message_selection().then_some(MessageSelectionEvent::Clear);
+    ) -> Option<SelectionEvent> {
+        if mouse_button != MouseButton::Left {
+            return None;
         }
 
-        // MouseInput only reports press/release. we start message selection on left press,
-        // this keeps the selection as a *client* overlay and avoids interfering with neovim
-        // mouse handling outside message windows.
-        if !down && mouse_button == MouseButton::Left {
-            if let Some(selection) = self.message_selection.take() {
-                let end = self.get_relative_position(&selection.draw_details, editor_state);
-                self.drag_details = None;
-                self.has_moved = false;
-                return Some(MessageSelectionEvent::Finish(MessageSelection {
-                    grid_id: selection.draw_details.id,
-                    start: selection.start,
-                    end,
-                }));
+        // MouseInput only reports press/release. The selection is a *client* overlay drawn by the
+        // renderer, so neither the press nor the release is forwarded to neovim while selecting.
+        if !down {
+            let state = self.selection.as_mut().filter(|state| state.dragging)?;
+            state.dragging = false;
+            let selection = state.selection;
+            self.drag_details = None;
+            self.has_moved = false;
+
+            // A plain click without any movement only clears the previous selection
+            if state.click_count == 1 && selection.start == selection.end {
+                self.selection = None;
+                return Some(SelectionEvent::Clear);
             }
+            return Some(SelectionEvent::Finish(selection));
         }
 
-        if down && mouse_button == MouseButton::Left {
-            let details = self.get_window_details_under_mouse_raw(editor_state);
-            let allow_selection = details
-                .map(|details| matches!(details.window_type, WindowType::Message { .. }))
-                .unwrap_or(false);
+        let settings = self.settings.get::<WindowSettings>();
+        let modifiers = editor_state.keyboard_manager.current_modifiers().state();
+        let details = self.get_window_details_under_mouse_raw(editor_state);
+        let allow_selection = settings.gui_selection_modifier.is_pressed(modifiers)
+            || (settings.message_area_drag_selection
+                && details.is_some_and(|details| {
+                    matches!(details.window_type, WindowType::Message { .. })
+                }));
+
+        let Some(details) = details.filter(|_| allow_selection) else {
+            self.last_click = None;
+            return self.clear_selection().then_some(SelectionEvent::Clear);
+        };
 
-            if !allow_selection && self.message_selection.is_some() {
-                self.message_selection = None;
-                return Some(MessageSelectionEvent::Clear);
+        let position = self.get_relative_position(details, editor_state);
+        let now = Instant::now();
+        let click_count = match &self.last_click {
+            Some(last_click)
+                if last_click.grid_id == details.id
+                    && last_click.position == position
+                    && now.duration_since(last_click.time) <= MULTI_CLICK_INTERVAL =>
+            {
+                last_click.count % 3 + 1
             }
+            _ => 1,
+        };
+        self.last_click =
+            Some(LastClick { time: now, grid_id: details.id, position, count: click_count });
 
-            if let Some(details) = details {
-                if allow_selection {
-                    let position = self.get_relative_position(details, editor_state);
-                    self.message_selection = Some(MessageSelectionState {
-                        draw_details: details.clone(),
-                        start: position,
-                        end: position,
-                    });
-                    self.drag_details = None;
-                    self.has_moved = false;
-                    return Some(MessageSelectionEvent::Update(MessageSelection {
-                        grid_id: details.id,
-                        start: position,
-                        end: position,
-                    }));
-                }
+        let (anchor_start, anchor_end, mode) = match click_count {
+            1 if settings.gui_selection_block_modifier.is_pressed(modifiers) => {
+                (position, position, SelectionMode::Block)
             }
-        }
+            1 => (position, position, SelectionMode::Character),
+            2 => {
+                let (start, end) = Self::word_at(editor_state, details.id, position);
+                (start, end, SelectionMode::Character)
+            }
+            _ => (position, position, SelectionMode::Line),
+        };
 
-        None
+        let selection = GuiSelection::new(details.id, anchor_start, anchor_end, mode);
+        self.selection = Some(SelectionState {
+            selection,
+            anchor_start,
+            anchor_end,
+            click_count,
+            dragging: true,
+        });
+        self.drag_details = None;
+        self.has_moved = false;
+        Some(SelectionEvent::Update(selection))
     }
 
     fn send_nvim_mouse_button(
@@ -448,21 +516,20 @@ impl MouseManager {
         editor_state: &EditorState,
         neovim_handler: &NeovimHandler,
     ) -> PointerTransitionResult {
-        let message_selection_event =
-            self.update_message_selection_on_button(mouse_button, down, editor_state);
-        let consume_mouse_input = matches!(
-            message_selection_event.as_ref(),
-            Some(MessageSelectionEvent::Update(_) | MessageSelectionEvent::Finish(_))
-        );
+        let was_selecting = self.selection.as_ref().is_some_and(|state| state.dragging);
+        let selection_event = self.update_selection_on_button(mouse_button, down, editor_state);
+        let consume_mouse_input = was_selecting
+            || matches!(
+                selection_event.as_ref(),
+                Some(SelectionEvent::Update(_) | SelectionEvent::Finish(_))
+            );
 
         if !consume_mouse_input {
             self.send_nvim_mouse_button(mouse_button, down, editor_state, neovim_handler);
         }
 
         PointerTransitionResult {
-            overlay_event: message_selection_event
-                .map(OverlayEvent::MessageSelection)
-                .unwrap_or_default(),
+            overlay_event: selection_event.map(OverlayEvent::Selection).unwrap_or_default(),
         }
     }
 
@@ -666,19 +733,24 @@ impl MouseManager {
             full_region,
             window,
             keyboard_manager,
+            renderer,
         };
-        let hide_mouse_when_typing = self.settings.get::<WindowSettings>().hide_mouse_when_typing;
+        let WindowSettings { hide_mouse_when_typing, ctrl_scroll_zoom, .. } =
+            self.settings.get::<WindowSettings>();
+        let scroll_zooms =
+            ctrl_scroll_zoom && keyboard_manager.current_modifiers().state().control_key();
         let mut overlay_event = OverlayEvent::default();
+        let mut zoom = None;
         match event {
             WindowEvent::CursorMoved { position, .. } => {
-                let message_selection_event = self.handle_pointer_motion(
+                let selection_event = self.handle_pointer_motion(
                     (position.x as f32, position.y as f32).into(),
                     &editor_state,
                     neovim_handler,
                 );
-                overlay_event = match message_selection_event {
-                    MessageSelectionEvent::Outside => OverlayEvent::default(),
-                    event => OverlayEvent::MessageSelection(event),
+                overlay_event = match selection_event {
+                    SelectionEvent::Outside => OverlayEvent::default(),
+                    event => OverlayEvent::Selection(event),
                 };
                 if self.mouse_hidden && window.has_focus() {
                     self.request_cursor_visible(window);
@@ -693,6 +765,18 @@ impl MouseManager {
                     self.force_cursor_visible(window);
                 }
             }
+            WindowEvent::PinchGesture { delta, .. } => zoom = Some(1.0 + *delta as f32),
+            WindowEvent::MouseWheel { delta: MouseScrollDelta::LineDelta(_, y), .. }
+                if scroll_zooms =>
+            {
+                zoom = Some(ZOOM_STEP.powf(*y));
+            }
+            WindowEvent::MouseWheel { delta: MouseScrollDelta::PixelDelta(delta), .. }
+                if scroll_zooms =>
+            {
+                let lines = delta.y as f32 / editor_state.grid_scale.height();
+                zoom = Some(ZOOM_STEP.powf(lines));
+            }
             WindowEvent::MouseWheel { delta: MouseScrollDelta::LineDelta(x, y), .. } => {
                 self.handle_line_scroll((*x, *y).into(), &editor_state, neovim_handler)
             }
@@ -734,6 +818,6 @@ impl MouseManager {
             _ => {}
         }
 
-        MouseEventResult { overlay_event }
+        MouseEventResult { overlay_event, zoom }
     }
 }
@@ -0,0 +1,133 @@
+//! Recreates the windows of the previous run and their Neovim sessions, when
+//! `neovide_restore_session` is enabled.
+//!
+//! Each window gets a `:mksession` file, which is written through RPC just before its Neovim quits.
+//! The geometry and working directory of the windows are stored next to it in
+//! `neovide-session.json`.
+
+use std::{fs, io, path::PathBuf};
+
+use log::{error, warn};
+use serde::{Deserialize, Serialize};
+use winit::window::Window;
+
+use super::{RouteId, WindowSettings};
+use crate::{
+    cmd_line::CmdLineSettings,
+    settings::{PersistentWindowSettings, Settings, neovide_std_datapath},
+    units::GridSize,
+};
+
+const SESSION_FILE: &str = "neovide-session.json";
+const SESSIONS_DIRECTORY: &str = "sessions";
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SessionWindow {
+    pub window: PersistentWindowSettings,
+    pub directory: PathBuf,
+    pub session_file: PathBuf,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct Session {
+    /// The focused window first.
+    windows: Vec<SessionWindow>,
+}
+
+fn session_path() -> PathBuf {
+    neovide_std_datapath().join(SESSION_FILE)
+}
+
+/// Sessions are not recorded for remote servers, since those outlive Neovide.
+pub fn session_enabled(settings: &Settings) -> bool {
+    settings.get::<WindowSettings>().restore_session
+        && settings.get::<CmdLineSettings>().server.is_none()
+}
+
+/// The `:mksession` file of a window.
+pub fn session_file(route_id: RouteId) -> PathBuf {
+    neovide_std_datapath().join(SESSIONS_DIRECTORY).join(format!("window-{}.vim", route_id.0))
+}
+
+/// Loads the windows of the previous run. Nothing is restored when files are given on the command
+/// line.
+pub fn load_session(settings: &Settings) -> Vec<SessionWindow> {
+    if !session_enabled(settings) || !settings.get::<CmdLineSettings>().files_to_open.is_empty() {
+        return Vec::new();
+    }
+    let Ok(json) = fs::read_to_string(session_path()) else {
+        return Vec::new();
+    };
+    match serde_json::from_str::<Session>(&json) {
+        Ok(session) => {
+            session.windows.into_iter().filter(|window| window.session_file.exists()).collect()
+        }
+        Err(error) => {
+            warn!("Could not load the session: {error}");
+            Vec::new()
+        }
+    }
+}
+
+pub fn save_session(windows: Vec<SessionWindow>) {
+    log::debug!("Saving session: {windows:?}");
+    if let Err(error) = write_session(&Session { windows }) {
+        error!("Could not save the session: {error}");
+    }
+}
+
+fn write_session(session: &Session) -> io::Result<()> {
+    fs::create_dir_all(neovide_std_datapath())?;
+    fs::write(session_path(), serde_json::to_string(session)?)?;
+
+    // Remove the session files of windows that were closed
+    let Ok(entries) = fs::read_dir(neovide_std_datapath().join(SESSIONS_DIRECTORY)) else {
+        return Ok(());
+    };
+    for path in entries.flatten().map(|entry| entry.path()) {
+        if !session.windows.iter().any(|window| window.session_file == path) {
+            fs::remove_file(path)?;
+        }
+    }
+    Ok(())
+}
+
+/// The geometry of a window, or `None` when it's minimized and the size can't be trusted.
+pub fn window_geometry(
+    window: &Window,
+    grid_size: GridSize<u32>,
+) -> Option<PersistentWindowSettings> {
+    if window.is_minimized() == Some(true) {
+        return None;
+    }
+    let geometry = if window.is_maximized() {
+        PersistentWindowSettings::Maximized { grid_size: Some(grid_size) }
+    } else {
+        PersistentWindowSettings::Windowed {
+            position: window.outer_position().unwrap_or_default(),
+            pixel_size: Some(window.inner_size()),
+            grid_size: Some(grid_size),
+        }
+    };
+    Some(geometry)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_round_trip() {
+        let session = Session {
+            windows: vec![SessionWindow {
+                window: PersistentWindowSettings::Maximized { grid_size: None },
+                directory: PathBuf::from("/project"),
+                session_file: session_file(RouteId(3)),
+            }],
+        };
+        let json = serde_json::to_string(&session).unwrap();
+        let loaded: Session = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.windows, session.windows);
+        assert!(loaded.windows[0].session_file.ends_with("sessions/window-3.vim"));
+    }
+}
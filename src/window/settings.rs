@@ -1,4 +1,5 @@
 use rmpv::Value;
+use winit::keyboard::ModifiersState;
 
 use crate::error_msg;
 use crate::settings::*;
@@ -8,42 +9,92 @@ pub use crate::platform::macos::settings::*;
 
 #[derive(Clone, SettingGroup, PartialEq)]
 pub struct WindowSettings {
+    /// Ask for confirmation before quitting with unsaved changes.
     pub confirm_quit: bool,
+    /// Prevents the cursor from flickering to the command line when it shouldn't.
     pub cursor_hack: bool,
+    /// Open the window in fullscreen mode.
     pub fullscreen: bool,
+    /// Let Neovim detect the grid under the mouse. Requires Neovim 0.12.
     pub has_mouse_grid_detection: bool,
+    /// Hide the mouse cursor while typing.
     pub hide_mouse_when_typing: bool,
+    /// Enable the input method editor.
     pub input_ime: bool,
+    /// Send <C-i>, <C-m> and <C-[> as <Tab>, <CR> and <Esc>, like terminals do.
+    pub input_legacy_keys: bool,
+    /// The Neovim modes in which keys of non-Latin layouts are sent as the US QWERTY key at the
+    /// same position. Keys pressed together with Ctrl, Meta or Super are translated in all modes.
+    pub input_physical_keys: PhysicalKeyModes,
+    /// Treat the keyboard as an ISO layout.
     pub iso_layout: bool,
+    /// The opacity of the `Normal` highlight group background.
     pub normal_opacity: f32,
+    /// The opacity of the window, from 0.0 to 1.0.
     #[alias = "transparency"]
     pub opacity: f32,
+    /// Padding below the grid, in pixels.
     pub padding_bottom: u32,
+    /// Padding left of the grid, in pixels.
     pub padding_left: u32,
+    /// Padding right of the grid, in pixels.
     pub padding_right: u32,
+    /// Padding above the grid, in pixels.
     pub padding_top: u32,
+    /// The refresh rate while animating, in frames per second.
     pub refresh_rate: u64,
+    /// The refresh rate while unfocused, in frames per second.
     pub refresh_rate_idle: u64,
+    /// Restore the scale factor, opacity, font size, fullscreen state and theme of the previous
+    /// session, `profile` remembers them per config profile and `directory` per working directory.
+    pub remember_settings: RememberSettings,
+    /// Restore the window position of the previous session.
     pub remember_window_position: bool,
+    /// Restore the window size of the previous session.
     pub remember_window_size: bool,
+    /// Recreate all windows and their Neovim sessions of the previous run.
+    pub restore_session: bool,
+    /// Multiplier for the size of the text and the UI.
     pub scale_factor: f32,
+    /// Zoom with Ctrl and the scroll wheel, in addition to pinching the touchpad. Ctrl+scroll is
+    /// then not sent to Neovim.
+    pub ctrl_scroll_zoom: bool,
+    /// How long the zoom animation takes, in seconds.
+    pub zoom_animation_length: f32,
+    /// Draw a grey border around opaque windows.
     pub show_border: bool,
+    /// The window theme, `bg_color` follows the `background` option.
     pub theme: ThemeSettings,
+    /// How far a touch has to move before it scrolls instead of clicks.
     pub touch_deadzone: f32,
+    /// How long a touch has to be held before it starts a visual selection, in seconds.
     pub touch_drag_timeout: f32,
+    /// Blur the background of transparent windows.
     pub window_blurred: bool,
+    /// Allow selecting text in the message area by dragging.
     pub message_area_drag_selection: bool,
+    /// Modifier that starts a GUI text selection when dragging, none by default since the clicks
+    /// with it aren't sent to Neovim.
+    pub gui_selection_modifier: SelectionModifier,
+    /// Modifier that starts a block GUI text selection when dragging.
+    pub gui_selection_block_modifier: SelectionModifier,
 
+    /// Removed, use `input_macos_option_key_is_meta` instead.
     #[cfg(target_os = "macos")]
     pub input_macos_alt_is_meta: bool,
+    /// Which Option keys are interpreted as Meta.
     #[cfg(target_os = "macos")]
     pub input_macos_option_key_is_meta: OptionAsMeta,
+    /// Use pre-Lion fullscreen without a separate space.
     #[cfg(target_os = "macos")]
     pub macos_simple_fullscreen: bool,
+    /// Highlight the matching pair with the system find indicator.
     #[cfg(target_os = "macos")]
     pub highlight_matching_pair: bool,
+    /// The background color of the title bar.
     #[cfg(target_os = "windows")]
     pub title_background_color: String,
+    /// The text color of the title bar.
     #[cfg(target_os = "windows")]
     pub title_text_color: String,
 
@@ -64,6 +115,8 @@ impl Default for WindowSettings {
             has_mouse_grid_detection: false,
             hide_mouse_when_typing: false,
             input_ime: true,
+            input_legacy_keys: false,
+            input_physical_keys: PhysicalKeyModes::default(),
             iso_layout: false,
             normal_opacity: 1.0,
             opacity: 1.0,
@@ -73,15 +126,21 @@ impl Default for WindowSettings {
             padding_top: 0,
             refresh_rate: 60,
             refresh_rate_idle: 5,
+            remember_settings: RememberSettings::Never,
             remember_window_position: true,
             remember_window_size: true,
+            restore_session: false,
             scale_factor: 1.0,
+            ctrl_scroll_zoom: false,
+            zoom_animation_length: 0.15,
             show_border: true,
             theme: ThemeSettings::Auto,
             touch_deadzone: 6.0,
             touch_drag_timeout: 0.17,
             window_blurred: false,
             message_area_drag_selection: true,
+            gui_selection_modifier: SelectionModifier::None,
+            gui_selection_block_modifier: SelectionModifier::Ctrl,
 
             #[cfg(target_os = "macos")]
             input_macos_alt_is_meta: false,
@@ -113,7 +172,7 @@ pub enum ThemeSettings {
 }
 
 impl ParseFromValue for ThemeSettings {
-    fn parse_from_value(&mut self, value: Value) {
+    fn parse_from_value(&mut self, value: Value) -> bool {
         if value.is_str() {
             *self = match value.as_str().unwrap() {
                 "auto" => ThemeSettings::Auto,
@@ -124,13 +183,23 @@ impl ParseFromValue for ThemeSettings {
                     error_msg!(
                         "Setting Theme expected one of `auto`, `dark`, `light`, `bg_color`, but received {value:?}"
                     );
-                    return;
+                    return false;
                 }
             };
+            true
         } else {
             error_msg!("Setting Theme expected string, but received {value:?}");
+            false
         }
     }
+
+    fn value_kind() -> &'static str {
+        "string"
+    }
+
+    fn allowed_values() -> &'static [&'static str] {
+        &["auto", "dark", "light", "bg_color"]
+    }
 }
 
 impl From<ThemeSettings> for Value {
@@ -143,3 +212,172 @@ impl From<ThemeSettings> for Value {
         }
     }
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RememberSettings {
+    Never,
+    Profile,
+    Directory,
+}
+
+impl ParseFromValue for RememberSettings {
+    fn parse_from_value(&mut self, value: Value) -> bool {
+        if value.is_str() {
+            *self = match value.as_str().unwrap() {
+                "never" => RememberSettings::Never,
+                "profile" => RememberSettings::Profile,
+                "directory" => RememberSettings::Directory,
+                value => {
+                    error_msg!(
+                        "Setting RememberSettings expected one of `never`, `profile`, `directory`, but received {value:?}"
+                    );
+                    return false;
+                }
+            };
+            true
+        } else {
+            error_msg!("Setting RememberSettings expected string, but received {value:?}");
+            false
+        }
+    }
+
+    fn value_kind() -> &'static str {
+        "string"
+    }
+
+    fn allowed_values() -> &'static [&'static str] {
+        &["never", "profile", "directory"]
+    }
+}
+
+impl From<RememberSettings> for Value {
+    fn from(value: RememberSettings) -> Self {
+        match value {
+            RememberSettings::Never => Value::from("never"),
+            RememberSettings::Profile => Value::from("profile"),
+            RememberSettings::Directory => Value::from("directory"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelectionModifier {
+    None,
+    Shift,
+    Ctrl,
+    Alt,
+    Super,
+}
+
+impl SelectionModifier {
+    pub fn is_pressed(self, modifiers: ModifiersState) -> bool {
+        match self {
+            SelectionModifier::None => false,
+            SelectionModifier::Shift => modifiers.shift_key(),
+            SelectionModifier::Ctrl => modifiers.control_key(),
+            SelectionModifier::Alt => modifiers.alt_key(),
+            SelectionModifier::Super => modifiers.super_key(),
+        }
+    }
+}
+
+impl ParseFromValue for SelectionModifier {
+    fn parse_from_value(&mut self, value: Value) -> bool {
+        if value.is_str() {
+            *self = match value.as_str().unwrap() {
+                "none" => SelectionModifier::None,
+                "shift" => SelectionModifier::Shift,
+                "ctrl" => SelectionModifier::Ctrl,
+                "alt" => SelectionModifier::Alt,
+                "super" => SelectionModifier::Super,
+                value => {
+                    error_msg!(
+                        "Setting SelectionModifier expected one of `none`, `shift`, `ctrl`, `alt`, `super`, but received {value:?}"
+                    );
+                    return false;
+                }
+            };
+            true
+        } else {
+            error_msg!("Setting SelectionModifier expected string, but received {value:?}");
+            false
+        }
+    }
+
+    fn value_kind() -> &'static str {
+        "string"
+    }
+
+    fn allowed_values() -> &'static [&'static str] {
+        &["none", "shift", "ctrl", "alt", "super"]
+    }
+}
+
+impl From<SelectionModifier> for Value {
+    fn from(value: SelectionModifier) -> Self {
+        match value {
+            SelectionModifier::None => Value::from("none"),
+            SelectionModifier::Shift => Value::from("shift"),
+            SelectionModifier::Ctrl => Value::from("ctrl"),
+            SelectionModifier::Alt => Value::from("alt"),
+            SelectionModifier::Super => Value::from("super"),
+        }
+    }
+}
+
+/// The names of the Neovim modes, as listed in `:help mode_info_set`. Empty disables the
+/// translation of physical keys.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PhysicalKeyModes(Vec<String>);
+
+impl PhysicalKeyModes {
+    pub fn is_enabled(&self) -> bool {
+        !self.0.is_empty()
+    }
+
+    pub fn contains(&self, mode: &str) -> bool {
+        self.0.iter().any(|name| name == mode)
+    }
+}
+
+impl ParseFromValue for PhysicalKeyModes {
+    fn parse_from_value(&mut self, value: Value) -> bool {
+        match value {
+            Value::String(mode) => {
+                self.0 = mode.into_str().into_iter().filter(|mode| !mode.is_empty()).collect();
+                true
+            }
+            Value::Array(modes) => {
+                let names = modes.iter().map(|mode| mode.as_str().map(str::to_owned)).collect();
+                match names {
+                    Some(names) => {
+                        self.0 = names;
+                        true
+                    }
+                    None => {
+                        error_msg!(
+                            "Setting PhysicalKeyModes expected an array of mode names, but received {modes:?}"
+                        );
+                        false
+                    }
+                }
+            }
+            value => {
+                error_msg!(
+                    "Setting PhysicalKeyModes expected a string or an array of strings, but received {value:?}"
+                );
+                false
+            }
+        }
+    }
+
+    fn value_kind() -> &'static str {
+        "string|string[]"
+    }
+}
+
+impl From<PhysicalKeyModes> for Value {
+    fn from(modes: PhysicalKeyModes) -> Self {
+        Value::from(modes.0.into_iter().map(Value::from).collect::<Vec<_>>())
+    }
+}
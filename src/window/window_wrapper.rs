@@ -1,7 +1,15 @@
-use std::{cell::RefCell, fmt, rc::Rc, sync::Arc};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fmt,
+    rc::Rc,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use log::trace;
 use raw_window_handle::{HasWindowHandle, RawWindowHandle};
+use rmpv::Value;
 use rustc_hash::FxHashMap;
 use winit::{
     dpi,
@@ -11,20 +19,23 @@ use winit::{
 };
 
 use super::{
-    EventPayload, EventTarget, KeyboardManager, MessageSelectionEvent, MouseManager, OverlayEvent,
-    RouteId, UserEvent, WindowCommand, WindowSettings, WindowSettingsChanged, WindowSize,
+    EventPayload, EventTarget, KeyboardManager, MouseManager, OverlayEvent, RouteId,
+    SelectionEvent, UserEvent, WindowCommand, WindowSettings, WindowSettingsChanged, WindowSize,
+    key_bindings::GuiAction,
+    session::{self, SessionWindow},
 };
 
 #[cfg(target_os = "macos")]
 use {
     crate::units::{GridPos, Pixel},
     crate::window::MacShortcutCommand,
+    crate::window::keyboard_manager::KeyboardEventResult,
     crate::window::macos::tab_navigation::{TabNavigationAction, TabNavigationHotkeys},
     crate::window::macos::{
         MacosWindowFeature, TouchpadStage, hide_application, is_focus_suppressed,
-        is_tab_overview_active, native_tab_bar_enabled, trigger_tab_overview,
+        is_tab_overview_active, native_tab_bar_enabled, request_new_window, trigger_tab_overview,
     },
-    crate::{error_msg, window::settings},
+    crate::window::settings,
     glamour::Point2,
     std::collections::VecDeque,
     winit::platform::macos::{self, WindowExtMacOS},
@@ -33,22 +44,34 @@ use {
 use crate::{
     CmdLineSettings,
     bridge::{
-        NeovimHandler, NeovimRuntime, ParallelCommand, RestartDetails, SerialCommand, send_ui,
-        set_active_route_handler, unregister_route_handler,
+        EditorMode, NeovimHandler, NeovimRuntime, ParallelCommand, RestartDetails, SerialCommand,
+        send_ui, set_active_route_handler, unregister_route_handler,
     },
     clipboard::ClipboardHandle,
     cmd_line::{GeometryArgs, MouseCursorIcon},
-    profiling::{tracy_frame, tracy_gpu_collect, tracy_gpu_zone, tracy_plot, tracy_zone},
+    error_msg,
+    profiling::{tracy_plot, tracy_zone},
     renderer::{
-        DrawCommand, MessageSelection, Renderer, RendererSettingsChanged, SkiaRenderer, VSync,
+        DrawCommand, GuiSelection, Renderer, RendererSettingsChanged, VSync,
+        command_palette::{CommandPaletteInput, PaletteCommand},
         create_skia_renderer,
+        fonts::{
+            font_options::{FontDescription, FontOptions},
+            highlight_fonts::HighlightFonts,
+        },
+        input_inspector,
+        render_thread::FramePresenter,
+        screencast::ScreencastSettings,
+        settings_panel::SettingsPanelInput,
+        zoom::ZOOM_STEP,
     },
     running_tracker::RunningTracker,
     settings::{
-        Config, DEFAULT_GRID_SIZE, HotReloadConfigs, MIN_GRID_SIZE, RendererHotReloadConfigs,
-        Settings, SettingsChanged, WindowHotReloadConfigs, clamped_grid_size, font::FontSettings,
-        load_last_window_settings,
+        Config, DEFAULT_GRID_SIZE, HotReloadConfigs, MIN_GRID_SIZE, PersistentWindowSettings,
+        RendererHotReloadConfigs, SettingTables, Settings, SettingsChanged, WindowHotReloadConfigs,
+        clamped_grid_size, config, font::FontSettings, load_last_window_settings,
     },
+    startup_time,
     units::{GridRect, GridScale, GridSize, PixelPos, PixelRect, PixelSize},
     window::{
         PhysicalSize, ShouldRender, ThemeSettings, create_window, determine_grid_size,
@@ -63,6 +86,8 @@ use {
 };
 
 const GRID_TOLERANCE: f32 = 1e-3;
+/// How long quitting waits for each Neovim to write its session.
+const SESSION_SAVE_TIMEOUT: Duration = Duration::from_secs(1);
 
 fn round_or_op<Op: FnOnce(f32) -> f32>(v: f32, op: Op) -> f32 {
     let rounded = v.round();
@@ -95,8 +120,8 @@ enum GeometryTarget {
 }
 
 pub struct RouteWindow {
-    pub skia_renderer: Rc<RefCell<Box<dyn SkiaRenderer>>>,
-    pub winit_window: Rc<Window>,
+    pub presenter: FramePresenter,
+    pub winit_window: Arc<Window>,
     pub neovim_handler: NeovimHandler,
     pub mouse_manager: Rc<RefCell<Box<MouseManager>>>,
     pub renderer: Rc<RefCell<Box<Renderer>>>,
@@ -110,7 +135,7 @@ pub struct RouteWindow {
 impl fmt::Debug for RouteWindow {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.debug_struct("RouteWindow")
-            .field("skia_renderer", &"...")
+            .field("presenter", &"...")
             .field("winit_window", &self.winit_window)
             .field("neovim_handler", &self.neovim_handler)
             .finish()
@@ -142,6 +167,9 @@ struct RouteState {
     ime_area: (dpi::PhysicalPosition<u32>, dpi::PhysicalSize<u32>),
     inferred_theme: Option<Theme>,
     vsync: Option<VSync>,
+    screenshot_requested: bool,
+    /// The current Neovim mode, which decides how keys are translated.
+    mode: EditorMode,
 }
 
 impl RouteState {
@@ -158,6 +186,8 @@ impl RouteState {
             ime_area: Default::default(),
             inferred_theme: None,
             vsync: None,
+            screenshot_requested: false,
+            mode: EditorMode::Normal,
         }
     }
 }
@@ -192,6 +222,10 @@ pub struct WinitWindowWrapper {
     pending_restart: FxHashMap<RouteId, RestartRequest>,
     keyboard_manager: KeyboardManager,
     ui_state: UIState,
+    /// The windows of the previous run that still have to be recreated.
+    session_windows: Vec<SessionWindow>,
+    /// The geometry of the restored session window that becomes the initial window.
+    initial_session_window: Option<PersistentWindowSettings>,
 
     settings: Arc<Settings>,
     clipboard: ClipboardHandle,
@@ -209,12 +243,20 @@ pub struct WinitWindowWrapper {
 impl WinitWindowWrapper {
     pub fn new(
         _initial_font_settings: Option<FontSettings>,
+        initial_key_bindings: Option<HashMap<String, String>>,
         settings: Arc<Settings>,
         runtime_tracker: RunningTracker,
         clipboard_handle: ClipboardHandle,
     ) -> Self {
         let runtime =
             NeovimRuntime::new(clipboard_handle.clone()).expect("Failed to create neovim runtime");
+        #[cfg_attr(target_os = "macos", allow(unused_mut))]
+        let mut session_windows = session::load_session(&settings);
+        // Only macOS supports multiple windows
+        #[cfg(not(target_os = "macos"))]
+        session_windows.truncate(1);
+        let mut keyboard_manager = KeyboardManager::new(settings.clone());
+        keyboard_manager.key_bindings_mut().set_config(initial_key_bindings.as_ref());
 
         Self {
             routes: Default::default(),
@@ -223,8 +265,10 @@ impl WinitWindowWrapper {
             runtime: Some(runtime),
             runtime_tracker,
             pending_restart: FxHashMap::default(),
-            keyboard_manager: KeyboardManager::new(settings.clone()),
+            keyboard_manager,
             ui_state: UIState::Initing,
+            session_windows,
+            initial_session_window: None,
             settings: settings.clone(),
             clipboard: clipboard_handle,
             #[cfg(target_os = "macos")]
@@ -238,12 +282,21 @@ impl WinitWindowWrapper {
         }
     }
 
-    pub fn request_window_creation(&mut self, proxy: &EventLoopProxy<EventPayload>) {
+    /// Creates the initial window, while Neovim is started in the background.
+    pub fn create_initial_window(
+        &mut self,
+        event_loop: &ActiveEventLoop,
+        proxy: &EventLoopProxy<EventPayload>,
+    ) {
         if !self.routes.is_empty() || !self.route_cores.is_empty() {
             return;
         }
 
-        let persisted_window_settings = load_last_window_settings().ok();
+        let session_window = self.next_session_window();
+        let persisted_window_settings = match &session_window {
+            Some(session_window) => Some(session_window.window.clone()),
+            None => load_last_window_settings(&self.settings).ok(),
+        };
         let desired_window_size =
             determine_window_size(persisted_window_settings.as_ref(), &self.settings.clone());
         let desired_grid_size =
@@ -263,16 +316,26 @@ impl WinitWindowWrapper {
         let route_id = RouteId::next();
         let runtime = self.runtime.as_mut().expect("Neovim runtime has not been initialized");
 
-        let neovim_handler = runtime
-            .launch(
-                route_id,
-                proxy.clone(),
-                desired_grid_size,
-                self.runtime_tracker.clone(),
-                self.settings.clone(),
-                &config,
-            )
-            .expect("Failed to launch neovim runtime");
+        let launch = runtime.launch(
+            route_id,
+            proxy.clone(),
+            desired_grid_size,
+            self.runtime_tracker.clone(),
+            self.settings.clone(),
+            &config,
+        );
+        let neovim_handler = launch.handler.clone();
+
+        if let Some(session_window) = session_window {
+            send_ui(
+                ParallelCommand::SourceSession {
+                    session_file: session_window.session_file,
+                    directory: session_window.directory,
+                },
+                &neovim_handler,
+            );
+            self.initial_session_window = Some(session_window.window);
+        }
 
         self.route_cores.insert(
             route_id,
@@ -289,6 +352,50 @@ impl WinitWindowWrapper {
                 font_changed_last_frame: false,
             },
         );
+
+        // The window is created while Neovim starts, and stays hidden until the first frame
+        self.pending_window_creation_route = Some(route_id);
+        self.ui_state = UIState::WaitingForWindowCreate;
+        self.try_create_window(event_loop, proxy);
+
+        let runtime = self.runtime.as_ref().expect("Neovim runtime has not been initialized");
+        runtime.wait_for_launch(launch).expect("Failed to launch neovim runtime");
+    }
+
+    fn next_session_window(&mut self) -> Option<SessionWindow> {
+        (!self.session_windows.is_empty()).then(|| self.session_windows.remove(0))
+    }
+
+    /// Records all windows and their Neovim sessions, used when quitting Neovide while the windows
+    /// are still open.
+    pub fn save_session(&self) {
+        let Some(runtime) = &self.runtime else {
+            return;
+        };
+        if self.routes.is_empty() || !session::session_enabled(&self.settings) {
+            return;
+        }
+
+        let focused = self.get_focused_route();
+        let mut routes: Vec<_> = self.routes.iter().collect();
+        routes.sort_by_key(|(window_id, _)| Some(**window_id) != focused);
+        let windows = routes
+            .into_iter()
+            .filter_map(|(_, route)| {
+                let grid_size = route.window.renderer.borrow().get_grid_size();
+                let window = session::window_geometry(&route.window.winit_window, grid_size)?;
+                let session_file = session::session_file(route.route_id);
+                let neovim_handler = &route.window.neovim_handler;
+                match runtime.save_session(neovim_handler, &session_file, SESSION_SAVE_TIMEOUT) {
+                    Ok(directory) => Some(SessionWindow { window, directory, session_file }),
+                    Err(error) => {
+                        log::warn!("Could not save the session: {error:?}");
+                        None
+                    }
+                }
+            })
+            .collect();
+        session::save_session(windows);
     }
 
     pub fn has_pending_window_creation(&self) -> bool {
@@ -401,6 +508,28 @@ impl WinitWindowWrapper {
                 }
             }
             WindowCommand::ListAvailableFonts => self.send_font_names(target_window_id),
+            WindowCommand::ShowSettingsPanel => {
+                if let Some(route) = self.routes.get(&target_window_id) {
+                    route.window.renderer.borrow_mut().settings_panel.open();
+                }
+            }
+            WindowCommand::BindKey { keys, action } => {
+                self.keyboard_manager.key_bindings_mut().bind(&keys, action.as_deref());
+            }
+            WindowCommand::SetHighlightFont { group, fonts } => {
+                self.set_highlight_font(group, fonts);
+            }
+            WindowCommand::ModeChanged(mode) => {
+                if let Some(route) = self.routes.get_mut(&target_window_id) {
+                    route.state.mode = mode;
+                }
+            }
+            WindowCommand::RegisterPaletteCommand { title, registered } => {
+                if let Some(route) = self.routes.get(&target_window_id) {
+                    let mut renderer = route.window.renderer.borrow_mut();
+                    renderer.command_palette.register_user_command(title, registered);
+                }
+            }
             WindowCommand::FocusWindow => {
                 if let Some(route) = &self.routes.get(&target_window_id) {
                     let window = route.window.winit_window.clone();
@@ -485,6 +614,22 @@ impl WinitWindowWrapper {
                     }
                 }
             }
+            WindowCommand::SessionSaved { session_file, directory } => {
+                // Closing one of several windows leaves the session alone, it's only recorded when
+                // the last window is closed
+                if self.routes.len() != 1 {
+                    return;
+                }
+                let Some(route) = self.routes.get(&target_window_id) else {
+                    return;
+                };
+                let grid_size = route.window.renderer.borrow().get_grid_size();
+                if let Some(window) =
+                    session::window_geometry(&route.window.winit_window, grid_size)
+                {
+                    session::save_session(vec![SessionWindow { window, directory, session_file }]);
+                }
+            }
             #[cfg(windows)]
             WindowCommand::RegisterRightClick => register_right_click(),
             #[cfg(windows)]
@@ -498,6 +643,17 @@ impl WinitWindowWrapper {
         };
 
         match command {
+            // init.lua can bind keys before the window is created
+            WindowCommand::BindKey { keys, action } => {
+                self.keyboard_manager.key_bindings_mut().bind(&keys, action.as_deref());
+            }
+            WindowCommand::RegisterPaletteCommand { title, registered } => {
+                let mut renderer = route_core.renderer.borrow_mut();
+                renderer.command_palette.register_user_command(title, registered);
+            }
+            WindowCommand::SetHighlightFont { group, fonts } => {
+                self.set_highlight_font(group, fonts);
+            }
             WindowCommand::TitleChanged(new_title) => {
                 route_core.title = new_title;
             }
@@ -571,11 +727,11 @@ impl WinitWindowWrapper {
                     if let Some(route) = self.routes.get_mut(window_id) {
                         let mut renderer = route.window.renderer.borrow_mut();
                         let scale_factor = renderer.os_scale_factor;
-                        let renderer_user_scale_factor = renderer.user_scale_factor;
                         renderer.user_scale_factor = user_scale_factor.into();
+                        renderer.zoom.set_scale_factor(user_scale_factor);
                         renderer
                             .grid_renderer
-                            .handle_scale_factor_update(scale_factor * renderer_user_scale_factor);
+                            .handle_scale_factor_update(scale_factor * renderer.user_scale_factor);
                         route.state.font_changed_last_frame = true;
                     }
                 }
@@ -594,8 +750,8 @@ impl WinitWindowWrapper {
                 if !enabled {
                     for window_id in window_ids.iter() {
                         if let Some(route) = self.routes.get(window_id) {
-                            route.window.mouse_manager.borrow_mut().clear_message_selection();
-                            route.window.renderer.borrow_mut().set_message_selection(None);
+                            route.window.mouse_manager.borrow_mut().clear_selection();
+                            route.window.renderer.borrow_mut().set_selection(None);
                         }
                     }
                 }
@@ -674,8 +830,15 @@ impl WinitWindowWrapper {
             | RendererSettingsChanged::PixelGeometry(..) => {
                 for window_id in window_ids.iter() {
                     if let Some(route) = self.routes.get_mut(window_id) {
-                        let mut skia_renderer = route.window.skia_renderer.borrow_mut();
-                        skia_renderer.resize();
+                        route.window.presenter.resize();
+                        route.state.font_changed_last_frame = true;
+                    }
+                }
+            }
+            RendererSettingsChanged::GlyphAtlas(..)
+            | RendererSettingsChanged::GlyphAtlasSize(..) => {
+                for window_id in window_ids.iter() {
+                    if let Some(route) = self.routes.get_mut(window_id) {
                         route.state.font_changed_last_frame = true;
                     }
                 }
@@ -809,35 +972,171 @@ impl WinitWindowWrapper {
         };
 
         #[cfg(target_os = "macos")]
-        if !consumed_key_event {
-            self.keyboard_manager.handle_event(event, neovim_handler);
-        }
+        let keyboard_result = if consumed_key_event {
+            KeyboardEventResult::default()
+        } else {
+            self.keyboard_manager.handle_event(event, &route.state.mode, neovim_handler)
+        };
 
         #[cfg(not(target_os = "macos"))]
-        self.keyboard_manager.handle_event(event, neovim_handler);
+        let keyboard_result =
+            self.keyboard_manager.handle_event(event, &route.state.mode, neovim_handler);
 
         {
             let mut renderer = route.window.renderer.borrow_mut();
             renderer.handle_event(event);
+            if let Some(keys) = &keyboard_result.keys {
+                let screencast_settings = self.settings.get::<ScreencastSettings>();
+                renderer.screencast.record(&screencast_settings, keys, &route.state.mode);
+            }
+        }
+
+        if let Some(action) = keyboard_result.action {
+            self.handle_gui_action(window_id, action);
+        }
+        if let Some(factor) = mouse_result.zoom {
+            let anchor = route.window.mouse_manager.borrow().window_position;
+            self.zoom(window_id, |scale_factor| scale_factor * factor, Some(anchor));
         }
 
         Some(mouse_result.overlay_event)
     }
 
+    fn handle_gui_action(&mut self, window_id: WindowId, action: GuiAction) {
+        let WindowSettings { fullscreen, .. } = self.settings.get::<WindowSettings>();
+        match action {
+            GuiAction::ToggleFullscreen => self.set_setting_value("fullscreen", !fullscreen),
+            GuiAction::ZoomIn => {
+                self.zoom(window_id, |scale_factor| scale_factor * ZOOM_STEP, None)
+            }
+            GuiAction::ZoomOut => {
+                self.zoom(window_id, |scale_factor| scale_factor / ZOOM_STEP, None)
+            }
+            GuiAction::ZoomReset => self.zoom(window_id, |_| 1.0, None),
+            GuiAction::ToggleProfiler => self.toggle_setting("profiler"),
+            GuiAction::ToggleInputInspector => self.toggle_setting("input_inspector"),
+            GuiAction::CopyInputLog => self.copy_input_log(),
+            GuiAction::ShowSettings => {
+                if let Some(route) = self.routes.get(&window_id) {
+                    route.window.renderer.borrow_mut().settings_panel.open();
+                }
+            }
+            GuiAction::ShowCommandPalette => {
+                if let Some(route) = self.routes.get(&window_id) {
+                    route.window.renderer.borrow_mut().command_palette.open();
+                }
+            }
+            GuiAction::Screenshot => {
+                // Taken after the next frame is drawn, so that the palette is already closed
+                if let Some(route) = self.routes.get_mut(&window_id) {
+                    route.state.screenshot_requested = true;
+                }
+            }
+            GuiAction::ReloadConfig => config::reload_config(),
+            GuiAction::ListFonts => self.send_font_names(window_id),
+            GuiAction::SwitchTheme => self.switch_theme(),
+            GuiAction::Paste => self.paste_clipboard(window_id),
+            #[cfg(target_os = "macos")]
+            GuiAction::NewWindow => request_new_window(),
+            #[cfg(not(target_os = "macos"))]
+            GuiAction::NewWindow => log::warn!("Multiple windows are only supported on macOS"),
+        }
+    }
+
+    /// Starts animating the zoom of the window. The anchor stays in place, and defaults to the
+    /// text cursor.
+    fn zoom(
+        &mut self,
+        window_id: WindowId,
+        scale_factor: impl FnOnce(f32) -> f32,
+        anchor: Option<PixelPos<f32>>,
+    ) {
+        let Some(route) = self.routes.get(&window_id) else {
+            return;
+        };
+        let mut renderer = route.window.renderer.borrow_mut();
+        let anchor = anchor.unwrap_or_else(|| renderer.get_cursor_destination());
+        let target = scale_factor(renderer.zoom.target());
+        renderer.zoom.zoom_to(target, anchor);
+    }
+
+    /// Cycles through the allowed values of `g:neovide_theme`.
+    fn switch_theme(&mut self) {
+        let metadata = self.settings.setting_metadata();
+        let Some(theme) = metadata.iter().find(|metadata| metadata.name == "theme") else {
+            return;
+        };
+        let themes = theme.allowed_values;
+        let current = self.settings.value("theme");
+        let position = themes
+            .iter()
+            .position(|theme| current.as_ref().and_then(Value::as_str) == Some(*theme));
+        let next = position.map_or(0, |position| (position + 1) % themes.len());
+        if let Some(theme) = themes.get(next) {
+            self.set_setting_value("theme", *theme);
+        }
+    }
+
+    fn set_setting_value(&mut self, name: &str, value: impl Into<Value>) {
+        if let Some((value, event)) = self.settings.set_value(name, value.into()) {
+            self.broadcast_setting_change(name.to_owned(), value, event);
+        }
+    }
+
+    fn toggle_setting(&mut self, name: &str) {
+        let value = self.settings.value(name).and_then(|value| value.as_bool());
+        self.set_setting_value(name, !value.unwrap_or(false));
+    }
+
+    fn copy_input_log(&self) {
+        let Some(clipboard) = self.clipboard.upgrade() else {
+            return;
+        };
+        let Ok(mut clipboard) = clipboard.lock() else {
+            return;
+        };
+        if let Err(error) = clipboard.set_contents(input_inspector::log_text(), "+") {
+            log::warn!("Could not copy the input log: {error}");
+        }
+    }
+
+    fn paste_clipboard(&self, window_id: WindowId) {
+        let Some(route) = self.routes.get(&window_id) else {
+            return;
+        };
+        let Some(clipboard) = self.clipboard.upgrade() else {
+            return;
+        };
+        let Ok(mut clipboard) = clipboard.lock() else {
+            return;
+        };
+        match clipboard.get_contents("+") {
+            Ok(text) => send_ui(SerialCommand::Paste(text), &route.window.neovim_handler),
+            Err(error) => log::warn!("Could not read the clipboard: {error}"),
+        }
+    }
+
     pub fn handle_window_event(&mut self, window_id: WindowId, event: WindowEvent) -> bool {
+        input_inspector::record_event(&event, self.keyboard_manager.current_modifiers().state());
+
+        // The command palette and the settings panel take all the input while they are open
+        if self.handle_command_palette_input(window_id, &event)
+            || self.handle_settings_panel_input(window_id, &event)
+        {
+            return self.ui_state >= UIState::FirstFrame;
+        }
+
         // Events can still arrive after the associated window has been torn down.
         let Some(overlay_event) = self.preprocess_window_input(window_id, &event) else {
             return false;
         };
 
-        // Message selection via mouse drag is a *client* overlay rendered by Neovide, not a
-        // Neovim draw command. It can change on mouse move/release without new draw commands,
-        // so force a redraw whenever the message selection state changes.
-        let message_selection_needs_render = match overlay_event {
+        // The GUI selection is a *client* overlay rendered by Neovide, not a Neovim draw
+        // command. It can change on mouse move/release without new draw commands, so force a
+        // redraw whenever the selection state changes.
+        let selection_needs_render = match overlay_event {
             OverlayEvent::Unchanged => false,
-            OverlayEvent::MessageSelection(action) => {
-                self.apply_message_selection_event(window_id, action)
-            }
+            OverlayEvent::Selection(action) => self.apply_selection_event(window_id, action),
         };
 
         let mut should_render = true;
@@ -861,8 +1160,7 @@ impl WinitWindowWrapper {
                     self.handle_scale_factor_update(window_id, scale_factor);
                 }
                 WindowEvent::Resized { .. } => {
-                    let mut skia_renderer = route.window.skia_renderer.borrow_mut();
-                    skia_renderer.resize();
+                    route.window.presenter.resize();
                     #[cfg(target_os = "macos")]
                     {
                         resized = true;
@@ -931,7 +1229,7 @@ impl WinitWindowWrapper {
             self.sync_native_tabs_resize(window_id);
         }
 
-        should_render |= message_selection_needs_render;
+        should_render |= selection_needs_render;
 
         if let Some(focus) = pending_focus_event {
             #[cfg(target_os = "macos")]
@@ -1006,75 +1304,87 @@ impl WinitWindowWrapper {
         }
     }
 
-    fn apply_message_selection_event(
-        &mut self,
-        window_id: WindowId,
-        action: MessageSelectionEvent,
-    ) -> bool {
+    fn handle_command_palette_input(&mut self, window_id: WindowId, event: &WindowEvent) -> bool {
+        let Some(route) = self.routes.get(&window_id) else {
+            return false;
+        };
+        let input = route.window.renderer.borrow_mut().command_palette.handle_event(event);
+
+        match input {
+            CommandPaletteInput::Ignored => return false,
+            CommandPaletteInput::Consumed => {}
+            CommandPaletteInput::Run(PaletteCommand::Action(action)) => {
+                self.handle_gui_action(window_id, action);
+            }
+            CommandPaletteInput::Run(PaletteCommand::User(title)) => {
+                send_ui(ParallelCommand::RunPaletteCommand(title), &route.window.neovim_handler);
+            }
+        }
+        true
+    }
+
+    fn handle_settings_panel_input(&mut self, window_id: WindowId, event: &WindowEvent) -> bool {
+        let Some(route) = self.routes.get(&window_id) else {
+            return false;
+        };
+        let input = route.window.renderer.borrow_mut().settings_panel.handle_event(event);
+
+        match input {
+            SettingsPanelInput::Ignored => return false,
+            SettingsPanelInput::Consumed => {}
+            SettingsPanelInput::Set { name, value } => {
+                if let Some((value, event)) = self.settings.set_value(&name, value) {
+                    self.broadcast_setting_change(name, value, event);
+                }
+            }
+            SettingsPanelInput::Save(names) => {
+                let values = names
+                    .iter()
+                    .filter_map(|name| {
+                        let (section, key) = self.settings.config_key(name)?;
+                        Some((section, key, self.settings.value(name)?))
+                    })
+                    .collect::<Vec<_>>();
+                let result = config::save_setting_values(&values);
+                if let Some(route) = self.routes.get(&window_id) {
+                    route.window.renderer.borrow_mut().settings_panel.handle_saved(result);
+                }
+            }
+        }
+        true
+    }
+
+    fn apply_selection_event(&mut self, window_id: WindowId, action: SelectionEvent) -> bool {
         let Some(route) = self.routes.get(&window_id) else {
             return false;
         };
 
         match action {
-            MessageSelectionEvent::Outside => false,
-            MessageSelectionEvent::Clear => {
-                route.window.renderer.borrow_mut().set_message_selection(None);
+            SelectionEvent::Outside => false,
+            SelectionEvent::Clear => {
+                route.window.renderer.borrow_mut().set_selection(None);
                 true
             }
-            MessageSelectionEvent::Update(selection) => {
-                route.window.renderer.borrow_mut().set_message_selection(Some(selection));
+            SelectionEvent::Update(selection) => {
+                route.window.renderer.borrow_mut().set_selection(Some(selection));
                 true
             }
-            MessageSelectionEvent::Finish(selection) => {
-                self.copy_message_selection(window_id, selection);
-                route.window.renderer.borrow_mut().set_message_selection(None);
+            SelectionEvent::Finish(selection) => {
+                route.window.renderer.borrow_mut().set_selection(Some(selection));
+                self.copy_selection(window_id, selection);
                 true
             }
         }
     }
 
-    fn copy_message_selection(&self, window_id: WindowId, selection: MessageSelection) {
+    fn copy_selection(&self, window_id: WindowId, selection: GuiSelection) {
         let Some(route) = self.routes.get(&window_id) else {
             return;
         };
-        let renderer = route.window.renderer.borrow();
-        let Some(window) = renderer.rendered_windows.get(&selection.grid_id) else {
+        let Some(text) = route.window.renderer.borrow().selection_text(&selection) else {
             return;
         };
 
-        let height = window.grid_size.height;
-        let width = window.grid_size.width;
-        if height == 0 || width == 0 {
-            return;
-        }
-
-        let max_row = height - 1;
-        let max_col = width - 1;
-        let start_row = selection.start.y.min(max_row);
-        let end_row = selection.end.y.min(max_row);
-        let (row_start, row_end) =
-            if start_row <= end_row { (start_row, end_row) } else { (end_row, start_row) };
-
-        let start_col = selection.start.x.min(max_col);
-        let end_col = selection.end.x.min(max_col);
-        let (col_start, col_end) =
-            if start_col <= end_col { (start_col, end_col) } else { (end_col, start_col) };
-
-        let mut lines = Vec::new();
-        for row in row_start..=row_end {
-            let row_start_col = if row == row_start { col_start } else { 0 };
-            let row_end_col = if row == row_end { col_end } else { max_col };
-            if let Some(line) = window.line_text_range(row, row_start_col, row_end_col) {
-                lines.push(line);
-            }
-        }
-
-        if lines.is_empty() || lines.iter().all(|line| line.is_empty()) {
-            return;
-        }
-
-        let text = lines.join("\n");
-
         if let Some(clipboard) = self.clipboard.upgrade() {
             if let Ok(mut clipboard) = clipboard.lock() {
                 #[cfg(target_os = "linux")]
@@ -1092,6 +1402,12 @@ impl WinitWindowWrapper {
         if needs_window && !self.has_routes_for_target(target) {
             return;
         }
+        if needs_window {
+            // Settings can change anything in the frame, so it's redrawn fully
+            for route in self.routes.values() {
+                route.window.renderer.borrow_mut().invalidate();
+            }
+        }
 
         match payload {
             UserEvent::DrawCommandBatch(batch) => match target {
@@ -1296,30 +1612,34 @@ impl WinitWindowWrapper {
             return;
         };
 
-        let mut renderer = route.window.renderer.borrow_mut();
-        let window = route.window.winit_window.clone();
-        let mut skia_renderer = route.window.skia_renderer.borrow_mut();
-        let Some(vsync) = route.state.vsync.as_mut() else {
+        if route.state.vsync.is_none() {
             return;
-        };
-
-        renderer.draw_frame(skia_renderer.canvas(), Some(&content_rect), dt);
-
-        skia_renderer.flush();
-
-        {
-            tracy_gpu_zone!("wait for vsync");
-            vsync.wait_for_vsync();
         }
 
-        skia_renderer.swap_buffers();
-        if self.ui_state == UIState::FirstFrame {
-            window.set_visible(true);
+        let show_window = self.ui_state == UIState::FirstFrame;
+        if show_window {
             self.ui_state = UIState::Showing;
         }
+        let size = route.window.winit_window.inner_size();
+        route.window.presenter.draw_frame(
+            &mut route.window.renderer.borrow_mut(),
+            size.width,
+            size.height,
+            &content_rect,
+            dt,
+            std::mem::take(&mut route.state.screenshot_requested),
+            show_window,
+        );
+    }
 
-        tracy_frame();
-        tracy_gpu_collect();
+    /// Shows the window once its first frame is presented, so that it doesn't flash empty.
+    pub fn handle_first_frame_presented(&mut self, window_id: WindowId) {
+        let Some(route) = self.routes.get(&window_id) else {
+            return;
+        };
+        route.window.winit_window.set_visible(true);
+        startup_time::mark("first frame");
+        startup_time::finish(self.settings.get::<CmdLineSettings>().startuptime.as_deref());
     }
 
     pub fn refresh_rate_for_window(&self, window_id: WindowId, settings: &Settings) -> Option<f32> {
@@ -1355,7 +1675,12 @@ impl WinitWindowWrapper {
         );
         tracy_plot!("animate_frame", res as u8 as f64);
         renderer.prepare_lines(false);
-        #[allow(clippy::let_and_return)]
+
+        // The fonts and the grid are only resized once the zoom animation has settled
+        if let Some(scale_factor) = renderer.zoom.take_settled() {
+            drop(renderer);
+            self.set_setting_value("scale_factor", scale_factor);
+        }
         res
     }
 
@@ -1381,7 +1706,15 @@ impl WinitWindowWrapper {
 
         tracy_zone!("try_create_window");
 
-        let persisted_window_settings = load_last_window_settings().ok();
+        let session_window =
+            if creating_initial_window { None } else { self.next_session_window() };
+        let session_geometry = if creating_initial_window {
+            self.initial_session_window.take()
+        } else {
+            session_window.as_ref().map(|session_window| session_window.window.clone())
+        };
+        let persisted_window_settings =
+            session_geometry.clone().or_else(|| load_last_window_settings(&self.settings).ok());
         let mut desired_window_size =
             determine_window_size(persisted_window_settings.as_ref(), &self.settings.clone());
         let mut desired_grid_size =
@@ -1390,7 +1723,7 @@ impl WinitWindowWrapper {
         #[cfg(target_os = "macos")]
         let mut host_window_position: Option<winit::dpi::PhysicalPosition<i32>> = None;
 
-        if !self.routes.is_empty() {
+        if !self.routes.is_empty() && session_geometry.is_none() {
             if let Some(host_id) = self.get_focused_route() {
                 if let Some(host_route) = self.routes.get(&host_id) {
                     desired_window_size =
@@ -1413,7 +1746,9 @@ impl WinitWindowWrapper {
         };
         let theme = self.get_theme_for(initial_inferred_theme);
         let maximized = matches!(desired_window_size, WindowSize::Maximized);
+        let window_start = Instant::now();
         let window_config = create_window(event_loop, maximized, "Neovide", &self.settings, theme);
+        startup_time::record("create window", window_start);
         let window = Rc::new(window_config.window.clone());
         let mut route_title = String::from("Neovide");
         let mut route_last_synced_grid_size = None;
@@ -1421,6 +1756,7 @@ impl WinitWindowWrapper {
         let mut route_mouse_enabled = true;
         let mut should_apply_initial_window_size = false;
         let mut route_font_changed_last_frame = false;
+        let mut pending_launch = None;
 
         let WindowSettings {
             input_ime,
@@ -1468,16 +1804,26 @@ impl WinitWindowWrapper {
 
                 let runtime =
                     self.runtime.as_mut().expect("Neovim runtime has not been initialized");
-                let neovim_handler = runtime
-                    .launch(
-                        route_id,
-                        proxy.clone(),
-                        desired_grid_size,
-                        self.runtime_tracker.clone(),
-                        self.settings.clone(),
-                        &config,
-                    )
-                    .expect("Failed to launch neovim runtime");
+                let launch = runtime.launch(
+                    route_id,
+                    proxy.clone(),
+                    desired_grid_size,
+                    self.runtime_tracker.clone(),
+                    self.settings.clone(),
+                    &config,
+                );
+                let neovim_handler = launch.handler.clone();
+                pending_launch = Some(launch);
+
+                if let Some(session_window) = session_window {
+                    send_ui(
+                        ParallelCommand::SourceSession {
+                            session_file: session_window.session_file,
+                            directory: session_window.directory,
+                        },
+                        &neovim_handler,
+                    );
+                }
 
                 (renderer, neovim_handler, None)
             };
@@ -1515,6 +1861,9 @@ impl WinitWindowWrapper {
         if let Some(position) = host_window_position {
             window.set_outer_position(position);
         }
+        if let Some(PersistentWindowSettings::Windowed { position, .. }) = session_geometry {
+            window.set_outer_position(position);
+        }
 
         // Check that window is visible in some monitor, and reposition it if not.
         if let Ok(previous_position) = window.outer_position() {
@@ -1557,12 +1906,11 @@ impl WinitWindowWrapper {
         let cmd_line_settings = self.settings.get::<CmdLineSettings>();
         let srgb = cmd_line_settings.srgb;
         let vsync_enabled = cmd_line_settings.vsync;
-        let skia_renderer: Rc<RefCell<Box<dyn SkiaRenderer>>> = Rc::new(RefCell::new(
-            create_skia_renderer(&window_config, srgb, vsync_enabled, self.settings.clone()),
-        ));
-
-        // Create a separate binding for the mutable borrow
-        let window = skia_renderer.borrow_mut().window();
+        let context_start = Instant::now();
+        let skia_renderer =
+            create_skia_renderer(&window_config, srgb, vsync_enabled, self.settings.clone());
+        startup_time::record("create graphics context", context_start);
+        let window = skia_renderer.window();
         window.set_title(&route_title);
 
         #[cfg(target_os = "windows")]
@@ -1607,9 +1955,15 @@ impl WinitWindowWrapper {
             }
         }
 
-        let skia_renderer_ref: &dyn SkiaRenderer = &**skia_renderer.borrow();
-        let vsync =
-            VSync::new(vsync_enabled, skia_renderer_ref, proxy.clone(), self.settings.clone());
+        let vsync = VSync::new(vsync_enabled, skia_renderer.as_ref(), proxy.clone());
+        let timer = vsync.create_timer(self.settings.clone());
+        let presenter = FramePresenter::new(
+            skia_renderer,
+            timer,
+            self.settings.clone(),
+            proxy.clone(),
+            window.id(),
+        );
 
         // It's important that this is created before the window is resized, since it can change the padding and affect the size
         #[cfg(target_os = "macos")]
@@ -1648,7 +2002,7 @@ impl WinitWindowWrapper {
             route_id,
             window: RouteWindow {
                 renderer,
-                skia_renderer: skia_renderer.clone(),
+                presenter,
                 winit_window: window.clone(),
                 neovim_handler,
                 mouse_manager: Rc::new(RefCell::new(Box::new(mouse_manager))),
@@ -1683,6 +2037,19 @@ impl WinitWindowWrapper {
             }
         }
 
+        // The remaining windows of the restored session are created once the first one exists
+        #[cfg(target_os = "macos")]
+        if creating_initial_window {
+            for _ in &self.session_windows {
+                let _ = proxy.send_event(EventPayload::all(UserEvent::CreateWindow));
+            }
+        }
+
+        if let Some(launch) = pending_launch {
+            let runtime = self.runtime.as_ref().expect("Neovim runtime has not been initialized");
+            runtime.wait_for_launch(launch).expect("Failed to launch neovim runtime");
+        }
+
         {
             tracy_zone!("request_redraw");
             window.request_redraw();
@@ -1904,7 +2271,39 @@ impl WinitWindowWrapper {
             HotReloadConfigs::Renderer(config) => {
                 self.handle_renderer_config_changed(config);
             }
+            HotReloadConfigs::Settings(tables) => {
+                self.handle_setting_tables_changed(tables);
+            }
+            HotReloadConfigs::Profile(profile) => {
+                for route in self.routes.values() {
+                    send_ui(
+                        ParallelCommand::SetSetting {
+                            name: "profile".to_owned(),
+                            value: profile.clone().unwrap_or_default().into(),
+                        },
+                        &route.window.neovim_handler,
+                    );
+                }
+            }
+        }
+    }
+
+    fn handle_setting_tables_changed(&mut self, tables: SettingTables) {
+        for (name, value, event) in self.settings.apply_config_tables(tables.iter()) {
+            self.broadcast_setting_change(name, value, event);
+        }
+    }
+
+    /// Applies a setting that was changed by Neovide itself, instead of by nvim.
+    fn broadcast_setting_change(&mut self, name: String, value: Value, event: SettingsChanged) {
+        // Keep the g:neovide_* variables in sync with the new values
+        for route in self.routes.values() {
+            send_ui(
+                ParallelCommand::SetSetting { name: name.clone(), value: value.clone() },
+                &route.window.neovim_handler,
+            );
         }
+        self.handle_user_event(EventPayload::all(UserEvent::SettingsChanged(event)));
     }
 
     fn handle_window_config_changed(&mut self, config: WindowHotReloadConfigs) {
@@ -1918,10 +2317,22 @@ impl WinitWindowWrapper {
             WindowHotReloadConfigs::Geometry(geometry) => {
                 self.handle_config_geometry_changed(geometry);
             }
+            WindowHotReloadConfigs::Keys(keys) => {
+                self.keyboard_manager.key_bindings_mut().set_config(keys.as_ref());
+            }
         }
     }
 
     fn handle_renderer_config_changed(&mut self, config: RendererHotReloadConfigs) {
+        if let RendererHotReloadConfigs::Font(font) = &config {
+            let options: FontOptions = (**font).clone().map(Into::into).unwrap_or_default();
+            let mut highlight_fonts = self.settings.get::<HighlightFonts>();
+            if highlight_fonts.set_config(options.highlight) {
+                self.settings.set(&highlight_fonts);
+                self.handle_highlight_fonts_changed();
+            }
+        }
+
         let Some(route) = self.focused_route_mut() else {
             return;
         };
@@ -1931,6 +2342,31 @@ impl WinitWindowWrapper {
         route.state.font_changed_last_frame = true;
     }
 
+    /// Sets the fonts of a highlight group. Like the fonts of the config file, they are used by
+    /// every window.
+    fn set_highlight_font(&mut self, group: String, fonts: Option<Vec<FontDescription>>) {
+        let mut highlight_fonts = self.settings.get::<HighlightFonts>();
+        highlight_fonts.set(group, fonts);
+        self.settings.set(&highlight_fonts);
+        self.handle_highlight_fonts_changed();
+    }
+
+    fn handle_highlight_fonts_changed(&mut self) {
+        let highlight_fonts = self.settings.get::<HighlightFonts>();
+        for route in self.routes.values_mut() {
+            let mut renderer = route.window.renderer.borrow_mut();
+            renderer.grid_renderer.update_highlight_fonts(highlight_fonts.clone());
+            route.state.font_changed_last_frame = true;
+            route.window.neovim_handler.update_highlight_fonts();
+        }
+        for route_core in self.route_cores.values_mut() {
+            let mut renderer = route_core.renderer.borrow_mut();
+            renderer.grid_renderer.update_highlight_fonts(highlight_fonts.clone());
+            route_core.font_changed_last_frame = true;
+            route_core.neovim_handler.update_highlight_fonts();
+        }
+    }
+
     fn handle_config_title_hidden_changed(&mut self, title_hidden: Option<bool>) {
         let title_hidden = title_hidden.unwrap_or(false);
         let mut cmd_line_settings = self.settings.get::<CmdLineSettings>();
@@ -2374,8 +2810,7 @@ impl WinitWindowWrapper {
         let _ = window.request_inner_size(new_size);
 
         if let Some(route) = self.routes.get(&window_id) {
-            let mut skia_renderer = route.window.skia_renderer.borrow_mut();
-            skia_renderer.resize();
+            route.window.presenter.resize();
         }
     }
 
@@ -2542,7 +2977,6 @@ impl WinitWindowWrapper {
             return;
         };
         let mut renderer = route.window.renderer.borrow_mut();
-        let mut skia_renderer = route.window.skia_renderer.borrow_mut();
         #[cfg(target_os = "macos")]
         {
             if let Some(macos_feature) = macos_feature {
@@ -2550,7 +2984,7 @@ impl WinitWindowWrapper {
             }
         }
         renderer.handle_os_scale_factor_change(scale_factor);
-        skia_renderer.resize();
+        route.window.presenter.resize();
     }
 
     #[cfg(windows)]
@@ -2570,9 +3004,8 @@ impl WinitWindowWrapper {
             return;
         };
 
-        let skia_renderer = route.window.skia_renderer.borrow();
         let winit_color = Self::parse_winit_color(color);
-        skia_renderer.window().set_title_background_color(winit_color);
+        route.window.winit_window.set_title_background_color(winit_color);
     }
 
     #[cfg(windows)]
@@ -2581,9 +3014,8 @@ impl WinitWindowWrapper {
             return;
         };
 
-        let skia_renderer = route.window.skia_renderer.borrow();
         if let Some(winit_color) = Self::parse_winit_color(color) {
-            skia_renderer.window().set_title_text_color(winit_color);
+            route.window.winit_window.set_title_text_color(winit_color);
         }
     }
 }